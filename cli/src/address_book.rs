@@ -0,0 +1,247 @@
+use crate::config::Network;
+use crate::crypto::parse_address;
+use crate::error::WalletError;
+use crate::storage;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the address book inside the wallet directory
+pub const ADDRESS_BOOK_FILE: &str = "address_book.json";
+
+/// Longest contact name accepted
+pub const MAX_CONTACT_NAME_LENGTH: usize = 64;
+
+/// A named recipient
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Contact {
+    /// Unique name (case-insensitive)
+    pub name: String,
+    /// Canonical address
+    pub address: String,
+    /// Optional note shown next to the contact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Network the address belongs to
+    pub network: Network,
+}
+
+impl Contact {
+    /// Create a contact, validating the name and parsing the address
+    pub fn new(name: &str, address: &str, memo: Option<String>, network: Network) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty()
+            || name.chars().count() > MAX_CONTACT_NAME_LENGTH
+            || name.chars().any(char::is_control)
+        {
+            return Err(WalletError::invalid_input(format!(
+                "Contact name must be 1-{} characters without control characters",
+                MAX_CONTACT_NAME_LENGTH
+            ))
+            .into());
+        }
+        // Names that look like addresses would make recipient lookup ambiguous
+        if parse_address(name).is_ok() {
+            return Err(WalletError::invalid_input("Contact name must not be an address").into());
+        }
+
+        Ok(Contact {
+            name: name.to_string(),
+            address: parse_address(address)?,
+            memo: memo.filter(|memo| !memo.trim().is_empty()),
+            network,
+        })
+    }
+}
+
+/// Contacts shared by all wallets in a wallet directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AddressBook {
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+}
+
+impl AddressBook {
+    /// Path of the address book in `wallet_dir`
+    pub fn path<P: AsRef<Path>>(wallet_dir: P) -> PathBuf {
+        wallet_dir.as_ref().join(ADDRESS_BOOK_FILE)
+    }
+
+    /// Load the address book, or an empty one if the file doesn't exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(AddressBook::default());
+        }
+        // A tampered address book could redirect payments, so it gets the wallet file checks
+        storage::check_private_file(path)?;
+        let mut book: AddressBook = serde_json::from_str(&fs::read_to_string(path)?)?;
+        // The desktop app writes addresses without the 0x prefix
+        for contact in &mut book.contacts {
+            contact.address = parse_address(&contact.address)?;
+        }
+        Ok(book)
+    }
+
+    /// Save the address book, creating its directory if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            storage::create_private_dir(parent)?;
+        }
+        storage::write_private_file(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Find a contact by name (case-insensitive)
+    pub fn get(&self, name: &str) -> Option<&Contact> {
+        let name = name.trim();
+        self.contacts
+            .iter()
+            .find(|contact| contact.name.eq_ignore_ascii_case(name))
+    }
+
+    fn get_mut(&mut self, name: &str) -> Result<&mut Contact> {
+        let name = name.trim();
+        self.contacts
+            .iter_mut()
+            .find(|contact| contact.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| WalletError::not_found(format!("Contact not found: {}", name)).into())
+    }
+
+    /// Contacts sorted by name, optionally only those on `network`
+    pub fn list(&self, network: Option<Network>) -> Vec<&Contact> {
+        let mut contacts: Vec<&Contact> = self
+            .contacts
+            .iter()
+            .filter(|contact| network.is_none_or(|network| contact.network == network))
+            .collect();
+        contacts.sort_by_key(|contact| contact.name.to_lowercase());
+        contacts
+    }
+
+    /// Add a contact, refusing duplicate names
+    pub fn add(&mut self, contact: Contact) -> Result<()> {
+        if self.get(&contact.name).is_some() {
+            return Err(WalletError::already_exists(format!(
+                "Contact already exists: {}",
+                contact.name
+            ))
+            .into());
+        }
+        self.contacts.push(contact);
+        Ok(())
+    }
+
+    /// Replace the contact called `name`, which may be renamed
+    pub fn update(&mut self, name: &str, contact: Contact) -> Result<()> {
+        if !contact.name.eq_ignore_ascii_case(name.trim()) && self.get(&contact.name).is_some() {
+            return Err(WalletError::already_exists(format!(
+                "Contact already exists: {}",
+                contact.name
+            ))
+            .into());
+        }
+        *self.get_mut(name)? = contact;
+        Ok(())
+    }
+
+    /// Remove a contact by name
+    pub fn remove(&mut self, name: &str) -> Result<Contact> {
+        let contact = self.get_mut(name)?.clone();
+        self.contacts.retain(|other| other != &contact);
+        Ok(contact)
+    }
+
+    /// Resolve a recipient given as an address or a contact name on `network`
+    pub fn resolve(&self, recipient: &str, network: Network) -> Result<String> {
+        if let Ok(address) = parse_address(recipient) {
+            return Ok(address);
+        }
+
+        let contact = self.get(recipient).ok_or_else(|| {
+            WalletError::not_found(format!(
+                "Recipient is neither an address nor a contact: {}",
+                recipient
+            ))
+        })?;
+        if contact.network != network {
+            return Err(WalletError::invalid_input(format!(
+                "Contact {} is on {}, but the wallet is on {}",
+                contact.name, contact.network, network
+            ))
+            .into());
+        }
+        Ok(contact.address.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use tempfile::TempDir;
+
+    const ALICE: &str = "0x3F2A6b1c9d8e7f60514233a4b5c6d7e8f9a0b1c2";
+
+    #[test]
+    fn test_address_book_crud() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = AddressBook::path(temp_dir.path());
+        let mut book = AddressBook::load(&path).unwrap();
+        assert!(book.contacts.is_empty());
+
+        let alice = Contact::new(" Alice ", ALICE, Some("rent".into()), Network::Mainnet).unwrap();
+        assert_eq!(alice.name, "Alice");
+        assert_eq!(alice.address, ALICE.to_lowercase());
+        book.add(alice.clone()).unwrap();
+        book.add(Contact::new("bob", &ALICE[2..], None, Network::Testnet).unwrap())
+            .unwrap();
+
+        let duplicate = Contact::new("ALICE", ALICE, None, Network::Mainnet).unwrap();
+        let error = book.add(duplicate).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::AlreadyExists);
+
+        book.save(&path).unwrap();
+        let mut book = AddressBook::load(&path).unwrap();
+        assert_eq!(book.get("alice"), Some(&alice));
+        assert_eq!(book.list(Some(Network::Testnet)).len(), 1);
+
+        let renamed = Contact::new("Carol", ALICE, None, Network::Mainnet).unwrap();
+        assert!(book
+            .update(
+                "alice",
+                Contact::new("Bob", ALICE, None, Network::Mainnet).unwrap()
+            )
+            .is_err());
+        book.update("alice", renamed).unwrap();
+        assert!(book.get("alice").is_none());
+        assert_eq!(book.remove("carol").unwrap().name, "Carol");
+        assert!(book.remove("carol").is_err());
+    }
+
+    #[test]
+    fn test_contact_validation_and_resolve() {
+        assert!(Contact::new("short", "0x1234", None, Network::Mainnet).is_err());
+        assert!(Contact::new("bad", &"zz".repeat(20), None, Network::Mainnet).is_err());
+        assert!(Contact::new("", ALICE, None, Network::Mainnet).is_err());
+        assert!(Contact::new(ALICE, ALICE, None, Network::Mainnet).is_err());
+        assert!(Contact::new("tab\tname", ALICE, None, Network::Mainnet).is_err());
+
+        let mut book = AddressBook::default();
+        book.add(Contact::new("alice", ALICE, None, Network::Testnet).unwrap())
+            .unwrap();
+        assert_eq!(
+            book.resolve("Alice", Network::Testnet).unwrap(),
+            ALICE.to_lowercase()
+        );
+        assert_eq!(
+            book.resolve(&ALICE[2..], Network::Mainnet).unwrap(),
+            ALICE.to_lowercase()
+        );
+        assert!(book.resolve("alice", Network::Mainnet).is_err());
+        let error = book.resolve("mallory", Network::Testnet).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+    }
+}
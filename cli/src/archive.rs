@@ -0,0 +1,629 @@
+use crate::address_book::AddressBook;
+use crate::config::{Settings, MAX_KDF_ITERATIONS, MIN_KDF_ITERATIONS};
+use crate::error::{ErrorKind, WalletError};
+use crate::secret::write_secret_file;
+use crate::storage::{self, WalletName, WalletStore, Wallets};
+use crate::types::AccountManager;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `format` field identifying a backup archive
+pub const ARCHIVE_FORMAT: &str = "rwmth-backup";
+/// Newest archive version this build can read
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const KDF: &str = "pbkdf2-sha256";
+const CIPHER: &str = "xchacha20poly1305";
+
+/// Unencrypted part of an archive, authenticated along with the ciphertext
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    /// PBKDF2 salt (hex)
+    salt: String,
+    cipher: String,
+    /// XChaCha20-Poly1305 nonce (hex)
+    nonce: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveFile {
+    #[serde(flatten)]
+    header: ArchiveHeader,
+    /// Encrypted `BackupContents` JSON (base64)
+    ciphertext: String,
+}
+
+/// Everything a backup archive holds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackupContents {
+    pub created_at: DateTime<Utc>,
+    /// Wallets by name, as the JSON backend stores them, so desktop wallets round-trip too
+    pub wallets: BTreeMap<String, Value>,
+    pub address_book: Option<AddressBook>,
+    /// Settings from the config file, without environment overrides
+    pub settings: Option<Settings>,
+}
+
+/// What restoring a wallet from an archive would do
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletPreview {
+    pub name: String,
+    /// Account addresses, when the wallet format is recognized
+    pub addresses: Option<Vec<String>>,
+    /// A wallet with this name already exists
+    pub exists: bool,
+}
+
+/// Summary of an archive's contents, shown before restoring
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupPreview {
+    pub created_at: DateTime<Utc>,
+    pub wallets: Vec<WalletPreview>,
+    /// Number of contacts, if the archive has an address book
+    pub contacts: Option<usize>,
+    pub settings: bool,
+}
+
+/// How to treat wallets, the address book and settings that already exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Only add what's missing: new wallets, accounts and contacts
+    Merge,
+    /// Overwrite existing files, keeping the old ones beside them as `.bak` files
+    Replace,
+}
+
+/// What a restore changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    /// Wallets that didn't exist and were written
+    pub restored: Vec<String>,
+    /// Existing wallets that gained accounts or multisig accounts
+    pub merged: Vec<String>,
+    /// Existing wallets that were overwritten
+    pub replaced: Vec<String>,
+    /// Existing wallets that already had everything in the archive
+    pub unchanged: Vec<String>,
+    /// Existing wallets in a format that can't be merged, left alone
+    pub skipped: Vec<String>,
+    pub contacts_added: usize,
+    pub address_book_replaced: bool,
+    pub settings_restored: bool,
+    /// Where replaced files were moved to
+    pub set_aside: Vec<PathBuf>,
+}
+
+impl BackupContents {
+    /// Gather the named wallets from `wallets`, the address book from `wallet_dir`,
+    /// and the settings from `config_path` when given and present
+    pub fn collect(
+        wallet_dir: &Path,
+        wallets: &Wallets,
+        names: &[WalletName],
+        config_path: Option<&Path>,
+    ) -> Result<Self> {
+        let mut contents = BackupContents {
+            created_at: Utc::now(),
+            ..Default::default()
+        };
+
+        for name in names {
+            let store = wallets.store(name)?;
+            if !store.exists() {
+                return Err(WalletError::not_found(format!("Wallet not found: {}", name)).into());
+            }
+            let wallet = store
+                .export()
+                .with_context(|| format!("Invalid wallet {}", name))?;
+            contents.wallets.insert(name.to_string(), wallet);
+        }
+
+        let book_path = AddressBook::path(wallet_dir);
+        if book_path.exists() {
+            contents.address_book = Some(AddressBook::load(&book_path)?);
+        }
+        if let Some(path) = config_path.filter(|path| path.exists()) {
+            contents.settings = Some(Settings::load_file(path)?);
+        }
+        Ok(contents)
+    }
+
+    /// Describe the archive's contents against what's in `stores`
+    pub fn preview(&self, stores: &Wallets) -> Result<BackupPreview> {
+        let mut wallets = Vec::new();
+        for (name, wallet) in &self.wallets {
+            let name = WalletName::new(name)?;
+            wallets.push(WalletPreview {
+                name: name.to_string(),
+                addresses: wallet_addresses(wallet),
+                exists: stores.store(&name)?.exists(),
+            });
+        }
+
+        Ok(BackupPreview {
+            created_at: self.created_at,
+            wallets,
+            contacts: self.address_book.as_ref().map(|book| book.contacts.len()),
+            settings: self.settings.is_some(),
+        })
+    }
+
+    /// Write the archive's wallets into `wallets`, its address book into
+    /// `wallet_dir` and its settings to `config_path`. Nothing is written unless
+    /// every wallet name and the settings are valid.
+    pub fn restore(
+        &self,
+        wallet_dir: &Path,
+        wallets: &Wallets,
+        config_path: &Path,
+        mode: RestoreMode,
+    ) -> Result<RestoreReport> {
+        let mut restores = Vec::new();
+        for (name, wallet) in &self.wallets {
+            restores.push((WalletName::new(name)?, wallet));
+        }
+        if let Some(settings) = &self.settings {
+            settings.validate().context("Invalid settings in backup")?;
+        }
+
+        let mut report = RestoreReport::default();
+        storage::create_private_dir(wallet_dir)?;
+
+        for (name, wallet) in restores {
+            let store = wallets.store(&name)?;
+            if !store.exists() {
+                store
+                    .import(wallet)
+                    .with_context(|| format!("Failed to restore wallet {}", name))?;
+                report.restored.push(name.to_string());
+                continue;
+            }
+
+            match mode {
+                RestoreMode::Replace => {
+                    report
+                        .set_aside
+                        .push(set_aside_wallet(wallet_dir, &name, store.as_ref())?);
+                    store
+                        .import(wallet)
+                        .with_context(|| format!("Failed to restore wallet {}", name))?;
+                    report.replaced.push(name.to_string());
+                }
+                RestoreMode::Merge => {
+                    let local = serde_json::from_value::<AccountManager>(store.export()?);
+                    let backup = serde_json::from_value::<AccountManager>(wallet.clone());
+                    let (Ok(mut local), Ok(backup)) = (local, backup) else {
+                        report.skipped.push(name.to_string());
+                        continue;
+                    };
+                    if merge_wallet(&mut local, &backup) {
+                        store.save(&local)?;
+                        report.merged.push(name.to_string());
+                    } else {
+                        report.unchanged.push(name.to_string());
+                    }
+                }
+            }
+        }
+
+        if let Some(book) = &self.address_book {
+            let path = AddressBook::path(wallet_dir);
+            if mode == RestoreMode::Replace || !path.exists() {
+                if path.exists() {
+                    report.set_aside.push(set_aside(&path)?);
+                }
+                book.save(&path)?;
+                report.address_book_replaced = true;
+            } else {
+                let mut local = AddressBook::load(&path)?;
+                for contact in &book.contacts {
+                    if local.get(&contact.name).is_none() {
+                        local.add(contact.clone())?;
+                        report.contacts_added += 1;
+                    }
+                }
+                if report.contacts_added > 0 {
+                    local.save(&path)?;
+                }
+            }
+        }
+
+        if let Some(settings) = &self.settings {
+            if mode == RestoreMode::Replace || !config_path.exists() {
+                if config_path.exists() {
+                    report.set_aside.push(set_aside(config_path)?);
+                }
+                settings.save(config_path)?;
+                report.settings_restored = true;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Addresses of the accounts in a CLI or desktop wallet file
+fn wallet_addresses(wallet: &Value) -> Option<Vec<String>> {
+    if let Ok(manager) = serde_json::from_value::<AccountManager>(wallet.clone()) {
+        return Some(
+            manager
+                .account_map
+                .iter()
+                .map(|account| account.get_address())
+                .collect(),
+        );
+    }
+    wallet
+        .get("accounts")?
+        .as_array()?
+        .iter()
+        .map(|account| {
+            account
+                .get("address")?
+                .as_str()
+                .map(|address| format!("0x{}", address.trim_start_matches("0x")))
+        })
+        .collect()
+}
+
+/// Add the backup's accounts, their metadata and multisig accounts that `local`
+/// lacks, keeping everything `local` already has. Returns whether anything changed.
+fn merge_wallet(local: &mut AccountManager, backup: &AccountManager) -> bool {
+    let mut changed = false;
+    for account in &backup.account_map {
+        if !local.account_map.contains(account) {
+            local.account_map.push(account.clone());
+            changed = true;
+        }
+    }
+    for (address, metadata) in &backup.account_metadata {
+        if !local.account_metadata.contains_key(address) {
+            local
+                .account_metadata
+                .insert(address.clone(), metadata.clone());
+            changed = true;
+        }
+    }
+    for multisig in &backup.multisig_accounts {
+        if !local
+            .multisig_accounts
+            .iter()
+            .any(|existing| existing.name == multisig.name)
+        {
+            local.multisig_accounts.push(multisig.clone());
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn set_aside_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}.bak", Utc::now().format("%Y%m%dT%H%M%S%.3fZ")));
+    PathBuf::from(name)
+}
+
+/// Move `path` to `<path>.<timestamp>.bak`, returning the new path
+fn set_aside(path: &Path) -> Result<PathBuf> {
+    let target = set_aside_path(path);
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Save the wallet in `store` as `<wallet dir>/<name>.json.<timestamp>.bak`,
+/// whichever backend holds it, returning the file's path
+fn set_aside_wallet(
+    wallet_dir: &Path,
+    name: &WalletName,
+    store: &dyn WalletStore,
+) -> Result<PathBuf> {
+    let target = set_aside_path(&storage::wallet_file(wallet_dir, name));
+    storage::write_private_file(&target, serde_json::to_string_pretty(&store.export()?)?)?;
+    Ok(target)
+}
+
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn decrypt_error() -> anyhow::Error {
+    WalletError::new(
+        ErrorKind::Crypto,
+        "Wrong password, or the backup archive is corrupted or was modified",
+    )
+    .into()
+}
+
+/// Encrypt `contents` into archive text with a key derived from `password`
+pub fn seal(contents: &BackupContents, password: &str, iterations: u32) -> Result<String> {
+    let salt: [u8; 16] = rand::thread_rng().gen();
+    let nonce: [u8; 24] = rand::thread_rng().gen();
+    let header = ArchiveHeader {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        kdf: KDF.to_string(),
+        iterations,
+        salt: hex::encode(salt),
+        cipher: CIPHER.to_string(),
+        nonce: hex::encode(nonce),
+    };
+
+    let key = derive_key(password, &salt, iterations);
+    let aad = serde_json::to_vec(&header)?;
+    let plaintext = serde_json::to_vec(contents)?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| WalletError::new(ErrorKind::Crypto, "Failed to encrypt backup"))?;
+
+    Ok(serde_json::to_string_pretty(&ArchiveFile {
+        header,
+        ciphertext: BASE64.encode(ciphertext),
+    })?)
+}
+
+/// Decrypt archive text, verifying that neither the header nor the contents were changed
+pub fn open(archive: &str, password: &str) -> Result<BackupContents> {
+    let file: ArchiveFile = serde_json::from_str(archive)
+        .map_err(|_| WalletError::invalid_input("Not a backup archive"))?;
+    let header = &file.header;
+    if header.format != ARCHIVE_FORMAT {
+        return Err(WalletError::invalid_input("Not a backup archive").into());
+    }
+    if header.version > ARCHIVE_VERSION {
+        return Err(WalletError::invalid_input(format!(
+            "Backup archive version {} is newer than this build supports",
+            header.version
+        ))
+        .into());
+    }
+    if header.kdf != KDF || header.cipher != CIPHER {
+        return Err(WalletError::invalid_input(format!(
+            "Unsupported backup encryption: {} with {}",
+            header.kdf, header.cipher
+        ))
+        .into());
+    }
+    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&header.iterations) {
+        return Err(
+            WalletError::invalid_input("Backup archive KDF iterations out of range").into(),
+        );
+    }
+
+    let salt = hex::decode(&header.salt)?;
+    let nonce = hex::decode(&header.nonce)?;
+    if nonce.len() != 24 {
+        return Err(WalletError::invalid_input("Invalid backup archive nonce").into());
+    }
+    let ciphertext = BASE64
+        .decode(&file.ciphertext)
+        .map_err(|_| WalletError::invalid_input("Invalid backup archive ciphertext"))?;
+
+    let key = derive_key(password, &salt, header.iterations);
+    let aad = serde_json::to_vec(header)?;
+    let plaintext = XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| decrypt_error())?;
+
+    serde_json::from_slice(&plaintext).context("Invalid backup archive contents")
+}
+
+/// Encrypt `contents` into a new archive file readable only by the owner
+pub fn write_archive(
+    path: &Path,
+    contents: &BackupContents,
+    password: &str,
+    iterations: u32,
+) -> Result<()> {
+    write_secret_file(path, &seal(contents, password, iterations)?)
+}
+
+/// Read and decrypt an archive file
+pub fn read_archive(path: &Path, password: &str) -> Result<BackupContents> {
+    let archive = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read backup archive {}", path.display()))?;
+    open(&archive, password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address_book::Contact;
+    use crate::config::{Network, StorageBackend};
+    use crate::crypto::generate_keypair;
+    use crate::storage::Storage;
+    use tempfile::TempDir;
+
+    const ITERATIONS: u32 = MIN_KDF_ITERATIONS;
+
+    fn wallet_name(name: &str) -> WalletName {
+        WalletName::new(name).unwrap()
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let mut contents = BackupContents::default();
+        contents
+            .wallets
+            .insert("work".to_string(), serde_json::json!({ "accounts": [] }));
+        contents.settings = Some(Settings::default());
+
+        let archive = seal(&contents, "correct horse", ITERATIONS).unwrap();
+        assert_eq!(open(&archive, "correct horse").unwrap(), contents);
+
+        let error = open(&archive, "wrong horse").unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Crypto);
+
+        // Changing the authenticated header or the ciphertext is detected
+        let mut file: ArchiveFile = serde_json::from_str(&archive).unwrap();
+        file.header.iterations += 1;
+        let tampered = serde_json::to_string(&file).unwrap();
+        assert_eq!(
+            ErrorKind::of(&open(&tampered, "correct horse").unwrap_err()),
+            ErrorKind::Crypto
+        );
+
+        let mut file: ArchiveFile = serde_json::from_str(&archive).unwrap();
+        let mut ciphertext = BASE64.decode(&file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        file.ciphertext = BASE64.encode(ciphertext);
+        let tampered = serde_json::to_string(&file).unwrap();
+        assert_eq!(
+            ErrorKind::of(&open(&tampered, "correct horse").unwrap_err()),
+            ErrorKind::Crypto
+        );
+
+        assert!(open("{}", "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let source = TempDir::new().unwrap();
+        let config_path = source.path().join("config.toml");
+        let work = Storage::new(storage::wallet_file(source.path(), &wallet_name("work")))
+            .init_account_manager()
+            .unwrap();
+        Settings::default().save(&config_path).unwrap();
+        let mut book = AddressBook::default();
+        book.add(
+            Contact::new(
+                "alice",
+                "0x1111111111111111111111111111111111111111",
+                None,
+                Network::Mainnet,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        book.save(AddressBook::path(source.path())).unwrap();
+
+        let contents = BackupContents::collect(
+            source.path(),
+            &Wallets::Json(source.path().to_path_buf()),
+            &[wallet_name("work")],
+            Some(&config_path),
+        )
+        .unwrap();
+        let archive_path = source.path().join("backup.rwmth");
+        write_archive(&archive_path, &contents, "pw", ITERATIONS).unwrap();
+        assert!(write_archive(&archive_path, &contents, "pw", ITERATIONS).is_err());
+        let contents = read_archive(&archive_path, "pw").unwrap();
+
+        // Restoring into an empty directory writes everything, here into the sled backend
+        let target = TempDir::new().unwrap();
+        let target_config = target.path().join("config.toml");
+        let target_wallets = Wallets::open(StorageBackend::Sled, target.path()).unwrap();
+        let preview = contents.preview(&target_wallets).unwrap();
+        assert_eq!(preview.wallets.len(), 1);
+        assert!(!preview.wallets[0].exists);
+        assert_eq!(preview.contacts, Some(1));
+        let report = contents
+            .restore(
+                target.path(),
+                &target_wallets,
+                &target_config,
+                RestoreMode::Merge,
+            )
+            .unwrap();
+        assert_eq!(report.restored, vec!["work"]);
+        assert!(report.address_book_replaced && report.settings_restored);
+
+        // Merging into a wallet that has diverged keeps its accounts and adds the missing ones
+        let target_storage = target_wallets.store(&wallet_name("work")).unwrap();
+        let mut local = AccountManager::default();
+        let extra = generate_keypair().unwrap();
+        local.account_map.push(extra.clone());
+        target_storage.save(&local).unwrap();
+        let report = contents
+            .restore(
+                target.path(),
+                &target_wallets,
+                &target_config,
+                RestoreMode::Merge,
+            )
+            .unwrap();
+        assert_eq!(report.merged, vec!["work"]);
+        assert!(report.set_aside.is_empty() && !report.settings_restored);
+        let merged = target_storage.load().unwrap();
+        assert_eq!(merged.account_map.len(), 3);
+        assert!(merged.account_map.contains(&extra));
+        assert!(merged.account_map.contains(&work.account_map[0]));
+        let report = contents
+            .restore(
+                target.path(),
+                &target_wallets,
+                &target_config,
+                RestoreMode::Merge,
+            )
+            .unwrap();
+        assert_eq!(report.unchanged, vec!["work"]);
+
+        // Replacing keeps the previous file beside the restored one
+        let report = contents
+            .restore(
+                target.path(),
+                &target_wallets,
+                &target_config,
+                RestoreMode::Replace,
+            )
+            .unwrap();
+        assert_eq!(report.replaced, vec!["work"]);
+        assert_eq!(report.set_aside.len(), 3);
+        assert!(report.set_aside.iter().all(|path| path.exists()));
+        assert_eq!(target_storage.load().unwrap(), work);
+        assert_eq!(target_wallets.list().unwrap(), vec!["work"]);
+    }
+
+    #[test]
+    fn test_restore_rejects_hostile_names() {
+        let mut contents = BackupContents::default();
+        contents
+            .wallets
+            .insert("ok".to_string(), serde_json::json!({}));
+        contents
+            .wallets
+            .insert("../escape".to_string(), serde_json::json!({}));
+
+        let target = TempDir::new().unwrap();
+        let wallet_dir = target.path().join("wallets");
+        let error = contents
+            .restore(
+                &wallet_dir,
+                &Wallets::Json(wallet_dir.clone()),
+                &target.path().join("config.toml"),
+                RestoreMode::Merge,
+            )
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidInput);
+        assert!(!wallet_dir.exists());
+        assert!(!target.path().join("escape.json").exists());
+    }
+}
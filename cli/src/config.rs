@@ -0,0 +1,910 @@
+use crate::crypto::parse_address;
+use crate::error::WalletError;
+use crate::storage::{self, WalletName};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Directory name shared with the desktop app
+const APP_DIR_NAME: &str = "rwmth";
+
+/// Name of the configuration file inside the application directory
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// PBKDF2 iterations used before the setting existed
+pub const MIN_KDF_ITERATIONS: u32 = 10_000;
+pub const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+
+/// Application directory: `$XDG_CONFIG_HOME/rwmth`, falling back to `~/.config/rwmth`
+pub fn app_dir() -> PathBuf {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".config")
+        });
+    config_dir.join(APP_DIR_NAME)
+}
+
+/// Configuration file path: `RWMTH_CONFIG`, else `<app dir>/config.toml`
+pub fn default_config_path() -> PathBuf {
+    std::env::var_os("RWMTH_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| app_dir().join(CONFIG_FILE_NAME))
+}
+
+/// Network the wallet talks to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Devnet,
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Mainnet => write!(f, "mainnet"),
+            Network::Testnet => write!(f, "testnet"),
+            Network::Devnet => write!(f, "devnet"),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            "devnet" => Ok(Network::Devnet),
+            other => Err(format!("Unknown network: {}", other)),
+        }
+    }
+}
+
+/// Chain, node and display settings of one network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkProfile {
+    /// Chain id bound into every transaction signing hash, so a signature is
+    /// only valid on this network
+    pub chain_id: u64,
+    /// Node RPC endpoint, unless `node.url` overrides it
+    pub node_url: String,
+    /// Prefix addresses are displayed with, and accepted with as input
+    pub address_prefix: String,
+    /// Block explorer, linked as `<explorer_url>/tx/<hash>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+}
+
+impl NetworkProfile {
+    /// `address` (0x-prefixed hex) as displayed on this network
+    pub fn display_address(&self, address: &str) -> String {
+        format!(
+            "{}{}",
+            self.address_prefix,
+            address.trim_start_matches("0x")
+        )
+    }
+
+    /// Replace this network's prefix on an address with `0x`, leaving anything
+    /// else (contact names, plain addresses) as it is
+    pub fn normalize_address<'a>(&self, input: &'a str) -> std::borrow::Cow<'a, str> {
+        match input
+            .strip_prefix(self.address_prefix.as_str())
+            .and_then(|rest| parse_address(rest).ok())
+        {
+            Some(address) => address.into(),
+            None => input.into(),
+        }
+    }
+
+    /// Explorer link for the transaction with hash `hash`
+    pub fn transaction_url(&self, hash: &str) -> Option<String> {
+        self.explorer_url
+            .as_ref()
+            .map(|url| format!("{}/tx/{}", url.trim_end_matches('/'), hash))
+    }
+}
+
+/// Profiles of the known networks; the built-in values can be changed in the config file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Networks {
+    pub mainnet: NetworkProfile,
+    pub testnet: NetworkProfile,
+    pub devnet: NetworkProfile,
+}
+
+impl Default for Networks {
+    fn default() -> Self {
+        Self {
+            mainnet: NetworkProfile {
+                chain_id: 1,
+                node_url: "https://node.mazzaroth.io".to_string(),
+                address_prefix: "0x".to_string(),
+                explorer_url: Some("https://explorer.mazzaroth.io".to_string()),
+            },
+            testnet: NetworkProfile {
+                chain_id: 2,
+                node_url: "https://testnet.node.mazzaroth.io".to_string(),
+                address_prefix: "0x".to_string(),
+                explorer_url: Some("https://testnet.explorer.mazzaroth.io".to_string()),
+            },
+            devnet: NetworkProfile {
+                chain_id: 1337,
+                node_url: "http://localhost:8081".to_string(),
+                address_prefix: "0x".to_string(),
+                explorer_url: None,
+            },
+        }
+    }
+}
+
+impl Networks {
+    pub const ALL: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Devnet];
+
+    pub fn get(&self, network: Network) -> &NetworkProfile {
+        match network {
+            Network::Mainnet => &self.mainnet,
+            Network::Testnet => &self.testnet,
+            Network::Devnet => &self.devnet,
+        }
+    }
+
+    fn get_mut(&mut self, network: Network) -> &mut NetworkProfile {
+        match network {
+            Network::Mainnet => &mut self.mainnet,
+            Network::Testnet => &mut self.testnet,
+            Network::Devnet => &mut self.devnet,
+        }
+    }
+}
+
+/// How quickly a transaction should be included, trading off the gas price paid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeTier {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl fmt::Display for FeeTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeTier::Slow => write!(f, "slow"),
+            FeeTier::Normal => write!(f, "normal"),
+            FeeTier::Fast => write!(f, "fast"),
+        }
+    }
+}
+
+impl FromStr for FeeTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "slow" => Ok(FeeTier::Slow),
+            "normal" => Ok(FeeTier::Normal),
+            "fast" => Ok(FeeTier::Fast),
+            other => Err(format!("Unknown fee tier: {}", other)),
+        }
+    }
+}
+
+/// Most verbose log level that is printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_tracing(&self) -> tracing::Level {
+        match self {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogLevel::Error => write!(f, "error"),
+            LogLevel::Warn => write!(f, "warn"),
+            LogLevel::Info => write!(f, "info"),
+            LogLevel::Debug => write!(f, "debug"),
+            LogLevel::Trace => write!(f, "trace"),
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            other => Err(format!("Unknown log level: {}", other)),
+        }
+    }
+}
+
+/// How wallets are stored in the wallet directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// One pretty-printed `<name>.json` file per wallet
+    #[default]
+    Json,
+    /// A sled database in `<wallet dir>/wallets.db`
+    Sled,
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageBackend::Json => write!(f, "json"),
+            StorageBackend::Sled => write!(f, "sled"),
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(StorageBackend::Json),
+            "sled" => Ok(StorageBackend::Sled),
+            other => Err(format!("Unknown storage backend: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalletSettings {
+    /// Directory holding wallet files (defaults to the application directory)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Storage backend for wallets in the wallet directory
+    pub backend: StorageBackend,
+    /// Wallet used when none is named on the command line
+    pub default_wallet: String,
+    /// Minutes of inactivity before the desktop app locks (0 = never)
+    pub auto_lock_minutes: u32,
+    /// PBKDF2 iterations for newly encrypted keys
+    pub kdf_iterations: u32,
+}
+
+impl Default for WalletSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            backend: StorageBackend::Json,
+            default_wallet: "default".to_string(),
+            auto_lock_minutes: 15,
+            kdf_iterations: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeSettings {
+    /// Node RPC endpoint, overriding the network's `node_url`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub network: Network,
+    /// Request timeout in seconds
+    pub timeout_secs: u64,
+    pub auto_reconnect: bool,
+}
+
+impl Default for NodeSettings {
+    fn default() -> Self {
+        Self {
+            url: None,
+            network: Network::Mainnet,
+            timeout_secs: 30,
+            auto_reconnect: true,
+        }
+    }
+}
+
+/// How gas limits and prices are filled in from the node's estimates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeSettings {
+    /// Gas price tier used unless one is chosen
+    pub tier: FeeTier,
+    /// Percent added to the node's gas estimate for the gas limit
+    pub gas_limit_margin_percent: u32,
+    /// Highest gas limit an estimate may set
+    pub max_gas_limit: u64,
+    /// Highest gas price an estimate may set; higher node prices are capped to it
+    pub max_gas_price: u64,
+}
+
+impl Default for FeeSettings {
+    fn default() -> Self {
+        Self {
+            tier: FeeTier::Normal,
+            gas_limit_margin_percent: 20,
+            max_gas_limit: 10_000_000,
+            max_gas_price: 1_000_000,
+        }
+    }
+}
+
+/// Signer methods a dApp origin can be allowed to call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DappMethod {
+    Accounts,
+    SignMessage,
+    SignTransaction,
+}
+
+impl DappMethod {
+    pub const ALL: [DappMethod; 3] = [
+        DappMethod::Accounts,
+        DappMethod::SignMessage,
+        DappMethod::SignTransaction,
+    ];
+}
+
+impl fmt::Display for DappMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DappMethod::Accounts => write!(f, "accounts"),
+            DappMethod::SignMessage => write!(f, "signMessage"),
+            DappMethod::SignTransaction => write!(f, "signTransaction"),
+        }
+    }
+}
+
+/// What one dApp origin may do through the desktop app's signer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DappPermission {
+    /// Accounts the origin can see and sign with
+    pub accounts: Vec<String>,
+    pub methods: BTreeSet<DappMethod>,
+    /// Most the origin's transactions may cost in total, amounts and fees included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spending_limit: Option<u64>,
+    /// Cost of the transactions signed for the origin so far
+    #[serde(default)]
+    pub spent: u64,
+    pub granted_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl DappPermission {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// Whether `address` is one of the accounts the origin may use
+    pub fn allows_account(&self, address: &str) -> bool {
+        parse_address(address).is_ok_and(|address| {
+            self.accounts
+                .iter()
+                .any(|account| parse_address(account).is_ok_and(|account| account == address))
+        })
+    }
+
+    /// What the origin may still spend, if it is limited
+    pub fn remaining(&self) -> Option<u64> {
+        self.spending_limit
+            .map(|limit| limit.saturating_sub(self.spent))
+    }
+}
+
+/// Localhost JSON-RPC server through which dApps ask the desktop app for signatures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignerSettings {
+    /// Off unless turned on, since it lets local web pages ask for signatures
+    pub enabled: bool,
+    /// Port on 127.0.0.1 the server listens on
+    pub port: u16,
+    /// Seconds a request waits for approval before it is rejected
+    pub approval_timeout_secs: u64,
+}
+
+impl Default for SignerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8547,
+            approval_timeout_secs: 120,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    pub level: LogLevel,
+    pub to_file: bool,
+    /// Log file size before rotation, in megabytes
+    pub max_file_size_mb: u32,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            to_file: false,
+            max_file_size_mb: 10,
+        }
+    }
+}
+
+/// Application settings shared by the CLI and the desktop app
+///
+/// Layers, lowest to highest precedence: built-in defaults, the config file,
+/// `RWMTH_*` environment variables, then command line flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub wallet: WalletSettings,
+    pub node: NodeSettings,
+    pub networks: Networks,
+    pub fee: FeeSettings,
+    pub signer: SignerSettings,
+    /// dApp permissions by origin, granted and revoked in the desktop app
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dapps: BTreeMap<String, DappPermission>,
+    pub log: LogSettings,
+}
+
+/// Environment variables and the settings key each one overrides
+const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("RWMTH_WALLET_DIR", "wallet.dir"),
+    ("RWMTH_WALLET_BACKEND", "wallet.backend"),
+    ("RWMTH_WALLET", "wallet.default_wallet"),
+    ("RWMTH_AUTO_LOCK_MINUTES", "wallet.auto_lock_minutes"),
+    ("RWMTH_KDF_ITERATIONS", "wallet.kdf_iterations"),
+    ("RWMTH_NODE_URL", "node.url"),
+    ("RWMTH_NETWORK", "node.network"),
+    ("RWMTH_NODE_TIMEOUT", "node.timeout_secs"),
+    ("RWMTH_FEE_TIER", "fee.tier"),
+    ("RWMTH_LOG_LEVEL", "log.level"),
+];
+
+/// Check `url` is an http(s) URL with a host
+fn validate_url(what: &str, url: &str) -> Result<()> {
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| anyhow!("{} must start with http:// or https://: {}", what, url))?;
+    if host.is_empty() || host.starts_with('/') || url.contains(char::is_whitespace) {
+        return Err(anyhow!("Invalid {}: {}", what.to_lowercase(), url));
+    }
+    Ok(())
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid value for {}: {}", key, value))
+}
+
+impl Settings {
+    /// Load defaults, the config file (if present) and environment overrides
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut settings = Self::load_file(path)?;
+        settings.apply_env(|name| std::env::var(name).ok())?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Load defaults and the config file only
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        let settings: Settings = toml::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        Ok(settings)
+    }
+
+    /// Validate and write the settings to `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.validate()?;
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            storage::create_private_dir(parent)?;
+        }
+        storage::write_private_file(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Apply `RWMTH_*` overrides, looking variables up with `var`
+    pub fn apply_env<F>(&mut self, var: F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        for (name, key) in ENV_OVERRIDES {
+            if let Some(value) = var(name).filter(|value| !value.is_empty()) {
+                self.set(key, &value)
+                    .with_context(|| format!("Invalid environment variable {}", name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set a single value by its dotted key, e.g. `node.url`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "wallet.dir" => self.wallet.dir = Some(PathBuf::from(value)),
+            "wallet.backend" => self.wallet.backend = parse_value(key, value)?,
+            "wallet.default_wallet" => self.wallet.default_wallet = value.to_string(),
+            "wallet.auto_lock_minutes" => self.wallet.auto_lock_minutes = parse_value(key, value)?,
+            "wallet.kdf_iterations" => self.wallet.kdf_iterations = parse_value(key, value)?,
+            "node.url" => self.node.url = (!value.is_empty()).then(|| value.to_string()),
+            "node.network" => self.node.network = parse_value(key, value)?,
+            "node.timeout_secs" => self.node.timeout_secs = parse_value(key, value)?,
+            "node.auto_reconnect" => self.node.auto_reconnect = parse_value(key, value)?,
+            "fee.tier" => self.fee.tier = parse_value(key, value)?,
+            "fee.gas_limit_margin_percent" => {
+                self.fee.gas_limit_margin_percent = parse_value(key, value)?
+            }
+            "fee.max_gas_limit" => self.fee.max_gas_limit = parse_value(key, value)?,
+            "fee.max_gas_price" => self.fee.max_gas_price = parse_value(key, value)?,
+            "signer.enabled" => self.signer.enabled = parse_value(key, value)?,
+            "signer.port" => self.signer.port = parse_value(key, value)?,
+            "signer.approval_timeout_secs" => {
+                self.signer.approval_timeout_secs = parse_value(key, value)?
+            }
+            "log.level" => self.log.level = parse_value(key, value)?,
+            "log.to_file" => self.log.to_file = parse_value(key, value)?,
+            "log.max_file_size_mb" => self.log.max_file_size_mb = parse_value(key, value)?,
+            _ => {
+                let (network, field) = key
+                    .strip_prefix("networks.")
+                    .and_then(|rest| rest.split_once('.'))
+                    .ok_or_else(|| anyhow!("Unknown setting: {}", key))?;
+                let network: Network = parse_value(key, network)?;
+                let profile = self.networks.get_mut(network);
+                match field {
+                    "chain_id" => profile.chain_id = parse_value(key, value)?,
+                    "node_url" => profile.node_url = value.to_string(),
+                    "address_prefix" => profile.address_prefix = value.to_string(),
+                    "explorer_url" => {
+                        profile.explorer_url = (!value.is_empty()).then(|| value.to_string())
+                    }
+                    _ => return Err(anyhow!("Unknown setting: {}", key)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every value is in range
+    pub fn validate(&self) -> Result<()> {
+        WalletName::new(&self.wallet.default_wallet).context("Invalid default wallet")?;
+        if self.wallet.auto_lock_minutes > 24 * 60 {
+            return Err(anyhow!("Auto-lock timeout must be at most 1440 minutes"));
+        }
+        if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&self.wallet.kdf_iterations) {
+            return Err(anyhow!(
+                "KDF iterations must be between {} and {}",
+                MIN_KDF_ITERATIONS,
+                MAX_KDF_ITERATIONS
+            ));
+        }
+
+        if let Some(url) = &self.node.url {
+            validate_url("Node URL", url)?;
+        }
+        for network in Networks::ALL {
+            let profile = self.networks.get(network);
+            validate_url("Node URL", &profile.node_url)
+                .with_context(|| format!("Invalid {} profile", network))?;
+            if let Some(url) = &profile.explorer_url {
+                validate_url("Explorer URL", url)
+                    .with_context(|| format!("Invalid {} profile", network))?;
+            }
+            let prefix = &profile.address_prefix;
+            if prefix.is_empty()
+                || prefix.len() > 8
+                || !prefix
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == ':')
+            {
+                return Err(anyhow!(
+                    "Address prefix of {} must be 1 to 8 letters, digits or ':': {}",
+                    network,
+                    prefix
+                ));
+            }
+            // Distinct chain ids are what keeps a signature from being replayed elsewhere
+            if let Some(other) = Networks::ALL.into_iter().find(|other| {
+                *other != network && self.networks.get(*other).chain_id == profile.chain_id
+            }) {
+                return Err(anyhow!(
+                    "Networks {} and {} have the same chain id {}",
+                    network,
+                    other,
+                    profile.chain_id
+                ));
+            }
+        }
+        if !(1..=300).contains(&self.node.timeout_secs) {
+            return Err(anyhow!(
+                "Connection timeout must be between 1 and 300 seconds"
+            ));
+        }
+
+        if self.fee.gas_limit_margin_percent > 500 {
+            return Err(anyhow!("Gas limit margin must be at most 500 percent"));
+        }
+        if self.fee.max_gas_limit == 0 || self.fee.max_gas_price == 0 {
+            return Err(anyhow!("Gas limit and gas price caps must be above 0"));
+        }
+
+        // Privileged ports would need root, and 0 would pick a port dApps can't find
+        if self.signer.port < 1024 {
+            return Err(anyhow!("Signer port must be at least 1024"));
+        }
+        if !(10..=600).contains(&self.signer.approval_timeout_secs) {
+            return Err(anyhow!(
+                "Signer approval timeout must be between 10 and 600 seconds"
+            ));
+        }
+        for (origin, permission) in &self.dapps {
+            // An origin is a scheme and a host, without a path
+            let valid = origin.split_once("://").is_some_and(|(scheme, host)| {
+                !scheme.is_empty() && !host.is_empty() && !host.contains('/')
+            });
+            if !valid {
+                return Err(anyhow!("Invalid dApp origin: {}", origin));
+            }
+            for account in &permission.accounts {
+                parse_address(account)
+                    .with_context(|| format!("Invalid account for dApp {}", origin))?;
+            }
+        }
+
+        if !(1..=1024).contains(&self.log.max_file_size_mb) {
+            return Err(anyhow!("Max log file size must be between 1 and 1024 MB"));
+        }
+        Ok(())
+    }
+
+    /// Profile of the active network (`node.network`)
+    pub fn network(&self) -> &NetworkProfile {
+        self.networks.get(self.node.network)
+    }
+
+    /// Node endpoint: `node.url` if set, else the active network's
+    pub fn node_url(&self) -> &str {
+        self.node.url.as_deref().unwrap_or(&self.network().node_url)
+    }
+
+    /// Refuse a transaction signed for another chain than the active network's
+    pub fn check_chain_id(&self, chain_id: u64) -> Result<()> {
+        let profile = self.network();
+        if chain_id != profile.chain_id {
+            let network = Networks::ALL
+                .into_iter()
+                .find(|network| self.networks.get(*network).chain_id == chain_id)
+                .map(|network| format!(" ({})", network))
+                .unwrap_or_default();
+            return Err(WalletError::invalid_input(format!(
+                "Transaction is for chain {}{}, but {} is chain {}",
+                chain_id, network, self.node.network, profile.chain_id
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Wallet directory from the settings, else the application directory
+    pub fn wallet_dir(&self) -> PathBuf {
+        self.wallet.dir.clone().unwrap_or_else(app_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_layered_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE_NAME);
+
+        // Missing file gives the defaults
+        assert_eq!(Settings::load_file(&path).unwrap(), Settings::default());
+
+        fs::write(
+            &path,
+            "[node]\nurl = \"http://localhost:8081\"\nnetwork = \"testnet\"\n\n[log]\nlevel = \"debug\"\n",
+        )
+        .unwrap();
+        let mut settings = Settings::load_file(&path).unwrap();
+        assert_eq!(settings.node_url(), "http://localhost:8081");
+        assert_eq!(settings.node.network, Network::Testnet);
+        assert_eq!(settings.log.level, LogLevel::Debug);
+        // Values missing from the file keep their defaults
+        assert_eq!(settings.node.timeout_secs, 30);
+
+        let env: HashMap<&str, &str> = [
+            ("RWMTH_NETWORK", "devnet"),
+            ("RWMTH_WALLET", "work"),
+            ("RWMTH_WALLET_BACKEND", "sled"),
+        ]
+        .into_iter()
+        .collect();
+        settings
+            .apply_env(|name| env.get(name).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(settings.node.network, Network::Devnet);
+        assert_eq!(settings.wallet.default_wallet, "work");
+        assert_eq!(settings.wallet.backend, StorageBackend::Sled);
+        assert_eq!(settings.node_url(), "http://localhost:8081");
+        assert_eq!(settings.network().chain_id, 1337);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_saved_settings_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("rwmth").join(CONFIG_FILE_NAME);
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        Settings::default().save(&path).unwrap();
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        assert_eq!(mode(&path), 0o600);
+
+        // A config written before by something else is tightened on save
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        Settings::default().save(&path).unwrap();
+        assert_eq!(mode(&path), 0o600);
+    }
+
+    #[test]
+    fn test_network_profiles() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.node_url(), "https://node.mazzaroth.io");
+        settings.set("node.network", "testnet").unwrap();
+        assert_eq!(settings.node_url(), "https://testnet.node.mazzaroth.io");
+        settings.set("node.url", "http://localhost:9000").unwrap();
+        assert_eq!(settings.node_url(), "http://localhost:9000");
+        settings.set("node.url", "").unwrap();
+        assert_eq!(settings.node_url(), "https://testnet.node.mazzaroth.io");
+
+        settings.check_chain_id(2).unwrap();
+        let error = settings.check_chain_id(1).unwrap_err();
+        assert!(error.to_string().contains("(mainnet)"));
+
+        settings
+            .set("networks.testnet.address_prefix", "tmth")
+            .unwrap();
+        settings.validate().unwrap();
+        let profile = settings.network();
+        let address = "0x1111111111111111111111111111111111111111";
+        let displayed = profile.display_address(address);
+        assert_eq!(displayed, "tmth1111111111111111111111111111111111111111");
+        assert_eq!(profile.normalize_address(&displayed), address);
+        assert_eq!(profile.normalize_address("tmthalice"), "tmthalice");
+        assert_eq!(
+            profile.transaction_url("0xab").unwrap(),
+            "https://testnet.explorer.mazzaroth.io/tx/0xab"
+        );
+        assert_eq!(settings.networks.devnet.transaction_url("0xab"), None);
+
+        // A partial profile in the file is rejected rather than silently mixed with defaults
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, "[networks.devnet]\nchain_id = 7\n").unwrap();
+        assert!(Settings::load_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_validation_and_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join(CONFIG_FILE_NAME);
+
+        let mut settings = Settings::default();
+        settings.set("wallet.kdf_iterations", "200000").unwrap();
+        settings.set("node.auto_reconnect", "false").unwrap();
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load_file(&path).unwrap(), settings);
+
+        assert!(settings.set("node.timeout_secs", "soon").is_err());
+        assert!(settings.set("node.colour", "blue").is_err());
+
+        let mut invalid = settings.clone();
+        invalid.node.url = Some("ftp://node".to_string());
+        assert!(invalid.save(&path).is_err());
+
+        let mut invalid = settings.clone();
+        invalid.wallet.kdf_iterations = 1000;
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = settings.clone();
+        invalid.set("fee.max_gas_price", "0").unwrap();
+        assert!(invalid.validate().is_err());
+        assert!(invalid.set("fee.tier", "instant").is_err());
+
+        let mut invalid = settings.clone();
+        invalid.set("signer.port", "80").unwrap();
+        assert!(invalid.validate().is_err());
+
+        let mut dapp = settings.clone();
+        let permission = DappPermission {
+            accounts: vec![format!("0x{}", "ab".repeat(20))],
+            methods: [DappMethod::Accounts].into(),
+            spending_limit: Some(1000),
+            spent: 0,
+            granted_at: Utc::now(),
+            expires_at: None,
+        };
+        assert!(permission.allows_account(&"AB".repeat(20)));
+        assert_eq!(permission.remaining(), Some(1000));
+        dapp.dapps
+            .insert("https://dapp.example".to_string(), permission.clone());
+        dapp.save(&path).unwrap();
+        assert_eq!(Settings::load_file(&path).unwrap(), dapp);
+        let mut invalid = settings.clone();
+        invalid
+            .dapps
+            .insert("https://dapp.example/app".to_string(), permission);
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = settings.clone();
+        invalid.set("networks.testnet.chain_id", "1").unwrap();
+        assert!(invalid.validate().is_err());
+        assert!(invalid.set("networks.moonnet.chain_id", "9").is_err());
+
+        fs::write(&path, "[node]\nport = 1\n").unwrap();
+        assert!(Settings::load_file(&path).is_err());
+    }
+}
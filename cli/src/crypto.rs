@@ -1,9 +1,12 @@
-use crate::types::{AccountKey, AccountKeyPair, AccountManager};
-use anyhow::{anyhow, Result};
+use crate::error::WalletError;
+use crate::types::{AccountKey, AccountKeyPair, AccountManager, SignatureScheme};
+use anyhow::Result;
 use bip39::Mnemonic;
 use hex;
 use rand::Rng;
-use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey};
+use secp256k1::{
+    ecdsa::Signature, schnorr, Keypair, Message, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey,
+};
 
 const COIN_TYPE: u32 = 55555;
 
@@ -72,6 +75,64 @@ pub fn verify_signature(
     Ok(secp.verify_ecdsa(&message, &signature, &public_key).is_ok())
 }
 
+/// Sign a message with a private key using BIP340 Schnorr
+pub fn sign_message_schnorr(message: &[u8; 32], private_key: &[u8; 32]) -> Result<[u8; 64]> {
+    let secp = Secp256k1::new();
+    let keypair = Keypair::from_seckey_slice(&secp, private_key)?;
+    let message = Message::from_digest(*message);
+    let signature = secp.sign_schnorr_with_rng(&message, &keypair, &mut rand::thread_rng());
+    Ok(*signature.as_ref())
+}
+
+/// Verify a BIP340 Schnorr signature against an x-only public key
+pub fn verify_signature_schnorr(
+    message: &[u8; 32],
+    signature: &[u8; 64],
+    x_only_public_key: &[u8; 32],
+) -> Result<bool> {
+    let secp = Secp256k1::new();
+    let public_key = XOnlyPublicKey::from_slice(x_only_public_key)?;
+    let message = Message::from_digest(*message);
+    let signature = schnorr::Signature::from_slice(signature)?;
+
+    Ok(secp
+        .verify_schnorr(&signature, &message, &public_key)
+        .is_ok())
+}
+
+/// Get the BIP340 x-only public key of a compressed public key
+pub fn x_only_public_key(public_key: &[u8; 33]) -> Result<[u8; 32]> {
+    let public_key = PublicKey::from_slice(public_key)?;
+    Ok(public_key.x_only_public_key().0.serialize())
+}
+
+/// Sign a message with the given signature scheme
+pub fn sign_message_with_scheme(
+    message: &[u8; 32],
+    private_key: &[u8; 32],
+    scheme: SignatureScheme,
+) -> Result<[u8; 64]> {
+    match scheme {
+        SignatureScheme::Ecdsa => sign_message(message, private_key),
+        SignatureScheme::Schnorr => sign_message_schnorr(message, private_key),
+    }
+}
+
+/// Verify a signature made with the given signature scheme
+pub fn verify_signature_with_scheme(
+    message: &[u8; 32],
+    signature: &[u8; 64],
+    public_key: &[u8; 33],
+    scheme: SignatureScheme,
+) -> Result<bool> {
+    match scheme {
+        SignatureScheme::Ecdsa => verify_signature(message, signature, public_key),
+        SignatureScheme::Schnorr => {
+            verify_signature_schnorr(message, signature, &x_only_public_key(public_key)?)
+        }
+    }
+}
+
 /// Create a new account manager with BIP39 mnemonic
 pub fn create_account_manager_with_mnemonic(mnemonic: &str) -> Result<AccountManager> {
     // Generate the first account using m/44'/555555'/0'/0/0 derivation path
@@ -81,6 +142,9 @@ pub fn create_account_manager_with_mnemonic(mnemonic: &str) -> Result<AccountMan
     Ok(AccountManager {
         account_map: vec![account.clone()],
         now_selected_account: account,
+        multisig_accounts: Vec::new(),
+        account_metadata: Default::default(),
+        network: None,
     })
 }
 
@@ -101,10 +165,10 @@ pub fn add_account_from_mnemonic(
 /// Import account from private key
 pub fn import_account_from_private_key(private_key_hex: &str) -> Result<AccountKeyPair> {
     let private_key_bytes = hex::decode(private_key_hex.trim_start_matches("0x"))
-        .map_err(|_| anyhow!("Invalid private key format"))?;
+        .map_err(|_| WalletError::invalid_input("Invalid private key format"))?;
 
     if private_key_bytes.len() != 32 {
-        return Err(anyhow!("Private key must be 32 bytes"));
+        return Err(WalletError::invalid_input("Private key must be 32 bytes").into());
     }
 
     let secp = Secp256k1::new();
@@ -128,23 +192,51 @@ pub fn validate_mnemonic(mnemonic: &str) -> Result<bool> {
     }
 }
 
+/// Parse an address (20 bytes as hex, `0x` optional) into its canonical lowercase `0x` form
+pub fn parse_address(address: &str) -> Result<String> {
+    let trimmed = address.trim();
+    let digits = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(WalletError::invalid_input(format!("Invalid address: {}", address)).into());
+    }
+    Ok(format!("0x{}", digits.to_ascii_lowercase()))
+}
+
 /// Get account info for display
 pub fn get_account_info(
     account: &AccountKeyPair,
     index: usize,
     is_selected: bool,
+    metadata: crate::types::AccountMetadata,
 ) -> crate::types::AccountInfo {
     crate::types::AccountInfo {
         address: account.get_address(),
         public_key: account.get_public_key_hex(),
         index,
         is_selected,
+        metadata,
     }
 }
 
 /// Sign transaction data
 pub fn sign_transaction_data(data: &[u8], private_key: &[u8; 32]) -> Result<[u8; 64]> {
-    // Hash the transaction data first
+    sign_transaction_data_with_scheme(data, private_key, SignatureScheme::Ecdsa)
+}
+
+/// Sign transaction data with the given signature scheme
+pub fn sign_transaction_data_with_scheme(
+    data: &[u8],
+    private_key: &[u8; 32],
+    scheme: SignatureScheme,
+) -> Result<[u8; 64]> {
+    sign_message_with_scheme(&hash_transaction_data(data), private_key, scheme)
+}
+
+/// Hash transaction data into the 32-byte message that gets signed
+pub fn hash_transaction_data(data: &[u8]) -> [u8; 32] {
     use sha2::{Digest, Sha256};
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -152,8 +244,7 @@ pub fn sign_transaction_data(data: &[u8], private_key: &[u8; 32]) -> Result<[u8;
 
     let mut message = [0u8; 32];
     message.copy_from_slice(&hash);
-
-    sign_message(&message, private_key)
+    message
 }
 
 #[cfg(test)]
@@ -194,6 +285,43 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_schnorr_sign_verify() {
+        let keypair = generate_keypair().unwrap();
+        let other = generate_keypair().unwrap();
+        let message = [7u8; 32];
+
+        let signature =
+            sign_message_with_scheme(&message, &keypair.private_key, SignatureScheme::Schnorr)
+                .unwrap();
+        let x_only = x_only_public_key(&keypair.public_key.0).unwrap();
+        assert_eq!(&x_only[..], &keypair.public_key.0[1..]);
+
+        assert!(verify_signature_schnorr(&message, &signature, &x_only).unwrap());
+        assert!(verify_signature_with_scheme(
+            &message,
+            &signature,
+            &keypair.public_key.0,
+            SignatureScheme::Schnorr
+        )
+        .unwrap());
+        assert!(!verify_signature_with_scheme(
+            &message,
+            &signature,
+            &other.public_key.0,
+            SignatureScheme::Schnorr
+        )
+        .unwrap());
+        // A Schnorr signature must not pass as ECDSA
+        assert!(!verify_signature_with_scheme(
+            &message,
+            &signature,
+            &keypair.public_key.0,
+            SignatureScheme::Ecdsa
+        )
+        .unwrap_or(false));
+    }
+
     #[test]
     fn test_account_manager_creation() {
         // Test with a simpler approach - just test that we can generate a keypair
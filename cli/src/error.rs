@@ -0,0 +1,154 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Error category, reported in JSON output and as the process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Anything not covered below
+    General,
+    /// Invalid command line usage
+    Usage,
+    /// Wallet, account, file or other item does not exist
+    NotFound,
+    /// Item already exists and would be overwritten
+    AlreadyExists,
+    /// Malformed hex, mnemonic, key, file contents or argument value
+    InvalidInput,
+    /// Key or signature operation failed
+    Crypto,
+    /// Reading or writing a file failed
+    Io,
+    /// Invalid settings or configuration file
+    Config,
+    /// The node could not be reached or rejected a request
+    Network,
+}
+
+impl ErrorKind {
+    /// Process exit code for this category
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::AlreadyExists => 4,
+            ErrorKind::InvalidInput => 5,
+            ErrorKind::Crypto => 6,
+            ErrorKind::Io => 7,
+            ErrorKind::Config => 8,
+            ErrorKind::Network => 9,
+        }
+    }
+
+    /// Categorize an error by the first recognized cause in its chain
+    pub fn of(error: &anyhow::Error) -> ErrorKind {
+        if let Some(error) = error.downcast_ref::<WalletError>() {
+            return error.kind;
+        }
+
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<WalletError>() {
+                return error.kind;
+            }
+            if let Some(error) = cause.downcast_ref::<std::io::Error>() {
+                return match error.kind() {
+                    std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+                    std::io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+                    _ => ErrorKind::Io,
+                };
+            }
+            if cause.is::<toml::de::Error>() {
+                return ErrorKind::Config;
+            }
+            if cause.is::<reqwest::Error>() {
+                return ErrorKind::Network;
+            }
+            if cause.is::<secp256k1::Error>() {
+                return ErrorKind::Crypto;
+            }
+            if cause.is::<serde_json::Error>()
+                || cause.is::<bincode::ErrorKind>()
+                || cause.is::<hex::FromHexError>()
+                || cause.is::<bip39::Error>()
+                || cause.is::<png::DecodingError>()
+            {
+                return ErrorKind::InvalidInput;
+            }
+        }
+        ErrorKind::General
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::General => "general",
+            ErrorKind::Usage => "usage",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::AlreadyExists => "already_exists",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::Crypto => "crypto",
+            ErrorKind::Io => "io",
+            ErrorKind::Config => "config",
+            ErrorKind::Network => "network",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Error with an explicit category
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct WalletError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl WalletError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        WalletError {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::AlreadyExists, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_kind_classification() {
+        let error = anyhow::Error::from(WalletError::not_found("Wallet not found"));
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+
+        let error = hex::decode("zz")
+            .context("Invalid transaction data")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidInput);
+
+        let error = anyhow::Error::from(std::fs::read("/nonexistent/rwmth").unwrap_err());
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+
+        assert_eq!(
+            ErrorKind::of(&anyhow::anyhow!("Something else")),
+            ErrorKind::General
+        );
+        assert_eq!(ErrorKind::Config.exit_code(), 8);
+        assert_eq!(ErrorKind::AlreadyExists.to_string(), "already_exists");
+    }
+}
@@ -0,0 +1,156 @@
+//! Gas limit and gas price estimation from the node
+//!
+//! The node estimates the gas a transaction uses and suggests a gas price per
+//! tier. The gas limit gets the configured margin on top of the estimate, since
+//! the state a transaction runs against can change before it is included, and
+//! both values are kept under the configured caps.
+
+use crate::config::{FeeSettings, FeeTier};
+use crate::error::WalletError;
+use crate::node::NodeClient;
+use crate::types::Transaction;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Gas prices the node suggests for each tier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasPrices {
+    pub slow: u64,
+    pub normal: u64,
+    pub fast: u64,
+}
+
+impl GasPrices {
+    pub fn get(&self, tier: FeeTier) -> u64 {
+        match tier {
+            FeeTier::Slow => self.slow,
+            FeeTier::Normal => self.normal,
+            FeeTier::Fast => self.fast,
+        }
+    }
+}
+
+/// Gas limit and price for a transaction, after margins and caps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeeEstimate {
+    pub tier: FeeTier,
+    /// Gas the node expects the transaction to use
+    pub estimated_gas: u64,
+    pub gas_limit: u64,
+    pub gas_price: u64,
+    /// Whether the node's price for the tier was above `fee.max_gas_price`
+    pub capped: bool,
+    /// Prices of every tier, for choosing another
+    pub prices: GasPrices,
+}
+
+impl FeeEstimate {
+    /// Apply the margin and caps in `settings` to the node's estimate and prices
+    pub fn new(
+        settings: &FeeSettings,
+        tier: FeeTier,
+        estimated_gas: u64,
+        prices: GasPrices,
+    ) -> Result<Self> {
+        // A gas limit below the estimate would only get the transaction rejected
+        if estimated_gas > settings.max_gas_limit {
+            return Err(WalletError::invalid_input(format!(
+                "Estimated gas {} is above the gas limit cap {} (fee.max_gas_limit)",
+                estimated_gas, settings.max_gas_limit
+            ))
+            .into());
+        }
+        let gas_limit = estimated_gas
+            .saturating_mul(100 + u64::from(settings.gas_limit_margin_percent))
+            .div_ceil(100)
+            .min(settings.max_gas_limit);
+
+        let price = prices.get(tier);
+        let gas_price = price.min(settings.max_gas_price);
+        Ok(FeeEstimate {
+            tier,
+            estimated_gas,
+            gas_limit,
+            gas_price,
+            capped: gas_price < price,
+            prices,
+        })
+    }
+
+    /// Ask the node to estimate `transaction` and for the current gas prices
+    pub async fn fetch(
+        node: &NodeClient,
+        settings: &FeeSettings,
+        tier: FeeTier,
+        transaction: &Transaction,
+    ) -> Result<Self> {
+        let estimated_gas = node.estimate_gas(transaction).await?;
+        let prices = node.gas_prices().await?;
+        Self::new(settings, tier, estimated_gas, prices)
+    }
+
+    /// Most the estimated gas limit and price can cost in fees
+    pub fn max_fee(&self) -> u64 {
+        self.gas_limit.saturating_mul(self.gas_price)
+    }
+
+    /// `transaction` with the estimated gas limit and price
+    pub fn apply(&self, transaction: Transaction) -> Transaction {
+        Transaction {
+            gas_limit: self.gas_limit,
+            gas_price: self.gas_price,
+            ..transaction
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_fee_estimate_margin_and_caps() {
+        let prices = GasPrices {
+            slow: 1,
+            normal: 3,
+            fast: 8,
+        };
+        let settings = FeeSettings {
+            gas_limit_margin_percent: 20,
+            max_gas_limit: 100_000,
+            max_gas_price: 5,
+            ..FeeSettings::default()
+        };
+
+        let normal = FeeEstimate::new(&settings, FeeTier::Normal, 21_001, prices).unwrap();
+        // 20% on top, rounded up
+        assert_eq!(normal.gas_limit, 25_202);
+        assert_eq!(normal.gas_price, 3);
+        assert!(!normal.capped);
+        assert_eq!(normal.max_fee(), 75_606);
+
+        let fast = FeeEstimate::new(&settings, FeeTier::Fast, 90_000, prices).unwrap();
+        // The margin stops at the cap, and the price is capped
+        assert_eq!(fast.gas_limit, 100_000);
+        assert_eq!(fast.gas_price, 5);
+        assert!(fast.capped);
+
+        let transaction = Transaction {
+            chain_id: 1,
+            from: "0x1111111111111111111111111111111111111111".to_string(),
+            to: None,
+            amount: Some(1000),
+            data: None,
+            nonce: 0,
+            gas_limit: 0,
+            gas_price: 0,
+        };
+        let transaction = normal.apply(transaction);
+        assert_eq!(transaction.gas_limit, 25_202);
+        assert_eq!(transaction.total_cost(), 76_606);
+
+        let error = FeeEstimate::new(&settings, FeeTier::Slow, 100_001, prices).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidInput);
+    }
+}
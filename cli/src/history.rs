@@ -0,0 +1,480 @@
+//! Local history of the transactions built, signed and submitted by the wallet
+//!
+//! Records are keyed by the signing hash, which is known as soon as a
+//! transaction is built; the transaction hash is added when it is finalized.
+
+use crate::crypto::parse_address;
+use crate::error::WalletError;
+use crate::node::NodeClient;
+use crate::pst::{PartiallySignedTransaction, SignedTransaction};
+use crate::storage;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// File name of the transaction history inside the wallet directory
+pub const HISTORY_FILE: &str = "history.json";
+
+/// Records returned per page unless a limit is given
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Where a transaction is in its lifecycle, in the order it gets there
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    /// Built but missing signatures
+    Built,
+    /// Signed by enough keys to be finalized
+    Signed,
+    /// Sent to a node and not yet settled
+    Submitted,
+    /// Included on chain
+    Confirmed,
+    /// Rejected or reverted by the node
+    Failed,
+}
+
+impl TxStatus {
+    /// Whether the node has settled the transaction for good
+    pub fn is_final(&self) -> bool {
+        matches!(self, TxStatus::Confirmed | TxStatus::Failed)
+    }
+}
+
+impl fmt::Display for TxStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxStatus::Built => write!(f, "built"),
+            TxStatus::Signed => write!(f, "signed"),
+            TxStatus::Submitted => write!(f, "submitted"),
+            TxStatus::Confirmed => write!(f, "confirmed"),
+            TxStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl FromStr for TxStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "built" => Ok(TxStatus::Built),
+            "signed" => Ok(TxStatus::Signed),
+            "submitted" => Ok(TxStatus::Submitted),
+            "confirmed" => Ok(TxStatus::Confirmed),
+            "failed" => Ok(TxStatus::Failed),
+            other => Err(format!("Unknown transaction status: {}", other)),
+        }
+    }
+}
+
+/// One transaction in the history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxRecord {
+    /// Signing hash, which identifies the transaction from the moment it is built
+    pub id: String,
+    /// Hash of the signed transaction, once finalized
+    pub hash: Option<String>,
+    /// Sending account
+    pub account: String,
+    /// Chain the transaction is for (0 in records from before it was kept)
+    #[serde(default)]
+    pub chain_id: u64,
+    /// Recipient, if any
+    pub counterparty: Option<String>,
+    pub amount: Option<u64>,
+    pub nonce: u64,
+    /// Gas price paid, which a replacement has to outbid (0 in records from before it was kept)
+    #[serde(default)]
+    pub gas_price: u64,
+    pub status: TxStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Raw signed transaction bytes as hex, once finalized
+    pub raw: Option<String>,
+}
+
+impl TxRecord {
+    /// Record of a partially signed transaction: signed once the threshold is met
+    pub fn from_partial(pst: &PartiallySignedTransaction) -> Self {
+        let now = Utc::now();
+        let transaction = &pst.transaction;
+        TxRecord {
+            id: pst.signing_hash.to_hex(),
+            hash: None,
+            account: transaction.from.clone(),
+            chain_id: transaction.chain_id,
+            counterparty: transaction.to.clone(),
+            amount: transaction.amount,
+            nonce: transaction.nonce,
+            gas_price: transaction.gas_price,
+            status: if pst.is_complete() {
+                TxStatus::Signed
+            } else {
+                TxStatus::Built
+            },
+            created_at: now,
+            updated_at: now,
+            raw: None,
+        }
+    }
+
+    /// Record of a finalized transaction, with its hash and raw bytes
+    pub fn from_signed(signed: &SignedTransaction) -> Result<Self> {
+        let now = Utc::now();
+        let transaction = &signed.transaction;
+        Ok(TxRecord {
+            id: format!("0x{}", hex::encode(transaction.signing_hash()?)),
+            hash: Some(format!("0x{}", hex::encode(signed.hash()?))),
+            account: transaction.from.clone(),
+            chain_id: transaction.chain_id,
+            counterparty: transaction.to.clone(),
+            amount: transaction.amount,
+            nonce: transaction.nonce,
+            gas_price: transaction.gas_price,
+            status: TxStatus::Signed,
+            created_at: now,
+            updated_at: now,
+            raw: Some(format!("0x{}", hex::encode(signed.to_bytes()?))),
+        })
+    }
+}
+
+/// Which records to return; every field that is set must match
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryFilter {
+    pub account: Option<String>,
+    pub counterparty: Option<String>,
+    pub status: Option<TxStatus>,
+    /// Only records created at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only records created before this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &TxRecord) -> bool {
+        let same = |filter: &Option<String>, value: Option<&String>| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| value.is_some_and(|value| value.eq_ignore_ascii_case(filter)))
+        };
+        same(&self.account, Some(&record.account))
+            && same(&self.counterparty, record.counterparty.as_ref())
+            && self.status.is_none_or(|status| record.status == status)
+            && self.since.is_none_or(|since| record.created_at >= since)
+            && self.until.is_none_or(|until| record.created_at < until)
+    }
+}
+
+/// One page of matching records, newest first
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HistoryPage {
+    /// Number of records matching the filter
+    pub total: usize,
+    pub offset: usize,
+    pub records: Vec<TxRecord>,
+}
+
+/// Transactions of every wallet in a wallet directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct History {
+    #[serde(default)]
+    pub records: Vec<TxRecord>,
+}
+
+impl History {
+    /// Path of the history in `wallet_dir`
+    pub fn path<P: AsRef<Path>>(wallet_dir: P) -> PathBuf {
+        wallet_dir.as_ref().join(HISTORY_FILE)
+    }
+
+    /// Load the history, or an empty one if the file doesn't exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(History::default());
+        }
+        // Raw signed transactions in here get rebroadcast, so it gets the wallet file checks
+        storage::check_private_file(path)?;
+        let mut history: History = serde_json::from_str(&fs::read_to_string(path)?)?;
+        // The desktop app writes addresses without the 0x prefix
+        for record in &mut history.records {
+            record.account = parse_address(&record.account)?;
+            if let Some(counterparty) = &record.counterparty {
+                record.counterparty = Some(parse_address(counterparty)?);
+            }
+        }
+        Ok(history)
+    }
+
+    /// Save the history, creating its directory if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            storage::create_private_dir(parent)?;
+        }
+        storage::write_private_file(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Find a record by signing hash or transaction hash
+    pub fn get(&self, id_or_hash: &str) -> Option<&TxRecord> {
+        self.position(id_or_hash).map(|index| &self.records[index])
+    }
+
+    fn position(&self, id_or_hash: &str) -> Option<usize> {
+        self.records.iter().position(|record| {
+            record.id.eq_ignore_ascii_case(id_or_hash)
+                || record
+                    .hash
+                    .as_ref()
+                    .is_some_and(|hash| hash.eq_ignore_ascii_case(id_or_hash))
+        })
+    }
+
+    /// Add a record, or move the one with the same signing hash forward.
+    /// A status never goes back, so signing a submitted transaction again
+    /// doesn't make it look unsent.
+    pub fn record(&mut self, record: TxRecord) -> &TxRecord {
+        let index = match self
+            .records
+            .iter()
+            .position(|existing| existing.id == record.id)
+        {
+            Some(index) => {
+                let existing = &mut self.records[index];
+                existing.status = existing.status.max(record.status);
+                existing.hash = record.hash.or(existing.hash.take());
+                existing.raw = record.raw.or(existing.raw.take());
+                existing.updated_at = record.updated_at;
+                index
+            }
+            None => {
+                self.records.push(record);
+                self.records.len() - 1
+            }
+        };
+        &self.records[index]
+    }
+
+    /// Remove a transaction that was never submitted, such as an abandoned draft.
+    /// Submitted ones stay: the node may still include them, so they have to be
+    /// replaced or cancelled instead.
+    pub fn remove(&mut self, id_or_hash: &str) -> Result<TxRecord> {
+        let index = self.position(id_or_hash).ok_or_else(|| {
+            WalletError::not_found(format!("Transaction not found in history: {}", id_or_hash))
+        })?;
+        let record = &self.records[index];
+        if record.status >= TxStatus::Submitted {
+            return Err(WalletError::invalid_input(format!(
+                "Transaction {} is already {} and can't be dropped",
+                id_or_hash, record.status
+            ))
+            .into());
+        }
+        Ok(self.records.remove(index))
+    }
+
+    /// Set the status the node reports for the transaction with hash `hash`,
+    /// returning whether anything changed
+    pub fn settle(&mut self, hash: &str, status: TxStatus) -> bool {
+        let Some(record) = self.records.iter_mut().find(|record| {
+            record
+                .hash
+                .as_ref()
+                .is_some_and(|known| known.eq_ignore_ascii_case(hash))
+        }) else {
+            return false;
+        };
+        if record.status == status || record.status.is_final() {
+            return false;
+        }
+        record.status = status;
+        record.updated_at = Utc::now();
+        true
+    }
+
+    /// Hashes of finalized transactions the node hasn't settled yet
+    pub fn unsettled(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .filter(|record| !record.status.is_final())
+            .filter_map(|record| record.hash.clone())
+            .collect()
+    }
+
+    /// Ask `node` for the status of every unsettled transaction, returning how many changed
+    pub async fn reconcile(&mut self, node: &NodeClient) -> Result<usize> {
+        let mut updated = 0;
+        for hash in self.unsettled() {
+            let status = node.transaction_status(&hash).await?;
+            if status.is_some_and(|status| self.settle(&hash, status)) {
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Records matching `filter`, newest first, skipping `offset` and returning at most `limit`
+    pub fn query(&self, filter: &HistoryFilter, offset: usize, limit: usize) -> HistoryPage {
+        let mut records: Vec<&TxRecord> = self
+            .records
+            .iter()
+            .filter(|record| filter.matches(record))
+            .collect();
+        records.sort_by_key(|record| Reverse(record.created_at));
+        HistoryPage {
+            total: records.len(),
+            offset,
+            records: records
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Record `record` in the history file at `path`
+pub fn record<P: AsRef<Path>>(path: P, record: TxRecord) -> Result<TxRecord> {
+    let path = path.as_ref();
+    let mut history = History::load(path)?;
+    let record = history.record(record).clone();
+    history.save(path)?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_keypair;
+    use crate::error::ErrorKind;
+    use crate::types::{SignatureScheme, Transaction};
+    use tempfile::TempDir;
+
+    fn pst(nonce: u64, to: &str) -> (PartiallySignedTransaction, crate::types::AccountKeyPair) {
+        let keypair = generate_keypair().unwrap();
+        let transaction = Transaction {
+            chain_id: 1,
+            from: keypair.get_address(),
+            to: Some(to.to_string()),
+            amount: Some(5),
+            data: None,
+            nonce,
+            gas_limit: 21000,
+            gas_price: 1,
+        };
+        let pst = PartiallySignedTransaction::for_account(transaction, &keypair).unwrap();
+        (pst, keypair)
+    }
+
+    #[test]
+    fn test_record_moves_status_forward() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = History::path(temp_dir.path());
+        let (mut pst, keypair) = pst(0, "0x1111111111111111111111111111111111111111");
+
+        let built = record(&path, TxRecord::from_partial(&pst)).unwrap();
+        assert_eq!(built.status, TxStatus::Built);
+
+        pst.sign(&keypair, SignatureScheme::Ecdsa).unwrap();
+        let signed = pst.finalize().unwrap();
+        let mut submitted = TxRecord::from_signed(&signed).unwrap();
+        submitted.status = TxStatus::Submitted;
+        let submitted = record(&path, submitted).unwrap();
+        assert_eq!(submitted.id, built.id);
+        assert_eq!(submitted.created_at, built.created_at);
+        assert!(submitted.raw.is_some());
+
+        // Signing the same transaction again keeps it submitted with its hash
+        let again = record(&path, TxRecord::from_partial(&pst)).unwrap();
+        assert_eq!(again.status, TxStatus::Submitted);
+        assert_eq!(again.hash, submitted.hash);
+
+        let mut history = History::load(&path).unwrap();
+        assert_eq!(history.records.len(), 1);
+        let hash = submitted.hash.unwrap();
+        assert_eq!(history.unsettled(), vec![hash.clone()]);
+        assert!(history.settle(&hash, TxStatus::Confirmed));
+        assert!(history.unsettled().is_empty());
+        // Settled transactions stay settled
+        assert!(!history.settle(&hash, TxStatus::Submitted));
+        assert_eq!(history.get(&hash).unwrap().status, TxStatus::Confirmed);
+    }
+
+    #[test]
+    fn test_only_unsent_transactions_can_be_removed() {
+        let to = "0x1111111111111111111111111111111111111111";
+        let mut history = History::default();
+        let draft = history
+            .record(TxRecord::from_partial(&pst(0, to).0))
+            .clone();
+        let (mut sent, keypair) = pst(1, to);
+        sent.sign(&keypair, SignatureScheme::Ecdsa).unwrap();
+        let mut submitted = TxRecord::from_signed(&sent.finalize().unwrap()).unwrap();
+        submitted.status = TxStatus::Submitted;
+        let submitted = history.record(submitted).clone();
+
+        assert_eq!(history.remove(&draft.id).unwrap(), draft);
+        assert!(history.get(&draft.id).is_none());
+        let error = history.remove(&draft.id).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+
+        // The node may still include a submitted transaction
+        let error = history
+            .remove(submitted.hash.as_deref().unwrap())
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidInput);
+        assert_eq!(history.records, vec![submitted]);
+    }
+
+    #[test]
+    fn test_query_filters_and_pages() {
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        let mut history = History::default();
+        for nonce in 0..5 {
+            let (pst, _) = pst(nonce, if nonce % 2 == 0 { alice } else { bob });
+            let mut record = TxRecord::from_partial(&pst);
+            record.created_at += chrono::Duration::seconds(nonce as i64);
+            history.record(record);
+        }
+
+        let page = history.query(&HistoryFilter::default(), 1, 2);
+        assert_eq!(page.total, 5);
+        assert_eq!(
+            page.records.iter().map(|r| r.nonce).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+
+        let to_alice = HistoryFilter {
+            counterparty: Some(alice.to_uppercase().replace("0X", "0x")),
+            ..HistoryFilter::default()
+        };
+        let page = history.query(&to_alice, 0, DEFAULT_PAGE_SIZE);
+        assert_eq!(page.total, 3);
+        assert!(page
+            .records
+            .iter()
+            .all(|r| r.counterparty.as_deref() == Some(alice)));
+
+        let signed = HistoryFilter {
+            status: Some(TxStatus::Signed),
+            ..HistoryFilter::default()
+        };
+        assert_eq!(history.query(&signed, 0, DEFAULT_PAGE_SIZE).total, 0);
+
+        let since = HistoryFilter {
+            since: Some(history.records[3].created_at),
+            ..HistoryFilter::default()
+        };
+        assert_eq!(history.query(&since, 0, DEFAULT_PAGE_SIZE).total, 2);
+    }
+}
@@ -1,4 +1,17 @@
+pub mod address_book;
+pub mod archive;
+pub mod config;
 pub mod crypto;
+pub mod error;
+pub mod fee;
+pub mod history;
+pub mod multisig;
+pub mod node;
+pub mod nonce;
+pub mod pst;
+pub mod qr;
+pub mod secret;
+pub mod sled_store;
 pub mod storage;
 pub mod types;
 pub mod wallet;
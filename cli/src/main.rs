@@ -1,6 +1,25 @@
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use rwmth::address_book::{AddressBook, Contact};
+use rwmth::archive::{self, BackupContents, RestoreMode};
+use rwmth::config::{
+    default_config_path, FeeTier, LogLevel, Network, Networks, Settings, StorageBackend,
+};
+use rwmth::crypto::parse_address;
+use rwmth::error::{ErrorKind, WalletError};
+use rwmth::fee::FeeEstimate;
+use rwmth::history::{self, History, HistoryFilter, TxRecord, TxStatus, DEFAULT_PAGE_SIZE};
+use rwmth::node::NodeClient;
+use rwmth::nonce::{build_replacement, NonceManager, STUCK_AFTER_MINUTES};
+use rwmth::pst::{PartiallySignedTransaction, PstFormat};
+use rwmth::qr::{self, QrAssembler};
+use rwmth::secret::{self, SecretSource};
+use rwmth::storage::{self, WalletName, Wallets};
+use rwmth::types::{AccountMetadata, SignatureScheme, Transaction};
 use rwmth::wallet::WalletManager;
-use tracing::info;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
 
 #[derive(Parser)]
 #[command(name = "rwmth")]
@@ -12,260 +31,2505 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Path to account file
-    #[arg(short, long, default_value = "./wallets/default.json")]
-    account_file: String,
+    /// Wallet name, stored as <wallet-dir>/<name>.json [default: wallet.default_wallet setting]
+    #[arg(short, long, global = true, visible_alias = "name", short_alias = 'n')]
+    wallet: Option<WalletName>,
+
+    /// Directory holding wallet files [default: $XDG_CONFIG_HOME/rwmth or ~/.config/rwmth]
+    #[arg(long, global = true)]
+    wallet_dir: Option<PathBuf>,
+
+    /// Path to account file (overrides --wallet and --wallet-dir)
+    #[arg(short, long, global = true)]
+    account_file: Option<PathBuf>,
+
+    /// Configuration file [default: <wallet app dir>/config.toml, or RWMTH_CONFIG]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Node URL (overrides node.url and the network's node URL)
+    #[arg(long, global = true)]
+    node_url: Option<String>,
+
+    /// Network: mainnet, testnet or devnet (overrides the wallet's network and node.network)
+    #[arg(long, global = true)]
+    network: Option<Network>,
+
+    /// Log level: error, warn, info, debug or trace (overrides log.level)
+    #[arg(long, global = true)]
+    log_level: Option<LogLevel>,
+
+    /// Output format: text or json
+    #[arg(long, global = true, default_value = "text")]
+    output: OutputFormat,
+}
+
+/// Where to read a mnemonic from; prompts on the terminal when none is given
+#[derive(Args)]
+#[group(multiple = false)]
+struct MnemonicArgs {
+    /// BIP39 mnemonic phrase (visible in shell history and process lists; prefer the options below)
+    mnemonic: Option<String>,
+
+    /// Read the mnemonic from stdin
+    #[arg(long)]
+    mnemonic_stdin: bool,
+
+    /// Read the mnemonic from a file
+    #[arg(long, value_name = "PATH")]
+    mnemonic_file: Option<PathBuf>,
+
+    /// Read the mnemonic from the named environment variable
+    #[arg(long, value_name = "VAR")]
+    mnemonic_env: Option<String>,
+}
+
+impl MnemonicArgs {
+    fn read(self) -> anyhow::Result<String> {
+        let source = if let Some(mnemonic) = self.mnemonic {
+            SecretSource::Value(mnemonic)
+        } else if self.mnemonic_stdin {
+            SecretSource::Stdin
+        } else if let Some(path) = self.mnemonic_file {
+            SecretSource::File(path)
+        } else if let Some(name) = self.mnemonic_env {
+            SecretSource::Env(name)
+        } else {
+            SecretSource::Prompt("Mnemonic phrase: ".to_string())
+        };
+        secret::read_mnemonic(&source)
+    }
+}
+
+/// Where to read a backup archive password from (prompted for when none is given)
+#[derive(Args)]
+#[group(multiple = false)]
+struct PasswordArgs {
+    /// Read the archive password from stdin
+    #[arg(long)]
+    password_stdin: bool,
+
+    /// Read the archive password from a file
+    #[arg(long, value_name = "PATH")]
+    password_file: Option<PathBuf>,
+
+    /// Read the archive password from the named environment variable
+    #[arg(long, value_name = "VAR")]
+    password_env: Option<String>,
+}
+
+impl PasswordArgs {
+    /// Read the password, asking twice when prompting for a new one
+    fn read(self, confirm: bool) -> anyhow::Result<String> {
+        let source = if self.password_stdin {
+            SecretSource::Stdin
+        } else if let Some(path) = self.password_file {
+            SecretSource::File(path)
+        } else if let Some(name) = self.password_env {
+            SecretSource::Env(name)
+        } else {
+            SecretSource::Prompt("Archive password: ".to_string())
+        };
+
+        let password = source.read()?;
+        if confirm && matches!(source, SecretSource::Prompt(_)) {
+            let repeated = SecretSource::Prompt("Repeat password: ".to_string()).read()?;
+            if repeated != password {
+                return Err(WalletError::invalid_input("Passwords do not match").into());
+            }
+        }
+        Ok(password)
+    }
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Create a new wallet with BIP39 mnemonic
-    New {
-        /// Wallet name (optional, defaults to "default")
-        #[arg(short, long, default_value = "default")]
-        name: String,
-    },
+    New,
 
     /// Import wallet from mnemonic phrase
     Import {
-        /// BIP39 mnemonic phrase (24 words)
-        mnemonic: String,
-
-        /// Wallet name (optional, defaults to "default")
-        #[arg(short, long, default_value = "default")]
-        name: String,
+        #[command(flatten)]
+        mnemonic: MnemonicArgs,
     },
 
     /// List all accounts
-    List,
+    List {
+        /// Include hidden and archived accounts
+        #[arg(long)]
+        all: bool,
+    },
 
     /// List all wallets
     ListWallets,
 
     /// Add new account using mnemonic
     Add {
-        /// BIP39 mnemonic phrase
-        mnemonic: String,
+        #[command(flatten)]
+        mnemonic: MnemonicArgs,
     },
 
     /// Show selected account
     Selected,
 
+    /// Select the account used for signing
+    Select {
+        /// Account index (as shown by `list`) or address
+        account: String,
+    },
+
+    /// Show an account
+    Show {
+        /// Account index (as shown by `list`) or address
+        account: String,
+    },
+
+    /// Edit the label, notes, tags, flags or display order of an account
+    Edit {
+        /// Account index (as shown by `list`) or address
+        account: String,
+
+        /// Display name ("" to clear)
+        #[arg(long)]
+        label: Option<String>,
+
+        /// Free-text notes ("" to clear)
+        #[arg(long)]
+        notes: Option<String>,
+
+        /// Add a tag (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Remove a tag (repeatable)
+        #[arg(long = "untag", value_name = "TAG")]
+        untags: Vec<String>,
+
+        /// Hide the account from `list`
+        #[arg(long)]
+        hidden: Option<bool>,
+
+        /// Mark the account archived, hiding it from `list`
+        #[arg(long)]
+        archived: Option<bool>,
+
+        /// Display position in `list`
+        #[arg(long, conflicts_with = "no_order")]
+        order: Option<u32>,
+
+        /// Go back to ordering the account by its index
+        #[arg(long)]
+        no_order: bool,
+    },
+
+    /// Remove an account from the wallet
+    Remove {
+        /// Account index (as shown by `list`) or address
+        account: String,
+    },
+
+    /// Write an encrypted backup archive of the wallet, the address book and settings
+    Backup {
+        /// Archive file to create
+        file: PathBuf,
+
+        /// Back up every wallet in the wallet directory, not only the selected one
+        #[arg(long)]
+        all: bool,
+
+        /// Leave the settings out of the archive
+        #[arg(long)]
+        no_settings: bool,
+
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+
+    /// Restore wallets, the address book and settings from a backup archive
+    Restore {
+        /// Archive file to read
+        file: PathBuf,
+
+        /// Only show what the archive contains and which wallets already exist
+        #[arg(long)]
+        preview: bool,
+
+        /// Overwrite existing wallets, address book and settings instead of merging,
+        /// keeping the previous files as .bak files
+        #[arg(long, alias = "force", conflicts_with = "preview")]
+        replace: bool,
+
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+
+    /// Copy every wallet in the wallet directory to another storage backend
+    Migrate {
+        /// Backend to copy the wallets to: json or sled
+        #[arg(long)]
+        to: StorageBackend,
+
+        /// Backend to copy the wallets from [default: wallet.backend setting]
+        #[arg(long)]
+        from: Option<StorageBackend>,
+    },
+
     /// Export private key of selected account
-    Export,
+    Export {
+        /// Write the key to a new file readable only by you
+        #[arg(short, long, required_unless_present = "stdout")]
+        out: Option<PathBuf>,
+
+        /// Print the key instead, where it can end up in scrollback or logs
+        #[arg(long, conflicts_with = "out")]
+        stdout: bool,
+    },
 
     /// Sign transaction data
     Sign {
         /// Transaction data (hex format)
         data: String,
+
+        /// Signature scheme (ecdsa or schnorr)
+        #[arg(short, long, default_value = "ecdsa")]
+        scheme: SignatureScheme,
     },
 
     /// Show wallet information
     Info,
+
+    /// Manage M-of-N multisig accounts
+    Multisig {
+        #[command(subcommand)]
+        command: MultisigCommands,
+    },
+
+    /// Create, review and co-sign partially signed transaction files
+    Tx {
+        #[command(subcommand)]
+        command: TxCommands,
+    },
+
+    /// Show the on-chain nonce, pending nonces, gaps and stuck transactions of an account
+    Nonce {
+        /// Account index or address [default: selected account]
+        account: Option<String>,
+    },
+
+    /// List the transactions built, signed or submitted from this wallet directory
+    #[command(args_conflicts_with_subcommands = true)]
+    History {
+        #[command(subcommand)]
+        command: Option<HistoryCommands>,
+
+        /// Only transactions sent from this account (index or address)
+        #[arg(long)]
+        account: Option<String>,
+
+        /// Only transactions to this address or contact
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Only transactions with this status: built, signed, submitted, confirmed or failed
+        #[arg(long)]
+        status: Option<TxStatus>,
+
+        /// Only transactions created at or after this time (RFC 3339)
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only transactions created before this time (RFC 3339)
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
+
+        /// Number of transactions to show
+        #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+        limit: usize,
+
+        /// Number of matching transactions to skip
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+
+        /// Ask the node for the status of unsettled transactions first
+        #[arg(long)]
+        sync: bool,
+    },
+
+    /// Show or change persistent settings
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Move transaction files to and from an air-gapped machine as QR codes
+    Qr {
+        #[command(subcommand)]
+        command: QrCommands,
+    },
+
+    /// Manage the address book shared by the wallets in the wallet directory
+    Contact {
+        #[command(subcommand)]
+        command: ContactCommands,
+    },
+
+    /// Show the network profiles or choose the network of this wallet
+    Network {
+        #[command(subcommand)]
+        command: NetworkCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum MultisigCommands {
+    /// Create a multisig account from participant public keys
+    Create {
+        /// Multisig account name
+        name: String,
+
+        /// Number of signatures required
+        #[arg(short, long)]
+        threshold: usize,
+
+        /// Participant public key (hex), repeat for each participant
+        #[arg(short, long = "participant", required = true)]
+        participants: Vec<String>,
+    },
+
+    /// List multisig accounts
+    List,
+}
+
+#[derive(Subcommand)]
+enum TxCommands {
+    /// Create an unsigned transaction file
+    Create {
+        /// Recipient address or contact name
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Amount to transfer (in MTH)
+        #[arg(long)]
+        amount: Option<u64>,
+
+        /// Transaction data (hex format)
+        #[arg(long)]
+        data: Option<String>,
+
+        /// Nonce [default: the next free nonce from the node and pending transactions]
+        #[arg(long)]
+        nonce: Option<u64>,
+
+        /// Gas limit [default: the node's estimate plus fee.gas_limit_margin_percent,
+        /// or the replaced transaction's]
+        #[arg(long)]
+        gas_limit: Option<u64>,
+
+        /// Gas price [default: the node's price for the fee tier, or the lowest
+        /// accepted bump when replacing]
+        #[arg(long)]
+        gas_price: Option<u64>,
+
+        /// Fee tier for the estimated gas price: slow, normal or fast [default: fee.tier setting]
+        #[arg(long, conflicts_with_all = ["gas_price", "replace", "cancel"])]
+        fee: Option<FeeTier>,
+
+        /// Speed up the pending transaction with this hash: resend it with the same
+        /// nonce and a higher gas price, changing any fields given here
+        #[arg(long, value_name = "HASH", conflicts_with_all = ["nonce", "cancel"])]
+        replace: Option<String>,
+
+        /// Cancel the pending transaction with this hash by sending nothing to yourself
+        /// with the same nonce and a higher gas price
+        #[arg(long, value_name = "HASH", conflicts_with_all = ["nonce", "to", "amount", "data"])]
+        cancel: Option<String>,
+
+        /// Spend from this multisig account (name or address) instead of the selected account
+        #[arg(long)]
+        multisig: Option<String>,
+
+        /// Transaction file to write
+        #[arg(short, long)]
+        out: String,
+
+        /// File format (json or binary)
+        #[arg(short, long, default_value = "json")]
+        format: PstFormat,
+    },
+
+    /// Ask the node what a transaction would cost at each fee tier
+    Estimate {
+        /// Recipient address or contact name
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Amount to transfer (in MTH)
+        #[arg(long)]
+        amount: Option<u64>,
+
+        /// Transaction data (hex format)
+        #[arg(long)]
+        data: Option<String>,
+
+        /// Fee tier to highlight: slow, normal or fast [default: fee.tier setting]
+        #[arg(long)]
+        fee: Option<FeeTier>,
+
+        /// Spend from this multisig account (name or address) instead of the selected account
+        #[arg(long)]
+        multisig: Option<String>,
+    },
+
+    /// Show the decoded transaction, signers and collected signatures
+    Inspect {
+        /// Transaction file (json or binary)
+        file: String,
+    },
+
+    /// Add the selected account's signature to a transaction file
+    Sign {
+        /// Transaction file (json or binary)
+        file: String,
+
+        /// Signature scheme (ecdsa or schnorr)
+        #[arg(short, long, default_value = "ecdsa")]
+        scheme: SignatureScheme,
+
+        /// Write the result here instead of updating the file in place
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// File format of the result (json or binary)
+        #[arg(short, long, default_value = "json")]
+        format: PstFormat,
+    },
+
+    /// Merge signatures from several copies of the same transaction file
+    Combine {
+        /// Transaction files to merge
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<String>,
+
+        /// Transaction file to write
+        #[arg(short, long)]
+        out: String,
+
+        /// File format of the result (json or binary)
+        #[arg(short, long, default_value = "json")]
+        format: PstFormat,
+    },
+
+    /// Check the threshold is met and print the raw signed transaction
+    Finalize {
+        /// Transaction file (json or binary)
+        file: String,
+
+        /// Write the raw signed transaction bytes here instead of printing hex
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Broadcast the signed transaction to the node
+        #[arg(long)]
+        submit: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective settings after applying file, environment and flags
+    Show,
+
+    /// Print the path of the configuration file
+    Path,
+
+    /// Set a value in the configuration file, e.g. `node.url https://...`
+    Set {
+        /// Dotted setting key
+        key: String,
+
+        /// New value
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum QrCommands {
+    /// Show a transaction file as (animated, multi-part) QR codes
+    Export {
+        /// Transaction file (json or binary)
+        file: String,
+
+        /// Write one PNG per frame into this directory instead of showing them
+        #[arg(short, long)]
+        out_dir: Option<String>,
+
+        /// Payload bytes per frame
+        #[arg(long, default_value_t = qr::DEFAULT_CHUNK_SIZE)]
+        chunk_size: usize,
+
+        /// Milliseconds each frame is shown
+        #[arg(long, default_value_t = 800)]
+        interval: u64,
+
+        /// Print every frame once instead of looping
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Rebuild a transaction file from scanned QR codes
+    Import {
+        /// PNG images of QR frames, or text files with one scanned part per line ("-" for stdin)
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Transaction file to write
+        #[arg(short, long)]
+        out: String,
+
+        /// File format (json or binary)
+        #[arg(short, long, default_value = "json")]
+        format: PstFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommands {
+    /// Remove a transaction that was never submitted, such as an abandoned draft
+    Drop {
+        /// Signing hash or transaction hash
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NetworkCommands {
+    /// List the network profiles with their chain ids, marking the active one
+    List,
+
+    /// Use this wallet on a network whatever node.network says (--network still overrides it)
+    Use {
+        /// Network: mainnet, testnet or devnet
+        #[arg(required_unless_present = "clear")]
+        network: Option<Network>,
+
+        /// Go back to node.network
+        #[arg(long, conflicts_with = "network")]
+        clear: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContactCommands {
+    /// Add a contact
+    Add {
+        /// Unique contact name
+        name: String,
+
+        /// Contact address
+        address: String,
+
+        /// Note shown next to the contact
+        #[arg(long)]
+        memo: Option<String>,
+
+        /// Network of the address [default: the current network]
+        #[arg(long)]
+        network: Option<Network>,
+    },
+
+    /// List contacts on the current network
+    List {
+        /// Include contacts on every network
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Show a contact
+    Show {
+        /// Contact name
+        name: String,
+    },
+
+    /// Change a contact
+    Edit {
+        /// Contact name
+        name: String,
+
+        /// New name
+        #[arg(long)]
+        rename: Option<String>,
+
+        /// New address
+        #[arg(long)]
+        address: Option<String>,
+
+        /// New memo ("" to clear)
+        #[arg(long)]
+        memo: Option<String>,
+
+        /// New network
+        #[arg(long)]
+        network: Option<Network>,
+    },
+
+    /// Remove a contact
+    Remove {
+        /// Contact name
+        name: String,
+    },
+}
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human readable prose
+    #[default]
+    Text,
+    /// One JSON document per invocation on stdout
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!("Unknown output format: {}", other)),
+        }
+    }
+}
+
+/// Version of the JSON output documents, bumped on incompatible changes
+const OUTPUT_SCHEMA_VERSION: u32 = 1;
+
+/// Prints command results in the selected format
+struct Output {
+    format: OutputFormat,
+    /// Command name, unknown when the arguments could not be parsed
+    command: Option<&'static str>,
+}
+
+impl Output {
+    fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// Print `data` as the JSON result document, or run `text` to print prose
+    fn print(&self, data: Value, text: impl FnOnce()) -> anyhow::Result<()> {
+        match self.format {
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&json!({
+                    "version": OUTPUT_SCHEMA_VERSION,
+                    "ok": true,
+                    "command": self.command,
+                    "data": data,
+                }))?
+            ),
+            OutputFormat::Text => text(),
+        }
+        Ok(())
+    }
+
+    /// Print an error and return the exit code for its category
+    fn error(&self, error: &anyhow::Error) -> i32 {
+        let kind = ErrorKind::of(error);
+        match self.format {
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&json!({
+                    "version": OUTPUT_SCHEMA_VERSION,
+                    "ok": false,
+                    "command": self.command,
+                    "error": {
+                        "kind": kind,
+                        "code": kind.exit_code(),
+                        "message": format!("{:#}", error),
+                    },
+                }))
+                .unwrap_or_default()
+            ),
+            OutputFormat::Text => eprintln!("Error: {:#}", error),
+        }
+        kind.exit_code()
+    }
+}
+
+impl Commands {
+    /// Stable command name used in JSON output
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::New => "new",
+            Commands::Import { .. } => "import",
+            Commands::List { .. } => "list",
+            Commands::ListWallets => "list-wallets",
+            Commands::Add { .. } => "add",
+            Commands::Selected => "selected",
+            Commands::Select { .. } => "select",
+            Commands::Show { .. } => "show",
+            Commands::Edit { .. } => "edit",
+            Commands::Remove { .. } => "remove",
+            Commands::Backup { .. } => "backup",
+            Commands::Restore { .. } => "restore",
+            Commands::Migrate { .. } => "migrate",
+            Commands::Export { .. } => "export",
+            Commands::Sign { .. } => "sign",
+            Commands::Info => "info",
+            Commands::Multisig { command } => match command {
+                MultisigCommands::Create { .. } => "multisig create",
+                MultisigCommands::List => "multisig list",
+            },
+            Commands::Tx { command } => match command {
+                TxCommands::Create { .. } => "tx create",
+                TxCommands::Estimate { .. } => "tx estimate",
+                TxCommands::Inspect { .. } => "tx inspect",
+                TxCommands::Sign { .. } => "tx sign",
+                TxCommands::Combine { .. } => "tx combine",
+                TxCommands::Finalize { .. } => "tx finalize",
+            },
+            Commands::Nonce { .. } => "nonce",
+            Commands::History { command, .. } => match command {
+                Some(HistoryCommands::Drop { .. }) => "history drop",
+                None => "history",
+            },
+            Commands::Config { command } => match command {
+                ConfigCommands::Show => "config show",
+                ConfigCommands::Path => "config path",
+                ConfigCommands::Set { .. } => "config set",
+            },
+            Commands::Qr { command } => match command {
+                QrCommands::Export { .. } => "qr export",
+                QrCommands::Import { .. } => "qr import",
+            },
+            Commands::Network { command } => match command {
+                NetworkCommands::List => "network list",
+                NetworkCommands::Use { .. } => "network use",
+            },
+            Commands::Contact { command } => match command {
+                ContactCommands::Add { .. } => "contact add",
+                ContactCommands::List { .. } => "contact list",
+                ContactCommands::Show { .. } => "contact show",
+                ContactCommands::Edit { .. } => "contact edit",
+                ContactCommands::Remove { .. } => "contact remove",
+            },
+        }
+    }
+}
+
+fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(error) => {
+            // Usage errors are reported as JSON too when it was asked for
+            let args: Vec<String> = std::env::args().collect();
+            let json = args.iter().any(|arg| arg == "--output=json")
+                || args
+                    .windows(2)
+                    .any(|pair| pair[0] == "--output" && pair[1] == "json");
+            if json && error.use_stderr() {
+                let output = Output {
+                    format: OutputFormat::Json,
+                    command: None,
+                };
+                let message = error.to_string();
+                let error = anyhow::Error::from(WalletError::new(
+                    ErrorKind::Usage,
+                    message.trim().trim_start_matches("error: "),
+                ));
+                std::process::exit(output.error(&error));
+            }
+            error.exit();
+        }
+    };
+
+    let output = Output {
+        format: cli.output,
+        command: Some(cli.command.name()),
+    };
+    if let Err(error) = run(cli, &output) {
+        std::process::exit(output.error(&error));
+    }
+}
+
+/// Print the non-empty metadata fields of an account
+fn print_metadata(metadata: &AccountMetadata, indent: &str) {
+    if let Some(label) = &metadata.label {
+        println!("{}🏷️  Label: {}", indent, label);
+    }
+    if let Some(notes) = &metadata.notes {
+        println!("{}📝 Notes: {}", indent, notes);
+    }
+    if !metadata.tags.is_empty() {
+        println!("{}🔖 Tags: {}", indent, metadata.tags.join(", "));
+    }
+    if metadata.hidden {
+        println!("{}🙈 Hidden", indent);
+    }
+    if metadata.archived {
+        println!("{}📦 Archived", indent);
+    }
+    if let Some(order) = metadata.order {
+        println!("{}↕️  Order: {}", indent, order);
+    }
+}
+
+/// Print the details of a contact below its name
+fn print_contact(contact: &Contact) {
+    println!("     🔑 {}", contact.address);
+    println!("     🌐 {}", contact.network);
+    if let Some(memo) = &contact.memo {
+        println!("     📝 {}", memo);
+    }
+}
+
+/// Wrap an error as a configuration error, keeping its message chain
+fn config_error(error: anyhow::Error) -> anyhow::Error {
+    WalletError::new(ErrorKind::Config, format!("{:#}", error)).into()
+}
+
+fn pst_summary(pst: &PartiallySignedTransaction) -> Value {
+    json!({
+        "signing_hash": pst.signing_hash.to_hex(),
+        "threshold": pst.threshold,
+        "required_signers": pst.required_signers.iter().map(|key| key.to_hex()).collect::<Vec<_>>(),
+        "signatures": pst.signatures.iter().map(|signature| json!({
+            "public_key": signature.public_key.to_hex(),
+            "scheme": signature.scheme.to_string(),
+        })).collect::<Vec<_>>(),
+        "missing": pst.missing_signers().iter().map(|key| key.to_hex()).collect::<Vec<_>>(),
+        "complete": pst.is_complete(),
+        "max_fee": pst.transaction.max_fee(),
+        "total_cost": pst.transaction.total_cost(),
+    })
+}
+
+/// Print what a transaction costs at most, so it is seen before signing
+fn print_cost(transaction: &Transaction) {
+    println!(
+        "💰 Total cost: up to {} MTH ({} MTH + max fee {} MTH: gas limit {} × gas price {})",
+        transaction.total_cost(),
+        transaction.amount.unwrap_or(0),
+        transaction.max_fee(),
+        transaction.gas_limit,
+        transaction.gas_price
+    );
+}
+
+/// Ask the node for the gas of `transaction` at `tier`, warning when the price is capped
+fn estimate_fee(
+    node: &NodeClient,
+    settings: &Settings,
+    tier: FeeTier,
+    transaction: &Transaction,
+) -> anyhow::Result<FeeEstimate> {
+    let estimate =
+        block_on(FeeEstimate::fetch(node, &settings.fee, tier, transaction)).map_err(|error| {
+            error.context(format!(
+                "Could not estimate the fee from {}; pass --gas-limit and --gas-price",
+                node.url()
+            ))
+        })?;
+    if estimate.capped {
+        warn!(
+            "The node's {} gas price {} is above fee.max_gas_price, using {}; the transaction may be slow to confirm",
+            tier,
+            estimate.prices.get(tier),
+            estimate.gas_price
+        );
+    }
+    Ok(estimate)
+}
+
+/// Record a transaction in the history, warning instead of failing the command
+fn record_history(wallet_dir: &Path, record: TxRecord) {
+    if let Err(error) = history::record(History::path(wallet_dir), record) {
+        warn!("Failed to record transaction history: {}", error);
+    }
+}
+
+/// Run a node request to completion
+fn block_on<T>(request: impl std::future::Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    tokio::runtime::Runtime::new()?.block_on(request)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+fn run(cli: Cli, out: &Output) -> anyhow::Result<()> {
+    // Layer settings: defaults, config file, environment, then flags
+    let config_path = cli.config.clone().unwrap_or_else(default_config_path);
+    let mut settings = Settings::load_file(&config_path).map_err(config_error)?;
+    settings
+        .apply_env(|name| std::env::var(name).ok())
+        .map_err(config_error)?;
+    if let Some(dir) = &cli.wallet_dir {
+        settings.wallet.dir = Some(dir.clone());
+    }
+    if let Some(wallet) = &cli.wallet {
+        settings.wallet.default_wallet = wallet.to_string();
+    }
+    if let Some(url) = &cli.node_url {
+        settings.node.url = Some(url.clone());
+    }
+    if let Some(network) = cli.network {
+        settings.node.network = network;
+    }
+    if let Some(level) = cli.log_level {
+        settings.log.level = level;
+    }
+    // `config` commands still run with invalid settings so they can be inspected and fixed
+    if !matches!(cli.command, Commands::Config { .. }) {
+        settings.validate().map_err(config_error)?;
+    }
 
-    let cli = Cli::parse();
+    // Initialize logging on stderr so stdout only carries results
+    tracing_subscriber::fmt()
+        .with_max_level(settings.log.level.as_tracing())
+        .with_writer(std::io::stderr)
+        .init();
+
+    // `config` commands never open a wallet, and `migrate` opens both backends itself
+    let wallet_dir = settings.wallet_dir();
+    let wallets = match cli.command {
+        Commands::Config { .. } | Commands::Migrate { .. } => Wallets::Json(wallet_dir.clone()),
+        _ => Wallets::open(settings.wallet.backend, &wallet_dir)?,
+    };
 
-    // Determine account file path based on wallet name
-    let account_file = match &cli.command {
-        Commands::New { name } => format!("./wallets/{}.json", name),
-        Commands::Import { name, .. } => format!("./wallets/{}.json", name),
-        _ => cli.account_file.clone(),
+    // Resolve the wallet from --account-file, or the wallet name in the configured backend
+    let name = &settings.wallet.default_wallet;
+    let mut wallet = match (&cli.account_file, WalletName::new(name)) {
+        (Some(file), _) => WalletManager::new(file),
+        (None, Ok(name)) => WalletManager::with_store(wallets.store(&name)?),
+        // Names were validated with the settings; only `config` commands, which never
+        // open a wallet, can get here with an invalid one
+        (None, Err(_)) => WalletManager::new(PathBuf::new()),
     };
+    let account_file = wallet.location();
 
-    let mut wallet = WalletManager::new(&account_file);
+    // The wallet's own network applies unless --network names one
+    if cli.network.is_none() && !matches!(cli.command, Commands::Config { .. }) && wallet.exists() {
+        if let Some(network) = wallet.network()? {
+            settings.node.network = network;
+        }
+    }
+    let profile = settings.network().clone();
 
     match cli.command {
-        Commands::New { name } => {
+        Commands::New => {
             info!("Creating new wallet: {}", name);
             let (manager, mnemonic) = wallet.create_wallet()?;
+            let account = &manager.now_selected_account;
+
+            out.print(
+                json!({
+                    "wallet": name,
+                    "path": account_file,
+                    "mnemonic": mnemonic,
+                    "address": account.get_address(),
+                    "public_key": account.public_key.to_hex(),
+                }),
+                || {
+                    println!("\n🎉 Wallet Creation Successful!");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("📁 Wallet Name: {}", name);
+                    println!("📂 Storage Path: {}", account_file.display());
+                    println!("🔐 Security Level: BIP39 (256-bit entropy)");
+                    println!("🌐 Blockchain: Mazzaroth");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+                    println!("\n⚠️  CRITICAL SECURITY INFORMATION ⚠️");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("📝 BIP39 Recovery Phrase (24 words):");
+                    println!("   {}", mnemonic);
+                    println!("\n🔒 SECURITY REQUIREMENTS:");
+                    println!("   • Write down this phrase on paper");
+                    println!("   • Store in a secure, fireproof location");
+                    println!("   • Never share with anyone");
+                    println!("   • This is your only backup method");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-            println!("\n🎉 Wallet Creation Successful!");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("📁 Wallet Name: {}", name);
-            println!("📂 Storage Path: ./wallets/{}.json", name);
-            println!("🔐 Security Level: BIP39 (256-bit entropy)");
-            println!("🌐 Blockchain: Mazzaroth");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-            println!("\n⚠️  CRITICAL SECURITY INFORMATION ⚠️");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("📝 BIP39 Recovery Phrase (24 words):");
-            println!("   {}", mnemonic);
-            println!("\n🔒 SECURITY REQUIREMENTS:");
-            println!("   • Write down this phrase on paper");
-            println!("   • Store in a secure, fireproof location");
-            println!("   • Never share with anyone");
-            println!("   • This is your only backup method");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-            println!("\n💼 Account Information:");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("🔑 Primary Account Address:");
-            println!("   {}", manager.now_selected_account.get_address());
-            println!("📊 Public Key:");
-            println!("   {}", manager.now_selected_account.public_key.to_hex());
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-            println!("\n🚀 Next Steps:");
-            println!("   • Backup your recovery phrase securely");
-            println!("   • Test with small amounts first");
+                    println!("\n💼 Account Information:");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("🔑 Primary Account Address:");
+                    println!("   {}", profile.display_address(&account.get_address()));
+                    println!("📊 Public Key:");
+                    println!("   {}", account.public_key.to_hex());
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+                    println!("\n🚀 Next Steps:");
+                    println!("   • Backup your recovery phrase securely");
+                    println!("   • Test with small amounts first");
                     println!("   • Use 'rwmth list' to view accounts");
-        println!("   • Use 'rwmth info' for wallet details");
-            println!("\n✅ Wallet '{}' is ready for use!", name);
+                    println!("   • Use 'rwmth info' for wallet details");
+                    println!("\n✅ Wallet '{}' is ready for use!", name);
+                },
+            )?;
         }
 
-        Commands::Import { mnemonic, name } => {
+        Commands::Import { mnemonic } => {
             info!("Importing wallet from mnemonic: {}", name);
+            let mnemonic = mnemonic.read()?;
             let manager = wallet.import_wallet(&mnemonic)?;
+            let account = &manager.now_selected_account;
+
+            out.print(
+                json!({
+                    "wallet": name,
+                    "path": account_file,
+                    "address": account.get_address(),
+                    "public_key": account.public_key.to_hex(),
+                    "total_accounts": manager.account_map.len(),
+                }),
+                || {
+                    println!("\n🎉 Wallet Import Successful!");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("📁 Wallet Name: {}", name);
+                    println!("📂 Storage Path: {}", account_file.display());
+                    println!("🔐 Security Level: BIP39 (256-bit entropy)");
+                    println!("🌐 Blockchain: Mazzaroth");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-            println!("\n🎉 Wallet Import Successful!");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("📁 Wallet Name: {}", name);
-            println!("📂 Storage Path: ./wallets/{}.json", name);
-            println!("🔐 Security Level: BIP39 (256-bit entropy)");
-            println!("🌐 Blockchain: Mazzaroth");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-            println!("\n💼 Account Information:");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("🔑 Primary Account Address:");
-            println!("   {}", manager.now_selected_account.get_address());
-            println!("📊 Public Key:");
-            println!("   {}", manager.now_selected_account.public_key.to_hex());
-            println!("📈 Total Accounts: {}", manager.account_map.len());
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-            println!("\n🚀 Next Steps:");
-            println!("   • Verify your account addresses");
+                    println!("\n💼 Account Information:");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("🔑 Primary Account Address:");
+                    println!("   {}", profile.display_address(&account.get_address()));
+                    println!("📊 Public Key:");
+                    println!("   {}", account.public_key.to_hex());
+                    println!("📈 Total Accounts: {}", manager.account_map.len());
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+                    println!("\n🚀 Next Steps:");
+                    println!("   • Verify your account addresses");
                     println!("   • Use 'rwmth list' to view all accounts");
-        println!("   • Use 'rwmth info' for wallet details");
-            println!("\n✅ Wallet '{}' imported and ready for use!", name);
+                    println!("   • Use 'rwmth info' for wallet details");
+                    println!("\n✅ Wallet '{}' imported and ready for use!", name);
+                },
+            )?;
         }
 
-        Commands::List => {
+        Commands::List { all } => {
             info!("Listing accounts");
-            let accounts = wallet.list_accounts()?;
-            if accounts.is_empty() {
-                println!("\n💼 No accounts found in this wallet");
-                println!("💡 Add accounts with: rwmth add \"your mnemonic phrase\"");
-            } else {
-                println!("\n💼 Accounts in Wallet:");
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                let total_accounts = accounts.len();
-                for (i, account) in accounts.iter().enumerate() {
-                    let status = if account.is_selected {
-                        " 🔵 SELECTED"
-                    } else {
-                        ""
-                    };
-                    println!("  {}. 🔑 {}", i + 1, account.address);
-                    println!("     📊 Public Key: {}", account.public_key);
-                    println!(
-                        "     📍 Status: {}{}",
-                        if account.is_selected {
-                            "Active"
+            let mut accounts = wallet.list_accounts()?;
+            let total = accounts.len();
+            if !all {
+                accounts.retain(|account| account.metadata.is_listed());
+            }
+            let hidden = total - accounts.len();
+            out.print(json!({ "accounts": accounts, "hidden": hidden }), || {
+                if total == 0 {
+                    println!("\n💼 No accounts found in this wallet");
+                    println!("💡 Add accounts with: rwmth add");
+                } else if !accounts.is_empty() {
+                    println!("\n💼 Accounts in Wallet:");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    let total_accounts = accounts.len();
+                    for (i, account) in accounts.iter().enumerate() {
+                        let status = if account.is_selected {
+                            " 🔵 SELECTED"
                         } else {
-                            "Inactive"
-                        },
-                        status
-                    );
-                    if i < total_accounts - 1 {
-                        println!("     ──────────────────────────────────────────────");
+                            ""
+                        };
+                        println!(
+                            "  {}. 🔑 {}",
+                            account.index,
+                            profile.display_address(&account.address)
+                        );
+                        print_metadata(&account.metadata, "     ");
+                        println!("     📊 Public Key: {}", account.public_key);
+                        println!(
+                            "     📍 Status: {}{}",
+                            if account.is_selected {
+                                "Active"
+                            } else {
+                                "Inactive"
+                            },
+                            status
+                        );
+                        if i < total_accounts - 1 {
+                            println!("     ──────────────────────────────────────────────");
+                        }
                     }
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("💡 Use 'rwmth selected' to view current account details");
                 }
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                println!("💡 Use 'rwmth selected' to view current account details");
-            }
+                if hidden > 0 {
+                    println!(
+                        "🙈 {} hidden or archived account(s), use --all to show",
+                        hidden
+                    );
+                }
+            })?;
         }
 
         Commands::ListWallets => {
             info!("Listing all wallets");
-            let wallets_dir = std::path::Path::new("./wallets");
-            if !wallets_dir.exists() {
-                println!("No wallets directory found.");
-                return Ok(());
-            }
+            let backend = settings.wallet.backend;
+            let wallets = wallets.list()?;
 
-            let mut wallets = Vec::new();
-            if let Ok(entries) = std::fs::read_dir(wallets_dir) {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        if let Some(extension) = entry.path().extension() {
-                            if extension == "json" {
-                                if let Some(file_name) = entry.path().file_stem() {
-                                    if let Some(name) = file_name.to_str() {
-                                        wallets.push(name.to_string());
-                                    }
+            out.print(
+                json!({ "wallet_dir": wallet_dir, "backend": backend, "wallets": wallets }),
+                || {
+                    if wallets.is_empty() {
+                        println!("\n📁 No wallets found in {}", wallet_dir.display());
+                        println!("💡 Create your first wallet with: rwmth new");
+                    } else {
+                        println!("\n📁 Available Wallets:");
+                        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                        for (i, wallet_name) in wallets.iter().enumerate() {
+                            match backend {
+                                StorageBackend::Json => println!(
+                                    "  {}. 📂 {} ({}.json)",
+                                    i + 1,
+                                    wallet_name,
+                                    wallet_name
+                                ),
+                                StorageBackend::Sled => {
+                                    println!("  {}. 📂 {}", i + 1, wallet_name)
                                 }
                             }
                         }
+                        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                        println!(
+                            "💡 Use 'rwmth --wallet <name> [command]' to work with specific wallets"
+                        );
                     }
-                }
-            }
-
-            if wallets.is_empty() {
-                println!("\n📁 No wallets found in ./wallets/ directory");
-                println!("💡 Create your first wallet with: rwmth new");
-            } else {
-                println!("\n📁 Available Wallets:");
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                for (i, wallet_name) in wallets.iter().enumerate() {
-                    println!("  {}. 📂 {} ({}.json)", i + 1, wallet_name, wallet_name);
-                }
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                println!("💡 Use 'rwmth --account-file ./wallets/[name].json [command]' to work with specific wallets");
-            }
+                },
+            )?;
         }
 
         Commands::Add { mnemonic } => {
             info!("Adding new account");
+            let mnemonic = mnemonic.read()?;
             let account = wallet.add_account(&mnemonic)?;
-            println!("✅ New account added successfully!");
-            println!("🔑 Address: {}", account.get_address());
+            out.print(
+                json!({
+                    "address": account.get_address(),
+                    "public_key": account.public_key.to_hex(),
+                }),
+                || {
+                    println!("✅ New account added successfully!");
+                    println!(
+                        "🔑 Address: {}",
+                        profile.display_address(&account.get_address())
+                    );
+                },
+            )?;
         }
 
         Commands::Selected => {
             info!("Showing selected account");
             let account = wallet.get_selected_account()?;
-            println!("Selected Account:");
-            println!("  Address: {}", account.get_address());
-            println!("  Public Key: {}", account.get_public_key_hex());
+            out.print(
+                json!({
+                    "address": account.get_address(),
+                    "public_key": account.get_public_key_hex(),
+                    "x_only_public_key": account.get_x_only_public_key_hex(),
+                }),
+                || {
+                    println!("Selected Account:");
+                    println!(
+                        "  Address: {}",
+                        profile.display_address(&account.get_address())
+                    );
+                    println!("  Public Key: {}", account.get_public_key_hex());
+                    println!(
+                        "  X-Only Public Key: {}",
+                        account.get_x_only_public_key_hex()
+                    );
+                },
+            )?;
+        }
+
+        Commands::Select { account } => {
+            info!("Selecting account: {}", account);
+            let account = wallet.select_account(&profile.normalize_address(&account))?;
+            out.print(
+                json!({
+                    "address": account.get_address(),
+                    "public_key": account.get_public_key_hex(),
+                }),
+                || {
+                    println!(
+                        "✅ Selected account {}",
+                        profile.display_address(&account.get_address())
+                    )
+                },
+            )?;
         }
 
-        Commands::Export => {
+        Commands::Show { account } => {
+            info!("Showing account: {}", account);
+            let account = profile.normalize_address(&account).into_owned();
+            let metadata = wallet.get_account_metadata(&account)?;
+            let (index, account) = wallet.find_account(&account)?;
+            let is_selected = account == wallet.get_selected_account()?;
+            out.print(
+                json!({
+                    "index": index,
+                    "address": account.get_address(),
+                    "public_key": account.get_public_key_hex(),
+                    "x_only_public_key": account.get_x_only_public_key_hex(),
+                    "is_selected": is_selected,
+                    "metadata": metadata,
+                }),
+                || {
+                    println!("Account {}:", index);
+                    println!(
+                        "  Address: {}",
+                        profile.display_address(&account.get_address())
+                    );
+                    println!("  Public Key: {}", account.get_public_key_hex());
+                    println!(
+                        "  X-Only Public Key: {}",
+                        account.get_x_only_public_key_hex()
+                    );
+                    println!(
+                        "  Status: {}",
+                        if is_selected { "Selected" } else { "Inactive" }
+                    );
+                    print_metadata(&metadata, "  ");
+                },
+            )?;
+        }
+
+        Commands::Edit {
+            account,
+            label,
+            notes,
+            tags,
+            untags,
+            hidden,
+            archived,
+            order,
+            no_order,
+        } => {
+            info!("Editing account: {}", account);
+            let address = profile.normalize_address(&account);
+            let metadata = wallet.update_account_metadata(&address, |metadata| {
+                // Blank values clear the field when the metadata is normalized
+                if let Some(label) = label {
+                    metadata.label = Some(label);
+                }
+                if let Some(notes) = notes {
+                    metadata.notes = Some(notes);
+                }
+                metadata.tags.extend(tags);
+                metadata.tags.retain(|tag| !untags.contains(tag));
+                if let Some(hidden) = hidden {
+                    metadata.hidden = hidden;
+                }
+                if let Some(archived) = archived {
+                    metadata.archived = archived;
+                }
+                if order.is_some() || no_order {
+                    metadata.order = order;
+                }
+            })?;
+            out.print(json!({ "account": account, "metadata": metadata }), || {
+                println!("✅ Account {} updated", account);
+                print_metadata(&metadata, "  ");
+            })?;
+        }
+
+        Commands::Remove { account } => {
+            info!("Removing account: {}", account);
+            let removed = wallet.remove_account(&profile.normalize_address(&account))?;
+            let removed = profile.display_address(&removed.get_address());
+            let selected = profile.display_address(&wallet.get_selected_account()?.get_address());
+            out.print(json!({ "removed": removed, "selected": selected }), || {
+                println!("✅ Removed account {}", removed);
+                println!("🔵 Selected account: {}", selected);
+            })?;
+        }
+
+        Commands::Backup {
+            file,
+            all,
+            no_settings,
+            password,
+        } => {
+            if cli.account_file.is_some() {
+                return Err(WalletError::new(
+                    ErrorKind::Usage,
+                    "Backups cover wallets in the wallet directory; use --wallet instead of --account-file",
+                )
+                .into());
+            }
+            let names = if all {
+                wallets
+                    .list()?
+                    .iter()
+                    .map(|name| WalletName::new(name))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            } else {
+                vec![WalletName::new(name)?]
+            };
+
+            info!("Backing up {} wallet(s) to {}", names.len(), file.display());
+            let contents = BackupContents::collect(
+                &wallet_dir,
+                &wallets,
+                &names,
+                (!no_settings).then_some(config_path.as_path()),
+            )?;
+            let password = password.read(true)?;
+            archive::write_archive(&file, &contents, &password, settings.wallet.kdf_iterations)?;
+
+            let wallet_names: Vec<&String> = contents.wallets.keys().collect();
+            let contacts = contents
+                .address_book
+                .as_ref()
+                .map(|book| book.contacts.len());
+            out.print(
+                json!({
+                    "file": file,
+                    "wallets": wallet_names,
+                    "contacts": contacts,
+                    "settings": contents.settings.is_some(),
+                }),
+                || {
+                    println!("✅ Backup written to {}", file.display());
+                    println!("📁 Wallets: {}", wallet_names.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", "));
+                    println!("📇 Contacts: {}", contacts.unwrap_or(0));
+                    println!("⚙️  Settings: {}", if contents.settings.is_some() { "included" } else { "not included" });
+                    println!("\n⚠️  Keep the archive password safe: the backup can't be opened without it");
+                },
+            )?;
+        }
+
+        Commands::Restore {
+            file,
+            preview,
+            replace,
+            password,
+        } => {
+            let contents = archive::read_archive(&file, &password.read(false)?)?;
+
+            if preview {
+                let preview = contents.preview(&wallets)?;
+                out.print(serde_json::to_value(&preview)?, || {
+                    println!("📦 Backup from {}", preview.created_at.to_rfc3339());
+                    for wallet in &preview.wallets {
+                        let status = if wallet.exists {
+                            "exists, would merge"
+                        } else {
+                            "new"
+                        };
+                        match &wallet.addresses {
+                            Some(addresses) => println!(
+                                "📁 {} ({} accounts, {})",
+                                wallet.name,
+                                addresses.len(),
+                                status
+                            ),
+                            None => {
+                                println!("📁 {} (unrecognized format, {})", wallet.name, status)
+                            }
+                        }
+                        for address in wallet.addresses.iter().flatten() {
+                            println!("   {}", address);
+                        }
+                    }
+                    match preview.contacts {
+                        Some(contacts) => println!("📇 Address book: {} contacts", contacts),
+                        None => println!("📇 Address book: not included"),
+                    }
+                    println!(
+                        "⚙️  Settings: {}",
+                        if preview.settings {
+                            "included"
+                        } else {
+                            "not included"
+                        }
+                    );
+                })?;
+                return Ok(());
+            }
+
+            let mode = if replace {
+                RestoreMode::Replace
+            } else {
+                RestoreMode::Merge
+            };
+            info!("Restoring backup from {}", file.display());
+            let report = contents.restore(&wallet_dir, &wallets, &config_path, mode)?;
+            out.print(serde_json::to_value(&report)?, || {
+                println!("✅ Restored backup from {}", file.display());
+                for (label, wallets) in [
+                    ("Restored", &report.restored),
+                    ("Merged", &report.merged),
+                    ("Replaced", &report.replaced),
+                    ("Unchanged", &report.unchanged),
+                    ("Skipped (can't merge, use --replace)", &report.skipped),
+                ] {
+                    if !wallets.is_empty() {
+                        println!("📁 {}: {}", label, wallets.join(", "));
+                    }
+                }
+                if report.address_book_replaced {
+                    println!("📇 Address book restored");
+                } else if report.contacts_added > 0 {
+                    println!("📇 Added {} contacts", report.contacts_added);
+                }
+                if report.settings_restored {
+                    println!("⚙️  Settings restored to {}", config_path.display());
+                }
+                for path in &report.set_aside {
+                    println!("💾 Previous file kept at {}", path.display());
+                }
+            })?;
+        }
+
+        Commands::Migrate { to, from } => {
+            if cli.account_file.is_some() {
+                return Err(WalletError::new(
+                    ErrorKind::Usage,
+                    "Migration covers wallets in the wallet directory; use --wallet-dir instead of --account-file",
+                )
+                .into());
+            }
+            let from = from.unwrap_or(settings.wallet.backend);
+            if from == to {
+                return Err(WalletError::new(
+                    ErrorKind::Usage,
+                    format!("Wallets are already in the {} backend", to),
+                )
+                .into());
+            }
+
+            info!("Migrating wallets from {} to {}", from, to);
+            let source = Wallets::open(from, &wallet_dir)?;
+            let target = Wallets::open(to, &wallet_dir)?;
+            let report = storage::migrate_wallets(&source, &target, &[])?;
+            out.print(
+                json!({
+                    "from": from,
+                    "to": to,
+                    "migrated": report.migrated,
+                    "skipped": report.skipped,
+                }),
+                || {
+                    println!(
+                        "✅ Copied {} wallet(s) from {} to {}",
+                        report.migrated.len(),
+                        from,
+                        to
+                    );
+                    if !report.migrated.is_empty() {
+                        println!("📁 Migrated: {}", report.migrated.join(", "));
+                    }
+                    if !report.skipped.is_empty() {
+                        println!(
+                            "⏭️  Skipped (already in {}): {}",
+                            to,
+                            report.skipped.join(", ")
+                        );
+                    }
+                    if to != settings.wallet.backend {
+                        println!("💡 Use them with: rwmth config set wallet.backend {}", to);
+                    }
+                },
+            )?;
+        }
+
+        Commands::Export { out: target, .. } => {
             info!("Exporting private key");
+            let address = wallet.get_selected_account()?.get_address();
             let private_key = wallet.export_private_key()?;
-            println!("Private Key: {}", private_key);
+            match &target {
+                Some(target) => {
+                    secret::write_secret_file(target, &private_key)?;
+                    out.print(json!({ "address": address, "file": target }), || {
+                        println!("✅ Private key written to {}", target.display())
+                    })?;
+                }
+                None => out.print(
+                    json!({ "address": address, "private_key": private_key }),
+                    || println!("Private Key: {}", private_key),
+                )?,
+            }
         }
 
-        Commands::Sign { data } => {
-            info!("Signing transaction");
-            let data_bytes =
-                hex::decode(data.trim_start_matches("0x")).map_err(|_| "Invalid hex data")?;
-            let signature = wallet.sign_transaction(&data_bytes)?;
-            println!("Signature: 0x{}", hex::encode(signature));
+        Commands::Sign { data, scheme } => {
+            info!("Signing transaction ({})", scheme);
+            let data_bytes = hex::decode(data.trim_start_matches("0x"))
+                .map_err(|_| WalletError::invalid_input("Invalid hex data"))?;
+            let signature = wallet.sign_transaction_with_scheme(&data_bytes, scheme)?;
+            let public_key = wallet.get_selected_account()?.get_public_key_hex();
+            out.print(
+                json!({
+                    "signature": format!("0x{}", hex::encode(signature)),
+                    "scheme": scheme.to_string(),
+                    "public_key": public_key,
+                }),
+                || {
+                    println!("Signature: 0x{}", hex::encode(signature));
+                    println!("Scheme: {}", scheme);
+                },
+            )?;
         }
 
-        Commands::Info => {
-            info!("Showing wallet information");
-            if wallet.exists() {
-                println!("Wallet Information:");
-                println!("  Account File: {}", cli.account_file);
-                println!("  Status: Initialized");
+        Commands::Multisig { command } => match command {
+            MultisigCommands::Create {
+                name,
+                threshold,
+                participants,
+            } => {
+                info!("Creating multisig account: {}", name);
+                let multisig = wallet.create_multisig(&name, threshold, &participants)?;
+                out.print(json!(multisig), || {
+                    println!("✅ Multisig account created successfully!");
+                    println!("📁 Name: {}", multisig.name);
+                    println!("🔑 Address: {}", multisig.address);
+                    println!(
+                        "✍️  Threshold: {} of {}",
+                        multisig.threshold,
+                        multisig.participants.len()
+                    );
+                })?;
+            }
+
+            MultisigCommands::List => {
+                info!("Listing multisig accounts");
+                let accounts = wallet.list_multisig()?;
+                out.print(json!({ "multisig_accounts": accounts }), || {
+                    if accounts.is_empty() {
+                        println!("\n👥 No multisig accounts found in this wallet");
+                        println!("💡 Create one with: rwmth multisig create <name> -t <threshold> -p <key> -p <key>");
+                    } else {
+                        println!("\n👥 Multisig Accounts:");
+                        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                        for (i, multisig) in accounts.iter().enumerate() {
+                            println!("  {}. 🔑 {} ({})", i + 1, multisig.address, multisig.name);
+                            println!(
+                                "     ✍️  Threshold: {} of {}",
+                                multisig.threshold,
+                                multisig.participants.len()
+                            );
+                            for participant in &multisig.participants {
+                                println!("     📊 {}", participant.to_hex());
+                            }
+                        }
+                        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    }
+                })?;
+            }
+        },
+
+        Commands::Tx { command } => match command {
+            TxCommands::Create {
+                to,
+                amount,
+                data,
+                nonce,
+                gas_limit,
+                gas_price,
+                fee,
+                replace,
+                cancel,
+                multisig,
+                out: file,
+                format,
+            } => {
+                info!("Creating transaction file: {}", file);
+                let from = match &multisig {
+                    Some(name) => wallet.get_multisig(name)?.address,
+                    None => wallet.get_selected_account()?.get_address(),
+                };
+                if let Some(data) = &data {
+                    hex::decode(data.trim_start_matches("0x"))
+                        .map_err(|_| WalletError::invalid_input("Invalid hex data"))?;
+                }
+                let to = match to {
+                    Some(to) => Some(
+                        AddressBook::load(AddressBook::path(&wallet_dir))?
+                            .resolve(&profile.normalize_address(&to), settings.node.network)?,
+                    ),
+                    None => None,
+                };
+                let history = History::load(History::path(&wallet_dir))?;
+                let mut estimate = None;
+                let transaction = match replace.as_ref().or(cancel.as_ref()) {
+                    Some(hash) => {
+                        let original = history.get(hash).ok_or_else(|| {
+                            WalletError::not_found(format!(
+                                "Transaction not found in history: {}",
+                                hash
+                            ))
+                        })?;
+                        settings.check_chain_id(original.chain_id)?;
+                        if !original.account.eq_ignore_ascii_case(&from) {
+                            return Err(WalletError::invalid_input(format!(
+                                "Transaction {} was sent from {}, not {}",
+                                hash, original.account, from
+                            ))
+                            .into());
+                        }
+                        let replacement = build_replacement(original, cancel.is_some(), gas_price)?;
+                        Transaction {
+                            to: to.or(replacement.to),
+                            amount: amount.or(replacement.amount),
+                            data: data.or(replacement.data),
+                            gas_limit: gas_limit.unwrap_or(replacement.gas_limit),
+                            ..replacement
+                        }
+                    }
+                    None => {
+                        let node = NodeClient::new(&settings)?;
+                        let nonce = match nonce {
+                            Some(nonce) => nonce,
+                            None => block_on(NonceManager::fetch(
+                                &node,
+                                &from,
+                                profile.chain_id,
+                                &history,
+                            ))
+                            .map_err(|error| {
+                                error.context(format!(
+                                    "Could not get the nonce of {} from {}; pass --nonce",
+                                    from,
+                                    node.url()
+                                ))
+                            })?
+                            .next_nonce(),
+                        };
+                        let transaction = Transaction {
+                            chain_id: profile.chain_id,
+                            from,
+                            to,
+                            amount,
+                            data,
+                            nonce,
+                            gas_limit: gas_limit.unwrap_or(0),
+                            gas_price: gas_price.unwrap_or(0),
+                        };
+                        if gas_limit.is_some() && gas_price.is_some() {
+                            transaction
+                        } else {
+                            let tier = fee.unwrap_or(settings.fee.tier);
+                            let fee = estimate_fee(&node, &settings, tier, &transaction)?;
+                            let transaction = Transaction {
+                                gas_limit: gas_limit.unwrap_or(fee.gas_limit),
+                                gas_price: gas_price.unwrap_or(fee.gas_price),
+                                ..transaction
+                            };
+                            estimate = Some(fee);
+                            transaction
+                        }
+                    }
+                };
+                let pst = wallet.create_partial_transaction(transaction, multisig.as_deref())?;
+                pst.save(&file, format)?;
+                record_history(&wallet_dir, TxRecord::from_partial(&pst));
+                let mut data = pst_summary(&pst);
+                data["file"] = json!(file);
+                data["format"] = json!(format.to_string());
+                data["nonce"] = json!(pst.transaction.nonce);
+                data["gas_limit"] = json!(pst.transaction.gas_limit);
+                data["gas_price"] = json!(pst.transaction.gas_price);
+                data["estimate"] = json!(estimate);
+                out.print(data, || {
+                    println!("✅ Transaction file written to {} ({})", file, format);
+                    println!("🔏 Signing Hash: {}", pst.signing_hash.to_hex());
+                    println!("🔢 Nonce: {}", pst.transaction.nonce);
+                    if let Some(estimate) = &estimate {
+                        println!(
+                            "⛽ Estimated gas: {} ({} tier)",
+                            estimate.estimated_gas, estimate.tier
+                        );
+                    }
+                    print_cost(&pst.transaction);
+                    println!(
+                        "✍️  Signatures required: {} of {}",
+                        pst.threshold,
+                        pst.required_signers.len()
+                    );
+                })?;
+            }
+
+            TxCommands::Estimate {
+                to,
+                amount,
+                data,
+                fee,
+                multisig,
+            } => {
+                let from = match &multisig {
+                    Some(name) => wallet.get_multisig(name)?.address,
+                    None => wallet.get_selected_account()?.get_address(),
+                };
+                if let Some(data) = &data {
+                    hex::decode(data.trim_start_matches("0x"))
+                        .map_err(|_| WalletError::invalid_input("Invalid hex data"))?;
+                }
+                let to = match to {
+                    Some(to) => Some(
+                        AddressBook::load(AddressBook::path(&wallet_dir))?
+                            .resolve(&profile.normalize_address(&to), settings.node.network)?,
+                    ),
+                    None => None,
+                };
+                let node = NodeClient::new(&settings)?;
+                let history = History::load(History::path(&wallet_dir))?;
+                let nonce = block_on(NonceManager::fetch(
+                    &node,
+                    &from,
+                    profile.chain_id,
+                    &history,
+                ))?
+                .next_nonce();
+                let transaction = Transaction {
+                    chain_id: profile.chain_id,
+                    from,
+                    to,
+                    amount,
+                    data,
+                    nonce,
+                    gas_limit: 0,
+                    gas_price: 0,
+                };
+                let tier = fee.unwrap_or(settings.fee.tier);
+                let estimate = estimate_fee(&node, &settings, tier, &transaction)?;
+                let tiers: Vec<Value> = [FeeTier::Slow, FeeTier::Normal, FeeTier::Fast]
+                    .into_iter()
+                    .map(|tier| {
+                        let gas_price = estimate.prices.get(tier).min(settings.fee.max_gas_price);
+                        let transaction = Transaction {
+                            gas_limit: estimate.gas_limit,
+                            gas_price,
+                            ..transaction.clone()
+                        };
+                        json!({
+                            "tier": tier,
+                            "gas_price": gas_price,
+                            "max_fee": transaction.max_fee(),
+                            "total_cost": transaction.total_cost(),
+                        })
+                    })
+                    .collect();
+                let mut data = json!(estimate);
+                data["tiers"] = json!(tiers);
+                out.print(data, || {
+                    println!(
+                        "⛽ Estimated gas: {}, gas limit {} with a {}% margin",
+                        estimate.estimated_gas,
+                        estimate.gas_limit,
+                        settings.fee.gas_limit_margin_percent
+                    );
+                    for entry in &tiers {
+                        println!(
+                            "{} {:<6}  gas price {:<8}  max fee {:<12}  total up to {} MTH",
+                            if entry["tier"] == json!(estimate.tier) {
+                                "▶"
+                            } else {
+                                " "
+                            },
+                            entry["tier"].as_str().unwrap_or_default(),
+                            entry["gas_price"],
+                            entry["max_fee"],
+                            entry["total_cost"]
+                        );
+                    }
+                })?;
+            }
+
+            TxCommands::Inspect { file } => {
+                info!("Inspecting transaction file: {}", file);
+                let pst = PartiallySignedTransaction::load(&file)?;
+                let mut data = pst_summary(&pst);
+                data["transaction"] = json!(pst.transaction);
+                out.print(data, || {
+                    let transaction = &pst.transaction;
+                    println!("Transaction:");
+                    println!("  From: {}", transaction.from);
+                    println!("  To: {}", transaction.to.as_deref().unwrap_or("-"));
+                    println!(
+                        "  Amount: {}",
+                        transaction
+                            .amount
+                            .map(|amount| format!("{} MTH", amount))
+                            .unwrap_or_else(|| "-".to_string())
+                    );
+                    println!("  Data: {}", transaction.data.as_deref().unwrap_or("-"));
+                    println!("  Nonce: {}", transaction.nonce);
+                    println!("  Gas Limit: {}", transaction.gas_limit);
+                    println!("  Gas Price: {}", transaction.gas_price);
+                    println!("  Max Fee: {}", transaction.max_fee());
+                    println!("  Total Cost: up to {} MTH", transaction.total_cost());
+                    println!("  Signing Hash: {}", pst.signing_hash.to_hex());
+                    println!(
+                        "  Signatures: {} of {}",
+                        pst.signatures.len(),
+                        pst.threshold
+                    );
+                    for signature in &pst.signatures {
+                        println!(
+                            "    ✅ {} ({})",
+                            signature.public_key.to_hex(),
+                            signature.scheme
+                        );
+                    }
+                    for public_key in pst.missing_signers() {
+                        println!("    ⏳ {}", public_key.to_hex());
+                    }
+                    println!(
+                        "  Status: {}",
+                        if pst.is_complete() {
+                            "Ready to finalize"
+                        } else {
+                            "Pending"
+                        }
+                    );
+                })?;
+            }
+
+            TxCommands::Sign {
+                file,
+                scheme,
+                out: target,
+                format,
+            } => {
+                info!("Signing transaction file: {}", file);
+                let mut pst = PartiallySignedTransaction::load(&file)?;
+                settings.check_chain_id(pst.transaction.chain_id)?;
+                info!(
+                    "Signing a transaction costing up to {} MTH (max fee {})",
+                    pst.transaction.total_cost(),
+                    pst.transaction.max_fee()
+                );
+                wallet.sign_partial_transaction(&mut pst, scheme)?;
+                let target = target.unwrap_or(file);
+                pst.save(&target, format)?;
+                record_history(&wallet_dir, TxRecord::from_partial(&pst));
+                let mut data = pst_summary(&pst);
+                data["file"] = json!(target);
+                data["format"] = json!(format.to_string());
+                out.print(data, || {
+                    println!(
+                        "✅ Signature added ({} of {})",
+                        pst.signatures.len(),
+                        pst.threshold
+                    );
+                    print_cost(&pst.transaction);
+                    if pst.is_complete() {
+                        println!("🎉 Threshold reached, run 'rwmth tx finalize {}'", target);
+                    } else {
+                        println!("💡 Pass {} to the next signer", target);
+                    }
+                })?;
+            }
+
+            TxCommands::Combine {
+                files,
+                out: target,
+                format,
+            } => {
+                info!("Combining {} transaction files", files.len());
+                let mut combined = PartiallySignedTransaction::load(&files[0])?;
+                for file in &files[1..] {
+                    combined.combine(&PartiallySignedTransaction::load(file)?)?;
+                }
+                combined.save(&target, format)?;
+                record_history(&wallet_dir, TxRecord::from_partial(&combined));
+                let mut data = pst_summary(&combined);
+                data["file"] = json!(target);
+                data["format"] = json!(format.to_string());
+                out.print(data, || {
+                    println!("✅ Combined transaction written to {}", target);
+                    println!(
+                        "✍️  Signatures: {} of {}",
+                        combined.signatures.len(),
+                        combined.threshold
+                    );
+                })?;
+            }
+
+            TxCommands::Finalize {
+                file,
+                out: target,
+                submit,
+            } => {
+                info!("Finalizing transaction file: {}", file);
+                let pst = PartiallySignedTransaction::load(&file)?;
+                let signed = pst.finalize()?;
+                let raw = signed.to_bytes()?;
+                let hash = format!("0x{}", hex::encode(signed.hash()?));
+                if let Some(target) = &target {
+                    std::fs::write(target, &raw)?;
+                }
+                let mut record = TxRecord::from_signed(&signed)?;
+                if submit {
+                    settings.check_chain_id(signed.transaction.chain_id)?;
+                    let node = NodeClient::new(&settings)?;
+                    info!("Submitting transaction {} to {}", hash, node.url());
+                    let node_hash = block_on(node.submit_transaction(&raw))?;
+                    if !node_hash.eq_ignore_ascii_case(&hash) {
+                        warn!("Node reported hash {} for transaction {}", node_hash, hash);
+                    }
+                    record.status = TxStatus::Submitted;
+                }
+                record_history(&wallet_dir, record);
+                out.print(
+                    json!({
+                        "hash": hash,
+                        "file": target,
+                        "signed_transaction": format!("0x{}", hex::encode(&raw)),
+                        "submitted": submit,
+                        "explorer_url": profile.transaction_url(&hash),
+                    }),
+                    || {
+                        println!("Transaction Hash: {}", hash);
+                        match &target {
+                            Some(target) => println!("Signed Transaction: written to {}", target),
+                            None => println!("Signed Transaction: 0x{}", hex::encode(&raw)),
+                        }
+                        if submit {
+                            println!("📡 Submitted to {}", settings.node_url());
+                            if let Some(url) = profile.transaction_url(&hash) {
+                                println!("🔍 {}", url);
+                            }
+                        }
+                    },
+                )?;
+            }
+        },
 
-                let accounts = wallet.list_accounts()?;
-                println!("  Total Accounts: {}", accounts.len());
+        Commands::Nonce { account } => {
+            let account = match account {
+                Some(account) => match parse_address(&profile.normalize_address(&account)) {
+                    Ok(address) => address,
+                    Err(_) => wallet.find_account(&account)?.1.get_address(),
+                },
+                None => wallet.get_selected_account()?.get_address(),
+            };
+            info!("Checking nonces of {}", account);
+            let history = History::load(History::path(&wallet_dir))?;
+            let node = NodeClient::new(&settings)?;
+            let report = block_on(NonceManager::fetch(
+                &node,
+                &account,
+                profile.chain_id,
+                &history,
+            ))?
+            .report(Utc::now());
+            out.print(json!(report), || {
+                println!("Account: {}", report.account);
+                println!("On-chain Nonce: {}", report.on_chain);
+                println!("Next Nonce: {}", report.next);
+                let list = |nonces: &[u64]| {
+                    nonces
+                        .iter()
+                        .map(u64::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                if !report.pending.is_empty() {
+                    println!("Pending: {}", list(&report.pending));
+                }
+                if !report.gaps.is_empty() {
+                    println!(
+                        "⚠️  Gaps: {} (later transactions wait until these are sent)",
+                        list(&report.gaps)
+                    );
+                }
+                for hash in &report.stuck {
+                    println!(
+                        "⏳ Stuck: {} (blocked by a gap or pending over {} minutes)",
+                        hash, STUCK_AFTER_MINUTES
+                    );
+                }
+                for hash in &report.stale {
+                    println!(
+                        "🔁 Stale: {} (nonce already used, run 'rwmth history --sync')",
+                        hash
+                    );
+                }
+                for id in &report.drafts {
+                    println!(
+                        "📝 Draft: {} (not signed, so its nonce isn't held; remove it with 'rwmth history drop <ID>')",
+                        id
+                    );
+                }
+                if !report.stuck.is_empty() {
+                    println!("💡 Speed up with 'rwmth tx create --replace <HASH> --out <FILE>' or cancel with --cancel");
+                }
+            })?;
+        }
+
+        Commands::History {
+            command: Some(HistoryCommands::Drop { id }),
+            ..
+        } => {
+            info!("Dropping transaction {} from the history", id);
+            let path = History::path(&wallet_dir);
+            let mut history = History::load(&path)?;
+            let record = history.remove(&id)?;
+            history.save(&path)?;
+            out.print(json!(record), || {
+                println!(
+                    "✅ Transaction {} dropped; nonce {} is free again",
+                    record.id, record.nonce
+                )
+            })?;
+        }
 
-                let selected = wallet.get_selected_account()?;
-                println!("  Selected Account: {}", selected.get_address());
+        Commands::History {
+            command: None,
+            account,
+            to,
+            status,
+            since,
+            until,
+            limit,
+            offset,
+            sync,
+        } => {
+            info!("Listing transaction history");
+            let path = History::path(&wallet_dir);
+            let mut history = History::load(&path)?;
+            // Being offline shouldn't hide the history, so a failed sync only warns
+            let synced = if sync {
+                let reconciled =
+                    NodeClient::new(&settings).and_then(|node| block_on(history.reconcile(&node)));
+                match reconciled {
+                    Ok(updated) => {
+                        if updated > 0 {
+                            history.save(&path)?;
+                        }
+                        Some(updated)
+                    }
+                    Err(error) => {
+                        warn!("Failed to sync with {}: {}", settings.node_url(), error);
+                        None
+                    }
+                }
             } else {
-                println!("Wallet not found. Use 'new' to create a wallet or 'import' to import from mnemonic.");
+                None
+            };
+
+            let account = match account {
+                Some(account) => Some(match parse_address(&profile.normalize_address(&account)) {
+                    Ok(address) => address,
+                    Err(_) => wallet.find_account(&account)?.1.get_address(),
+                }),
+                None => None,
+            };
+            let counterparty = match to {
+                Some(to) => Some(
+                    AddressBook::load(AddressBook::path(&wallet_dir))?
+                        .resolve(&profile.normalize_address(&to), settings.node.network)?,
+                ),
+                None => None,
+            };
+            let filter = HistoryFilter {
+                account,
+                counterparty,
+                status,
+                since,
+                until,
+            };
+            let page = history.query(&filter, offset, limit);
+            out.print(
+                json!({
+                    "total": page.total,
+                    "offset": page.offset,
+                    "synced": synced,
+                    "transactions": page.records,
+                }),
+                || {
+                    if let Some(updated) = synced {
+                        println!(
+                            "🔄 {} transaction(s) updated from {}",
+                            updated,
+                            settings.node_url()
+                        );
+                    }
+                    if page.records.is_empty() {
+                        println!("\n📭 No transactions found");
+                        return;
+                    }
+                    println!(
+                        "\n📜 Transactions {}-{} of {}:",
+                        page.offset + 1,
+                        page.offset + page.records.len(),
+                        page.total
+                    );
+                    for record in &page.records {
+                        println!(
+                            "\n  {} [{}]",
+                            record.hash.as_deref().unwrap_or(&record.id),
+                            record.status
+                        );
+                        println!("     From: {}", record.account);
+                        println!("     To: {}", record.counterparty.as_deref().unwrap_or("-"));
+                        println!(
+                            "     Amount: {}",
+                            record
+                                .amount
+                                .map(|amount| format!("{} MTH", amount))
+                                .unwrap_or_else(|| "-".to_string())
+                        );
+                        println!("     Nonce: {}", record.nonce);
+                        println!(
+                            "     Created: {}",
+                            record.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+                        );
+                        println!(
+                            "     Updated: {}",
+                            record.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+                        );
+                    }
+                },
+            )?;
+        }
+
+        Commands::Config { command } => match command {
+            ConfigCommands::Show => {
+                info!("Showing settings");
+                let toml = settings.to_toml()?;
+                out.print(
+                    json!({
+                        "config_path": config_path,
+                        "wallet_dir": wallet_dir,
+                        "settings": settings,
+                    }),
+                    || {
+                        println!("# Config file: {}", config_path.display());
+                        println!("# Wallet directory: {}", wallet_dir.display());
+                        print!("{}", toml);
+                    },
+                )?;
+            }
+
+            ConfigCommands::Path => {
+                out.print(json!({ "config_path": config_path }), || {
+                    println!("{}", config_path.display())
+                })?;
+            }
+
+            ConfigCommands::Set { key, value } => {
+                info!("Setting {} in {}", key, config_path.display());
+                // Only the file layer is written; environment and flags still apply on top
+                let mut file_settings = Settings::load_file(&config_path).map_err(config_error)?;
+                file_settings.set(&key, &value).map_err(config_error)?;
+                file_settings.save(&config_path).map_err(config_error)?;
+                out.print(
+                    json!({ "config_path": config_path, "key": key, "value": value }),
+                    || println!("✅ {} = {} saved to {}", key, value, config_path.display()),
+                )?;
             }
+        },
+
+        Commands::Qr { command } => match command {
+            QrCommands::Export {
+                file,
+                out_dir,
+                chunk_size,
+                interval,
+                once,
+            } => {
+                info!("Exporting transaction file as QR codes: {}", file);
+                let pst = PartiallySignedTransaction::load(&file)?;
+                let parts = qr::encode_parts(&pst.to_bytes(PstFormat::Binary)?, chunk_size)?;
+
+                let mut files = Vec::new();
+                if let Some(out_dir) = &out_dir {
+                    std::fs::create_dir_all(out_dir)?;
+                    for (i, part) in parts.iter().enumerate() {
+                        let path =
+                            std::path::Path::new(out_dir).join(format!("frame-{:02}.png", i + 1));
+                        qr::write_png(&path, &qr::render_image(part)?)?;
+                        files.push(path);
+                    }
+                }
+
+                if out.is_json() || out_dir.is_some() {
+                    out.print(json!({ "parts": parts, "files": files }), || {
+                        println!(
+                            "✅ Wrote {} QR frame(s) to {}",
+                            files.len(),
+                            out_dir.as_deref().unwrap_or_default()
+                        )
+                    })?;
+                } else if once || parts.len() == 1 {
+                    for (i, part) in parts.iter().enumerate() {
+                        println!("{}", qr::render_terminal(part)?);
+                        println!("Frame {} of {}", i + 1, parts.len());
+                    }
+                } else {
+                    let frames = parts
+                        .iter()
+                        .map(|part| qr::render_terminal(part))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    for (i, frame) in frames.iter().enumerate().cycle() {
+                        print!("\x1B[2J\x1B[H");
+                        println!("{}", frame);
+                        println!("Frame {} of {} (Ctrl-C to stop)", i + 1, frames.len());
+                        std::thread::sleep(std::time::Duration::from_millis(interval));
+                    }
+                }
+            }
+
+            QrCommands::Import {
+                inputs,
+                out: target,
+                format,
+            } => {
+                info!(
+                    "Importing transaction file from {} QR input(s)",
+                    inputs.len()
+                );
+                let mut assembler = QrAssembler::new();
+                for input in &inputs {
+                    let texts = if input.to_ascii_lowercase().ends_with(".png") {
+                        qr::decode_image(&qr::read_png(input)?)?
+                    } else {
+                        let content = if input == "-" {
+                            std::io::read_to_string(std::io::stdin())?
+                        } else {
+                            std::fs::read_to_string(input)?
+                        };
+                        content
+                            .lines()
+                            .filter(|line| !line.trim().is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    };
+                    for text in texts {
+                        assembler.add(&text)?;
+                    }
+                }
+
+                if !assembler.is_complete() {
+                    return Err(WalletError::invalid_input(format!(
+                        "Received {} of {} QR parts, missing: {:?}",
+                        assembler.received(),
+                        assembler.total().unwrap_or(0),
+                        assembler.missing()
+                    ))
+                    .into());
+                }
+                let pst = PartiallySignedTransaction::from_bytes(&assembler.assemble()?)?;
+                pst.save(&target, format)?;
+                let mut data = pst_summary(&pst);
+                data["file"] = json!(target);
+                data["format"] = json!(format.to_string());
+                out.print(data, || {
+                    println!("✅ Transaction file written to {} ({})", target, format);
+                    println!("🔏 Signing Hash: {}", pst.signing_hash.to_hex());
+                    println!(
+                        "✍️  Signatures: {} of {}",
+                        pst.signatures.len(),
+                        pst.threshold
+                    );
+                })?;
+            }
+        },
+
+        Commands::Contact { command } => {
+            let book_path = AddressBook::path(&wallet_dir);
+            let mut book = AddressBook::load(&book_path)?;
+            let network = settings.node.network;
+
+            match command {
+                ContactCommands::Add {
+                    name,
+                    address,
+                    memo,
+                    network: contact_network,
+                } => {
+                    info!("Adding contact: {}", name);
+                    let contact =
+                        Contact::new(&name, &address, memo, contact_network.unwrap_or(network))?;
+                    book.add(contact.clone())?;
+                    book.save(&book_path)?;
+                    out.print(json!(contact), || {
+                        println!("✅ Contact '{}' added", contact.name);
+                        print_contact(&contact);
+                    })?;
+                }
+
+                ContactCommands::List { all } => {
+                    info!("Listing contacts");
+                    let contacts = book.list(if all { None } else { Some(network) });
+                    out.print(json!({ "network": network, "contacts": contacts }), || {
+                        if contacts.is_empty() {
+                            println!("\n📇 No contacts on {}", network);
+                            println!("💡 Add one with: rwmth contact add <name> <address>");
+                        } else {
+                            println!("\n📇 Contacts:");
+                            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                            for contact in &contacts {
+                                println!("  👤 {}", contact.name);
+                                print_contact(contact);
+                            }
+                            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                            println!("💡 Use a contact name with 'rwmth tx create --to <name>'");
+                        }
+                    })?;
+                }
+
+                ContactCommands::Show { name } => {
+                    let contact = book.get(&name).ok_or_else(|| {
+                        WalletError::not_found(format!("Contact not found: {}", name))
+                    })?;
+                    out.print(json!(contact), || {
+                        println!("👤 {}", contact.name);
+                        print_contact(contact);
+                    })?;
+                }
+
+                ContactCommands::Edit {
+                    name,
+                    rename,
+                    address,
+                    memo,
+                    network: contact_network,
+                } => {
+                    info!("Editing contact: {}", name);
+                    let current = book.get(&name).cloned().ok_or_else(|| {
+                        WalletError::not_found(format!("Contact not found: {}", name))
+                    })?;
+                    let contact = Contact::new(
+                        rename.as_deref().unwrap_or(&current.name),
+                        address.as_deref().unwrap_or(&current.address),
+                        memo.or(current.memo),
+                        contact_network.unwrap_or(current.network),
+                    )?;
+                    book.update(&name, contact.clone())?;
+                    book.save(&book_path)?;
+                    out.print(json!(contact), || {
+                        println!("✅ Contact '{}' updated", contact.name);
+                        print_contact(&contact);
+                    })?;
+                }
+
+                ContactCommands::Remove { name } => {
+                    info!("Removing contact: {}", name);
+                    let contact = book.remove(&name)?;
+                    book.save(&book_path)?;
+                    out.print(json!(contact), || {
+                        println!("✅ Contact '{}' removed", contact.name)
+                    })?;
+                }
+            }
+        }
+
+        Commands::Network { command } => match command {
+            NetworkCommands::List => {
+                let wallet_network = if wallet.exists() {
+                    wallet.network()?
+                } else {
+                    None
+                };
+                let networks: Vec<Value> = Networks::ALL
+                    .into_iter()
+                    .map(|network| {
+                        let mut entry = json!(settings.networks.get(network));
+                        entry["name"] = json!(network);
+                        entry["active"] = json!(network == settings.node.network);
+                        entry
+                    })
+                    .collect();
+                out.print(
+                    json!({
+                        "active": settings.node.network,
+                        "wallet": wallet_network,
+                        "networks": networks,
+                    }),
+                    || {
+                        for network in Networks::ALL {
+                            let profile = settings.networks.get(network);
+                            println!(
+                                "{} {} (chain {})",
+                                if network == settings.node.network {
+                                    "▶"
+                                } else {
+                                    " "
+                                },
+                                network,
+                                profile.chain_id
+                            );
+                            println!("    Node: {}", profile.node_url);
+                            println!("    Address Prefix: {}", profile.address_prefix);
+                            if let Some(url) = &profile.explorer_url {
+                                println!("    Explorer: {}", url);
+                            }
+                        }
+                        if let Some(network) = wallet_network {
+                            println!("💼 Wallet {} uses {}", name, network);
+                        }
+                        if settings.node.url.is_some() {
+                            println!("📡 node.url overrides the node: {}", settings.node_url());
+                        }
+                    },
+                )?;
+            }
+
+            NetworkCommands::Use { network, clear } => {
+                if !wallet.exists() {
+                    return Err(WalletError::not_found(format!(
+                        "Wallet not found: {}",
+                        account_file.display()
+                    ))
+                    .into());
+                }
+                let network = if clear { None } else { network };
+                wallet.set_network(network)?;
+                let active = network.unwrap_or(settings.node.network);
+                out.print(
+                    json!({ "wallet": name, "network": network, "chain_id": settings.networks.get(active).chain_id }),
+                    || match network {
+                        Some(network) => println!("✅ Wallet {} now uses {}", name, network),
+                        None => println!("✅ Wallet {} follows node.network again", name),
+                    },
+                )?;
+            }
+        },
+
+        Commands::Info => {
+            info!("Showing wallet information");
+            let initialized = wallet.exists();
+            let (total_accounts, selected_account) = if initialized {
+                (
+                    Some(wallet.list_accounts()?.len()),
+                    Some(wallet.get_selected_account()?.get_address()),
+                )
+            } else {
+                (None, None)
+            };
+
+            out.print(
+                json!({
+                    "initialized": initialized,
+                    "wallet": name,
+                    "account_file": account_file,
+                    "node_url": settings.node_url(),
+                    "network": settings.node.network,
+                    "chain_id": profile.chain_id,
+                    "total_accounts": total_accounts,
+                    "selected_account": selected_account,
+                }),
+                || {
+                    if initialized {
+                        println!("Wallet Information:");
+                        println!("  Account File: {}", account_file.display());
+                        println!(
+                            "  Node: {} ({}, chain {})",
+                            settings.node_url(),
+                            settings.node.network,
+                            profile.chain_id
+                        );
+                        println!("  Status: Initialized");
+                        println!("  Total Accounts: {}", total_accounts.unwrap_or_default());
+                        println!(
+                            "  Selected Account: {}",
+                            selected_account.as_deref().unwrap_or_default()
+                        );
+                    } else {
+                        println!("Wallet not found. Use 'new' to create a wallet or 'import' to import from mnemonic.");
+                    }
+                },
+            )?;
         }
     }
 
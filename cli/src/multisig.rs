@@ -0,0 +1,186 @@
+use crate::types::AccountKey;
+use anyhow::{anyhow, Result};
+use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Domain tag mixed into multisig address derivation
+const MULTISIG_ADDRESS_TAG: &[u8] = b"mazzaroth-multisig";
+
+/// Largest number of participants in a multisig account
+pub const MAX_PARTICIPANTS: usize = 16;
+
+/// M-of-N multisignature account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MultisigAccount {
+    /// Human readable name
+    pub name: String,
+    /// Number of signatures required (M)
+    pub threshold: usize,
+    /// Participant public keys (N), sorted
+    pub participants: Vec<AccountKey>,
+    /// Shared address derived from threshold and participants
+    pub address: String,
+}
+
+impl MultisigAccount {
+    /// Create a multisig account, validating threshold and participants
+    pub fn new(name: &str, threshold: usize, participants: Vec<AccountKey>) -> Result<Self> {
+        let mut participants = participants;
+        participants.sort_by_key(|key| key.0);
+        participants.dedup();
+
+        if participants.len() < 2 {
+            return Err(anyhow!(
+                "A multisig account needs at least 2 distinct participants"
+            ));
+        }
+        if participants.len() > MAX_PARTICIPANTS {
+            return Err(anyhow!(
+                "A multisig account supports at most {} participants",
+                MAX_PARTICIPANTS
+            ));
+        }
+        if threshold == 0 || threshold > participants.len() {
+            return Err(anyhow!(
+                "Threshold must be between 1 and {}",
+                participants.len()
+            ));
+        }
+        for participant in &participants {
+            PublicKey::from_slice(&participant.0)
+                .map_err(|_| anyhow!("Invalid participant key: {}", participant.to_hex()))?;
+        }
+
+        let address = derive_multisig_address(threshold, &participants);
+        Ok(MultisigAccount {
+            name: name.to_string(),
+            threshold,
+            participants,
+            address,
+        })
+    }
+
+    /// Check whether the key belongs to one of the participants
+    pub fn is_participant(&self, public_key: &AccountKey) -> bool {
+        self.participants.contains(public_key)
+    }
+
+    /// Check that the stored address matches threshold and participants
+    pub fn verify_address(&self) -> Result<()> {
+        let expected = derive_multisig_address(self.threshold, &self.participants);
+        if expected.trim_start_matches("0x") != self.address.trim_start_matches("0x") {
+            return Err(anyhow!("Multisig address does not match its participants"));
+        }
+        Ok(())
+    }
+}
+
+/// Derive the shared address: sha256(tag || M || N || sorted keys), first 20 bytes
+pub fn derive_multisig_address(threshold: usize, participants: &[AccountKey]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut keys: Vec<&AccountKey> = participants.iter().collect();
+    keys.sort_by_key(|key| key.0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(MULTISIG_ADDRESS_TAG);
+    hasher.update([threshold as u8, keys.len() as u8]);
+    for key in keys {
+        hasher.update(key.0);
+    }
+    let result = hasher.finalize();
+    format!("0x{}", hex::encode(&result[..20]))
+}
+
+/// Parse a participant public key given as compressed or uncompressed hex
+pub fn parse_participant_key(key_hex: &str) -> Result<AccountKey> {
+    let bytes = hex::decode(key_hex.trim_start_matches("0x"))
+        .map_err(|_| anyhow!("Invalid public key format: {}", key_hex))?;
+    let public_key =
+        PublicKey::from_slice(&bytes).map_err(|_| anyhow!("Invalid public key: {}", key_hex))?;
+    Ok(AccountKey(public_key.serialize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_keypair;
+    use crate::pst::{PartiallySignedTransaction, PstFormat};
+    use crate::types::{AccountKeyPair, SignatureScheme, Transaction};
+
+    fn keypairs(count: usize) -> Vec<AccountKeyPair> {
+        (0..count).map(|_| generate_keypair().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_multisig_address_is_order_independent() {
+        let keys = keypairs(3);
+        let participants: Vec<AccountKey> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let mut reversed = participants.clone();
+        reversed.reverse();
+
+        let a = MultisigAccount::new("team", 2, participants.clone()).unwrap();
+        let b = MultisigAccount::new("team", 2, reversed).unwrap();
+        assert_eq!(a.address, b.address);
+
+        // A different threshold gives a different address
+        let c = MultisigAccount::new("team", 3, participants).unwrap();
+        assert_ne!(a.address, c.address);
+    }
+
+    #[test]
+    fn test_multisig_threshold_validation() {
+        let participants: Vec<AccountKey> =
+            keypairs(2).iter().map(|k| k.public_key.clone()).collect();
+        assert!(MultisigAccount::new("team", 0, participants.clone()).is_err());
+        assert!(MultisigAccount::new("team", 3, participants.clone()).is_err());
+        assert!(MultisigAccount::new("team", 1, participants[..1].to_vec()).is_err());
+        assert!(MultisigAccount::new("team", 2, participants).is_ok());
+    }
+
+    #[test]
+    fn test_collect_signatures_until_threshold() {
+        let keys = keypairs(3);
+        let outsider = generate_keypair().unwrap();
+        let multisig = MultisigAccount::new(
+            "team",
+            2,
+            keys.iter().map(|k| k.public_key.clone()).collect(),
+        )
+        .unwrap();
+        let transaction = Transaction {
+            chain_id: 1,
+            from: multisig.address.clone(),
+            to: None,
+            amount: None,
+            data: Some("0x010203".to_string()),
+            nonce: 0,
+            gas_limit: 21000,
+            gas_price: 1,
+        };
+
+        // Each signer gets the file, signs and passes it on
+        let mut file = PartiallySignedTransaction::for_multisig(transaction, &multisig)
+            .unwrap()
+            .to_bytes(PstFormat::Binary)
+            .unwrap();
+        let mut pst = PartiallySignedTransaction::from_bytes(&file).unwrap();
+        assert!(pst.sign(&outsider, SignatureScheme::Ecdsa).is_err());
+        // Signing twice replaces the signature rather than counting it again
+        for (key, signed) in [(&keys[0], 1), (&keys[0], 1), (&keys[2], 2)] {
+            assert!(!pst.is_complete());
+            pst.sign(key, SignatureScheme::Ecdsa).unwrap();
+            file = pst.to_bytes(PstFormat::Binary).unwrap();
+            pst = PartiallySignedTransaction::from_bytes(&file).unwrap();
+            assert_eq!(pst.signatures.len(), signed);
+            assert_eq!(pst.missing_signers().len(), 3 - signed);
+        }
+        assert!(pst.is_complete());
+        pst.verify().unwrap();
+        assert_eq!(pst.finalize().unwrap().signatures.len(), 2);
+
+        // Tampering with the data invalidates the file
+        pst.transaction.data = Some("0x010204".to_string());
+        assert!(pst.verify().is_err());
+    }
+}
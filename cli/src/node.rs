@@ -0,0 +1,223 @@
+//! Client for the node's JSON-RPC endpoint
+//!
+//! Requests are JSON-RPC 2.0 calls posted to `node.url`. Hashes and raw
+//! transactions travel as `0x`-prefixed hex.
+
+use crate::config::Settings;
+use crate::error::{ErrorKind, WalletError};
+use crate::fee::GasPrices;
+use crate::history::TxStatus;
+use crate::types::Transaction;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Error object of a JSON-RPC response
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// Connection to one node
+pub struct NodeClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+fn network_error(message: impl Into<String>) -> anyhow::Error {
+    WalletError::new(ErrorKind::Network, message).into()
+}
+
+impl NodeClient {
+    /// Client for the node of the active network, timing out after `node.timeout_secs`
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(settings.node.timeout_secs))
+            .build()?;
+        Ok(NodeClient {
+            url: settings.node_url().to_string(),
+            client,
+        })
+    }
+
+    /// Node endpoint
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.error {
+            return Err(network_error(format!(
+                "Node rejected {}: {} ({})",
+                method, error.message, error.code
+            )));
+        }
+        Ok(serde_json::from_value(
+            response.result.unwrap_or(Value::Null),
+        )?)
+    }
+
+    /// Broadcast raw signed transaction bytes, returning the hash the node reports
+    pub async fn submit_transaction(&self, raw: &[u8]) -> Result<String> {
+        self.call("tx_submit", json!([format!("0x{}", hex::encode(raw))]))
+            .await
+    }
+
+    /// Next nonce the node will accept from `address`
+    pub async fn account_nonce(&self, address: &str) -> Result<u64> {
+        self.call("account_nonce", json!([address])).await
+    }
+
+    /// Gas the node expects `transaction` to use
+    pub async fn estimate_gas(&self, transaction: &Transaction) -> Result<u64> {
+        self.call("gas_estimate", json!([transaction])).await
+    }
+
+    /// Gas prices the node currently suggests for each fee tier
+    pub async fn gas_prices(&self) -> Result<GasPrices> {
+        self.call("gas_price", json!([])).await
+    }
+
+    /// Status of the transaction with hash `hash`, or `None` if the node doesn't know it
+    pub async fn transaction_status(&self, hash: &str) -> Result<Option<TxStatus>> {
+        let status: String = self.call("tx_status", json!([hash])).await?;
+        match status.as_str() {
+            "pending" => Ok(Some(TxStatus::Submitted)),
+            "confirmed" => Ok(Some(TxStatus::Confirmed)),
+            "failed" => Ok(Some(TxStatus::Failed)),
+            "unknown" => Ok(None),
+            other => Err(network_error(format!(
+                "Unknown transaction status from node: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Answer one JSON-RPC request per response, returning the requests received
+    fn serve(responses: Vec<Value>) -> (Settings, JoinHandle<Vec<Value>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut settings = Settings::default();
+        settings.node.url = Some(format!("http://{}", listener.local_addr().unwrap()));
+        settings.node.timeout_secs = 5;
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                requests.push(serde_json::from_slice(&body).unwrap());
+
+                let body = response.to_string();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (settings, handle)
+    }
+
+    #[tokio::test]
+    async fn test_node_client_requests() {
+        let (settings, server) = serve(vec![
+            json!({ "jsonrpc": "2.0", "id": 1, "result": "0xabcd" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": "confirmed" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": "unknown" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": 7 }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": 25000 }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": { "slow": 1, "normal": 2, "fast": 5 } }),
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "nonce too low" } }),
+        ]);
+        let node = NodeClient::new(&settings).unwrap();
+
+        assert_eq!(node.submit_transaction(&[1, 2]).await.unwrap(), "0xabcd");
+        assert_eq!(
+            node.transaction_status("0xabcd").await.unwrap(),
+            Some(TxStatus::Confirmed)
+        );
+        assert_eq!(node.transaction_status("0xabcd").await.unwrap(), None);
+        assert_eq!(node.account_nonce("0x1111").await.unwrap(), 7);
+        let transaction = Transaction {
+            chain_id: 1,
+            from: "0x1111111111111111111111111111111111111111".to_string(),
+            to: None,
+            amount: Some(1),
+            data: None,
+            nonce: 7,
+            gas_limit: 0,
+            gas_price: 0,
+        };
+        assert_eq!(node.estimate_gas(&transaction).await.unwrap(), 25000);
+        assert_eq!(
+            node.gas_prices().await.unwrap(),
+            GasPrices {
+                slow: 1,
+                normal: 2,
+                fast: 5
+            }
+        );
+        let error = node.submit_transaction(&[1, 2]).await.unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Network);
+        assert!(error.to_string().contains("nonce too low"));
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0]["method"], "tx_submit");
+        assert_eq!(requests[0]["params"], json!(["0x0102"]));
+        assert_eq!(requests[1]["method"], "tx_status");
+        assert_eq!(requests[1]["params"], json!(["0xabcd"]));
+        assert_eq!(requests[3]["method"], "account_nonce");
+        assert_eq!(requests[4]["method"], "gas_estimate");
+        assert_eq!(requests[4]["params"][0]["nonce"], 7);
+        assert_eq!(requests[5]["method"], "gas_price");
+    }
+}
@@ -0,0 +1,354 @@
+//! Nonce selection for one account from the node and the local history
+//!
+//! The node only knows the next nonce it will accept. Transactions the wallet
+//! has signed or submitted but the chain hasn't included yet come from the
+//! history, so a new transaction doesn't reuse a nonce that is already taken.
+//! Drafts still missing signatures don't hold their nonce: nothing settles a
+//! draft that is never signed, so one abandoned draft would block the account.
+
+use crate::error::WalletError;
+use crate::history::{History, TxRecord, TxStatus};
+use crate::node::NodeClient;
+use crate::pst::SignedTransaction;
+use crate::types::Transaction;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// A submitted transaction still pending after this many minutes is reported as stuck
+pub const STUCK_AFTER_MINUTES: i64 = 10;
+
+/// A replacement must raise the gas price by at least this much for nodes to accept it
+pub const REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// Nonces of one account on one chain: what the chain expects and what the wallet has in flight
+#[derive(Debug, Clone)]
+pub struct NonceManager {
+    account: String,
+    on_chain: u64,
+    /// Signed or submitted local transactions of the account, by nonce
+    pending: Vec<TxRecord>,
+    /// Built local transactions still missing signatures, by nonce
+    drafts: Vec<TxRecord>,
+    /// Unsettled local transactions whose nonce the chain has already used
+    stale: Vec<TxRecord>,
+}
+
+/// What `NonceManager` knows about an account, for display
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct NonceReport {
+    pub account: String,
+    /// Next nonce the node will accept
+    pub on_chain: u64,
+    /// Nonce a new transaction gets
+    pub next: u64,
+    /// Nonces of signed or submitted local transactions
+    pub pending: Vec<u64>,
+    /// Ids of built transactions still missing signatures, whose nonces
+    /// a new transaction may take
+    pub drafts: Vec<String>,
+    /// Nonces the node is waiting for before it can include later submitted transactions
+    pub gaps: Vec<u64>,
+    /// Ids of submitted transactions that are blocked by a gap or pending for too long
+    pub stuck: Vec<String>,
+    /// Ids of unsettled transactions whose nonce is already used on chain,
+    /// either confirmed and not synced yet or replaced
+    pub stale: Vec<String>,
+}
+
+impl NonceManager {
+    /// Split the account's unsettled history on `chain_id` around `on_chain`,
+    /// the next nonce the node accepts
+    pub fn new(account: &str, chain_id: u64, on_chain: u64, history: &History) -> Self {
+        let (unsettled, stale): (Vec<TxRecord>, Vec<TxRecord>) = history
+            .records
+            .iter()
+            .filter(|record| record.account.eq_ignore_ascii_case(account))
+            .filter(|record| record.chain_id == chain_id)
+            .filter(|record| !record.status.is_final())
+            .cloned()
+            .partition(|record| record.nonce >= on_chain);
+        let (mut drafts, mut pending): (Vec<TxRecord>, Vec<TxRecord>) = unsettled
+            .into_iter()
+            .partition(|record| record.status == TxStatus::Built);
+        pending.sort_by_key(|record| record.nonce);
+        drafts.sort_by_key(|record| record.nonce);
+        NonceManager {
+            account: account.to_string(),
+            on_chain,
+            pending,
+            drafts,
+            stale,
+        }
+    }
+
+    /// Ask the node of chain `chain_id` for the account's nonce and combine it with `history`
+    pub async fn fetch(
+        node: &NodeClient,
+        account: &str,
+        chain_id: u64,
+        history: &History,
+    ) -> Result<Self> {
+        let on_chain = node.account_nonce(account).await?;
+        Ok(Self::new(account, chain_id, on_chain, history))
+    }
+
+    /// Lowest nonce from the chain's onwards that no signed or submitted local
+    /// transaction uses. Gaps are filled first, since nothing after a gap can be included.
+    pub fn next_nonce(&self) -> u64 {
+        let used: BTreeSet<u64> = self.pending.iter().map(|record| record.nonce).collect();
+        (self.on_chain..)
+            .find(|nonce| !used.contains(nonce))
+            .unwrap_or(self.on_chain)
+    }
+
+    /// Nonces below the highest submitted transaction that no submitted transaction uses
+    pub fn gaps(&self) -> Vec<u64> {
+        let submitted: BTreeSet<u64> = self
+            .pending
+            .iter()
+            .filter(|record| record.status == TxStatus::Submitted)
+            .map(|record| record.nonce)
+            .collect();
+        match submitted.last() {
+            Some(&highest) => (self.on_chain..highest)
+                .filter(|nonce| !submitted.contains(nonce))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Submitted transactions that can't be included because of a gap before them,
+    /// or that have been waiting longer than `STUCK_AFTER_MINUTES` at `now`
+    pub fn stuck(&self, now: DateTime<Utc>) -> Vec<&TxRecord> {
+        let first_gap = self.gaps().first().copied();
+        self.pending
+            .iter()
+            .filter(|record| record.status == TxStatus::Submitted)
+            .filter(|record| {
+                first_gap.is_some_and(|gap| record.nonce > gap)
+                    || now - record.updated_at > Duration::minutes(STUCK_AFTER_MINUTES)
+            })
+            .collect()
+    }
+
+    /// Summary of the account's nonces at `now`
+    pub fn report(&self, now: DateTime<Utc>) -> NonceReport {
+        NonceReport {
+            account: self.account.clone(),
+            on_chain: self.on_chain,
+            next: self.next_nonce(),
+            pending: self.pending.iter().map(|record| record.nonce).collect(),
+            drafts: self.drafts.iter().map(|record| record.id.clone()).collect(),
+            gaps: self.gaps(),
+            stuck: self
+                .stuck(now)
+                .into_iter()
+                .map(|record| record.hash.clone().unwrap_or_else(|| record.id.clone()))
+                .collect(),
+            stale: self
+                .stale
+                .iter()
+                .map(|record| record.hash.clone().unwrap_or_else(|| record.id.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Lowest gas price a replacement of a transaction paying `gas_price` may use
+pub fn replacement_gas_price(gas_price: u64) -> u64 {
+    let bumped = gas_price
+        .saturating_mul(100 + REPLACEMENT_BUMP_PERCENT)
+        .div_ceil(100);
+    bumped.max(gas_price.saturating_add(1))
+}
+
+/// Build a transaction replacing `original`, an unsettled transaction from the history.
+///
+/// A speed-up (`cancel` false) resends the original transaction with a higher gas
+/// price. A cancel sends nothing to the sender itself with the same nonce.
+/// Either way `gas_price` defaults to the lowest accepted bump and may not be below it.
+pub fn build_replacement(
+    original: &TxRecord,
+    cancel: bool,
+    gas_price: Option<u64>,
+) -> Result<Transaction> {
+    if original.status.is_final() {
+        return Err(WalletError::invalid_input(format!(
+            "Transaction {} is already {}",
+            original.id, original.status
+        ))
+        .into());
+    }
+
+    let minimum = replacement_gas_price(original.gas_price);
+    let gas_price = gas_price.unwrap_or(minimum);
+    if gas_price < minimum {
+        return Err(WalletError::invalid_input(format!(
+            "Replacement gas price must be at least {} ({}% above {})",
+            minimum, REPLACEMENT_BUMP_PERCENT, original.gas_price
+        ))
+        .into());
+    }
+
+    if cancel {
+        return Ok(Transaction {
+            chain_id: original.chain_id,
+            from: original.account.clone(),
+            to: Some(original.account.clone()),
+            amount: Some(0),
+            data: None,
+            nonce: original.nonce,
+            gas_limit: 21000,
+            gas_price,
+        });
+    }
+
+    let raw = original.raw.as_deref().ok_or_else(|| {
+        WalletError::invalid_input(format!(
+            "Transaction {} was never finalized, so there is nothing to speed up",
+            original.id
+        ))
+    })?;
+    let signed = SignedTransaction::from_bytes(&hex::decode(raw.trim_start_matches("0x"))?)?;
+    Ok(Transaction {
+        gas_price,
+        ..signed.transaction
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(nonce: u64, status: TxStatus, minutes_ago: i64) -> TxRecord {
+        let at = Utc::now() - Duration::minutes(minutes_ago);
+        TxRecord {
+            id: format!("0x{:064x}", nonce),
+            hash: Some(format!("0x{:064x}", nonce + 100)),
+            account: "0x1111111111111111111111111111111111111111".to_string(),
+            chain_id: 1,
+            counterparty: None,
+            amount: Some(1),
+            nonce,
+            gas_price: 10,
+            status,
+            created_at: at,
+            updated_at: at,
+            raw: None,
+        }
+    }
+
+    #[test]
+    fn test_nonce_report() {
+        let account = "0x1111111111111111111111111111111111111111";
+        let history = History {
+            records: vec![
+                record(3, TxStatus::Submitted, 1),
+                record(4, TxStatus::Confirmed, 1),
+                record(5, TxStatus::Submitted, 1),
+                record(6, TxStatus::Built, 0),
+                record(8, TxStatus::Submitted, 30),
+                record(2, TxStatus::Submitted, 1),
+            ],
+        };
+        let report = NonceManager::new(account, 1, 3, &history).report(Utc::now());
+        assert_eq!(report.on_chain, 3);
+        assert_eq!(report.pending, vec![3, 5, 8]);
+        assert_eq!(report.drafts, vec![record(6, TxStatus::Built, 0).id]);
+        // The gap at 4 is filled before going past the highest pending nonce
+        assert_eq!(report.next, 4);
+        assert_eq!(report.gaps, vec![4, 6, 7]);
+        assert_eq!(
+            report.stuck,
+            vec![
+                record(5, TxStatus::Submitted, 0).hash.unwrap(),
+                record(8, TxStatus::Submitted, 0).hash.unwrap()
+            ]
+        );
+        assert_eq!(
+            report.stale,
+            vec![record(2, TxStatus::Submitted, 0).hash.unwrap()]
+        );
+
+        let empty = NonceManager::new(account, 1, 7, &History::default());
+        // Transactions on other chains don't take nonces here
+        assert_eq!(NonceManager::new(account, 2, 3, &history).next_nonce(), 3);
+        assert_eq!(empty.next_nonce(), 7);
+        assert!(empty.gaps().is_empty());
+
+        let other = NonceManager::new("0x2222222222222222222222222222222222222222", 1, 0, &history);
+        assert_eq!(other.next_nonce(), 0);
+    }
+
+    #[test]
+    fn test_abandoned_drafts_do_not_hold_nonces() {
+        let account = "0x1111111111111111111111111111111111111111";
+        let mut history = History {
+            records: (0..3)
+                .map(|nonce| record(nonce, TxStatus::Built, 0))
+                .collect(),
+        };
+        // Drafts nobody signs never settle, so they can't keep later transactions waiting
+        let report = NonceManager::new(account, 1, 0, &history).report(Utc::now());
+        assert_eq!(report.next, 0);
+        assert!(report.pending.is_empty());
+        assert_eq!(report.drafts.len(), 3);
+
+        // Once signed, a transaction keeps its nonce
+        history.records[0].status = TxStatus::Signed;
+        assert_eq!(NonceManager::new(account, 1, 0, &history).next_nonce(), 1);
+    }
+
+    #[test]
+    fn test_build_replacement() {
+        assert_eq!(replacement_gas_price(10), 11);
+        assert_eq!(replacement_gas_price(1), 2);
+        assert_eq!(replacement_gas_price(95), 105);
+
+        let pending = record(3, TxStatus::Submitted, 1);
+        let cancel = build_replacement(&pending, true, None).unwrap();
+        assert_eq!(cancel.nonce, 3);
+        assert_eq!(cancel.chain_id, 1);
+        assert_eq!(cancel.to.as_deref(), Some(pending.account.as_str()));
+        assert_eq!(cancel.amount, Some(0));
+        assert_eq!(cancel.gas_price, 11);
+
+        let error = build_replacement(&pending, true, Some(10)).unwrap_err();
+        assert_eq!(
+            crate::error::ErrorKind::of(&error),
+            crate::error::ErrorKind::InvalidInput
+        );
+        // Without the signed bytes there is nothing to resend
+        assert!(build_replacement(&pending, false, None).is_err());
+
+        let transaction = Transaction {
+            chain_id: 1,
+            from: pending.account.clone(),
+            to: Some("0x2222222222222222222222222222222222222222".to_string()),
+            amount: Some(1),
+            data: None,
+            nonce: 3,
+            gas_limit: 21000,
+            gas_price: 10,
+        };
+        let signed = SignedTransaction {
+            transaction: transaction.clone(),
+            signatures: Vec::new(),
+        };
+        let finalized = TxRecord {
+            raw: Some(format!("0x{}", hex::encode(signed.to_bytes().unwrap()))),
+            ..pending.clone()
+        };
+        let speed_up = build_replacement(&finalized, false, Some(20)).unwrap();
+        assert_eq!(
+            speed_up,
+            Transaction {
+                gas_price: 20,
+                ..transaction
+            }
+        );
+        assert!(build_replacement(&record(3, TxStatus::Confirmed, 1), true, None).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+use crate::crypto::{
+    hash_transaction_data, sign_message_with_scheme, verify_signature_with_scheme,
+};
+use crate::multisig::MultisigAccount;
+use crate::types::{AccountKey, AccountKeyPair, SignatureScheme, Transaction};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Magic bytes at the start of the binary encoding
+pub const PST_MAGIC: &[u8; 4] = b"MPST";
+
+/// Current version of the partially signed transaction format. Version 2
+/// signing hashes cover the chain id, so version 1 files can't be verified.
+pub const PST_VERSION: u8 = 2;
+
+/// Identifier stored in the JSON encoding
+const PST_FORMAT_NAME: &str = "rwmth-pst";
+
+/// Encoding of a partially signed transaction file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PstFormat {
+    /// Pretty-printed JSON, for offline review
+    #[default]
+    Json,
+    /// Magic bytes, version byte and bincode payload
+    Binary,
+}
+
+impl std::fmt::Display for PstFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PstFormat::Json => write!(f, "json"),
+            PstFormat::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl std::str::FromStr for PstFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(PstFormat::Json),
+            "binary" | "bin" => Ok(PstFormat::Binary),
+            other => Err(format!("Unknown transaction file format: {}", other)),
+        }
+    }
+}
+
+/// Fixed-size bytes, 0x-prefixed hex in JSON and raw bytes in binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> FixedBytes<N> {
+    /// Convert to 0x-prefixed hex string
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+impl<const N: usize> Serialize for FixedBytes<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedBytes<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bytes = if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            hex::decode(s.trim_start_matches("0x")).map_err(serde::de::Error::custom)?
+        } else {
+            Vec::<u8>::deserialize(deserializer)?
+        };
+        let array: [u8; N] = bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::custom(format!("Expected {} bytes", N)))?;
+        Ok(FixedBytes(array))
+    }
+}
+
+/// Signature contributed by one of the required signers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PartialSignature {
+    /// Signer public key
+    pub public_key: AccountKey,
+    /// Scheme the signature was made with
+    pub scheme: SignatureScheme,
+    /// Signature over the signing hash
+    pub signature: FixedBytes<64>,
+}
+
+/// Unsigned or partially signed transaction, passed between signers as a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartiallySignedTransaction {
+    /// Always `rwmth-pst`
+    pub format: String,
+    /// Format version
+    pub version: u8,
+    /// The decoded transaction
+    pub transaction: Transaction,
+    /// Hash every signer signs
+    pub signing_hash: FixedBytes<32>,
+    /// Public keys allowed to sign
+    pub required_signers: Vec<AccountKey>,
+    /// Number of signatures needed
+    pub threshold: usize,
+    /// Signatures collected so far
+    pub signatures: Vec<PartialSignature>,
+}
+
+/// Fully signed transaction ready to be broadcast
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTransaction {
+    /// The transaction
+    pub transaction: Transaction,
+    /// Signatures meeting the threshold
+    pub signatures: Vec<PartialSignature>,
+}
+
+impl SignedTransaction {
+    /// Raw bytes to submit to a node
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Decode raw signed transaction bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes).map_err(|e| anyhow!("Invalid signed transaction: {}", e))
+    }
+
+    /// Transaction hash (SHA-256 of the raw bytes)
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        Ok(hash_transaction_data(&self.to_bytes()?))
+    }
+}
+
+impl PartiallySignedTransaction {
+    /// Create an unsigned transaction requiring `threshold` of `required_signers`
+    pub fn new(
+        transaction: Transaction,
+        required_signers: Vec<AccountKey>,
+        threshold: usize,
+    ) -> Result<Self> {
+        if required_signers.is_empty() {
+            return Err(anyhow!("At least one signer is required"));
+        }
+        if threshold == 0 || threshold > required_signers.len() {
+            return Err(anyhow!(
+                "Threshold must be between 1 and {}",
+                required_signers.len()
+            ));
+        }
+
+        let signing_hash = FixedBytes(transaction.signing_hash()?);
+        Ok(PartiallySignedTransaction {
+            format: PST_FORMAT_NAME.to_string(),
+            version: PST_VERSION,
+            transaction,
+            signing_hash,
+            required_signers,
+            threshold,
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Create an unsigned transaction for a single-key account
+    pub fn for_account(transaction: Transaction, account: &AccountKeyPair) -> Result<Self> {
+        Self::new(transaction, vec![account.public_key.clone()], 1)
+    }
+
+    /// Create an unsigned transaction for a multisig account
+    pub fn for_multisig(transaction: Transaction, multisig: &MultisigAccount) -> Result<Self> {
+        if !transaction
+            .from
+            .trim_start_matches("0x")
+            .eq_ignore_ascii_case(multisig.address.trim_start_matches("0x"))
+        {
+            return Err(anyhow!(
+                "Transaction sender {} is not multisig {}",
+                transaction.from,
+                multisig.address
+            ));
+        }
+        Self::new(
+            transaction,
+            multisig.participants.clone(),
+            multisig.threshold,
+        )
+    }
+
+    /// Decode JSON or binary bytes, detecting the format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let pst: PartiallySignedTransaction = if let Some(payload) = bytes.strip_prefix(PST_MAGIC) {
+            let (version, payload) = payload
+                .split_first()
+                .ok_or_else(|| anyhow!("Truncated transaction file"))?;
+            if *version != PST_VERSION {
+                return Err(anyhow!("Unsupported transaction file version: {}", version));
+            }
+            bincode::deserialize(payload)
+                .map_err(|e| anyhow!("Invalid binary transaction file: {}", e))?
+        } else {
+            serde_json::from_slice(bytes)
+                .map_err(|e| anyhow!("Invalid JSON transaction file: {}", e))?
+        };
+
+        if pst.format != PST_FORMAT_NAME {
+            return Err(anyhow!("Not a transaction file: {}", pst.format));
+        }
+        if pst.version != PST_VERSION {
+            return Err(anyhow!(
+                "Unsupported transaction file version: {}",
+                pst.version
+            ));
+        }
+        pst.verify()?;
+        Ok(pst)
+    }
+
+    /// Encode in the given format
+    pub fn to_bytes(&self, format: PstFormat) -> Result<Vec<u8>> {
+        match format {
+            PstFormat::Json => Ok(serde_json::to_vec_pretty(self)?),
+            PstFormat::Binary => {
+                let mut bytes = PST_MAGIC.to_vec();
+                bytes.push(PST_VERSION);
+                bytes.extend(bincode::serialize(self)?);
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Load a transaction file in either format
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let bytes = fs::read(path.as_ref()).map_err(|e| {
+            anyhow!(
+                "Failed to read transaction file {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Save the transaction file in the given format
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: PstFormat) -> Result<()> {
+        fs::write(path, self.to_bytes(format)?)?;
+        Ok(())
+    }
+
+    /// Add a signature from one of the required signers
+    pub fn sign(&mut self, keypair: &AccountKeyPair, scheme: SignatureScheme) -> Result<()> {
+        if !self.required_signers.contains(&keypair.public_key) {
+            return Err(anyhow!(
+                "Account {} is not a required signer of this transaction",
+                keypair.get_address()
+            ));
+        }
+
+        let signature =
+            sign_message_with_scheme(&self.signing_hash.0, &keypair.private_key, scheme)?;
+        self.signatures
+            .retain(|existing| existing.public_key != keypair.public_key);
+        self.signatures.push(PartialSignature {
+            public_key: keypair.public_key.clone(),
+            scheme,
+            signature: FixedBytes(signature),
+        });
+        self.sort_signatures();
+        Ok(())
+    }
+
+    /// Merge signatures collected by another signer for the same transaction
+    pub fn combine(&mut self, other: &PartiallySignedTransaction) -> Result<()> {
+        if other.signing_hash != self.signing_hash
+            || other.required_signers != self.required_signers
+            || other.threshold != self.threshold
+        {
+            return Err(anyhow!("Transaction files do not match"));
+        }
+        other.verify()?;
+
+        for signature in &other.signatures {
+            if !self
+                .signatures
+                .iter()
+                .any(|existing| existing.public_key == signature.public_key)
+            {
+                self.signatures.push(signature.clone());
+            }
+        }
+        self.sort_signatures();
+        Ok(())
+    }
+
+    /// Whether enough signatures have been collected
+    pub fn is_complete(&self) -> bool {
+        self.signatures.len() >= self.threshold
+    }
+
+    /// Required signers that have not signed yet
+    pub fn missing_signers(&self) -> Vec<AccountKey> {
+        self.required_signers
+            .iter()
+            .filter(|key| {
+                !self
+                    .signatures
+                    .iter()
+                    .any(|signature| &signature.public_key == *key)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Check the signing hash and every collected signature
+    pub fn verify(&self) -> Result<()> {
+        if self.signing_hash.0 != self.transaction.signing_hash()? {
+            return Err(anyhow!("Signing hash does not match the transaction"));
+        }
+
+        for (i, signature) in self.signatures.iter().enumerate() {
+            if !self.required_signers.contains(&signature.public_key) {
+                return Err(anyhow!(
+                    "Signer {} is not a required signer",
+                    signature.public_key.to_hex()
+                ));
+            }
+            if self.signatures[..i]
+                .iter()
+                .any(|other| other.public_key == signature.public_key)
+            {
+                return Err(anyhow!(
+                    "Duplicate signature from {}",
+                    signature.public_key.to_hex()
+                ));
+            }
+            if !verify_signature_with_scheme(
+                &self.signing_hash.0,
+                &signature.signature.0,
+                &signature.public_key.0,
+                signature.scheme,
+            )? {
+                return Err(anyhow!(
+                    "Invalid signature from {}",
+                    signature.public_key.to_hex()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Produce the final signed transaction once the threshold is met
+    pub fn finalize(&self) -> Result<SignedTransaction> {
+        self.verify()?;
+        if !self.is_complete() {
+            return Err(anyhow!(
+                "Not enough signatures: {} of {}",
+                self.signatures.len(),
+                self.threshold
+            ));
+        }
+
+        Ok(SignedTransaction {
+            transaction: self.transaction.clone(),
+            signatures: self.signatures.clone(),
+        })
+    }
+
+    /// Keep signatures in required-signer order so files combine deterministically
+    fn sort_signatures(&mut self) {
+        let required = &self.required_signers;
+        self.signatures.sort_by_key(|signature| {
+            required
+                .iter()
+                .position(|key| key == &signature.public_key)
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_keypair;
+
+    fn transaction(from: &str) -> Transaction {
+        Transaction {
+            chain_id: 1,
+            from: from.to_string(),
+            to: Some("0x1111111111111111111111111111111111111111".to_string()),
+            amount: Some(42),
+            data: None,
+            nonce: 7,
+            gas_limit: 21000,
+            gas_price: 1,
+        }
+    }
+
+    #[test]
+    fn test_json_and_binary_round_trip() {
+        let keypair = generate_keypair().unwrap();
+        let mut pst =
+            PartiallySignedTransaction::for_account(transaction(&keypair.get_address()), &keypair)
+                .unwrap();
+        pst.sign(&keypair, SignatureScheme::Schnorr).unwrap();
+
+        for format in [PstFormat::Json, PstFormat::Binary] {
+            let bytes = pst.to_bytes(format).unwrap();
+            let decoded = PartiallySignedTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.transaction, pst.transaction);
+            assert_eq!(decoded.signatures, pst.signatures);
+        }
+
+        let binary = pst.to_bytes(PstFormat::Binary).unwrap();
+        assert!(binary.starts_with(PST_MAGIC));
+        assert!(binary.len() < pst.to_bytes(PstFormat::Json).unwrap().len());
+    }
+
+    #[test]
+    fn test_multisig_sign_combine_finalize() {
+        let keys: Vec<AccountKeyPair> = (0..3).map(|_| generate_keypair().unwrap()).collect();
+        let outsider = generate_keypair().unwrap();
+        let multisig = MultisigAccount::new(
+            "team",
+            2,
+            keys.iter().map(|k| k.public_key.clone()).collect(),
+        )
+        .unwrap();
+
+        let mut first =
+            PartiallySignedTransaction::for_multisig(transaction(&multisig.address), &multisig)
+                .unwrap();
+        let mut second = first.clone();
+        assert!(first.sign(&outsider, SignatureScheme::Ecdsa).is_err());
+
+        first.sign(&keys[0], SignatureScheme::Ecdsa).unwrap();
+        assert!(!first.is_complete());
+        assert!(first.finalize().is_err());
+        assert_eq!(first.missing_signers().len(), 2);
+
+        second.sign(&keys[2], SignatureScheme::Ecdsa).unwrap();
+        first.combine(&second).unwrap();
+        assert!(first.is_complete());
+
+        let signed = first.finalize().unwrap();
+        let raw = signed.to_bytes().unwrap();
+        assert_eq!(
+            SignedTransaction::from_bytes(&raw)
+                .unwrap()
+                .signatures
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_tampered_transaction_is_rejected() {
+        let keypair = generate_keypair().unwrap();
+        let mut pst =
+            PartiallySignedTransaction::for_account(transaction(&keypair.get_address()), &keypair)
+                .unwrap();
+        pst.sign(&keypair, SignatureScheme::Ecdsa).unwrap();
+
+        let mut tampered = pst.clone();
+        tampered.transaction.amount = Some(1_000_000);
+        let bytes = tampered.to_bytes(PstFormat::Json).unwrap();
+        assert!(PartiallySignedTransaction::from_bytes(&bytes).is_err());
+
+        // Moving the signed transaction to another chain invalidates it too
+        let mut replayed = pst;
+        replayed.transaction.chain_id = 2;
+        let bytes = replayed.to_bytes(PstFormat::Json).unwrap();
+        assert!(PartiallySignedTransaction::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_older_versions_are_rejected() {
+        let keypair = generate_keypair().unwrap();
+        let mut pst =
+            PartiallySignedTransaction::for_account(transaction(&keypair.get_address()), &keypair)
+                .unwrap();
+        pst.version = 1;
+
+        let json = pst.to_bytes(PstFormat::Json).unwrap();
+        let mut binary = pst.to_bytes(PstFormat::Binary).unwrap();
+        binary[PST_MAGIC.len()] = 1;
+        for bytes in [json, binary] {
+            let error = PartiallySignedTransaction::from_bytes(&bytes).unwrap_err();
+            assert_eq!(error.to_string(), "Unsupported transaction file version: 1");
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use anyhow::{anyhow, Result};
+use qrcode::render::unicode;
+use qrcode::{Color, EcLevel, QrCode};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Prefix of every QR part, e.g. `RWMTH:PST/1-3/0A1B2C3D/<HEX>`
+pub const QR_PREFIX: &str = "RWMTH:PST";
+
+/// Payload bytes carried by each QR frame by default
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+
+/// Most frames a payload may be split into; a scanned part claiming more is
+/// rejected before anything is sized by its total
+pub const MAX_QR_PARTS: usize = 1024;
+
+/// Pixels per QR module in rendered images
+const MODULE_SIZE: usize = 8;
+
+/// White border around rendered codes, in modules
+const QUIET_ZONE: usize = 4;
+
+/// One frame of a multi-part QR payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrPart {
+    /// 1-based frame number
+    pub index: usize,
+    /// Number of frames in the payload
+    pub total: usize,
+    /// First 4 bytes of SHA-256 over the whole payload
+    pub checksum: [u8; 4],
+    /// Payload bytes carried by this frame
+    pub data: Vec<u8>,
+}
+
+impl QrPart {
+    /// Parse the text read from a QR frame
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let rest = text
+            .strip_prefix(QR_PREFIX)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .ok_or_else(|| anyhow!("Not a transaction QR code: {}", text))?;
+
+        let mut fields = rest.splitn(3, '/');
+        let (position, checksum, data) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(position), Some(checksum), Some(data)) => (position, checksum, data),
+            _ => return Err(anyhow!("Malformed transaction QR code")),
+        };
+
+        let (index, total) = position
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Malformed QR part position: {}", position))?;
+        let index: usize = index
+            .parse()
+            .map_err(|_| anyhow!("Malformed QR part position: {}", position))?;
+        let total: usize = total
+            .parse()
+            .map_err(|_| anyhow!("Malformed QR part position: {}", position))?;
+        if total > MAX_QR_PARTS {
+            return Err(anyhow!(
+                "QR payload of {} parts is over the limit of {}",
+                total,
+                MAX_QR_PARTS
+            ));
+        }
+        if index == 0 || index > total {
+            return Err(anyhow!("QR part {} out of range 1-{}", index, total));
+        }
+
+        let checksum = hex::decode(checksum)
+            .ok()
+            .and_then(|bytes| <[u8; 4]>::try_from(bytes).ok())
+            .ok_or_else(|| anyhow!("Malformed QR part checksum: {}", checksum))?;
+        let data = hex::decode(data).map_err(|_| anyhow!("Malformed QR part data"))?;
+
+        Ok(QrPart {
+            index,
+            total,
+            checksum,
+            data,
+        })
+    }
+
+    /// Text encoded in the QR frame (uppercase so it fits QR alphanumeric mode)
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}/{}-{}/{}/{}",
+            QR_PREFIX,
+            self.index,
+            self.total,
+            hex::encode_upper(self.checksum),
+            hex::encode_upper(&self.data)
+        )
+    }
+}
+
+/// Split a payload into QR part texts of at most `chunk_size` bytes each
+pub fn encode_parts(payload: &[u8], chunk_size: usize) -> Result<Vec<String>> {
+    if chunk_size == 0 {
+        return Err(anyhow!("Chunk size must be greater than 0"));
+    }
+    if payload.is_empty() {
+        return Err(anyhow!("Nothing to encode"));
+    }
+
+    let checksum = payload_checksum(payload);
+    let total = payload.len().div_ceil(chunk_size);
+    if total > MAX_QR_PARTS {
+        return Err(anyhow!(
+            "Payload needs {} QR parts, over the limit of {}; use a larger chunk size",
+            total,
+            MAX_QR_PARTS
+        ));
+    }
+    Ok(payload
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            QrPart {
+                index: i + 1,
+                total,
+                checksum,
+                data: chunk.to_vec(),
+            }
+            .to_text()
+        })
+        .collect())
+}
+
+fn payload_checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(payload);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Collects QR parts in any order, ignoring duplicates, until the payload is complete
+#[derive(Debug, Default)]
+pub struct QrAssembler {
+    total: Option<usize>,
+    checksum: Option<[u8; 4]>,
+    parts: BTreeMap<usize, Vec<u8>>,
+}
+
+impl QrAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scanned part; returns false if it was already received
+    pub fn add(&mut self, text: &str) -> Result<bool> {
+        let part = QrPart::parse(text)?;
+        match (self.total, self.checksum) {
+            (Some(total), Some(checksum)) => {
+                if part.total != total || part.checksum != checksum {
+                    return Err(anyhow!("QR part belongs to a different transaction"));
+                }
+            }
+            _ => {
+                self.total = Some(part.total);
+                self.checksum = Some(part.checksum);
+            }
+        }
+
+        if self.parts.contains_key(&part.index) {
+            return Ok(false);
+        }
+        self.parts.insert(part.index, part.data);
+        Ok(true)
+    }
+
+    /// Number of distinct parts received so far
+    pub fn received(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Number of parts in the payload, once the first part is seen
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.total == Some(self.parts.len())
+    }
+
+    /// 1-based numbers of parts not yet received
+    pub fn missing(&self) -> Vec<usize> {
+        let total = self.total.unwrap_or(0);
+        (1..=total)
+            .filter(|index| !self.parts.contains_key(index))
+            .collect()
+    }
+
+    /// Join the parts and check the payload checksum
+    pub fn assemble(&self) -> Result<Vec<u8>> {
+        if !self.is_complete() {
+            return Err(anyhow!(
+                "Missing QR parts: {:?} of {}",
+                self.missing(),
+                self.total.unwrap_or(0)
+            ));
+        }
+
+        let payload: Vec<u8> = self.parts.values().flatten().copied().collect();
+        if Some(payload_checksum(&payload)) != self.checksum {
+            return Err(anyhow!("QR payload checksum mismatch"));
+        }
+        Ok(payload)
+    }
+}
+
+/// 8-bit greyscale image
+#[derive(Debug, Clone)]
+pub struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+fn qr_code(text: &str) -> Result<QrCode> {
+    QrCode::with_error_correction_level(text.as_bytes(), EcLevel::M)
+        .map_err(|e| anyhow!("Failed to encode QR code: {}", e))
+}
+
+/// Render a QR part as text for display in a terminal
+pub fn render_terminal(text: &str) -> Result<String> {
+    Ok(qr_code(text)?
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
+
+/// Render a QR part as a greyscale image with a quiet zone
+pub fn render_image(text: &str) -> Result<GrayImage> {
+    let code = qr_code(text)?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * MODULE_SIZE;
+
+    let mut pixels = vec![255u8; size * size];
+    for y in 0..size {
+        for x in 0..size {
+            let (mx, my) = (x / MODULE_SIZE, y / MODULE_SIZE);
+            if mx < QUIET_ZONE || my < QUIET_ZONE {
+                continue;
+            }
+            let (mx, my) = (mx - QUIET_ZONE, my - QUIET_ZONE);
+            if mx < modules && my < modules && colors[my * modules + mx] == Color::Dark {
+                pixels[y * size + x] = 0;
+            }
+        }
+    }
+
+    Ok(GrayImage {
+        width: size,
+        height: size,
+        pixels,
+    })
+}
+
+/// Decode every QR code found in an image
+pub fn decode_image(image: &GrayImage) -> Result<Vec<String>> {
+    let mut prepared =
+        rqrr::PreparedImage::prepare_from_greyscale(image.width, image.height, |x, y| {
+            image.pixels[y * image.width + x]
+        });
+
+    let texts: Vec<String> = prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| grid.decode().ok().map(|(_, text)| text))
+        .collect();
+    if texts.is_empty() {
+        return Err(anyhow!("No QR code found in image"));
+    }
+    Ok(texts)
+}
+
+/// Save an image as a greyscale PNG
+pub fn write_png<P: AsRef<Path>>(path: P, image: &GrayImage) -> Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        image.width as u32,
+        image.height as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    Ok(())
+}
+
+/// Load a PNG (any color type) as a greyscale image
+pub fn read_png<P: AsRef<Path>>(path: P) -> Result<GrayImage> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let channels = info.color_type.samples();
+    let pixels = buffer[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| match channels {
+            1 | 2 => pixel[0],
+            _ => {
+                ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000)
+                    as u8
+            }
+        })
+        .collect();
+
+    Ok(GrayImage {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_keypair;
+    use crate::pst::{PartiallySignedTransaction, PstFormat};
+    use crate::types::{SignatureScheme, Transaction};
+
+    fn signed_pst_bytes() -> Vec<u8> {
+        let keypair = generate_keypair().unwrap();
+        let transaction = Transaction {
+            chain_id: 1,
+            from: keypair.get_address(),
+            to: Some("0x0123456789abcdef0123456789abcdef01234567".to_string()),
+            amount: Some(42),
+            data: Some(format!("0x{}", "ab".repeat(200))),
+            nonce: 7,
+            gas_limit: 21000,
+            gas_price: 1,
+        };
+        let mut pst = PartiallySignedTransaction::for_account(transaction, &keypair).unwrap();
+        pst.sign(&keypair, SignatureScheme::Ecdsa).unwrap();
+        pst.to_bytes(PstFormat::Binary).unwrap()
+    }
+
+    #[test]
+    fn test_multipart_qr_image_round_trip() {
+        let payload = signed_pst_bytes();
+        let parts = encode_parts(&payload, 128).unwrap();
+        assert!(parts.len() > 1);
+
+        // Scan the frames out of order, with a repeat, as a camera would
+        let mut assembler = QrAssembler::new();
+        for part in parts.iter().rev().chain(parts.first()) {
+            let image = render_image(part).unwrap();
+            for text in decode_image(&image).unwrap() {
+                assembler.add(&text).unwrap();
+            }
+        }
+
+        assert!(assembler.is_complete());
+        let assembled = assembler.assemble().unwrap();
+        assert_eq!(assembled, payload);
+        let pst = PartiallySignedTransaction::from_bytes(&assembled).unwrap();
+        assert!(pst.is_complete());
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frame.png");
+        let part = &encode_parts(b"hello air gap", DEFAULT_CHUNK_SIZE).unwrap()[0];
+
+        write_png(&path, &render_image(part).unwrap()).unwrap();
+        let texts = decode_image(&read_png(&path).unwrap()).unwrap();
+        assert_eq!(texts, vec![part.clone()]);
+    }
+
+    #[test]
+    fn test_incomplete_or_mixed_parts_rejected() {
+        let first = encode_parts(&[1u8; 100], 40).unwrap();
+        let second = encode_parts(&[2u8; 100], 40).unwrap();
+
+        let mut assembler = QrAssembler::new();
+        assembler.add(&first[0]).unwrap();
+        assert!(assembler.add(&second[1]).is_err());
+        assert_eq!(assembler.missing(), vec![2, 3]);
+        assert!(assembler.assemble().is_err());
+
+        assert!(QrPart::parse("RWMTH:PST/4-3/00000000/AB").is_err());
+        // A hostile total is refused before anything is sized by it
+        assert!(QrPart::parse("RWMTH:PST/1-18446744073709551615/00000000/AB").is_err());
+        assert!(assembler.add("RWMTH:PST/1-1025/00000000/AB").is_err());
+        assert_eq!(assembler.missing(), vec![2, 3]);
+        assert!(encode_parts(&[0u8; MAX_QR_PARTS + 1], 1).is_err());
+        assert!(QrPart::parse("hello").is_err());
+    }
+}
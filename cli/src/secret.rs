@@ -0,0 +1,136 @@
+use crate::error::{ErrorKind, WalletError};
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Where to read a secret (mnemonic, private key) from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretSource {
+    /// Hidden prompt on the controlling terminal
+    Prompt(String),
+    /// Everything read from standard input
+    Stdin,
+    /// Contents of a file
+    File(PathBuf),
+    /// Value of the named environment variable
+    Env(String),
+    /// Given directly on the command line
+    Value(String),
+}
+
+impl SecretSource {
+    /// Read the secret, with surrounding whitespace removed
+    pub fn read(&self) -> Result<String> {
+        let secret = match self {
+            SecretSource::Prompt(prompt) => {
+                if !std::io::stdin().is_terminal() {
+                    return Err(WalletError::new(
+                        ErrorKind::Usage,
+                        "No terminal to prompt on; read the secret from stdin, a file or an environment variable instead",
+                    )
+                    .into());
+                }
+                rpassword::prompt_password(prompt).context("Failed to read from terminal")?
+            }
+            SecretSource::Stdin => {
+                let mut secret = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut secret)
+                    .context("Failed to read from stdin")?;
+                secret
+            }
+            SecretSource::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read secret file {}", path.display()))?,
+            SecretSource::Env(name) => std::env::var(name).map_err(|_| {
+                WalletError::not_found(format!("Environment variable {} is not set", name))
+            })?,
+            SecretSource::Value(value) => value.clone(),
+        };
+
+        let secret = secret.trim().to_string();
+        if secret.is_empty() {
+            return Err(WalletError::invalid_input("Secret is empty").into());
+        }
+        Ok(secret)
+    }
+}
+
+/// Read a mnemonic and collapse its whitespace to single spaces
+pub fn read_mnemonic(source: &SecretSource) -> Result<String> {
+    Ok(source
+        .read()?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Write a secret to a new file readable only by the owner (0600 on Unix).
+/// Existing files are never overwritten, so permissions can't be inherited.
+pub fn write_secret_file(path: &Path, secret: &str) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AlreadyExists {
+            WalletError::already_exists(format!("File already exists: {}", path.display())).into()
+        } else {
+            anyhow::Error::from(e).context(format!("Failed to create {}", path.display()))
+        }
+    })?;
+    writeln!(file, "{}", secret)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_secret_sources() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mnemonic.txt");
+        std::fs::write(&path, "  abandon   abandon\n  about \n").unwrap();
+        assert_eq!(
+            read_mnemonic(&SecretSource::File(path)).unwrap(),
+            "abandon abandon about"
+        );
+
+        std::env::set_var("RWMTH_TEST_SECRET_SOURCE", "secret\n");
+        assert_eq!(
+            SecretSource::Env("RWMTH_TEST_SECRET_SOURCE".to_string())
+                .read()
+                .unwrap(),
+            "secret"
+        );
+        assert!(SecretSource::Env("RWMTH_TEST_SECRET_UNSET".to_string())
+            .read()
+            .is_err());
+        assert!(SecretSource::Value("  ".to_string()).read().is_err());
+    }
+
+    #[test]
+    fn test_write_secret_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("key.txt");
+        write_secret_file(&path, "0xabc").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0xabc\n");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let error = write_secret_file(&path, "0xdef").unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0xabc\n");
+    }
+}
@@ -0,0 +1,308 @@
+//! Wallet storage in a sled database
+//!
+//! Every wallet is a tree named `wallet:<name>` holding:
+//!
+//! - `manager`: the wallet without its accounts (selection, multisig accounts, metadata)
+//! - `count`: the number of accounts
+//! - `account:<index>`: each account, keyed by its big-endian `u32` index
+//! - `address:<address>`: the index of the first account with that address
+//!
+//! Writes rewrite these keys in a single transaction, so a wallet is never left
+//! half updated, and accounts can be found by address without loading the rest.
+
+use crate::error::WalletError;
+use crate::storage::{create_private_dir, WalletName, WalletStore};
+use crate::types::{AccountKeyPair, AccountManager};
+use anyhow::{anyhow, Result};
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionalTree,
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Directory of the sled database inside the wallet directory
+pub const SLED_DB_DIR: &str = "wallets.db";
+
+const WALLET_TREE_PREFIX: &str = "wallet:";
+const MANAGER_KEY: &[u8] = b"manager";
+const COUNT_KEY: &[u8] = b"count";
+const ACCOUNT_PREFIX: &[u8] = b"account:";
+const ADDRESS_PREFIX: &[u8] = b"address:";
+
+type TxResult<T> = ConflictableTransactionResult<T, anyhow::Error>;
+
+/// The sled database holding the wallets of a wallet directory
+///
+/// sled locks the database while it is open, so a process opens it once and
+/// gets every wallet's store from here.
+#[derive(Clone)]
+pub struct SledWallets {
+    path: PathBuf,
+    db: sled::Db,
+}
+
+impl SledWallets {
+    /// Open the database in `wallet_dir`, creating it if needed
+    pub fn open<P: AsRef<Path>>(wallet_dir: P) -> Result<Self> {
+        let path = wallet_dir.as_ref().join(SLED_DB_DIR);
+        // sled creates its files inside this directory, so creating it private keeps them private
+        create_private_dir(&path)?;
+        let db = sled::open(&path).map_err(|error| {
+            anyhow!(
+                "Failed to open wallet database {}: {}",
+                path.display(),
+                error
+            )
+        })?;
+        Ok(SledWallets { path, db })
+    }
+
+    /// Directory of the database
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store of the wallet called `name`
+    pub fn wallet(&self, name: &WalletName) -> Result<SledStore> {
+        Ok(SledStore {
+            path: self.path.clone(),
+            tree: self
+                .db
+                .open_tree(format!("{}{}", WALLET_TREE_PREFIX, name))?,
+        })
+    }
+
+    /// Names of the wallets in the database, sorted
+    pub fn list_wallets(&self) -> Result<Vec<String>> {
+        let mut wallets = Vec::new();
+        for tree_name in self.db.tree_names() {
+            let Some(name) = std::str::from_utf8(&tree_name)
+                .ok()
+                .and_then(|name| name.strip_prefix(WALLET_TREE_PREFIX))
+            else {
+                continue;
+            };
+            // Opening a tree creates it, so empty trees are not wallets
+            if !self.db.open_tree(&tree_name)?.is_empty() {
+                wallets.push(name.to_string());
+            }
+        }
+        wallets.sort();
+        Ok(wallets)
+    }
+}
+
+/// One wallet in a sled database
+pub struct SledStore {
+    path: PathBuf,
+    tree: sled::Tree,
+}
+
+fn account_key(index: usize) -> Vec<u8> {
+    [ACCOUNT_PREFIX, &(index as u32).to_be_bytes()].concat()
+}
+
+fn address_key(address: &str) -> Vec<u8> {
+    [ADDRESS_PREFIX, address.as_bytes()].concat()
+}
+
+fn abort(error: impl Into<anyhow::Error>) -> ConflictableTransactionError<anyhow::Error> {
+    ConflictableTransactionError::Abort(error.into())
+}
+
+fn decode_index(bytes: &[u8]) -> TxResult<usize> {
+    let bytes: [u8; 4] = bytes
+        .try_into()
+        .map_err(|_| abort(anyhow!("Corrupted wallet database: bad index")))?;
+    Ok(u32::from_be_bytes(bytes) as usize)
+}
+
+fn read_account(tx: &TransactionalTree, index: usize) -> TxResult<AccountKeyPair> {
+    let account = tx.get(account_key(index))?.ok_or_else(|| {
+        abort(anyhow!(
+            "Corrupted wallet database: account {} is missing",
+            index
+        ))
+    })?;
+    serde_json::from_slice(&account).map_err(abort)
+}
+
+fn read_count(tx: &TransactionalTree) -> TxResult<usize> {
+    match tx.get(COUNT_KEY)? {
+        Some(count) => decode_index(&count),
+        None => Ok(0),
+    }
+}
+
+fn read_manager(tx: &TransactionalTree) -> TxResult<AccountManager> {
+    let manager = tx
+        .get(MANAGER_KEY)?
+        .ok_or_else(|| abort(WalletError::not_found("Wallet not found")))?;
+    let mut manager: AccountManager = serde_json::from_slice(&manager).map_err(abort)?;
+    for index in 0..read_count(tx)? {
+        manager.account_map.push(read_account(tx, index)?);
+    }
+    Ok(manager)
+}
+
+fn write_manager(tx: &TransactionalTree, manager: &AccountManager) -> TxResult<()> {
+    for index in 0..read_count(tx)? {
+        let old = read_account(tx, index)?;
+        tx.remove(address_key(&old.get_address()))?;
+        tx.remove(account_key(index))?;
+    }
+
+    let mut addresses = HashSet::new();
+    for (index, account) in manager.account_map.iter().enumerate() {
+        tx.insert(
+            account_key(index),
+            serde_json::to_vec(account).map_err(abort)?,
+        )?;
+        let address = account.get_address();
+        if addresses.insert(address.clone()) {
+            tx.insert(address_key(&address), &(index as u32).to_be_bytes())?;
+        }
+    }
+    tx.insert(COUNT_KEY, &(manager.account_map.len() as u32).to_be_bytes())?;
+
+    let without_accounts = AccountManager {
+        account_map: Vec::new(),
+        ..manager.clone()
+    };
+    tx.insert(
+        MANAGER_KEY,
+        serde_json::to_vec(&without_accounts).map_err(abort)?,
+    )?;
+    Ok(())
+}
+
+impl SledStore {
+    fn transaction<T>(&self, f: impl Fn(&TransactionalTree) -> TxResult<T>) -> Result<T> {
+        self.tree.transaction(f).map_err(|error| match error {
+            TransactionError::Abort(error) => error,
+            TransactionError::Storage(error) => error.into(),
+        })
+    }
+}
+
+impl WalletStore for SledStore {
+    fn location(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn exists(&self) -> bool {
+        self.tree.contains_key(MANAGER_KEY).unwrap_or(false)
+    }
+
+    fn load(&self) -> Result<AccountManager> {
+        self.transaction(read_manager)
+    }
+
+    fn save(&self, manager: &AccountManager) -> Result<()> {
+        self.transaction(|tx| write_manager(tx, manager))?;
+        self.tree.flush()?;
+        Ok(())
+    }
+
+    fn update(&self, change: &dyn Fn(&mut AccountManager) -> Result<()>) -> Result<AccountManager> {
+        let manager = self.transaction(|tx| {
+            let mut manager = read_manager(tx)?;
+            change(&mut manager).map_err(ConflictableTransactionError::Abort)?;
+            write_manager(tx, &manager)?;
+            Ok(manager)
+        })?;
+        self.tree.flush()?;
+        Ok(manager)
+    }
+
+    fn find_account(&self, address: &str) -> Result<Option<(usize, AccountKeyPair)>> {
+        self.transaction(|tx| match tx.get(address_key(address))? {
+            Some(index) => {
+                let index = decode_index(&index)?;
+                Ok(Some((index, read_account(tx, index)?)))
+            }
+            None => Ok(None),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address_book::ADDRESS_BOOK_FILE;
+    use crate::config::StorageBackend;
+    use crate::crypto::generate_keypair;
+    use crate::error::ErrorKind;
+    use crate::history::HISTORY_FILE;
+    use crate::storage::{migrate_wallets, Storage, Wallets};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sled_store_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let wallets = SledWallets::open(temp_dir.path()).unwrap();
+        let store = wallets.wallet(&WalletName::new("work").unwrap()).unwrap();
+        assert!(!store.exists());
+        assert!(wallets.list_wallets().unwrap().is_empty());
+
+        let first = store.init_account_manager().unwrap().account_map[0].clone();
+        let second = generate_keypair().unwrap();
+        store.add_account(second.clone()).unwrap();
+        assert_eq!(wallets.list_wallets().unwrap(), vec!["work"]);
+
+        assert_eq!(
+            store.find_account(&second.get_address()).unwrap(),
+            Some((1, second.clone()))
+        );
+
+        // A failed update leaves the wallet as it was
+        let error = store
+            .update(&|manager| {
+                manager.account_map.clear();
+                Err(WalletError::invalid_input("no").into())
+            })
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidInput);
+        assert_eq!(store.get_account_count().unwrap(), 2);
+
+        // Removing an account drops it from the address index
+        let manager = store.remove_account(0).unwrap();
+        assert_eq!(manager.account_map, vec![second.clone()]);
+        assert_eq!(manager.now_selected_account, second);
+        assert_eq!(store.find_account(&first.get_address()).unwrap(), None);
+        assert_eq!(
+            store.find_account(&second.get_address()).unwrap(),
+            Some((0, second.clone()))
+        );
+        assert_eq!(store.load().unwrap(), manager);
+    }
+
+    #[test]
+    fn test_migrate_between_backends() {
+        let temp_dir = TempDir::new().unwrap();
+        let json = Wallets::open(StorageBackend::Json, temp_dir.path()).unwrap();
+        let sled = Wallets::open(StorageBackend::Sled, temp_dir.path()).unwrap();
+        let work = WalletName::new("work").unwrap();
+        let manager = json.store(&work).unwrap().init_account_manager().unwrap();
+        Storage::new(temp_dir.path().join("default.json"))
+            .init_account_manager()
+            .unwrap();
+        // Other files in the wallet directory are not wallets
+        std::fs::write(temp_dir.path().join(ADDRESS_BOOK_FILE), "{}").unwrap();
+        std::fs::write(temp_dir.path().join(HISTORY_FILE), r#"{"records":[]}"#).unwrap();
+
+        let report = migrate_wallets(&json, &sled, &[]).unwrap();
+        assert_eq!(report.migrated, vec!["default", "work"]);
+        assert_eq!(sled.list().unwrap(), vec!["default", "work"]);
+        assert_eq!(sled.store(&work).unwrap().load().unwrap(), manager);
+
+        // Wallets already in the target are never overwritten
+        let report = migrate_wallets(&sled, &json, std::slice::from_ref(&work)).unwrap();
+        assert_eq!(report.skipped, vec!["work"]);
+
+        let missing = WalletName::new("missing").unwrap();
+        let error = migrate_wallets(&json, &sled, &[missing]).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+    }
+}
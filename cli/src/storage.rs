@@ -1,134 +1,473 @@
+use crate::config::StorageBackend;
+use crate::error::{ErrorKind, WalletError};
+use crate::sled_store::SledWallets;
 use crate::types::{AccountKeyPair, AccountManager};
-use anyhow::{anyhow, Result};
-use serde_json;
-use std::fs;
-use std::path::Path;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Maximum length of a wallet name, in characters
+pub const MAX_WALLET_NAME_LENGTH: usize = 64;
+
+/// Names that would collide with other files in the wallet directory:
+/// the address book and the transaction history
+const RESERVED_WALLET_NAMES: &[&str] = &["address_book", "history"];
+
+/// Device names Windows reserves in every directory, with or without an extension
+const WINDOWS_DEVICE_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// A wallet name that is safe to use as a file name inside the wallet directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WalletName(String);
+
+impl WalletName {
+    /// Validate a wallet name: no path separators, control characters,
+    /// leading dots, trailing dots or spaces, or reserved names
+    pub fn new(name: &str) -> Result<Self> {
+        let invalid = |reason: &str| -> anyhow::Error {
+            WalletError::invalid_input(format!("Invalid wallet name {:?}: {}", name, reason)).into()
+        };
+
+        if name.is_empty() {
+            return Err(invalid("name is empty"));
+        }
+        if name.chars().count() > MAX_WALLET_NAME_LENGTH {
+            return Err(invalid("name is too long"));
+        }
+        if name.chars().any(|c| {
+            c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+        }) {
+            return Err(invalid(
+                "name contains a path separator or reserved character",
+            ));
+        }
+        if name.starts_with('.') || name.ends_with(['.', ' ']) || name != name.trim() {
+            return Err(invalid(
+                "name starts with a dot or has surrounding whitespace",
+            ));
+        }
 
-/// Storage manager for account data
-pub struct Storage {
-    file_path: String,
+        let stem = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+        if RESERVED_WALLET_NAMES.contains(&name.to_ascii_lowercase().as_str())
+            || WINDOWS_DEVICE_NAMES.contains(&stem.as_str())
+        {
+            return Err(invalid("name is reserved"));
+        }
+
+        Ok(WalletName(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
-impl Storage {
-    /// Create a new storage instance
-    pub fn new<P: AsRef<Path>>(file_path: P) -> Self {
-        Storage {
-            file_path: file_path.as_ref().to_string_lossy().to_string(),
+impl FromStr for WalletName {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        WalletName::new(name)
+    }
+}
+
+impl fmt::Display for WalletName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Create `dir` and any missing parents, accessible only by the owner (0700 on Unix).
+/// Directories that already exist keep their permissions, with a warning when
+/// other users can access `dir` itself.
+pub fn create_private_dir<P: AsRef<Path>>(dir: P) -> Result<()> {
+    let dir = dir.as_ref();
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A new directory is 0700 at most, so only an existing one can be looser
+        let mode = fs::metadata(dir)?.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            tracing::warn!(
+                "{} has permissions {:03o} and is accessible by other users; run chmod 700 on it",
+                dir.display(),
+                mode
+            );
         }
     }
+    Ok(())
+}
 
-    /// Load account manager from file
-    pub fn load_account_manager(&self) -> Result<AccountManager> {
-        if !Path::new(&self.file_path).exists() {
-            return Err(anyhow!("Account file not found: {}", self.file_path));
+/// Write `contents` to a file readable only by the owner (0600 on Unix),
+/// tightening the permissions of an existing file before writing
+pub fn write_private_file<P: AsRef<Path>>(path: P, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_ref())?;
+    Ok(())
+}
+
+/// Check that a wallet file is private to the current user, the way SSH treats
+/// private keys: files owned by another user or writable by group or others are
+/// refused, and files readable by group or others load with a warning
+pub fn check_private_file<P: AsRef<Path>>(path: P) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let path = path.as_ref();
+        let metadata = fs::metadata(path)?;
+        // geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        if metadata.uid() != uid {
+            return Err(WalletError::new(
+                ErrorKind::Io,
+                format!(
+                    "Refusing to load {}: owned by uid {}, not the current user",
+                    path.display(),
+                    metadata.uid()
+                ),
+            )
+            .into());
         }
 
-        let content = fs::read_to_string(&self.file_path)?;
-        let account_manager: AccountManager = serde_json::from_str(&content)?;
-        Ok(account_manager)
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o022 != 0 {
+            return Err(WalletError::new(
+                ErrorKind::Io,
+                format!(
+                    "Refusing to load {}: permissions {:03o} allow others to modify it; run chmod 600 on it",
+                    path.display(),
+                    mode
+                ),
+            )
+            .into());
+        }
+        if mode & 0o044 != 0 {
+            tracing::warn!(
+                "{} has permissions {:03o} and is readable by other users; run chmod 600 on it",
+                path.display(),
+                mode
+            );
+        }
     }
+    Ok(())
+}
 
-    /// Save account manager to file
-    pub fn save_account_manager(&self, account_manager: &AccountManager) -> Result<()> {
-        // Create directory if it doesn't exist
-        if let Some(parent) = Path::new(&self.file_path).parent() {
-            fs::create_dir_all(parent)?;
+/// Path of the wallet file for `name` inside `wallet_dir`
+pub fn wallet_file<P: AsRef<Path>>(wallet_dir: P, name: &WalletName) -> PathBuf {
+    wallet_dir.as_ref().join(format!("{}.json", name))
+}
+
+/// Names of the wallet files in `wallet_dir`, sorted
+pub fn list_wallets<P: AsRef<Path>>(wallet_dir: P) -> Result<Vec<String>> {
+    let wallet_dir = wallet_dir.as_ref();
+    if !wallet_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut wallets = Vec::new();
+    for entry in fs::read_dir(wallet_dir)?.flatten() {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            // Files whose names couldn't have been created as wallets, like the address book and history, are skipped
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                if WalletName::new(name).is_ok() {
+                    wallets.push(name.to_string());
+                }
+            }
         }
+    }
+    wallets.sort();
+    Ok(wallets)
+}
 
-        let content = serde_json::to_string_pretty(account_manager)?;
-        fs::write(&self.file_path, content)?;
-        Ok(())
+/// Where the accounts of one wallet are kept
+///
+/// `Storage` keeps a wallet as a JSON file and `SledStore` as a tree in a sled
+/// database. The provided methods build on `load` and `update`.
+pub trait WalletStore {
+    /// File or database holding the wallet, for messages
+    fn location(&self) -> PathBuf;
+
+    /// Check if the wallet exists
+    fn exists(&self) -> bool;
+
+    /// Load the wallet
+    fn load(&self) -> Result<AccountManager>;
+
+    /// Save the wallet, replacing what was stored
+    fn save(&self, manager: &AccountManager) -> Result<()>;
+
+    /// Load the wallet, apply `change` and save the result. Nothing is saved when
+    /// `change` fails. `change` may run more than once if the store retries.
+    fn update(&self, change: &dyn Fn(&mut AccountManager) -> Result<()>) -> Result<AccountManager> {
+        let mut manager = self.load()?;
+        change(&mut manager)?;
+        self.save(&manager)?;
+        Ok(manager)
+    }
+
+    /// Index and key pair of the first account with `address` (in `0x` form)
+    fn find_account(&self, address: &str) -> Result<Option<(usize, AccountKeyPair)>> {
+        Ok(self
+            .load()?
+            .account_map
+            .into_iter()
+            .enumerate()
+            .find(|(_, account)| account.get_address() == address))
+    }
+
+    /// The wallet as the JSON document the file backend stores
+    fn export(&self) -> Result<Value> {
+        Ok(serde_json::to_value(self.load()?)?)
+    }
+
+    /// Replace the wallet with a JSON document from `export`
+    fn import(&self, wallet: &Value) -> Result<()> {
+        let manager: AccountManager = serde_json::from_value(wallet.clone())?;
+        self.save(&manager)
     }
 
     /// Initialize account manager (create if doesn't exist)
-    pub fn init_account_manager(&self) -> Result<AccountManager> {
-        if Path::new(&self.file_path).exists() {
-            self.load_account_manager()
+    fn init_account_manager(&self) -> Result<AccountManager> {
+        if self.exists() {
+            self.load()
         } else {
             // Create default account manager
             let account_manager = AccountManager::default();
-            self.save_account_manager(&account_manager)?;
+            self.save(&account_manager)?;
             Ok(account_manager)
         }
     }
 
     /// Add account to manager and save
-    pub fn add_account(&self, account: AccountKeyPair) -> Result<AccountManager> {
-        let mut manager = self.load_account_manager()?;
-        manager.account_map.push(account.clone());
-        manager.now_selected_account = account;
-        self.save_account_manager(&manager)?;
-        Ok(manager)
+    fn add_account(&self, account: AccountKeyPair) -> Result<AccountManager> {
+        self.update(&|manager| {
+            manager.account_map.push(account.clone());
+            manager.now_selected_account = account.clone();
+            Ok(())
+        })
+    }
+
+    /// Remove account by index, selecting its neighbour if it was the selected account
+    fn remove_account(&self, index: usize) -> Result<AccountManager> {
+        self.update(&|manager| {
+            if index >= manager.account_map.len() {
+                return Err(WalletError::not_found("Account index out of bounds").into());
+            }
+            if manager.account_map.len() == 1 {
+                return Err(WalletError::invalid_input("Cannot remove the last account").into());
+            }
+
+            let removed = manager.account_map.remove(index);
+            if !manager.account_map.contains(&removed) {
+                manager.account_metadata.remove(&removed.get_address());
+            }
+            if manager.now_selected_account == removed {
+                let next = index.min(manager.account_map.len() - 1);
+                manager.now_selected_account = manager.account_map[next].clone();
+            }
+            Ok(())
+        })
+    }
+
+    /// Set selected account by index
+    fn set_selected_account(&self, index: usize) -> Result<AccountManager> {
+        self.update(&|manager| {
+            if index >= manager.account_map.len() {
+                return Err(WalletError::not_found("Account index out of bounds").into());
+            }
+
+            manager.now_selected_account = manager.account_map[index].clone();
+            Ok(())
+        })
     }
 
-    /// Remove account by index
-    pub fn remove_account(&self, index: usize) -> Result<AccountManager> {
-        let mut manager = self.load_account_manager()?;
+    /// Get account by index
+    fn get_account(&self, index: usize) -> Result<AccountKeyPair> {
+        let manager = self.load()?;
 
         if index >= manager.account_map.len() {
-            return Err(anyhow!("Account index out of bounds"));
+            return Err(WalletError::not_found("Account index out of bounds").into());
         }
 
-        manager.account_map.remove(index);
+        Ok(manager.account_map[index].clone())
+    }
 
-        // Update selected account if needed
-        if manager.account_map.is_empty() {
-            return Err(anyhow!("Cannot remove the last account"));
-        }
+    /// Get selected account
+    fn get_selected_account(&self) -> Result<AccountKeyPair> {
+        Ok(self.load()?.now_selected_account)
+    }
 
-        if index == 0 || manager.now_selected_account == manager.account_map[index - 1] {
-            manager.now_selected_account = manager.account_map[0].clone();
-        }
+    /// List all accounts
+    fn list_accounts(&self) -> Result<Vec<AccountKeyPair>> {
+        Ok(self.load()?.account_map)
+    }
 
-        self.save_account_manager(&manager)?;
-        Ok(manager)
+    /// Get account count
+    fn get_account_count(&self) -> Result<usize> {
+        Ok(self.load()?.account_map.len())
     }
+}
 
-    /// Set selected account by index
-    pub fn set_selected_account(&self, index: usize) -> Result<AccountManager> {
-        let mut manager = self.load_account_manager()?;
+/// The wallets of a wallet directory, kept by the configured storage backend
+pub enum Wallets {
+    /// One `<name>.json` file per wallet
+    Json(PathBuf),
+    /// One tree per wallet in a sled database
+    Sled(SledWallets),
+}
 
-        if index >= manager.account_map.len() {
-            return Err(anyhow!("Account index out of bounds"));
-        }
+impl Wallets {
+    /// Open the wallets in `wallet_dir`; the sled database is created if needed
+    pub fn open<P: AsRef<Path>>(backend: StorageBackend, wallet_dir: P) -> Result<Self> {
+        let wallet_dir = wallet_dir.as_ref();
+        Ok(match backend {
+            StorageBackend::Json => Wallets::Json(wallet_dir.to_path_buf()),
+            StorageBackend::Sled => Wallets::Sled(SledWallets::open(wallet_dir)?),
+        })
+    }
 
-        manager.now_selected_account = manager.account_map[index].clone();
-        self.save_account_manager(&manager)?;
-        Ok(manager)
+    /// Store of the wallet called `name`
+    pub fn store(&self, name: &WalletName) -> Result<Box<dyn WalletStore>> {
+        Ok(match self {
+            Wallets::Json(wallet_dir) => Box::new(Storage::new(wallet_file(wallet_dir, name))),
+            Wallets::Sled(wallets) => Box::new(wallets.wallet(name)?),
+        })
     }
 
-    /// Get account by index
-    pub fn get_account(&self, index: usize) -> Result<AccountKeyPair> {
-        let manager = self.load_account_manager()?;
+    /// Names of the wallets, sorted
+    pub fn list(&self) -> Result<Vec<String>> {
+        match self {
+            Wallets::Json(wallet_dir) => list_wallets(wallet_dir),
+            Wallets::Sled(wallets) => wallets.list_wallets(),
+        }
+    }
+}
 
-        if index >= manager.account_map.len() {
-            return Err(anyhow!("Account index out of bounds"));
+/// What `migrate_wallets` did with each wallet
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    /// Wallets copied to the target backend
+    pub migrated: Vec<String>,
+    /// Wallets left alone because the target already has a wallet with the same name
+    pub skipped: Vec<String>,
+}
+
+/// Copy `names` (every wallet when empty) from `source` to `target`. Wallets
+/// already in `target` are skipped, and nothing is removed from `source`.
+pub fn migrate_wallets(
+    source: &Wallets,
+    target: &Wallets,
+    names: &[WalletName],
+) -> Result<MigrationReport> {
+    let names = if names.is_empty() {
+        source
+            .list()?
+            .iter()
+            .map(|name| WalletName::new(name))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        names.to_vec()
+    };
+
+    // Load everything first so an unreadable wallet stops the migration before any write
+    let mut wallets = Vec::new();
+    for name in names {
+        let store = source.store(&name)?;
+        if !store.exists() {
+            return Err(WalletError::not_found(format!("Wallet not found: {}", name)).into());
         }
+        wallets.push((name, store.load()?));
+    }
 
-        Ok(manager.account_map[index].clone())
+    let mut report = MigrationReport::default();
+    for (name, manager) in wallets {
+        let store = target.store(&name)?;
+        if store.exists() {
+            report.skipped.push(name.to_string());
+        } else {
+            store.save(&manager)?;
+            report.migrated.push(name.to_string());
+        }
     }
+    Ok(report)
+}
 
-    /// Get selected account
-    pub fn get_selected_account(&self) -> Result<AccountKeyPair> {
-        let manager = self.load_account_manager()?;
-        Ok(manager.now_selected_account.clone())
+/// Storage manager for account data, one JSON file per wallet
+pub struct Storage {
+    file_path: String,
+}
+
+impl Storage {
+    /// Create a new storage instance
+    pub fn new<P: AsRef<Path>>(file_path: P) -> Self {
+        Storage {
+            file_path: file_path.as_ref().to_string_lossy().to_string(),
+        }
     }
 
-    /// List all accounts
-    pub fn list_accounts(&self) -> Result<Vec<AccountKeyPair>> {
-        let manager = self.load_account_manager()?;
-        Ok(manager.account_map.clone())
+    /// Load account manager from file
+    pub fn load_account_manager(&self) -> Result<AccountManager> {
+        if !Path::new(&self.file_path).exists() {
+            return Err(WalletError::not_found(format!(
+                "Account file not found: {}",
+                self.file_path
+            ))
+            .into());
+        }
+
+        check_private_file(&self.file_path)?;
+        let content = fs::read_to_string(&self.file_path)?;
+        let account_manager: AccountManager = serde_json::from_str(&content)?;
+        Ok(account_manager)
     }
 
-    /// Get account count
-    pub fn get_account_count(&self) -> Result<usize> {
-        let manager = self.load_account_manager()?;
-        Ok(manager.account_map.len())
+    /// Save account manager to file
+    pub fn save_account_manager(&self, account_manager: &AccountManager) -> Result<()> {
+        self.write(&serde_json::to_string_pretty(account_manager)?)
     }
 
-    /// Check if account file exists
-    pub fn exists(&self) -> bool {
-        Path::new(&self.file_path).exists()
+    fn write(&self, content: &str) -> Result<()> {
+        // Create directory if it doesn't exist
+        if let Some(parent) = Path::new(&self.file_path).parent() {
+            create_private_dir(parent)?;
+        }
+
+        write_private_file(&self.file_path, content)?;
+        Ok(())
     }
 
     /// Get file path
@@ -139,32 +478,72 @@ impl Storage {
     /// Backup account file
     pub fn backup(&self, backup_path: &str) -> Result<()> {
         if !self.exists() {
-            return Err(anyhow!("Account file does not exist"));
+            return Err(WalletError::not_found("Account file does not exist").into());
         }
 
-        fs::copy(&self.file_path, backup_path)?;
+        check_private_file(&self.file_path)?;
+        write_private_file(backup_path, fs::read(&self.file_path)?)?;
         Ok(())
     }
 
     /// Restore account file from backup
     pub fn restore(&self, backup_path: &str) -> Result<()> {
         if !Path::new(backup_path).exists() {
-            return Err(anyhow!("Backup file does not exist"));
+            return Err(WalletError::not_found("Backup file does not exist").into());
         }
 
         // Validate backup file format
         let content = fs::read_to_string(backup_path)?;
         let _: AccountManager = serde_json::from_str(&content)?;
 
-        fs::copy(backup_path, &self.file_path)?;
+        write_private_file(&self.file_path, content)?;
         Ok(())
     }
 }
 
+impl WalletStore for Storage {
+    fn location(&self) -> PathBuf {
+        PathBuf::from(&self.file_path)
+    }
+
+    fn exists(&self) -> bool {
+        Path::new(&self.file_path).exists()
+    }
+
+    fn load(&self) -> Result<AccountManager> {
+        self.load_account_manager()
+    }
+
+    fn save(&self, manager: &AccountManager) -> Result<()> {
+        self.save_account_manager(manager)
+    }
+
+    /// The file as stored, so wallets written by the desktop app round-trip
+    fn export(&self) -> Result<Value> {
+        if !self.exists() {
+            return Err(WalletError::not_found(format!(
+                "Account file not found: {}",
+                self.file_path
+            ))
+            .into());
+        }
+
+        check_private_file(&self.file_path)?;
+        Ok(serde_json::from_str(&fs::read_to_string(&self.file_path)?)?)
+    }
+
+    fn import(&self, wallet: &Value) -> Result<()> {
+        self.write(&serde_json::to_string_pretty(wallet)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::address_book::ADDRESS_BOOK_FILE;
     use crate::crypto::generate_keypair;
+    use crate::error::ErrorKind;
+    use crate::history::HISTORY_FILE;
     use tempfile::TempDir;
 
     #[test]
@@ -194,6 +573,138 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_remove_account() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path().join("accounts.json"));
+        let first = storage.init_account_manager().unwrap().account_map[0].clone();
+        let second = generate_keypair().unwrap();
+        let third = generate_keypair().unwrap();
+        storage.add_account(second.clone()).unwrap();
+        storage.add_account(third.clone()).unwrap();
+
+        // Removing an unselected account keeps the selection
+        storage.set_selected_account(2).unwrap();
+        let manager = storage.remove_account(1).unwrap();
+        assert_eq!(manager.account_map, vec![first.clone(), third.clone()]);
+        assert_eq!(manager.now_selected_account, third);
+
+        // Removing the selected last entry selects the one before it
+        let manager = storage.remove_account(1).unwrap();
+        assert_eq!(manager.account_map, vec![first.clone()]);
+        assert_eq!(manager.now_selected_account, first);
+
+        assert!(storage.remove_account(0).is_err());
+        assert!(storage.remove_account(5).is_err());
+        assert_eq!(storage.get_account_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_list_wallets() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(list_wallets(temp_dir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+
+        for name in ["work", "default"] {
+            Storage::new(wallet_file(
+                temp_dir.path(),
+                &WalletName::new(name).unwrap(),
+            ))
+            .init_account_manager()
+            .unwrap();
+        }
+        fs::write(temp_dir.path().join("notes.txt"), "not a wallet").unwrap();
+        fs::write(temp_dir.path().join(ADDRESS_BOOK_FILE), "{}").unwrap();
+        fs::write(temp_dir.path().join(HISTORY_FILE), "{}").unwrap();
+
+        assert_eq!(
+            list_wallets(temp_dir.path()).unwrap(),
+            vec!["default", "work"]
+        );
+    }
+
+    #[test]
+    fn test_wallet_name_validation() {
+        for name in [
+            "default",
+            "work-2024",
+            "My Wallet",
+            "cold_storage.v2",
+            "钱包",
+        ] {
+            assert_eq!(WalletName::new(name).unwrap().as_str(), name);
+        }
+
+        let long_name = "a".repeat(MAX_WALLET_NAME_LENGTH + 1);
+        for name in [
+            "",
+            ".",
+            "..",
+            "../../.ssh/foo",
+            "..\\..\\windows",
+            "/etc/passwd",
+            "a/b",
+            "C:evil",
+            ".hidden",
+            "trailing.",
+            "trailing ",
+            " leading",
+            "new\nline",
+            "nul\0byte",
+            "tab\there",
+            "esc\u{1b}[31m",
+            "what?",
+            "a|b",
+            "CON",
+            "nul.txt",
+            "Lpt1",
+            "address_book",
+            "History",
+            long_name.as_str(),
+        ] {
+            let error = WalletName::new(name).unwrap_err();
+            assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidInput, "{:?}", name);
+        }
+
+        let name: WalletName = "work".parse().unwrap();
+        assert_eq!(
+            wallet_file("/data/wallets", &name),
+            Path::new("/data/wallets/work.json")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let wallet_dir = temp_dir.path().join("nested").join("wallets");
+        let file_path = wallet_dir.join("work.json");
+        let storage = Storage::new(&file_path);
+        storage.init_account_manager().unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&wallet_dir), 0o700);
+        assert_eq!(mode(&temp_dir.path().join("nested")), 0o700);
+        assert_eq!(mode(&file_path), 0o600);
+
+        // Readable by others only warns; writable by others is refused
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o644)).unwrap();
+        storage.load_account_manager().unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o666)).unwrap();
+        let error = storage.load_account_manager().unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Io);
+
+        // Saving tightens an existing file back to 0600
+        storage
+            .save_account_manager(&AccountManager::default())
+            .unwrap();
+        assert_eq!(mode(&file_path), 0o600);
+        storage.load_account_manager().unwrap();
+    }
+
     #[test]
     fn test_backup_restore() {
         let temp_dir = TempDir::new().unwrap();
@@ -1,5 +1,8 @@
+use crate::config::Network;
+use crate::multisig::MultisigAccount;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Account key type (33-byte compressed public key)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -10,7 +13,11 @@ impl serde::Serialize for AccountKey {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&hex::encode(self.0))
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
     }
 }
 
@@ -19,8 +26,12 @@ impl<'de> serde::Deserialize<'de> for AccountKey {
     where
         D: serde::Deserializer<'de>,
     {
-        let s = String::deserialize(deserializer)?;
-        let bytes = hex::decode(s).map_err(serde::de::Error::custom)?;
+        let bytes = if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            hex::decode(s.trim_start_matches("0x")).map_err(serde::de::Error::custom)?
+        } else {
+            Vec::<u8>::deserialize(deserializer)?
+        };
         if bytes.len() != 33 {
             return Err(serde::de::Error::custom("Invalid public key length"));
         }
@@ -49,6 +60,38 @@ impl AccountKey {
     }
 }
 
+/// Signature scheme used when signing or verifying
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// ECDSA over secp256k1 (compact 64-byte signatures)
+    #[default]
+    Ecdsa,
+    /// BIP340 Schnorr over secp256k1 (64-byte signatures, x-only public keys)
+    Schnorr,
+}
+
+impl std::fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureScheme::Ecdsa => write!(f, "ecdsa"),
+            SignatureScheme::Schnorr => write!(f, "schnorr"),
+        }
+    }
+}
+
+impl std::str::FromStr for SignatureScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ecdsa" => Ok(SignatureScheme::Ecdsa),
+            "schnorr" | "bip340" => Ok(SignatureScheme::Schnorr),
+            other => Err(format!("Unknown signature scheme: {}", other)),
+        }
+    }
+}
+
 /// Account key pair with public and private keys
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AccountKeyPair {
@@ -62,7 +105,7 @@ impl AccountKeyPair {
         // For Mazzaroth, we'll use the first 20 bytes of the SHA256 hash of the public key
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
-        hasher.update(&self.public_key.0);
+        hasher.update(self.public_key.0);
         let result = hasher.finalize();
         format!("0x{}", hex::encode(&result[..20]))
     }
@@ -76,6 +119,111 @@ impl AccountKeyPair {
     pub fn get_public_key_hex(&self) -> String {
         self.public_key.to_hex()
     }
+
+    /// Get BIP340 x-only public key as hex string
+    pub fn get_x_only_public_key_hex(&self) -> String {
+        // A compressed key is a parity byte followed by the 32-byte x coordinate
+        format!("0x{}", hex::encode(&self.public_key.0[1..]))
+    }
+}
+
+/// Longest account label accepted
+pub const MAX_LABEL_LENGTH: usize = 64;
+/// Longest account notes accepted
+pub const MAX_NOTES_LENGTH: usize = 1024;
+/// Longest account tag accepted
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// User-editable account details, kept separately from the keys
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountMetadata {
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Free-text notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Tags, sorted and unique
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Left out of listings unless asked for
+    #[serde(default)]
+    pub hidden: bool,
+    /// No longer in use; left out of listings unless asked for
+    #[serde(default)]
+    pub archived: bool,
+    /// Display position; accounts without one sort by their index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+}
+
+impl AccountMetadata {
+    /// Trim the label, drop empty text fields and sort the tags, so a blank
+    /// label or notes clears them
+    pub fn normalize(&mut self) {
+        self.label = self
+            .label
+            .take()
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty());
+        self.notes = self.notes.take().filter(|notes| !notes.trim().is_empty());
+        self.tags.sort();
+        self.tags.dedup();
+    }
+
+    /// Check lengths and characters of the user-supplied fields
+    pub fn validate(&self) -> anyhow::Result<()> {
+        use crate::error::WalletError;
+
+        if let Some(label) = &self.label {
+            if label.chars().count() > MAX_LABEL_LENGTH {
+                return Err(WalletError::invalid_input(format!(
+                    "Label must be at most {} characters",
+                    MAX_LABEL_LENGTH
+                ))
+                .into());
+            }
+            if label.chars().any(char::is_control) {
+                return Err(WalletError::invalid_input(
+                    "Label must not contain control characters",
+                )
+                .into());
+            }
+        }
+        if let Some(notes) = &self.notes {
+            if notes.chars().count() > MAX_NOTES_LENGTH {
+                return Err(WalletError::invalid_input(format!(
+                    "Notes must be at most {} characters",
+                    MAX_NOTES_LENGTH
+                ))
+                .into());
+            }
+        }
+        for tag in &self.tags {
+            if tag.is_empty()
+                || tag.chars().count() > MAX_TAG_LENGTH
+                || tag
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || c == ',')
+            {
+                return Err(WalletError::invalid_input(format!(
+                    "Invalid tag '{}': use 1-{} characters without spaces or commas",
+                    tag, MAX_TAG_LENGTH
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `list` leaves the account out by default
+    pub fn is_listed(&self) -> bool {
+        !self.hidden && !self.archived
+    }
+
+    fn is_empty(&self) -> bool {
+        self == &AccountMetadata::default()
+    }
 }
 
 /// Account manager (matches your existing format)
@@ -83,6 +231,44 @@ impl AccountKeyPair {
 pub struct AccountManager {
     pub account_map: Vec<AccountKeyPair>,
     pub now_selected_account: AccountKeyPair,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub multisig_accounts: Vec<MultisigAccount>,
+    /// Labels, notes and flags by account address
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub account_metadata: BTreeMap<String, AccountMetadata>,
+    /// Network the wallet is used on, overriding `node.network`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+}
+
+impl AccountManager {
+    /// Metadata of an account (default when none was set)
+    pub fn metadata(&self, account: &AccountKeyPair) -> AccountMetadata {
+        self.account_metadata
+            .get(&account.get_address())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Replace the metadata of an account, dropping empty entries
+    pub fn set_metadata(&mut self, account: &AccountKeyPair, metadata: AccountMetadata) {
+        let address = account.get_address();
+        if metadata.is_empty() {
+            self.account_metadata.remove(&address);
+        } else {
+            self.account_metadata.insert(address, metadata);
+        }
+    }
+
+    /// Account indexes in display order: by `order`, falling back to the index
+    pub fn display_order(&self) -> Vec<usize> {
+        let mut indexes: Vec<usize> = (0..self.account_map.len()).collect();
+        indexes.sort_by_key(|&index| {
+            let order = self.metadata(&self.account_map[index]).order;
+            (order.map_or(index as u64, u64::from), index)
+        });
+        indexes
+    }
 }
 
 impl Default for AccountManager {
@@ -92,6 +278,9 @@ impl Default for AccountManager {
         Self {
             account_map: vec![account.clone()],
             now_selected_account: account,
+            multisig_accounts: Vec::new(),
+            account_metadata: BTreeMap::new(),
+            network: None,
         }
     }
 }
@@ -110,8 +299,10 @@ pub struct WalletInfo {
 }
 
 /// Transaction data for signing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Transaction {
+    /// Chain the transaction is valid on, which binds the signing hash to one network
+    pub chain_id: u64,
     /// Sender address
     pub from: String,
     /// Recipient address
@@ -128,6 +319,28 @@ pub struct Transaction {
     pub gas_price: u64,
 }
 
+impl Transaction {
+    /// Canonical binary encoding of the transaction
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Hash every signer signs (SHA-256 of the canonical encoding)
+    pub fn signing_hash(&self) -> anyhow::Result<[u8; 32]> {
+        Ok(crate::crypto::hash_transaction_data(&self.encode()?))
+    }
+
+    /// Most the sender pays in fees: every unit of the gas limit at the gas price
+    pub fn max_fee(&self) -> u64 {
+        self.gas_limit.saturating_mul(self.gas_price)
+    }
+
+    /// Most the transaction can cost the sender: the amount plus the maximum fee
+    pub fn total_cost(&self) -> u64 {
+        self.amount.unwrap_or(0).saturating_add(self.max_fee())
+    }
+}
+
 /// Signature result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Signature {
@@ -150,4 +363,7 @@ pub struct AccountInfo {
     pub index: usize,
     /// Whether this is the selected account
     pub is_selected: bool,
+    /// Label, notes, tags and flags
+    #[serde(default)]
+    pub metadata: AccountMetadata,
 }
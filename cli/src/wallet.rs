@@ -1,29 +1,45 @@
 use crate::{
+    config::Network,
     crypto::{
         add_account_from_mnemonic, create_account_manager_with_mnemonic, generate_mnemonic,
-        get_account_info, sign_transaction_data, validate_mnemonic,
+        get_account_info, parse_address, sign_transaction_data_with_scheme, validate_mnemonic,
+    },
+    error::WalletError,
+    multisig::{parse_participant_key, MultisigAccount},
+    pst::PartiallySignedTransaction,
+    storage::{Storage, WalletStore},
+    types::{
+        AccountInfo, AccountKeyPair, AccountManager, AccountMetadata, SignatureScheme, Transaction,
     },
-    storage::Storage,
-    types::{AccountInfo, AccountKeyPair, AccountManager},
 };
 use anyhow::{anyhow, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Main wallet manager
 pub struct WalletManager {
-    storage: Storage,
+    storage: Box<dyn WalletStore>,
     mnemonic: Option<String>,
 }
 
 impl WalletManager {
-    /// Create a new wallet manager
+    /// Create a new wallet manager for a JSON account file
     pub fn new<P: AsRef<Path>>(account_file: P) -> Self {
+        Self::with_store(Box::new(Storage::new(account_file)))
+    }
+
+    /// Create a new wallet manager for a wallet in any storage backend
+    pub fn with_store(storage: Box<dyn WalletStore>) -> Self {
         WalletManager {
-            storage: Storage::new(account_file),
+            storage,
             mnemonic: None,
         }
     }
 
+    /// File or database holding the wallet
+    pub fn location(&self) -> PathBuf {
+        self.storage.location()
+    }
+
     /// Initialize wallet with existing account file
     pub fn init(&mut self) -> Result<AccountManager> {
         self.storage.init_account_manager()
@@ -32,14 +48,14 @@ impl WalletManager {
     /// Create a new wallet with BIP39 mnemonic
     pub fn create_wallet(&mut self) -> Result<(AccountManager, String)> {
         if self.storage.exists() {
-            return Err(anyhow!("Wallet already exists"));
+            return Err(WalletError::already_exists("Wallet already exists").into());
         }
 
         let mnemonic = generate_mnemonic()?;
         self.mnemonic = Some(mnemonic.clone());
 
         let account_manager = create_account_manager_with_mnemonic(&mnemonic)?;
-        self.storage.save_account_manager(&account_manager)?;
+        self.storage.save(&account_manager)?;
 
         Ok((account_manager, mnemonic))
     }
@@ -47,16 +63,16 @@ impl WalletManager {
     /// Import wallet from mnemonic
     pub fn import_wallet(&mut self, mnemonic: &str) -> Result<AccountManager> {
         if self.storage.exists() {
-            return Err(anyhow!("Wallet already exists"));
+            return Err(WalletError::already_exists("Wallet already exists").into());
         }
 
         if !validate_mnemonic(mnemonic)? {
-            return Err(anyhow!("Invalid mnemonic phrase"));
+            return Err(WalletError::invalid_input("Invalid mnemonic phrase").into());
         }
 
         self.mnemonic = Some(mnemonic.to_string());
         let account_manager = create_account_manager_with_mnemonic(mnemonic)?;
-        self.storage.save_account_manager(&account_manager)?;
+        self.storage.save(&account_manager)?;
 
         Ok(account_manager)
     }
@@ -64,46 +80,157 @@ impl WalletManager {
     /// Add new account using mnemonic
     pub fn add_account(&mut self, mnemonic: &str) -> Result<AccountKeyPair> {
         if !self.storage.exists() {
-            return Err(anyhow!("Wallet not found"));
+            return Err(WalletError::not_found("Wallet not found").into());
         }
 
         if !validate_mnemonic(mnemonic)? {
-            return Err(anyhow!("Invalid mnemonic phrase"));
+            return Err(WalletError::invalid_input("Invalid mnemonic phrase").into());
         }
 
-        let mut manager = self.storage.load_account_manager()?;
-        let new_account = add_account_from_mnemonic(&mut manager, mnemonic)?;
-        self.storage.save_account_manager(&manager)?;
+        let manager = self.storage.update(&|manager| {
+            add_account_from_mnemonic(manager, mnemonic)?;
+            Ok(())
+        })?;
 
-        Ok(new_account)
+        Ok(manager.account_map[manager.account_map.len() - 1].clone())
     }
 
     /// List all accounts
     pub fn list_accounts(&self) -> Result<Vec<AccountInfo>> {
         if !self.storage.exists() {
-            return Err(anyhow!("Wallet not found"));
+            return Err(WalletError::not_found("Wallet not found").into());
         }
 
-        let manager = self.storage.load_account_manager()?;
+        let manager = self.storage.load()?;
         let mut accounts = Vec::new();
 
-        for (index, account) in manager.account_map.iter().enumerate() {
+        for index in manager.display_order() {
+            let account = &manager.account_map[index];
             let is_selected = account == &manager.now_selected_account;
-            accounts.push(get_account_info(account, index, is_selected));
+            let metadata = manager.metadata(account);
+            accounts.push(get_account_info(account, index, is_selected, metadata));
         }
 
         Ok(accounts)
     }
 
+    /// Get the label, notes, tags and flags of an account by index or address
+    pub fn get_account_metadata(&self, index_or_address: &str) -> Result<AccountMetadata> {
+        let (_, account) = self.find_account(index_or_address)?;
+        Ok(self.storage.load()?.metadata(&account))
+    }
+
+    /// Edit the metadata of an account by index or address and save it
+    pub fn update_account_metadata<F>(
+        &mut self,
+        index_or_address: &str,
+        update: F,
+    ) -> Result<AccountMetadata>
+    where
+        F: FnOnce(&mut AccountMetadata),
+    {
+        let (_, account) = self.find_account(index_or_address)?;
+
+        let mut metadata = self.storage.load()?.metadata(&account);
+        update(&mut metadata);
+        metadata.normalize();
+        metadata.validate()?;
+
+        self.storage.update(&|manager| {
+            manager.set_metadata(&account, metadata.clone());
+            Ok(())
+        })?;
+        Ok(metadata)
+    }
+
+    /// Network chosen for this wallet, if any
+    pub fn network(&self) -> Result<Option<Network>> {
+        Ok(self.storage.load()?.network)
+    }
+
+    /// Use the wallet on `network`, or on `node.network` again with `None`
+    pub fn set_network(&mut self, network: Option<Network>) -> Result<()> {
+        self.storage.update(&|manager| {
+            manager.network = network;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
     /// Get selected account
     pub fn get_selected_account(&self) -> Result<AccountKeyPair> {
         self.storage.get_selected_account()
     }
 
+    /// Find an account by index (as shown by `list`) or address
+    pub fn find_account(&self, index_or_address: &str) -> Result<(usize, AccountKeyPair)> {
+        if !self.storage.exists() {
+            return Err(WalletError::not_found("Wallet not found").into());
+        }
+
+        if let Ok(index) = index_or_address.parse::<usize>() {
+            return Ok((index, self.storage.get_account(index)?));
+        }
+
+        let found = match parse_address(index_or_address) {
+            Ok(address) => self.storage.find_account(&address)?,
+            Err(_) => None,
+        };
+        found.ok_or_else(|| {
+            WalletError::not_found(format!("Account not found: {}", index_or_address)).into()
+        })
+    }
+
+    /// Select the account used for signing by index or address
+    pub fn select_account(&mut self, index_or_address: &str) -> Result<AccountKeyPair> {
+        let (index, _) = self.find_account(index_or_address)?;
+        let manager = self.storage.set_selected_account(index)?;
+        Ok(manager.now_selected_account)
+    }
+
+    /// Remove an account by index or address, returning the removed account
+    pub fn remove_account(&mut self, index_or_address: &str) -> Result<AccountKeyPair> {
+        let (index, account) = self.find_account(index_or_address)?;
+        self.storage.remove_account(index)?;
+        Ok(account)
+    }
+
+    /// Copy the wallet to the JSON file `backup_path`
+    pub fn backup<P: AsRef<Path>>(&self, backup_path: P) -> Result<()> {
+        if !self.storage.exists() {
+            return Err(WalletError::not_found("Wallet not found").into());
+        }
+        Storage::new(backup_path).import(&self.storage.export()?)
+    }
+
+    /// Replace the wallet file with a backup, refusing to overwrite unless `force` is set
+    pub fn restore<P: AsRef<Path>>(
+        &mut self,
+        backup_path: P,
+        force: bool,
+    ) -> Result<AccountManager> {
+        if self.storage.exists() && !force {
+            return Err(WalletError::already_exists("Wallet already exists").into());
+        }
+
+        let manager = Storage::new(backup_path).load()?;
+        self.storage.save(&manager)?;
+        Ok(manager)
+    }
+
     /// Sign transaction with selected account
     pub fn sign_transaction(&self, data: &[u8]) -> Result<[u8; 64]> {
+        self.sign_transaction_with_scheme(data, SignatureScheme::Ecdsa)
+    }
+
+    /// Sign transaction with selected account using the given signature scheme
+    pub fn sign_transaction_with_scheme(
+        &self,
+        data: &[u8],
+        scheme: SignatureScheme,
+    ) -> Result<[u8; 64]> {
         let selected_account = self.storage.get_selected_account()?;
-        sign_transaction_data(data, &selected_account.private_key)
+        sign_transaction_data_with_scheme(data, &selected_account.private_key, scheme)
     }
 
     /// Export private key of selected account
@@ -112,6 +239,90 @@ impl WalletManager {
         Ok(selected_account.get_private_key_hex())
     }
 
+    /// Create a multisig account from participant public keys (hex)
+    pub fn create_multisig(
+        &mut self,
+        name: &str,
+        threshold: usize,
+        participants: &[String],
+    ) -> Result<MultisigAccount> {
+        let manager = self.storage.load()?;
+        if manager.multisig_accounts.iter().any(|m| m.name == name) {
+            return Err(WalletError::already_exists(format!(
+                "Multisig account '{}' already exists",
+                name
+            ))
+            .into());
+        }
+
+        let participants = participants
+            .iter()
+            .map(|key| parse_participant_key(key))
+            .collect::<Result<Vec<_>>>()?;
+        let multisig = MultisigAccount::new(name, threshold, participants)?;
+        if manager
+            .multisig_accounts
+            .iter()
+            .any(|m| m.address == multisig.address)
+        {
+            return Err(anyhow!(
+                "Multisig account {} already exists",
+                multisig.address
+            ));
+        }
+
+        self.storage.update(&|manager| {
+            manager.multisig_accounts.push(multisig.clone());
+            Ok(())
+        })?;
+        Ok(multisig)
+    }
+
+    /// List multisig accounts
+    pub fn list_multisig(&self) -> Result<Vec<MultisigAccount>> {
+        let manager = self.storage.load()?;
+        Ok(manager.multisig_accounts)
+    }
+
+    /// Find a multisig account by name or address
+    pub fn get_multisig(&self, name_or_address: &str) -> Result<MultisigAccount> {
+        self.list_multisig()?
+            .into_iter()
+            .find(|m| m.name == name_or_address || m.address == name_or_address)
+            .ok_or_else(|| {
+                WalletError::not_found(format!("Multisig account not found: {}", name_or_address))
+                    .into()
+            })
+    }
+
+    /// Create an unsigned transaction file for the selected account or a multisig account
+    pub fn create_partial_transaction(
+        &self,
+        transaction: Transaction,
+        multisig: Option<&str>,
+    ) -> Result<PartiallySignedTransaction> {
+        match multisig {
+            Some(name_or_address) => {
+                let multisig = self.get_multisig(name_or_address)?;
+                PartiallySignedTransaction::for_multisig(transaction, &multisig)
+            }
+            None => {
+                let selected_account = self.storage.get_selected_account()?;
+                PartiallySignedTransaction::for_account(transaction, &selected_account)
+            }
+        }
+    }
+
+    /// Add the selected account's signature to a transaction file
+    pub fn sign_partial_transaction(
+        &self,
+        transaction: &mut PartiallySignedTransaction,
+        scheme: SignatureScheme,
+    ) -> Result<()> {
+        let selected_account = self.storage.get_selected_account()?;
+        transaction.sign(&selected_account, scheme)
+    }
+
     /// Check if wallet exists
     pub fn exists(&self) -> bool {
         self.storage.exists()
@@ -161,4 +372,97 @@ mod tests {
         let accounts = wallet.list_accounts().unwrap();
         assert_eq!(accounts.len(), 2);
     }
+
+    #[test]
+    fn test_select_and_remove_account() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut wallet = WalletManager::new(temp_dir.path().join("accounts.json"));
+        let (manager, _) = wallet.create_wallet().unwrap();
+        let first = manager.account_map[0].clone();
+        let second = wallet.add_account(&generate_mnemonic().unwrap()).unwrap();
+        assert_ne!(first, second);
+
+        // Select by index, then by address without the 0x prefix
+        assert_eq!(wallet.select_account("0").unwrap(), first);
+        let address = second.get_address().to_uppercase().replace("0X", "");
+        assert_eq!(wallet.select_account(&address).unwrap(), second);
+        assert_eq!(wallet.get_selected_account().unwrap(), second);
+        assert!(wallet.select_account("0xdeadbeef").is_err());
+
+        assert_eq!(
+            wallet.remove_account(&second.get_address()).unwrap(),
+            second
+        );
+        assert_eq!(wallet.get_selected_account().unwrap(), first);
+        assert!(wallet.remove_account("0").is_err());
+
+        // Restore needs force once the wallet exists
+        let backup = temp_dir.path().join("backup.json");
+        wallet.backup(&backup).unwrap();
+        assert!(wallet.restore(&backup, false).is_err());
+        assert_eq!(wallet.restore(&backup, true).unwrap().account_map.len(), 1);
+    }
+
+    #[test]
+    fn test_account_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut wallet = WalletManager::new(temp_dir.path().join("accounts.json"));
+        wallet.create_wallet().unwrap();
+        let second = wallet.add_account(&generate_mnemonic().unwrap()).unwrap();
+
+        let metadata = wallet
+            .update_account_metadata("1", |metadata| {
+                metadata.label = Some(" Savings ".to_string());
+                metadata.tags = vec!["cold".to_string(), "cold".to_string(), "b".to_string()];
+                metadata.order = Some(0);
+            })
+            .unwrap();
+        assert_eq!(metadata.label.as_deref(), Some("Savings"));
+        assert_eq!(metadata.tags, vec!["b", "cold"]);
+
+        // Ties on order fall back to the index
+        let accounts = wallet.list_accounts().unwrap();
+        assert_eq!(accounts[0].index, 0);
+        wallet
+            .update_account_metadata("0", |metadata| metadata.order = Some(1))
+            .unwrap();
+        let accounts = wallet.list_accounts().unwrap();
+        assert_eq!(accounts[0].address, second.get_address());
+        assert_eq!(accounts[0].metadata.label.as_deref(), Some("Savings"));
+
+        assert!(wallet
+            .update_account_metadata("1", |metadata| metadata.tags.push("two words".into()))
+            .is_err());
+        assert!(wallet
+            .update_account_metadata("1", |metadata| metadata.label = Some("\u{7}".into()))
+            .is_err());
+        assert!(wallet
+            .update_account_metadata("1", |metadata| metadata.label = Some("a".repeat(65)))
+            .is_err());
+        assert_eq!(
+            wallet.get_account_metadata(&second.get_address()).unwrap(),
+            metadata
+        );
+
+        // A blank label or notes clears it, as in the desktop app
+        let cleared = wallet
+            .update_account_metadata("1", |metadata| {
+                metadata.label = Some("  ".to_string());
+                metadata.notes = Some(String::new());
+            })
+            .unwrap();
+        assert_eq!(cleared.label, None);
+        assert_eq!(cleared.notes, None);
+        let metadata = wallet
+            .update_account_metadata("1", |metadata| metadata.label = Some("Savings".to_string()))
+            .unwrap();
+        assert_eq!(
+            wallet.get_account_metadata(&second.get_address()).unwrap(),
+            metadata
+        );
+
+        // Metadata goes away with the account
+        wallet.remove_account("1").unwrap();
+        assert!(wallet.list_accounts().unwrap()[0].metadata.label.is_none());
+    }
 }
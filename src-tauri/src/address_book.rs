@@ -0,0 +1,199 @@
+use crate::config::Network;
+use crate::crypto::CryptoManager;
+use crate::storage;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// File name of the address book inside the wallet directory (shared with the CLI)
+pub const ADDRESS_BOOK_FILE: &str = "address_book.json";
+
+/// Longest contact name accepted
+pub const MAX_CONTACT_NAME_LENGTH: usize = 64;
+
+/// Address as the CLI writes it in the address book: lowercase with a 0x prefix
+fn canonical_address(address: &str) -> Result<String> {
+    Ok(format!("0x{}", CryptoManager::parse_address(address)?))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Contact {
+    pub name: String,
+    /// In the CLI's canonical form, so both write the same file
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub network: Network,
+}
+
+impl Contact {
+    pub fn new(name: &str, address: &str, memo: Option<String>, network: Network) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty()
+            || name.chars().count() > MAX_CONTACT_NAME_LENGTH
+            || name.chars().any(char::is_control)
+        {
+            return Err(anyhow!(
+                "Contact name must be 1-{} characters without control characters",
+                MAX_CONTACT_NAME_LENGTH
+            ));
+        }
+        // Names that look like addresses would make recipient lookup ambiguous
+        if CryptoManager::parse_address(name).is_ok() {
+            return Err(anyhow!("Contact name must not be an address"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            address: canonical_address(address)?,
+            memo: memo.filter(|memo| !memo.trim().is_empty()),
+            network,
+        })
+    }
+}
+
+/// Contacts shared by all wallets in the wallet directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddressBook {
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+}
+
+impl AddressBook {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        // A tampered address book could redirect payments, so it gets the wallet file checks
+        storage::check_private_file(path)?;
+        let mut book: AddressBook = serde_json::from_str(&fs::read_to_string(path)?)?;
+        // Older versions of the app wrote bare addresses
+        for contact in &mut book.contacts {
+            contact.address = canonical_address(&contact.address)?;
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            storage::create_private_dir(parent)?;
+        }
+        storage::write_private_file(path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Contact> {
+        let name = name.trim();
+        self.contacts
+            .iter()
+            .find(|contact| contact.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Contacts sorted by name, optionally only those on `network`
+    pub fn list(&self, network: Option<Network>) -> Vec<Contact> {
+        let mut contacts: Vec<Contact> = self
+            .contacts
+            .iter()
+            .filter(|contact| network.is_none_or(|network| contact.network == network))
+            .cloned()
+            .collect();
+        contacts.sort_by_key(|contact| contact.name.to_lowercase());
+        contacts
+    }
+
+    pub fn add(&mut self, contact: Contact) -> Result<()> {
+        if self.get(&contact.name).is_some() {
+            return Err(anyhow!("Contact already exists: {}", contact.name));
+        }
+        self.contacts.push(contact);
+        Ok(())
+    }
+
+    /// Replace the contact called `name`, which may be renamed
+    pub fn update(&mut self, name: &str, contact: Contact) -> Result<()> {
+        if !contact.name.eq_ignore_ascii_case(name.trim()) && self.get(&contact.name).is_some() {
+            return Err(anyhow!("Contact already exists: {}", contact.name));
+        }
+        let existing = self
+            .contacts
+            .iter_mut()
+            .find(|existing| existing.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| anyhow!("Contact not found: {}", name))?;
+        *existing = contact;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<Contact> {
+        let contact = self
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Contact not found: {}", name))?;
+        self.contacts.retain(|other| other != &contact);
+        Ok(contact)
+    }
+
+    /// Resolve a recipient given as an address or a contact name on `network`,
+    /// returning the address in the wallet's form, without a prefix
+    pub fn resolve(&self, recipient: &str, network: Network) -> Result<String> {
+        if let Ok(address) = CryptoManager::parse_address(recipient) {
+            return Ok(address);
+        }
+
+        let contact = self.get(recipient).ok_or_else(|| {
+            anyhow!(
+                "Recipient is neither an address nor a contact: {}",
+                recipient
+            )
+        })?;
+        if contact.network != network {
+            return Err(anyhow!(
+                "Contact {} is on {}, but the wallet is on {}",
+                contact.name,
+                contact.network,
+                network
+            ));
+        }
+        CryptoManager::parse_address(&contact.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contacts_round_trip_in_the_cli_form() {
+        let dir = std::env::temp_dir().join(format!("rwmth-address-book-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let path = dir.join("wallets").join(ADDRESS_BOOK_FILE);
+        let address = "AbCdEf0123456789abcdef0123456789ABCDEF01";
+
+        let mut book = AddressBook::default();
+        let contact = Contact::new(" Alice ", address, None, Network::Mainnet).unwrap();
+        assert_eq!(contact.address, format!("0x{}", address.to_lowercase()));
+        book.add(contact.clone()).unwrap();
+        // The wallet directory is created if it doesn't exist yet
+        book.save(&path).unwrap();
+
+        let loaded = AddressBook::load(&path).unwrap();
+        assert_eq!(loaded.list(None), vec![contact]);
+        assert_eq!(
+            loaded.resolve("alice", Network::Mainnet).unwrap(),
+            address.to_lowercase()
+        );
+        assert!(loaded.resolve("alice", Network::Testnet).is_err());
+
+        // Files with bare addresses load in the canonical form
+        let bare = fs::read_to_string(&path)
+            .unwrap()
+            .replace(&format!("0x{}", address.to_lowercase()), address);
+        storage::write_private_file(&path, bare.as_bytes()).unwrap();
+        assert_eq!(
+            AddressBook::load(&path).unwrap().contacts[0].address,
+            format!("0x{}", address.to_lowercase())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,413 @@
+use crate::address_book::AddressBook;
+use crate::config::{MAX_KDF_ITERATIONS, MIN_KDF_ITERATIONS, Settings};
+use crate::storage::{self, WalletName, WalletStore};
+use crate::types::WalletData;
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `format` field identifying a backup archive (shared with the CLI)
+pub const ARCHIVE_FORMAT: &str = "rwmth-backup";
+/// Newest archive version this build can read
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const KDF: &str = "pbkdf2-sha256";
+const CIPHER: &str = "xchacha20poly1305";
+
+/// Unencrypted part of an archive, authenticated along with the ciphertext
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    /// PBKDF2 salt (hex)
+    salt: String,
+    cipher: String,
+    /// XChaCha20-Poly1305 nonce (hex)
+    nonce: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveFile {
+    #[serde(flatten)]
+    header: ArchiveHeader,
+    /// Encrypted `BackupContents` JSON (base64)
+    ciphertext: String,
+}
+
+/// Everything a backup archive holds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupContents {
+    pub created_at: DateTime<Utc>,
+    /// Wallets by name, as the JSON backend stores them, so CLI wallets round-trip too
+    pub wallets: BTreeMap<String, Value>,
+    pub address_book: Option<AddressBook>,
+    /// Settings from the config file, without environment overrides
+    pub settings: Option<Settings>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletPreview {
+    pub name: String,
+    /// Account addresses, when the wallet format is recognized
+    pub addresses: Option<Vec<String>>,
+    /// A wallet with this name already exists
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupPreview {
+    pub created_at: DateTime<Utc>,
+    pub wallets: Vec<WalletPreview>,
+    /// Number of contacts, if the archive has an address book
+    pub contacts: Option<usize>,
+    pub settings: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Only add what's missing: new wallets, accounts and contacts
+    Merge,
+    /// Overwrite existing files, keeping the old ones beside them as `.bak` files
+    Replace,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    pub restored: Vec<String>,
+    pub merged: Vec<String>,
+    pub replaced: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Existing wallets in a format that can't be merged, left alone
+    pub skipped: Vec<String>,
+    pub contacts_added: usize,
+    pub address_book_replaced: bool,
+    pub settings_restored: bool,
+    /// Where replaced files were moved to
+    pub set_aside: Vec<PathBuf>,
+}
+
+impl BackupContents {
+    /// Gather the named wallets and the address book from `storage`, and the
+    /// settings from `config_path` when given and present
+    pub fn collect(
+        storage: &dyn WalletStore,
+        wallets: &[WalletName],
+        config_path: Option<&Path>,
+    ) -> Result<Self> {
+        let mut contents = BackupContents {
+            created_at: Utc::now(),
+            ..Default::default()
+        };
+
+        for name in wallets {
+            let wallet = storage
+                .export_wallet(name)
+                .with_context(|| format!("Invalid wallet {}", name))?
+                .ok_or_else(|| anyhow!("Wallet not found: {}", name))?;
+            contents.wallets.insert(name.to_string(), wallet);
+        }
+
+        let book_path = storage.address_book_path();
+        if book_path.exists() {
+            contents.address_book = Some(AddressBook::load(&book_path)?);
+        }
+        if let Some(path) = config_path.filter(|path| path.exists()) {
+            contents.settings = Some(Settings::load_file(path)?);
+        }
+        Ok(contents)
+    }
+
+    pub fn preview(&self, storage: &dyn WalletStore) -> Result<BackupPreview> {
+        let mut wallets = Vec::new();
+        for (name, wallet) in &self.wallets {
+            let name = WalletName::new(name)?;
+            wallets.push(WalletPreview {
+                name: name.to_string(),
+                addresses: serde_json::from_value::<WalletData>(wallet.clone())
+                    .ok()
+                    .map(|wallet| wallet.accounts.into_iter().map(|a| a.address).collect()),
+                exists: storage.wallet_exists(&name),
+            });
+        }
+
+        Ok(BackupPreview {
+            created_at: self.created_at,
+            wallets,
+            contacts: self.address_book.as_ref().map(|book| book.contacts.len()),
+            settings: self.settings.is_some(),
+        })
+    }
+
+    /// Write the archive's wallets and address book into `storage` and its
+    /// settings to `config_path`. Nothing is written unless every wallet name
+    /// and the settings are valid.
+    pub fn restore(
+        &self,
+        storage: &dyn WalletStore,
+        config_path: &Path,
+        mode: RestoreMode,
+    ) -> Result<RestoreReport> {
+        let mut wallets = Vec::new();
+        for (name, wallet) in &self.wallets {
+            wallets.push((WalletName::new(name)?, wallet));
+        }
+        if let Some(settings) = &self.settings {
+            settings.validate().context("Invalid settings in backup")?;
+        }
+
+        let mut report = RestoreReport::default();
+        for (name, wallet) in wallets {
+            if !storage.wallet_exists(&name) {
+                storage
+                    .import_wallet(&name, wallet)
+                    .with_context(|| format!("Failed to restore wallet {}", name))?;
+                report.restored.push(name.to_string());
+                continue;
+            }
+
+            match mode {
+                RestoreMode::Replace => {
+                    report.set_aside.push(set_aside_wallet(storage, &name)?);
+                    storage
+                        .import_wallet(&name, wallet)
+                        .with_context(|| format!("Failed to restore wallet {}", name))?;
+                    report.replaced.push(name.to_string());
+                }
+                RestoreMode::Merge => {
+                    let local = storage.load_wallet(&name);
+                    let backup = serde_json::from_value::<WalletData>(wallet.clone());
+                    let (Ok(Some(mut local)), Ok(backup)) = (local, backup) else {
+                        report.skipped.push(name.to_string());
+                        continue;
+                    };
+                    if merge_wallet(&mut local, &backup) {
+                        storage.save_wallet(&name, &local)?;
+                        report.merged.push(name.to_string());
+                    } else {
+                        report.unchanged.push(name.to_string());
+                    }
+                }
+            }
+        }
+
+        if let Some(book) = &self.address_book {
+            let path = storage.address_book_path();
+            if mode == RestoreMode::Replace || !path.exists() {
+                if path.exists() {
+                    report.set_aside.push(set_aside(&path)?);
+                }
+                book.save(&path)?;
+                report.address_book_replaced = true;
+            } else {
+                let mut local = AddressBook::load(&path)?;
+                for contact in &book.contacts {
+                    if local.get(&contact.name).is_none() {
+                        local.add(contact.clone())?;
+                        report.contacts_added += 1;
+                    }
+                }
+                if report.contacts_added > 0 {
+                    local.save(&path)?;
+                }
+            }
+        }
+
+        if let Some(settings) = &self.settings
+            && (mode == RestoreMode::Replace || !config_path.exists())
+        {
+            if config_path.exists() {
+                report.set_aside.push(set_aside(config_path)?);
+            }
+            settings.save(config_path)?;
+            report.settings_restored = true;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Add the backup's accounts and multisig accounts that `local` lacks,
+/// keeping everything `local` already has. Returns whether anything changed.
+fn merge_wallet(local: &mut WalletData, backup: &WalletData) -> bool {
+    let mut changed = false;
+    for account in &backup.accounts {
+        if !local.accounts.iter().any(|a| a.address == account.address) {
+            let mut account = account.clone();
+            account.mark_as_unselected();
+            local.add_account(account);
+            changed = true;
+        }
+    }
+    for multisig in &backup.multisig_accounts {
+        if !local
+            .multisig_accounts
+            .iter()
+            .any(|existing| existing.name == multisig.name)
+        {
+            local.multisig_accounts.push(multisig.clone());
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn set_aside_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}.bak", Utc::now().format("%Y%m%dT%H%M%S%.3fZ")));
+    PathBuf::from(name)
+}
+
+/// Move `path` to `<path>.<timestamp>.bak`, returning the new path
+fn set_aside(path: &Path) -> Result<PathBuf> {
+    let target = set_aside_path(path);
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Save a wallet as `<data dir>/<name>.json.<timestamp>.bak`, whichever
+/// backend holds it, returning the file's path
+fn set_aside_wallet(storage: &dyn WalletStore, wallet_name: &WalletName) -> Result<PathBuf> {
+    let wallet = storage
+        .export_wallet(wallet_name)?
+        .ok_or_else(|| anyhow!("Wallet not found: {}", wallet_name))?;
+    let target = set_aside_path(&storage.data_dir().join(format!("{}.json", wallet_name)));
+    storage::write_private_file(&target, serde_json::to_string_pretty(&wallet)?.as_bytes())?;
+    Ok(target)
+}
+
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// Encrypt `contents` into archive text with a key derived from `password`
+pub fn seal(contents: &BackupContents, password: &str, iterations: u32) -> Result<String> {
+    let salt: [u8; 16] = rand::thread_rng().r#gen();
+    let nonce: [u8; 24] = rand::thread_rng().r#gen();
+    let header = ArchiveHeader {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        kdf: KDF.to_string(),
+        iterations,
+        salt: hex::encode(salt),
+        cipher: CIPHER.to_string(),
+        nonce: hex::encode(nonce),
+    };
+
+    let key = derive_key(password, &salt, iterations);
+    let aad = serde_json::to_vec(&header)?;
+    let plaintext = serde_json::to_vec(contents)?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt backup"))?;
+
+    Ok(serde_json::to_string_pretty(&ArchiveFile {
+        header,
+        ciphertext: BASE64.encode(ciphertext),
+    })?)
+}
+
+/// Decrypt archive text, verifying that neither the header nor the contents were changed
+pub fn open(archive: &str, password: &str) -> Result<BackupContents> {
+    let file: ArchiveFile =
+        serde_json::from_str(archive).map_err(|_| anyhow!("Not a backup archive"))?;
+    let header = &file.header;
+    if header.format != ARCHIVE_FORMAT {
+        return Err(anyhow!("Not a backup archive"));
+    }
+    if header.version > ARCHIVE_VERSION {
+        return Err(anyhow!(
+            "Backup archive version {} is newer than this build supports",
+            header.version
+        ));
+    }
+    if header.kdf != KDF || header.cipher != CIPHER {
+        return Err(anyhow!(
+            "Unsupported backup encryption: {} with {}",
+            header.kdf,
+            header.cipher
+        ));
+    }
+    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&header.iterations) {
+        return Err(anyhow!("Backup archive KDF iterations out of range"));
+    }
+
+    let salt = hex::decode(&header.salt)?;
+    let nonce = hex::decode(&header.nonce)?;
+    if nonce.len() != 24 {
+        return Err(anyhow!("Invalid backup archive nonce"));
+    }
+    let ciphertext = BASE64
+        .decode(&file.ciphertext)
+        .map_err(|_| anyhow!("Invalid backup archive ciphertext"))?;
+
+    let key = derive_key(password, &salt, header.iterations);
+    let aad = serde_json::to_vec(header)?;
+    let plaintext = XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| {
+            anyhow!("Wrong password, or the backup archive is corrupted or was modified")
+        })?;
+
+    serde_json::from_slice(&plaintext).context("Invalid backup archive contents")
+}
+
+/// Encrypt `contents` into a new archive file readable only by the owner,
+/// refusing to overwrite an existing file
+pub fn write_archive(
+    path: &Path,
+    contents: &BackupContents,
+    password: &str,
+    iterations: u32,
+) -> Result<()> {
+    let archive = seal(contents, password, iterations)?;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AlreadyExists {
+            anyhow!("File already exists: {}", path.display())
+        } else {
+            anyhow::Error::from(e).context(format!("Failed to create {}", path.display()))
+        }
+    })?;
+    file.write_all(archive.as_bytes())?;
+    Ok(())
+}
+
+pub fn read_archive(path: &Path, password: &str) -> Result<BackupContents> {
+    let archive = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read backup archive {}", path.display()))?;
+    open(&archive, password)
+}
@@ -1,10 +1,11 @@
+use crate::types::SignatureScheme;
 use anyhow::Result;
 use bip39::{Language, Mnemonic};
 use hex;
 use hmac::Hmac;
 use pbkdf2::pbkdf2;
 use rand::Rng;
-use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use secp256k1::{Keypair, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey};
 use sha2::{Digest, Sha256};
 
 pub struct CryptoManager;
@@ -38,13 +39,72 @@ impl CryptoManager {
 
     /// Sign data with private key
     pub fn sign_data(private_key: &SecretKey, data: &[u8]) -> Result<Vec<u8>> {
-        let secp = Secp256k1::new();
         let mut hasher = Sha256::new();
         hasher.update(data);
         let hash = hasher.finalize();
-        let message = secp256k1::Message::from_digest(hash.into());
-        let signature = secp.sign_ecdsa(&message, private_key);
-        Ok(signature.serialize_der().to_vec())
+        Self::sign_hash(private_key, &hash.into())
+    }
+
+    /// Sign an already computed 32-byte hash with private key
+    pub fn sign_hash(private_key: &SecretKey, hash: &[u8; 32]) -> Result<Vec<u8>> {
+        Self::sign_hash_with_scheme(private_key, hash, SignatureScheme::Ecdsa)
+    }
+
+    /// Sign a 32-byte hash with the given signature scheme
+    pub fn sign_hash_with_scheme(
+        private_key: &SecretKey,
+        hash: &[u8; 32],
+        scheme: SignatureScheme,
+    ) -> Result<Vec<u8>> {
+        let secp = Secp256k1::new();
+        let message = secp256k1::Message::from_digest(*hash);
+        match scheme {
+            SignatureScheme::Ecdsa => {
+                let signature = secp.sign_ecdsa(&message, private_key);
+                Ok(signature.serialize_der().to_vec())
+            }
+            SignatureScheme::Schnorr => {
+                let keypair = Keypair::from_secret_key(&secp, private_key);
+                let signature =
+                    secp.sign_schnorr_with_rng(&message, &keypair, &mut rand::thread_rng());
+                Ok(signature.as_ref().to_vec())
+            }
+        }
+    }
+
+    /// Verify a signature over a 32-byte hash made with the given scheme
+    pub fn verify_hash(
+        public_key: &PublicKey,
+        hash: &[u8; 32],
+        signature: &[u8],
+        scheme: SignatureScheme,
+    ) -> Result<bool> {
+        let secp = Secp256k1::new();
+        let message = secp256k1::Message::from_digest(*hash);
+        match scheme {
+            SignatureScheme::Ecdsa => {
+                let signature = secp256k1::ecdsa::Signature::from_der(signature)?;
+                Ok(secp.verify_ecdsa(&message, &signature, public_key).is_ok())
+            }
+            SignatureScheme::Schnorr => {
+                let signature = secp256k1::schnorr::Signature::from_slice(signature)?;
+                let x_only = Self::get_x_only_public_key(public_key);
+                Ok(secp.verify_schnorr(&signature, &message, &x_only).is_ok())
+            }
+        }
+    }
+
+    /// Get the BIP340 x-only public key used by Schnorr signatures
+    pub fn get_x_only_public_key(public_key: &PublicKey) -> XOnlyPublicKey {
+        public_key.x_only_public_key().0
+    }
+
+    /// Get the x-only public key as `0x`-prefixed hex, as the CLI prints it
+    pub fn get_x_only_public_key_hex(public_key: &PublicKey) -> String {
+        format!(
+            "0x{}",
+            hex::encode(Self::get_x_only_public_key(public_key).serialize())
+        )
     }
 
     /// Generate address from public key (Mazzaroth format)
@@ -56,10 +116,28 @@ impl CryptoManager {
         hex::encode(&result[..20]) // Use first 20 bytes for address
     }
 
+    /// Parse an address (20 bytes as hex, `0x` optional) into its canonical lowercase form
+    pub fn parse_address(address: &str) -> Result<String> {
+        let trimmed = address.trim();
+        let digits = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+            .unwrap_or(trimmed);
+        if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("Invalid address: {}", address));
+        }
+        Ok(digits.to_ascii_lowercase())
+    }
+
     /// Encrypt data with password
-    pub fn encrypt_data(data: &[u8], password: &str, salt: &[u8]) -> Result<Vec<u8>> {
+    pub fn encrypt_data(
+        data: &[u8],
+        password: &str,
+        salt: &[u8],
+        iterations: u32,
+    ) -> Result<Vec<u8>> {
         let mut key = [0u8; 32];
-        let _ = pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, 10000, &mut key);
+        let _ = pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations, &mut key);
 
         // Simple XOR encryption (in production, use proper encryption)
         let mut encrypted = Vec::new();
@@ -70,9 +148,14 @@ impl CryptoManager {
     }
 
     /// Decrypt data with password
-    pub fn decrypt_data(encrypted_data: &[u8], password: &str, salt: &[u8]) -> Result<Vec<u8>> {
+    pub fn decrypt_data(
+        encrypted_data: &[u8],
+        password: &str,
+        salt: &[u8],
+        iterations: u32,
+    ) -> Result<Vec<u8>> {
         let mut key = [0u8; 32];
-        let _ = pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, 10000, &mut key);
+        let _ = pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations, &mut key);
 
         // Simple XOR decryption
         let mut decrypted = Vec::new();
@@ -82,3 +165,69 @@ impl CryptoManager {
         Ok(decrypted)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify() {
+        let private_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let public_key = CryptoManager::get_public_key(&private_key);
+        let hash = [1u8; 32];
+
+        let signature = CryptoManager::sign_hash(&private_key, &hash).unwrap();
+        assert!(
+            CryptoManager::verify_hash(&public_key, &hash, &signature, SignatureScheme::Ecdsa)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_schnorr_sign_verify() {
+        let private_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let public_key = CryptoManager::get_public_key(&private_key);
+        let other = CryptoManager::get_public_key(&SecretKey::from_slice(&[2u8; 32]).unwrap());
+        let hash = [7u8; 32];
+
+        let signature =
+            CryptoManager::sign_hash_with_scheme(&private_key, &hash, SignatureScheme::Schnorr)
+                .unwrap();
+        assert_eq!(signature.len(), 64);
+
+        assert!(
+            CryptoManager::verify_hash(&public_key, &hash, &signature, SignatureScheme::Schnorr)
+                .unwrap()
+        );
+        assert!(
+            !CryptoManager::verify_hash(&other, &hash, &signature, SignatureScheme::Schnorr)
+                .unwrap()
+        );
+        assert!(
+            !CryptoManager::verify_hash(
+                &public_key,
+                &[8u8; 32],
+                &signature,
+                SignatureScheme::Schnorr
+            )
+            .unwrap()
+        );
+        // A Schnorr signature must not pass as ECDSA
+        assert!(
+            !CryptoManager::verify_hash(&public_key, &hash, &signature, SignatureScheme::Ecdsa)
+                .unwrap_or(false)
+        );
+    }
+
+    #[test]
+    fn test_x_only_public_key_matches_the_cli() {
+        let private_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let public_key = CryptoManager::get_public_key(&private_key);
+
+        // The CLI drops the parity byte of the compressed key and adds `0x`
+        assert_eq!(
+            CryptoManager::get_x_only_public_key_hex(&public_key),
+            format!("0x{}", hex::encode(&public_key.serialize()[1..]))
+        );
+    }
+}
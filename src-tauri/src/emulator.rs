@@ -0,0 +1,232 @@
+//! Software stand-in for an external signer
+//!
+//! Serves the `crate::signing` socket protocol with keys derived from a seed,
+//! so the external signer path can be tested, and device bridges compared
+//! with it, without hardware. It can be set to reject signing requests as if
+//! the user had declined them on the device.
+//!
+//! Only built for tests, or for other crates with the `emulator` feature.
+
+use crate::signing::{self, DeviceRequest, DeviceResponse, LocalSigner, Signer};
+use anyhow::{Result, anyhow};
+use secp256k1::SecretKey;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+pub struct Emulator {
+    socket: PathBuf,
+    rejecting: Arc<AtomicBool>,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Emulator {
+    /// Listen on `socket` with keys derived from `seed`
+    pub fn start(socket: impl Into<PathBuf>, seed: [u8; 32]) -> Result<Self> {
+        let socket = socket.into();
+        let listener = UnixListener::bind(&socket)?;
+        let rejecting = Arc::new(AtomicBool::new(false));
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let rejecting = rejecting.clone();
+            let stopping = stopping.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopping.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // A broken connection only ends that connection
+                    if let Ok(stream) = stream
+                        && let Err(e) = serve(stream, &seed, &rejecting)
+                    {
+                        tracing::debug!("Emulated signer connection failed: {}", e);
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            socket,
+            rejecting,
+            stopping,
+            thread: Some(thread),
+        })
+    }
+
+    /// Key number `key_index` of the emulator started with `seed`
+    pub fn key(seed: &[u8; 32], key_index: u32) -> SecretKey {
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        hasher.update(key_index.to_be_bytes());
+        // A hash is a valid secp256k1 key for all but a negligible share of inputs
+        SecretKey::from_slice(&hasher.finalize()).expect("hash is a valid key")
+    }
+
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    /// Answer signing requests with an error, as if the user declined them
+    pub fn set_rejecting(&self, rejecting: bool) {
+        self.rejecting.store(rejecting, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        UnixStream::connect(&self.socket).ok();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+        fs::remove_file(&self.socket).ok();
+    }
+}
+
+/// Answer requests on one connection until the wallet closes it
+fn serve(mut stream: UnixStream, seed: &[u8; 32], rejecting: &AtomicBool) -> Result<()> {
+    while let Some(request) = signing::read_frame::<DeviceRequest>(&mut stream)? {
+        let response =
+            answer(seed, request, rejecting.load(Ordering::SeqCst)).unwrap_or_else(|e| {
+                DeviceResponse::Error {
+                    message: e.to_string(),
+                }
+            });
+        signing::write_frame(&mut stream, &response)?;
+    }
+    Ok(())
+}
+
+fn answer(seed: &[u8; 32], request: DeviceRequest, rejecting: bool) -> Result<DeviceResponse> {
+    let signature = match request {
+        DeviceRequest::GetPublicKey { key_index } => {
+            let signer = LocalSigner::new(Emulator::key(seed, key_index));
+            return Ok(DeviceResponse::PublicKey {
+                public_key: hex::encode(signer.public_key()?.serialize()),
+            });
+        }
+        _ if rejecting => return Err(anyhow!("Rejected on the device")),
+        DeviceRequest::SignHash {
+            key_index,
+            hash,
+            scheme,
+        } => {
+            let hash: [u8; 32] = hex::decode(hash)?
+                .try_into()
+                .map_err(|_| anyhow!("Hash must be 32 bytes"))?;
+            LocalSigner::new(Emulator::key(seed, key_index)).sign_hash(&hash, scheme)?
+        }
+        DeviceRequest::SignMessage {
+            key_index,
+            message,
+            scheme,
+        } => LocalSigner::new(Emulator::key(seed, key_index)).sign_message(&message, scheme)?,
+    };
+    Ok(DeviceResponse::Signature {
+        signature: hex::encode(signature),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoManager;
+    use crate::signing::{ExternalAccount, ExternalSigner, MAX_FRAME_BYTES};
+    use crate::types::SignatureScheme;
+    use std::io::{Cursor, Write};
+
+    const SEED: [u8; 32] = [7; 32];
+
+    fn start(name: &str) -> Emulator {
+        let socket = std::env::temp_dir().join(format!(
+            "rwmth-emulator-{}-{}.sock",
+            name,
+            std::process::id()
+        ));
+        fs::remove_file(&socket).ok();
+        Emulator::start(socket, SEED).unwrap()
+    }
+
+    #[test]
+    fn test_external_signer_signs_with_the_device_key() {
+        let emulator = start("sign");
+        let account = ExternalAccount {
+            socket: emulator.socket().to_path_buf(),
+            key_index: 1,
+        };
+        let external = ExternalSigner::connect(&account).unwrap();
+        let local = LocalSigner::new(Emulator::key(&SEED, 1));
+        let public_key = local.public_key().unwrap();
+        assert_eq!(external.public_key().unwrap(), public_key);
+
+        let hash = [3u8; 32];
+        for scheme in [SignatureScheme::Ecdsa, SignatureScheme::Schnorr] {
+            let signature = external.sign_hash(&hash, scheme).unwrap();
+            assert!(signing::verify_signature(
+                &public_key,
+                &hash,
+                &signature,
+                scheme
+            ));
+            let encoded = signing::encode_signature(&signature, scheme).unwrap();
+            assert!(CryptoManager::verify_hash(&public_key, &hash, &encoded, scheme).unwrap());
+
+            // The device hashes messages the same way the wallet does
+            let signature = external.sign_message("hello", scheme).unwrap();
+            assert!(signing::verify_signature(
+                &public_key,
+                &signing::message_hash("hello"),
+                &signature,
+                scheme
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rejections_and_malformed_frames() {
+        let emulator = start("reject");
+        let account = ExternalAccount {
+            socket: emulator.socket().to_path_buf(),
+            key_index: 0,
+        };
+        let external = ExternalSigner::connect(&account).unwrap();
+        emulator.set_rejecting(true);
+        let error = external
+            .sign_hash(&[0; 32], SignatureScheme::Ecdsa)
+            .unwrap_err();
+        assert!(error.to_string().contains("Rejected on the device"));
+        emulator.set_rejecting(false);
+        assert!(external.sign_hash(&[0; 32], SignatureScheme::Ecdsa).is_ok());
+
+        // An oversized frame ends the connection without an answer
+        let mut stream = UnixStream::connect(emulator.socket()).unwrap();
+        stream
+            .write_all(&((MAX_FRAME_BYTES + 1) as u32).to_be_bytes())
+            .unwrap();
+        assert!(
+            signing::read_frame::<DeviceResponse>(&mut stream)
+                .unwrap()
+                .is_none()
+        );
+
+        let mut frame = Vec::new();
+        signing::write_frame(&mut frame, &DeviceRequest::GetPublicKey { key_index: 0 }).unwrap();
+        assert_eq!(
+            signing::read_frame::<DeviceRequest>(&mut Cursor::new(&frame)).unwrap(),
+            Some(DeviceRequest::GetPublicKey { key_index: 0 })
+        );
+        frame.truncate(frame.len() - 1);
+        assert!(signing::read_frame::<DeviceRequest>(&mut Cursor::new(&frame)).is_err());
+
+        // No device listening
+        drop(emulator);
+        assert!(ExternalSigner::connect(&account).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+//! Local history of the transactions built, signed and submitted by the wallet
+//!
+//! Records are keyed by the signing hash, which is known as soon as a
+//! transaction is built; the transaction hash is added when it is finalized.
+//! The file is shared with the CLI, so hashes and raw bytes are `0x`-prefixed hex.
+
+use crate::crypto::CryptoManager;
+use crate::pst::{PartiallySignedTransaction, SignedTransaction};
+use crate::storage;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the transaction history inside the wallet directory (shared with the CLI)
+pub const HISTORY_FILE: &str = "history.json";
+
+/// Records returned per page unless a limit is given
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Where a transaction is in its lifecycle, in the order it gets there
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    /// Built but missing signatures
+    Built,
+    /// Signed by enough keys to be finalized
+    Signed,
+    /// Sent to a node and not yet settled
+    Submitted,
+    /// Included on chain
+    Confirmed,
+    /// Rejected or reverted by the node
+    Failed,
+}
+
+impl TxStatus {
+    /// Whether the node has settled the transaction for good
+    pub fn is_final(&self) -> bool {
+        matches!(self, TxStatus::Confirmed | TxStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxRecord {
+    /// Signing hash, which identifies the transaction from the moment it is built
+    pub id: String,
+    /// Hash of the signed transaction, once finalized
+    pub hash: Option<String>,
+    /// Sending account
+    pub account: String,
+    /// Chain the transaction is for (0 in records from before it was kept)
+    #[serde(default)]
+    pub chain_id: u64,
+    /// Recipient, if any
+    pub counterparty: Option<String>,
+    pub amount: Option<u64>,
+    pub nonce: u64,
+    /// Gas price paid, which a replacement has to outbid (0 in records from before it was kept)
+    #[serde(default)]
+    pub gas_price: u64,
+    pub status: TxStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Raw signed transaction bytes, once finalized
+    pub raw: Option<String>,
+}
+
+impl TxRecord {
+    /// Record of a partially signed transaction: signed once the threshold is met
+    pub fn from_partial(pst: &PartiallySignedTransaction) -> Self {
+        let now = Utc::now();
+        let transaction = &pst.transaction;
+        Self {
+            id: pst.signing_hash.to_hex(),
+            hash: None,
+            account: transaction.from.clone(),
+            chain_id: transaction.chain_id,
+            counterparty: transaction.to.clone(),
+            amount: transaction.amount,
+            nonce: transaction.nonce,
+            gas_price: transaction.gas_price,
+            status: if pst.is_complete() {
+                TxStatus::Signed
+            } else {
+                TxStatus::Built
+            },
+            created_at: now,
+            updated_at: now,
+            raw: None,
+        }
+    }
+
+    /// Record of a finalized transaction, with its hash and raw bytes
+    pub fn from_signed(signed: &SignedTransaction) -> Result<Self> {
+        let now = Utc::now();
+        let transaction = &signed.transaction;
+        let raw = signed.to_bytes()?;
+        Ok(Self {
+            id: format!("0x{}", hex::encode(transaction.signing_hash()?)),
+            hash: Some(format!("0x{}", hex::encode(Sha256::digest(&raw)))),
+            account: transaction.from.clone(),
+            chain_id: transaction.chain_id,
+            counterparty: transaction.to.clone(),
+            amount: transaction.amount,
+            nonce: transaction.nonce,
+            gas_price: transaction.gas_price,
+            status: TxStatus::Signed,
+            created_at: now,
+            updated_at: now,
+            raw: Some(format!("0x{}", hex::encode(raw))),
+        })
+    }
+}
+
+/// Which records to return; every field that is set must match
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryFilter {
+    pub account: Option<String>,
+    pub counterparty: Option<String>,
+    pub status: Option<TxStatus>,
+    /// Only records created at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only records created before this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &TxRecord) -> bool {
+        let same = |filter: &Option<String>, value: Option<&String>| {
+            filter.as_ref().is_none_or(|filter| value == Some(filter))
+        };
+        same(&self.account, Some(&record.account))
+            && same(&self.counterparty, record.counterparty.as_ref())
+            && self.status.is_none_or(|status| record.status == status)
+            && self.since.is_none_or(|since| record.created_at >= since)
+            && self.until.is_none_or(|until| record.created_at < until)
+    }
+}
+
+/// One page of matching records, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPage {
+    /// Number of records matching the filter
+    pub total: usize,
+    pub offset: usize,
+    pub records: Vec<TxRecord>,
+}
+
+/// Transactions of every wallet in the wallet directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    #[serde(default)]
+    pub records: Vec<TxRecord>,
+}
+
+impl History {
+    pub fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join(HISTORY_FILE)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        // Raw signed transactions in here get rebroadcast, so it gets the wallet file checks
+        storage::check_private_file(path)?;
+        let mut history: History = serde_json::from_str(&fs::read_to_string(path)?)?;
+        // The CLI writes addresses with a 0x prefix
+        for record in &mut history.records {
+            record.account = CryptoManager::parse_address(&record.account)?;
+            if let Some(counterparty) = &record.counterparty {
+                record.counterparty = Some(CryptoManager::parse_address(counterparty)?);
+            }
+        }
+        Ok(history)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        storage::write_private_file(path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Find a record by signing hash or transaction hash
+    pub fn get(&self, id_or_hash: &str) -> Option<&TxRecord> {
+        self.position(id_or_hash).map(|index| &self.records[index])
+    }
+
+    fn position(&self, id_or_hash: &str) -> Option<usize> {
+        self.records.iter().position(|record| {
+            record.id == id_or_hash || record.hash.as_deref() == Some(id_or_hash)
+        })
+    }
+
+    /// Remove a transaction that was never submitted, such as an abandoned draft.
+    /// Submitted ones stay: the node may still include them, so they have to be
+    /// replaced or cancelled instead.
+    pub fn remove(&mut self, id_or_hash: &str) -> Result<TxRecord> {
+        let index = self
+            .position(id_or_hash)
+            .ok_or_else(|| anyhow!("Transaction not found in history: {}", id_or_hash))?;
+        let record = &self.records[index];
+        if record.status >= TxStatus::Submitted {
+            return Err(anyhow!(
+                "Transaction {} was already submitted and can't be dropped",
+                id_or_hash
+            ));
+        }
+        Ok(self.records.remove(index))
+    }
+
+    /// Add a record, or move the one with the same signing hash forward.
+    /// A status never goes back, so signing a submitted transaction again
+    /// doesn't make it look unsent.
+    pub fn record(&mut self, record: TxRecord) {
+        match self
+            .records
+            .iter_mut()
+            .find(|existing| existing.id == record.id)
+        {
+            Some(existing) => {
+                existing.status = existing.status.max(record.status);
+                existing.hash = record.hash.or(existing.hash.take());
+                existing.raw = record.raw.or(existing.raw.take());
+                existing.updated_at = record.updated_at;
+            }
+            None => self.records.push(record),
+        }
+    }
+
+    /// Set the status the node reports for the transaction with hash `hash`,
+    /// returning whether anything changed
+    pub fn settle(&mut self, hash: &str, status: TxStatus) -> bool {
+        let Some(record) = self
+            .records
+            .iter_mut()
+            .find(|record| record.hash.as_deref() == Some(hash))
+        else {
+            return false;
+        };
+        if record.status == status || record.status.is_final() {
+            return false;
+        }
+        record.status = status;
+        record.updated_at = Utc::now();
+        true
+    }
+
+    /// Hashes of finalized transactions the node hasn't settled yet
+    pub fn unsettled(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .filter(|record| !record.status.is_final())
+            .filter_map(|record| record.hash.clone())
+            .collect()
+    }
+
+    /// Records matching `filter`, newest first, skipping `offset` and returning at most `limit`
+    pub fn query(&self, filter: &HistoryFilter, offset: usize, limit: usize) -> HistoryPage {
+        let mut records: Vec<&TxRecord> = self
+            .records
+            .iter()
+            .filter(|record| filter.matches(record))
+            .collect();
+        records.sort_by_key(|record| Reverse(record.created_at));
+        HistoryPage {
+            total: records.len(),
+            offset,
+            records: records
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+}
@@ -1,16 +1,162 @@
+pub mod address_book;
+pub mod archive;
+pub mod config;
 pub mod crypto;
+#[cfg(all(unix, any(test, feature = "emulator")))]
+pub mod emulator;
+pub mod fee;
+pub mod history;
+pub mod multisig;
+pub mod node;
+pub mod nonce;
+pub mod pst;
+pub mod qr;
+pub mod signer;
+pub mod signing;
+pub mod sled_store;
 pub mod storage;
+pub mod subscription;
+pub mod typed_data;
 pub mod types;
 pub mod wallet;
 
+use crate::address_book::Contact;
+use crate::archive::{BackupPreview, RestoreReport};
+use crate::config::{
+    DappMethod, DappPermission, FeeTier, Network, Settings, SignerSettings, StorageBackend,
+};
+use crate::fee::FeeEstimate;
+use crate::history::{DEFAULT_PAGE_SIZE, HistoryFilter, HistoryPage, TxRecord};
+use crate::multisig::MultisigAccount;
+use crate::node::NodeClient;
+use crate::nonce::{NonceManager, NonceReport};
+use crate::pst::{FinalizedTransaction, PstFormat, PstInspection};
+use crate::qr::{QrFrame, QrImportResult};
+use crate::signer::{Approval, SIGNER_REQUEST_EVENT, SignerBackend, SignerRequest, SignerServer};
+use crate::storage::MigrationReport;
+use crate::subscription::{Backoff, NODE_EVENT, NodeCache, NodeEvent, SubscriptionConfig};
+use crate::typed_data::TypedDataPreview;
 use crate::types::*;
-use crate::wallet::WalletManager;
-use std::sync::Mutex;
-use tauri::State;
+use crate::wallet::{AccountSigner, WalletManager};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 // Global wallet manager state
 type WalletState = Mutex<WalletManager>;
 
+/// Signer server, if enabled, with the settings it was started with
+type SignerState = Mutex<Option<(SignerSettings, SignerServer)>>;
+
+/// Shows dApp requests in the UI and carries them out with the wallet manager
+struct AppSigner(AppHandle);
+
+impl SignerBackend for AppSigner {
+    fn prompt(&self, request: &SignerRequest) {
+        if let Err(e) = self.0.emit(SIGNER_REQUEST_EVENT, request) {
+            tracing::warn!("Failed to show signer request: {}", e);
+        }
+    }
+
+    fn grant(
+        &self,
+        origin: &str,
+        methods: BTreeSet<DappMethod>,
+        approval: Approval,
+    ) -> anyhow::Result<DappPermission> {
+        let state = self.0.state::<WalletState>();
+        let mut wallet_manager = state
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock wallet manager"))?;
+        wallet_manager.grant_dapp_permission(
+            origin,
+            methods,
+            approval.accounts,
+            approval.spending_limit,
+            approval.expires_at,
+        )
+    }
+
+    fn authorize(
+        &self,
+        origin: &str,
+        method: DappMethod,
+        params: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let state = self.0.state::<WalletState>();
+        let wallet_manager = state
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock wallet manager"))?;
+        signer::authorize(&wallet_manager, origin, method, params).map(|_| ())
+    }
+
+    fn execute(
+        &self,
+        origin: &str,
+        method: DappMethod,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        signer::execute(&self.0.state::<WalletState>(), origin, method, params)
+    }
+}
+
+/// Start, restart or stop the signer server to match `settings`
+async fn sync_signer(app: &AppHandle, settings: &SignerSettings) -> anyhow::Result<()> {
+    let state = app.state::<SignerState>();
+    let running = {
+        let mut signer = state
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock signer"))?;
+        if signer
+            .as_ref()
+            .is_some_and(|(running, _)| running == settings)
+        {
+            return Ok(());
+        }
+        signer.take()
+    };
+    // Stop the old server first so a restart can take the same port
+    if let Some((_, server)) = running {
+        server.stop().await;
+    }
+    if settings.enabled {
+        let server = SignerServer::start(
+            settings.port,
+            std::time::Duration::from_secs(settings.approval_timeout_secs),
+            Arc::new(AppSigner(app.clone())),
+        )?;
+        *state
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock signer"))? =
+            Some((settings.clone(), server));
+    }
+    Ok(())
+}
+
+/// Node subscription, if one is running, and the state it keeps up to date
+#[derive(Default)]
+struct SubscriptionState {
+    cache: Arc<Mutex<NodeCache>>,
+    task: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+/// Signer of the selected account, taken out so the wallet isn't locked while it signs
+fn selected_signer(state: &WalletState) -> Result<AccountSigner, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager.selected_signer().map_err(|e| e.to_string())
+}
+
+/// Run `task` on a blocking thread. External signers wait for the user to
+/// confirm on the device, which would otherwise stall the async runtime.
+async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn create_wallet(
     wallet_name: String,
@@ -49,9 +195,105 @@ async fn list_wallets(state: State<'_, WalletState>) -> Result<Vec<String>, Stri
 }
 
 #[tauri::command]
-async fn list_accounts(state: State<'_, WalletState>) -> Result<Vec<AccountInfo>, String> {
+async fn delete_wallet(
+    wallet_name: String,
+    password: String,
+    state: State<'_, WalletState>,
+) -> Result<String, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .delete_wallet(&wallet_name, &password)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn rename_wallet(
+    old_name: String,
+    new_name: String,
+    state: State<'_, WalletState>,
+) -> Result<(), String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .rename_wallet(&old_name, &new_name)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_backup(
+    path: String,
+    password: String,
+    wallet_names: Option<Vec<String>>,
+    include_settings: Option<bool>,
+    state: State<'_, WalletState>,
+) -> Result<BackupPreview, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .create_backup(
+            &path,
+            &password,
+            &wallet_names.unwrap_or_default(),
+            include_settings.unwrap_or(true),
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn preview_backup(
+    path: String,
+    password: String,
+    state: State<'_, WalletState>,
+) -> Result<BackupPreview, String> {
     let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
-    wallet_manager.list_accounts().map_err(|e| e.to_string())
+    wallet_manager
+        .preview_backup(&path, &password)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn restore_backup(
+    path: String,
+    password: String,
+    replace: Option<bool>,
+    state: State<'_, WalletState>,
+) -> Result<RestoreReport, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .restore_backup(&path, &password, replace.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn migrate_storage(
+    to: StorageBackend,
+    state: State<'_, WalletState>,
+) -> Result<MigrationReport, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .migrate_storage(to)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_accounts(
+    include_hidden: Option<bool>,
+    state: State<'_, WalletState>,
+) -> Result<Vec<AccountInfo>, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .list_accounts(include_hidden.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_account_metadata(
+    index: usize,
+    metadata: AccountMetadata,
+    state: State<'_, WalletState>,
+) -> Result<AccountInfo, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .update_account_metadata(index, metadata)
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -83,6 +325,32 @@ async fn add_account(
         .map_err(|e| e.to_string())
 }
 
+/// Add an account whose key stays on the external signer at `socket`
+#[tauri::command]
+async fn add_external_account(
+    socket: String,
+    key_index: u32,
+    state: State<'_, WalletState>,
+) -> Result<AccountInfo, String> {
+    #[cfg(unix)]
+    {
+        let account = signing::ExternalAccount {
+            socket: socket.into(),
+            key_index,
+        };
+        let signer = run_blocking(move || signing::ExternalSigner::connect(&account)).await?;
+        let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        wallet_manager
+            .add_external_account(&signer)
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (state, socket, key_index);
+        Err("External signers are only supported on Unix".to_string())
+    }
+}
+
 #[tauri::command]
 async fn create_account(state: State<'_, WalletState>) -> Result<AccountInfo, String> {
     let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
@@ -92,14 +360,590 @@ async fn create_account(state: State<'_, WalletState>) -> Result<AccountInfo, St
 #[tauri::command]
 async fn sign_transaction(
     data: String,
+    scheme: Option<SignatureScheme>,
     state: State<'_, WalletState>,
 ) -> Result<SignTransactionResponse, String> {
+    let signer = selected_signer(&state)?;
+    run_blocking(move || signer.sign_transaction(&data, scheme.unwrap_or_default())).await
+}
+
+#[tauri::command]
+async fn get_x_only_public_key(state: State<'_, WalletState>) -> Result<String, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .get_x_only_public_key()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn preview_typed_data(
+    typed_data: String,
+    state: State<'_, WalletState>,
+) -> Result<TypedDataPreview, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .preview_typed_data(&typed_data)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn sign_typed_data(
+    typed_data: String,
+    scheme: Option<SignatureScheme>,
+    state: State<'_, WalletState>,
+) -> Result<SignTypedDataResponse, String> {
+    let (signer, typed_data) = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        let typed_data = wallet_manager
+            .parse_typed_data(&typed_data)
+            .map_err(|e| e.to_string())?;
+        let signer = wallet_manager
+            .selected_signer()
+            .map_err(|e| e.to_string())?;
+        (signer, typed_data)
+    };
+    run_blocking(move || signer.sign_typed_data(&typed_data, scheme.unwrap_or_default())).await
+}
+
+#[tauri::command]
+async fn create_multisig_account(
+    name: String,
+    threshold: usize,
+    participants: Vec<String>,
+    state: State<'_, WalletState>,
+) -> Result<MultisigAccount, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .create_multisig_account(&name, threshold, &participants)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_multisig_accounts(
+    state: State<'_, WalletState>,
+) -> Result<Vec<MultisigAccount>, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .list_multisig_accounts()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_partial_transaction(
+    transaction: Transaction,
+    multisig: Option<String>,
+    format: Option<PstFormat>,
+    state: State<'_, WalletState>,
+) -> Result<String, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .create_partial_transaction(transaction, multisig.as_deref(), format.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn inspect_partial_transaction(
+    pst: String,
+    state: State<'_, WalletState>,
+) -> Result<PstInspection, String> {
     let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
     wallet_manager
-        .sign_transaction(&data)
+        .inspect_partial_transaction(&pst)
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn sign_partial_transaction(
+    pst: String,
+    scheme: Option<SignatureScheme>,
+    format: Option<PstFormat>,
+    state: State<'_, WalletState>,
+) -> Result<String, String> {
+    let (signer, mut pst) = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        let pst = wallet_manager
+            .open_partial_transaction(&pst)
+            .map_err(|e| e.to_string())?;
+        let signer = wallet_manager
+            .selected_signer()
+            .map_err(|e| e.to_string())?;
+        (signer, pst)
+    };
+    let pst = run_blocking(move || {
+        signer.sign_partial_transaction(&mut pst, scheme.unwrap_or_default())?;
+        Ok(pst)
+    })
+    .await?;
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .record_partial_transaction(&pst, format.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn combine_partial_transactions(
+    psts: Vec<String>,
+    format: Option<PstFormat>,
+    state: State<'_, WalletState>,
+) -> Result<String, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .combine_partial_transactions(&psts, format.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn finalize_partial_transaction(
+    pst: String,
+    state: State<'_, WalletState>,
+) -> Result<FinalizedTransaction, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .finalize_partial_transaction(&pst)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn submit_transaction(
+    signed_transaction: String,
+    state: State<'_, WalletState>,
+) -> Result<String, String> {
+    let settings = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        wallet_manager
+            .check_submission(&signed_transaction)
+            .map_err(|e| e.to_string())?;
+        wallet_manager.active_settings()
+    };
+    let raw =
+        hex::decode(signed_transaction.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    let hash = NodeClient::new(&settings)
+        .map_err(|e| e.to_string())?
+        .submit_transaction(&raw)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .record_submitted(&signed_transaction)
+        .map_err(|e| e.to_string())?;
+    Ok(hash)
+}
+
+#[tauri::command]
+async fn list_transactions(
+    filter: Option<HistoryFilter>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, WalletState>,
+) -> Result<HistoryPage, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .list_transactions(
+            filter.unwrap_or_default(),
+            offset.unwrap_or(0),
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn drop_transaction(
+    id_or_hash: String,
+    state: State<'_, WalletState>,
+) -> Result<TxRecord, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .drop_transaction(&id_or_hash)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_nonce_state(
+    account: Option<String>,
+    state: State<'_, WalletState>,
+) -> Result<NonceReport, String> {
+    let (settings, account, history) = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        let account = wallet_manager
+            .nonce_account(account.as_deref())
+            .map_err(|e| e.to_string())?;
+        let history = wallet_manager
+            .transaction_history()
+            .map_err(|e| e.to_string())?;
+        (wallet_manager.active_settings(), account, history)
+    };
+    let node = NodeClient::new(&settings).map_err(|e| e.to_string())?;
+    let nonces = NonceManager::fetch(&node, &account, settings.network().chain_id, &history)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(nonces.report(chrono::Utc::now()))
+}
+
+/// Gas limit and price for `transaction` from the node, after the configured margin and caps
+#[tauri::command]
+async fn estimate_fee(
+    transaction: Transaction,
+    tier: Option<FeeTier>,
+    state: State<'_, WalletState>,
+) -> Result<FeeEstimate, String> {
+    let settings = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        wallet_manager.active_settings()
+    };
+    let node = NodeClient::new(&settings).map_err(|e| e.to_string())?;
+    let tier = tier.unwrap_or(settings.fee.tier);
+    FeeEstimate::fetch(&node, &settings.fee, tier, &transaction)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn build_replacement(
+    id_or_hash: String,
+    cancel: bool,
+    gas_price: Option<u64>,
+    state: State<'_, WalletState>,
+) -> Result<Transaction, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .build_replacement(&id_or_hash, cancel, gas_price)
+        .map_err(|e| e.to_string())
+}
+
+/// Subscribe to the node's events for the loaded wallet's accounts, replacing
+/// any running subscription. Events are emitted to the frontend as `node-event`.
+#[tauri::command]
+async fn start_subscriptions(
+    app: AppHandle,
+    state: State<'_, WalletState>,
+    subscriptions: State<'_, SubscriptionState>,
+) -> Result<(), String> {
+    let (settings, accounts) = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        let accounts = wallet_manager
+            .list_accounts(true)
+            .map_err(|e| e.to_string())?;
+        (wallet_manager.active_settings(), accounts)
+    };
+    let config = SubscriptionConfig {
+        url: settings.ws_url(),
+        accounts: accounts
+            .into_iter()
+            .map(|account| account.address)
+            .collect(),
+        reconnect: settings.node.auto_reconnect,
+        backoff: Backoff::default(),
+    };
+
+    let mut task = subscriptions
+        .task
+        .lock()
+        .map_err(|_| "Failed to lock subscriptions")?;
+    if let Some(task) = task.take() {
+        task.abort();
+    }
+    *subscriptions
+        .cache
+        .lock()
+        .map_err(|_| "Failed to lock node cache")? = NodeCache::default();
+
+    let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let cache = subscriptions.cache.clone();
+    *task = Some(tauri::async_runtime::spawn(async move {
+        let subscriber = tauri::async_runtime::spawn(subscription::run(config, cache, sender));
+        while let Some(event) = events.recv().await {
+            if let NodeEvent::Transaction { hash, status } = &event {
+                let settled = app
+                    .state::<WalletState>()
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("Failed to lock wallet manager"))
+                    .and_then(|wallet_manager| {
+                        wallet_manager.settle_transactions(&[(hash.clone(), *status)])
+                    });
+                if let Err(e) = settled {
+                    tracing::warn!("Failed to update transaction {}: {}", hash, e);
+                }
+            }
+            if let Err(e) = app.emit(NODE_EVENT, &event) {
+                tracing::warn!("Failed to emit node event: {}", e);
+            }
+        }
+        subscriber.abort();
+    }));
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_subscriptions(subscriptions: State<'_, SubscriptionState>) -> Result<(), String> {
+    let mut task = subscriptions
+        .task
+        .lock()
+        .map_err(|_| "Failed to lock subscriptions")?;
+    if let Some(task) = task.take() {
+        task.abort();
+    }
+    subscriptions
+        .cache
+        .lock()
+        .map_err(|_| "Failed to lock node cache")?
+        .connected = false;
+    Ok(())
+}
+
+/// Balances and transaction statuses last reported by the node
+#[tauri::command]
+async fn get_node_cache(subscriptions: State<'_, SubscriptionState>) -> Result<NodeCache, String> {
+    let cache = subscriptions
+        .cache
+        .lock()
+        .map_err(|_| "Failed to lock node cache")?;
+    Ok(cache.clone())
+}
+
+/// Ask the node for the status of unsettled transactions, returning how many changed
+#[tauri::command]
+async fn sync_transactions(state: State<'_, WalletState>) -> Result<usize, String> {
+    // The node is queried without holding the lock so the UI stays responsive
+    let (settings, hashes) = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        let hashes = wallet_manager
+            .unsettled_transactions()
+            .map_err(|e| e.to_string())?;
+        (wallet_manager.active_settings(), hashes)
+    };
+    let node = NodeClient::new(&settings).map_err(|e| e.to_string())?;
+    let mut statuses = Vec::new();
+    for hash in hashes {
+        if let Some(status) = node
+            .transaction_status(&hash)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            statuses.push((hash, status));
+        }
+    }
+
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .settle_transactions(&statuses)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_qr_frames(
+    pst: String,
+    chunk_size: Option<usize>,
+    state: State<'_, WalletState>,
+) -> Result<Vec<QrFrame>, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .export_qr_frames(&pst, chunk_size.unwrap_or(crate::qr::DEFAULT_CHUNK_SIZE))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn import_qr_parts(
+    parts: Vec<String>,
+    format: Option<PstFormat>,
+    state: State<'_, WalletState>,
+) -> Result<QrImportResult, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .import_qr_parts(&parts, format.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_contacts(
+    all: Option<bool>,
+    state: State<'_, WalletState>,
+) -> Result<Vec<Contact>, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .list_contacts(all.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn add_contact(
+    name: String,
+    address: String,
+    memo: Option<String>,
+    network: Option<Network>,
+    state: State<'_, WalletState>,
+) -> Result<Contact, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .add_contact(&name, &address, memo, network)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_contact(
+    name: String,
+    contact: Contact,
+    state: State<'_, WalletState>,
+) -> Result<Contact, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .update_contact(&name, contact)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn remove_contact(name: String, state: State<'_, WalletState>) -> Result<Contact, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .remove_contact(&name)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn resolve_recipient(
+    recipient: String,
+    state: State<'_, WalletState>,
+) -> Result<String, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .resolve_recipient(&recipient)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_settings(state: State<'_, WalletState>) -> Result<Settings, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    Ok(wallet_manager.get_settings())
+}
+
+#[tauri::command]
+async fn update_settings(
+    app: AppHandle,
+    mut settings: Settings,
+    state: State<'_, WalletState>,
+) -> Result<Settings, String> {
+    let settings = {
+        let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        // dApp permissions change through their own commands and while
+        // dApps spend, so a settings page opened earlier can't undo that
+        settings.dapps = wallet_manager.get_settings().dapps;
+        wallet_manager
+            .update_settings(settings)
+            .map_err(|e| e.to_string())?
+    };
+    sync_signer(&app, &settings.signer)
+        .await
+        .map_err(|e| format!("Settings saved, but the signer could not start: {}", e))?;
+    Ok(settings)
+}
+
+#[derive(Debug, serde::Serialize)]
+struct SignerStatus {
+    /// Address the server listens on, if it is running
+    address: Option<String>,
+    /// Requests waiting for approval, oldest first
+    pending: Vec<SignerRequest>,
+}
+
+#[tauri::command]
+async fn get_signer_status(signer: State<'_, SignerState>) -> Result<SignerStatus, String> {
+    let signer = signer.lock().map_err(|_| "Failed to lock signer")?;
+    Ok(match signer.as_ref() {
+        Some((_, server)) => SignerStatus {
+            address: Some(server.address().to_string()),
+            pending: server.pending(),
+        },
+        None => SignerStatus {
+            address: None,
+            pending: Vec::new(),
+        },
+    })
+}
+
+/// Approve or reject a dApp request shown to the user
+#[tauri::command]
+async fn respond_signer_request(
+    id: u64,
+    approval: Approval,
+    signer: State<'_, SignerState>,
+) -> Result<(), String> {
+    let signer = signer.lock().map_err(|_| "Failed to lock signer")?;
+    let (_, server) = signer.as_ref().ok_or("The signer is not running")?;
+    server.respond(id, approval).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Serialize)]
+struct DappConnection {
+    origin: String,
+    #[serde(flatten)]
+    permission: DappPermission,
+    /// Whether the origin has a session with the running signer
+    connected: bool,
+}
+
+#[tauri::command]
+async fn list_dapp_permissions(
+    state: State<'_, WalletState>,
+    signer: State<'_, SignerState>,
+) -> Result<Vec<DappConnection>, String> {
+    let permissions = {
+        let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        wallet_manager.dapp_permissions()
+    };
+    let sessions = signer
+        .lock()
+        .map_err(|_| "Failed to lock signer")?
+        .as_ref()
+        .map(|(_, server)| server.sessions())
+        .unwrap_or_default();
+    Ok(permissions
+        .into_iter()
+        .map(|(origin, permission)| DappConnection {
+            connected: sessions.contains(&origin),
+            origin,
+            permission,
+        })
+        .collect())
+}
+
+/// Remove the permissions of `origin` and end its session
+#[tauri::command]
+async fn revoke_dapp_permission(
+    origin: String,
+    state: State<'_, WalletState>,
+    signer: State<'_, SignerState>,
+) -> Result<DappPermission, String> {
+    let permission = {
+        let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+        wallet_manager
+            .revoke_dapp_permission(&origin)
+            .map_err(|e| e.to_string())?
+    };
+    if let Some((_, server)) = signer.lock().map_err(|_| "Failed to lock signer")?.as_ref() {
+        server.disconnect(&origin);
+    }
+    Ok(permission)
+}
+
+#[tauri::command]
+async fn get_active_network(state: State<'_, WalletState>) -> Result<ActiveNetwork, String> {
+    let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    Ok(wallet_manager.active_network())
+}
+
+/// Use the loaded wallet on `network`, or on the configured network with `None`
+#[tauri::command]
+async fn set_wallet_network(
+    network: Option<Network>,
+    state: State<'_, WalletState>,
+) -> Result<ActiveNetwork, String> {
+    let mut wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
+    wallet_manager
+        .set_wallet_network(network)
+        .map_err(|e| e.to_string())?;
+    Ok(wallet_manager.active_network())
+}
+
 #[tauri::command]
 async fn get_wallet_info(state: State<'_, WalletState>) -> Result<Option<WalletInfo>, String> {
     let wallet_manager = state.lock().map_err(|_| "Failed to lock wallet manager")?;
@@ -113,24 +957,91 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    // Load settings, then initialize logging at the configured level
+    let settings = Settings::load(config::default_config_path());
+    let level = settings
+        .as_ref()
+        .map(|settings| settings.log.level)
+        .unwrap_or_default();
+    tracing_subscriber::fmt()
+        .with_max_level(level.as_tracing())
+        .init();
+    let settings = settings.unwrap_or_else(|e| {
+        tracing::warn!("Using default settings: {}", e);
+        Settings::default()
+    });
+    let signer_settings = settings.signer.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(Mutex::new(WalletManager::new()))
+        .manage(Mutex::new(WalletManager::new(settings)))
+        .manage(SubscriptionState::default())
+        .manage(SignerState::default())
+        .setup(move |app| {
+            // Started on the async runtime, which the server runs on
+            let app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = sync_signer(&app, &signer_settings).await {
+                    tracing::warn!("Failed to start the signer: {}", e);
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             create_wallet,
             import_wallet,
             load_wallet,
             list_wallets,
+            delete_wallet,
+            rename_wallet,
+            create_backup,
+            preview_backup,
+            restore_backup,
+            migrate_storage,
             list_accounts,
+            update_account_metadata,
             select_account,
             get_selected_account,
             add_account,
+            add_external_account,
             create_account,
             sign_transaction,
+            get_x_only_public_key,
+            preview_typed_data,
+            sign_typed_data,
+            create_multisig_account,
+            list_multisig_accounts,
+            create_partial_transaction,
+            inspect_partial_transaction,
+            sign_partial_transaction,
+            combine_partial_transactions,
+            finalize_partial_transaction,
+            submit_transaction,
+            list_transactions,
+            sync_transactions,
+            drop_transaction,
+            start_subscriptions,
+            stop_subscriptions,
+            get_node_cache,
+            get_nonce_state,
+            estimate_fee,
+            build_replacement,
+            export_qr_frames,
+            import_qr_parts,
+            list_contacts,
+            add_contact,
+            update_contact,
+            remove_contact,
+            resolve_recipient,
+            get_settings,
+            update_settings,
+            get_active_network,
+            get_signer_status,
+            respond_signer_request,
+            list_dapp_permissions,
+            revoke_dapp_permission,
+            set_wallet_network,
             get_wallet_info
         ])
         .run(tauri::generate_context!())
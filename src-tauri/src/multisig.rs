@@ -0,0 +1,99 @@
+use anyhow::{Result, anyhow};
+use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Domain tag mixed into multisig address derivation (shared with the CLI)
+const MULTISIG_ADDRESS_TAG: &[u8] = b"mazzaroth-multisig";
+
+const MAX_PARTICIPANTS: usize = 16;
+
+/// M-of-N multisignature account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MultisigAccount {
+    pub name: String,
+    pub threshold: usize,
+    /// Compressed participant public keys (hex), sorted
+    pub participants: Vec<String>,
+    pub address: String,
+}
+
+impl MultisigAccount {
+    pub fn new(name: &str, threshold: usize, participants: &[String]) -> Result<Self> {
+        let mut keys = participants
+            .iter()
+            .map(|key| compress_public_key(key))
+            .collect::<Result<Vec<_>>>()?;
+        keys.sort();
+        keys.dedup();
+
+        if keys.len() < 2 {
+            return Err(anyhow!(
+                "A multisig account needs at least 2 distinct participants"
+            ));
+        }
+        if keys.len() > MAX_PARTICIPANTS {
+            return Err(anyhow!(
+                "A multisig account supports at most {} participants",
+                MAX_PARTICIPANTS
+            ));
+        }
+        if threshold == 0 || threshold > keys.len() {
+            return Err(anyhow!("Threshold must be between 1 and {}", keys.len()));
+        }
+
+        let address = Self::derive_address(threshold, &keys);
+        Ok(Self {
+            name: name.to_string(),
+            threshold,
+            participants: keys.iter().map(hex::encode).collect(),
+            address,
+        })
+    }
+
+    /// sha256(tag || M || N || sorted compressed keys), first 20 bytes
+    fn derive_address(threshold: usize, keys: &[[u8; 33]]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(MULTISIG_ADDRESS_TAG);
+        hasher.update([threshold as u8, keys.len() as u8]);
+        for key in keys {
+            hasher.update(key);
+        }
+        let result = hasher.finalize();
+        hex::encode(&result[..20])
+    }
+
+    pub fn is_participant(&self, public_key: &str) -> bool {
+        self.participants
+            .iter()
+            .any(|key| key.eq_ignore_ascii_case(public_key.trim_start_matches("0x")))
+    }
+
+    pub fn verify_address(&self) -> Result<()> {
+        let keys = self
+            .participants
+            .iter()
+            .map(|key| compress_public_key(key))
+            .collect::<Result<Vec<_>>>()?;
+        let mut sorted = keys.clone();
+        sorted.sort();
+        if keys != sorted {
+            return Err(anyhow!("Multisig participants are not sorted"));
+        }
+
+        let expected = Self::derive_address(self.threshold, &keys);
+        if expected != self.address.trim_start_matches("0x") {
+            return Err(anyhow!("Multisig address does not match its participants"));
+        }
+        Ok(())
+    }
+}
+
+/// Normalize a compressed or uncompressed public key (hex) to compressed bytes
+pub fn compress_public_key(key_hex: &str) -> Result<[u8; 33]> {
+    let bytes = hex::decode(key_hex.trim_start_matches("0x"))
+        .map_err(|_| anyhow!("Invalid public key format: {}", key_hex))?;
+    let public_key =
+        PublicKey::from_slice(&bytes).map_err(|_| anyhow!("Invalid public key: {}", key_hex))?;
+    Ok(public_key.serialize())
+}
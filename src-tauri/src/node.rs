@@ -0,0 +1,111 @@
+//! Client for the node's JSON-RPC endpoint (same protocol as the CLI)
+//!
+//! Requests are JSON-RPC 2.0 calls posted to the active network's node URL. Hashes and raw
+//! transactions travel as `0x`-prefixed hex.
+
+use crate::config::Settings;
+use crate::fee::GasPrices;
+use crate::history::TxStatus;
+use crate::types::Transaction;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+pub struct NodeClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl NodeClient {
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(settings.node.timeout_secs))
+            .build()?;
+        Ok(Self {
+            url: settings.node_url().to_string(),
+            client,
+        })
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.error {
+            return Err(anyhow!(
+                "Node rejected {}: {} ({})",
+                method,
+                error.message,
+                error.code
+            ));
+        }
+        Ok(serde_json::from_value(
+            response.result.unwrap_or(Value::Null),
+        )?)
+    }
+
+    /// Broadcast raw signed transaction bytes, returning the hash the node reports
+    pub async fn submit_transaction(&self, raw: &[u8]) -> Result<String> {
+        self.call("tx_submit", json!([format!("0x{}", hex::encode(raw))]))
+            .await
+    }
+
+    /// Next nonce the node will accept from `address`
+    pub async fn account_nonce(&self, address: &str) -> Result<u64> {
+        let address = format!("0x{}", address.trim_start_matches("0x"));
+        self.call("account_nonce", json!([address])).await
+    }
+
+    /// Gas the node expects `transaction` to use
+    pub async fn estimate_gas(&self, transaction: &Transaction) -> Result<u64> {
+        self.call("gas_estimate", json!([transaction])).await
+    }
+
+    /// Gas prices the node currently suggests for each fee tier
+    pub async fn gas_prices(&self) -> Result<GasPrices> {
+        self.call("gas_price", json!([])).await
+    }
+
+    /// Status of the transaction with hash `hash`, or `None` if the node doesn't know it
+    pub async fn transaction_status(&self, hash: &str) -> Result<Option<TxStatus>> {
+        let status: String = self.call("tx_status", json!([hash])).await?;
+        parse_status(&status)
+    }
+}
+
+/// Transaction status as the node names it, or `None` for a transaction it doesn't know
+pub fn parse_status(status: &str) -> Result<Option<TxStatus>> {
+    match status {
+        "pending" => Ok(Some(TxStatus::Submitted)),
+        "confirmed" => Ok(Some(TxStatus::Confirmed)),
+        "failed" => Ok(Some(TxStatus::Failed)),
+        "unknown" => Ok(None),
+        other => Err(anyhow!("Unknown transaction status from node: {}", other)),
+    }
+}
@@ -0,0 +1,387 @@
+use crate::multisig::{MultisigAccount, compress_public_key};
+use crate::signing::Signer;
+use crate::types::{SignatureScheme, Transaction};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use secp256k1::{Message, PublicKey, Secp256k1};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Magic bytes at the start of the binary encoding (shared with the CLI)
+const PST_MAGIC: &[u8; 4] = b"MPST";
+/// Version 2 signing hashes cover the chain id, so version 1 files can't be verified
+const PST_VERSION: u8 = 2;
+const PST_FORMAT_NAME: &str = "rwmth-pst";
+
+/// Encoding used when handing a transaction file to the frontend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PstFormat {
+    /// Pretty-printed JSON text
+    #[default]
+    Json,
+    /// Binary encoding as base64 text
+    Binary,
+}
+
+/// Fixed-size bytes, 0x-prefixed hex in JSON and raw bytes in binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> FixedBytes<N> {
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+impl<const N: usize> Serialize for FixedBytes<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedBytes<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bytes = if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            hex::decode(s.trim_start_matches("0x")).map_err(serde::de::Error::custom)?
+        } else {
+            Vec::<u8>::deserialize(deserializer)?
+        };
+        let array: [u8; N] = bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::custom(format!("Expected {} bytes", N)))?;
+        Ok(FixedBytes(array))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PartialSignature {
+    /// Compressed signer public key
+    pub public_key: FixedBytes<33>,
+    pub scheme: SignatureScheme,
+    /// Compact ECDSA or BIP340 Schnorr signature over the signing hash
+    pub signature: FixedBytes<64>,
+}
+
+/// Unsigned or partially signed transaction, passed between signers as a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartiallySignedTransaction {
+    pub format: String,
+    pub version: u8,
+    pub transaction: Transaction,
+    pub signing_hash: FixedBytes<32>,
+    pub required_signers: Vec<FixedBytes<33>>,
+    pub threshold: usize,
+    pub signatures: Vec<PartialSignature>,
+}
+
+/// Fully signed transaction ready to be broadcast
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTransaction {
+    pub transaction: Transaction,
+    pub signatures: Vec<PartialSignature>,
+}
+
+/// Summary of a transaction file for review in the UI
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PstInspection {
+    pub transaction: Transaction,
+    pub signing_hash: String,
+    pub threshold: usize,
+    pub required_signers: Vec<String>,
+    pub signed: Vec<String>,
+    pub missing: Vec<String>,
+    pub complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FinalizedTransaction {
+    pub hash: String,               // Hex string
+    pub signed_transaction: String, // Hex string
+}
+
+impl PartiallySignedTransaction {
+    pub fn new(
+        transaction: Transaction,
+        required_signers: Vec<FixedBytes<33>>,
+        threshold: usize,
+    ) -> Result<Self> {
+        if required_signers.is_empty() {
+            return Err(anyhow!("At least one signer is required"));
+        }
+        if threshold == 0 || threshold > required_signers.len() {
+            return Err(anyhow!(
+                "Threshold must be between 1 and {}",
+                required_signers.len()
+            ));
+        }
+
+        let signing_hash = FixedBytes(transaction.signing_hash()?);
+        Ok(Self {
+            format: PST_FORMAT_NAME.to_string(),
+            version: PST_VERSION,
+            transaction,
+            signing_hash,
+            required_signers,
+            threshold,
+            signatures: Vec::new(),
+        })
+    }
+
+    pub fn for_multisig(transaction: Transaction, multisig: &MultisigAccount) -> Result<Self> {
+        if !transaction
+            .from
+            .trim_start_matches("0x")
+            .eq_ignore_ascii_case(multisig.address.trim_start_matches("0x"))
+        {
+            return Err(anyhow!(
+                "Transaction sender {} is not multisig {}",
+                transaction.from,
+                multisig.address
+            ));
+        }
+
+        let participants = multisig
+            .participants
+            .iter()
+            .map(|key| compress_public_key(key).map(FixedBytes))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(transaction, participants, multisig.threshold)
+    }
+
+    /// Decode JSON or binary bytes, detecting the format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let pst: Self = if let Some(payload) = bytes.strip_prefix(PST_MAGIC) {
+            let (version, payload) = payload
+                .split_first()
+                .ok_or_else(|| anyhow!("Truncated transaction file"))?;
+            if *version != PST_VERSION {
+                return Err(anyhow!("Unsupported transaction file version: {}", version));
+            }
+            bincode::deserialize(payload)
+                .map_err(|e| anyhow!("Invalid binary transaction file: {}", e))?
+        } else {
+            serde_json::from_slice(bytes)
+                .map_err(|e| anyhow!("Invalid JSON transaction file: {}", e))?
+        };
+
+        if pst.format != PST_FORMAT_NAME {
+            return Err(anyhow!("Not a transaction file: {}", pst.format));
+        }
+        if pst.version != PST_VERSION {
+            return Err(anyhow!(
+                "Unsupported transaction file version: {}",
+                pst.version
+            ));
+        }
+        pst.verify()?;
+        Ok(pst)
+    }
+
+    pub fn to_bytes(&self, format: PstFormat) -> Result<Vec<u8>> {
+        match format {
+            PstFormat::Json => Ok(serde_json::to_vec_pretty(self)?),
+            PstFormat::Binary => {
+                let mut bytes = PST_MAGIC.to_vec();
+                bytes.push(PST_VERSION);
+                bytes.extend(bincode::serialize(self)?);
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decode the text form used by the frontend: JSON, or base64 of the binary encoding
+    pub fn from_text(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.starts_with('{') {
+            Self::from_bytes(text.as_bytes())
+        } else {
+            let bytes = BASE64
+                .decode(text)
+                .map_err(|_| anyhow!("Transaction file is neither JSON nor base64"))?;
+            Self::from_bytes(&bytes)
+        }
+    }
+
+    pub fn to_text(&self, format: PstFormat) -> Result<String> {
+        let bytes = self.to_bytes(format)?;
+        match format {
+            PstFormat::Json => Ok(String::from_utf8(bytes)?),
+            PstFormat::Binary => Ok(BASE64.encode(bytes)),
+        }
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer, scheme: SignatureScheme) -> Result<()> {
+        let public_key = FixedBytes(signer.public_key()?.serialize());
+        if !self.required_signers.contains(&public_key) {
+            return Err(anyhow!(
+                "Selected account is not a required signer of this transaction"
+            ));
+        }
+
+        let signature = signer.sign_hash(&self.signing_hash.0, scheme)?;
+
+        self.signatures
+            .retain(|existing| existing.public_key != public_key);
+        self.signatures.push(PartialSignature {
+            public_key,
+            scheme,
+            signature: FixedBytes(signature),
+        });
+        self.sort_signatures();
+        Ok(())
+    }
+
+    pub fn combine(&mut self, other: &Self) -> Result<()> {
+        if other.signing_hash != self.signing_hash
+            || other.required_signers != self.required_signers
+            || other.threshold != self.threshold
+        {
+            return Err(anyhow!("Transaction files do not match"));
+        }
+        other.verify()?;
+
+        for signature in &other.signatures {
+            if !self
+                .signatures
+                .iter()
+                .any(|existing| existing.public_key == signature.public_key)
+            {
+                self.signatures.push(signature.clone());
+            }
+        }
+        self.sort_signatures();
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures.len() >= self.threshold
+    }
+
+    pub fn missing_signers(&self) -> Vec<FixedBytes<33>> {
+        self.required_signers
+            .iter()
+            .filter(|key| {
+                !self
+                    .signatures
+                    .iter()
+                    .any(|signature| &signature.public_key == *key)
+            })
+            .copied()
+            .collect()
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        if self.signing_hash.0 != self.transaction.signing_hash()? {
+            return Err(anyhow!("Signing hash does not match the transaction"));
+        }
+
+        let secp = Secp256k1::verification_only();
+        let message = Message::from_digest(self.signing_hash.0);
+        for (i, signature) in self.signatures.iter().enumerate() {
+            let signer = signature.public_key.to_hex();
+            if !self.required_signers.contains(&signature.public_key) {
+                return Err(anyhow!("Signer {} is not a required signer", signer));
+            }
+            if self.signatures[..i]
+                .iter()
+                .any(|other| other.public_key == signature.public_key)
+            {
+                return Err(anyhow!("Duplicate signature from {}", signer));
+            }
+
+            let public_key = PublicKey::from_slice(&signature.public_key.0)?;
+            let valid = match signature.scheme {
+                SignatureScheme::Ecdsa => {
+                    secp256k1::ecdsa::Signature::from_compact(&signature.signature.0)
+                        .map(|sig| secp.verify_ecdsa(&message, &sig, &public_key).is_ok())
+                        .unwrap_or(false)
+                }
+                SignatureScheme::Schnorr => {
+                    secp256k1::schnorr::Signature::from_slice(&signature.signature.0)
+                        .map(|sig| {
+                            secp.verify_schnorr(&sig, &message, &public_key.x_only_public_key().0)
+                                .is_ok()
+                        })
+                        .unwrap_or(false)
+                }
+            };
+            if !valid {
+                return Err(anyhow!("Invalid signature from {}", signer));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finalize(&self) -> Result<SignedTransaction> {
+        self.verify()?;
+        if !self.is_complete() {
+            return Err(anyhow!(
+                "Not enough signatures: {} of {}",
+                self.signatures.len(),
+                self.threshold
+            ));
+        }
+
+        Ok(SignedTransaction {
+            transaction: self.transaction.clone(),
+            signatures: self.signatures.clone(),
+        })
+    }
+
+    pub fn inspect(&self) -> PstInspection {
+        PstInspection {
+            transaction: self.transaction.clone(),
+            signing_hash: self.signing_hash.to_hex(),
+            threshold: self.threshold,
+            required_signers: self.required_signers.iter().map(|k| k.to_hex()).collect(),
+            signed: self
+                .signatures
+                .iter()
+                .map(|signature| signature.public_key.to_hex())
+                .collect(),
+            missing: self.missing_signers().iter().map(|k| k.to_hex()).collect(),
+            complete: self.is_complete(),
+        }
+    }
+
+    fn sort_signatures(&mut self) {
+        let required = &self.required_signers;
+        self.signatures.sort_by_key(|signature| {
+            required
+                .iter()
+                .position(|key| key == &signature.public_key)
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+impl SignedTransaction {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes).map_err(|e| anyhow!("Invalid signed transaction: {}", e))
+    }
+
+    pub fn finalized(&self) -> Result<FinalizedTransaction> {
+        let raw = self.to_bytes()?;
+        Ok(FinalizedTransaction {
+            hash: hex::encode(Sha256::digest(&raw)),
+            signed_transaction: hex::encode(raw),
+        })
+    }
+}
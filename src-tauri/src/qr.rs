@@ -0,0 +1,250 @@
+use anyhow::{Result, anyhow};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Prefix of every QR part (shared with the CLI), e.g. `RWMTH:PST/1-3/0A1B2C3D/<HEX>`
+const QR_PREFIX: &str = "RWMTH:PST";
+
+/// Payload bytes carried by each QR frame by default
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+
+/// Most frames a payload may be split into (shared with the CLI); a scanned
+/// part claiming more is rejected before anything is sized by its total
+const MAX_QR_PARTS: usize = 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QrFrame {
+    pub index: usize,
+    pub total: usize,
+    pub part: String,
+    pub svg: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QrImportResult {
+    pub received: usize,
+    pub total: usize,
+    pub missing: Vec<usize>,
+    pub complete: bool,
+    pub pst: Option<String>,
+}
+
+/// Progress of reassembling scanned parts
+pub struct QrScan {
+    pub received: usize,
+    pub total: usize,
+    pub missing: Vec<usize>,
+    /// The joined payload, once every part is scanned
+    pub payload: Option<Vec<u8>>,
+}
+
+/// One frame of a multi-part QR payload
+struct QrPart {
+    index: usize,
+    total: usize,
+    checksum: [u8; 4],
+    data: Vec<u8>,
+}
+
+impl QrPart {
+    fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let rest = text
+            .strip_prefix(QR_PREFIX)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .ok_or_else(|| anyhow!("Not a transaction QR code: {}", text))?;
+
+        let fields: Vec<&str> = rest.splitn(3, '/').collect();
+        let [position, checksum, data] = fields[..] else {
+            return Err(anyhow!("Malformed transaction QR code"));
+        };
+
+        let (index, total) = position
+            .split_once('-')
+            .and_then(|(index, total)| Some((index.parse().ok()?, total.parse().ok()?)))
+            .ok_or_else(|| anyhow!("Malformed QR part position: {}", position))?;
+        if total > MAX_QR_PARTS {
+            return Err(anyhow!(
+                "QR payload of {} parts is over the limit of {}",
+                total,
+                MAX_QR_PARTS
+            ));
+        }
+        if index == 0 || index > total {
+            return Err(anyhow!("QR part {} out of range 1-{}", index, total));
+        }
+
+        let checksum = hex::decode(checksum)
+            .ok()
+            .and_then(|bytes| <[u8; 4]>::try_from(bytes).ok())
+            .ok_or_else(|| anyhow!("Malformed QR part checksum: {}", checksum))?;
+        let data = hex::decode(data).map_err(|_| anyhow!("Malformed QR part data"))?;
+
+        Ok(Self {
+            index,
+            total,
+            checksum,
+            data,
+        })
+    }
+}
+
+fn payload_checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(payload);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Split a payload into QR part texts of at most `chunk_size` bytes each
+pub fn encode_parts(payload: &[u8], chunk_size: usize) -> Result<Vec<String>> {
+    if chunk_size == 0 {
+        return Err(anyhow!("Chunk size must be greater than 0"));
+    }
+    if payload.is_empty() {
+        return Err(anyhow!("Nothing to encode"));
+    }
+
+    // Uppercase hex keeps every part in QR alphanumeric mode
+    let checksum = hex::encode_upper(payload_checksum(payload));
+    let total = payload.len().div_ceil(chunk_size);
+    if total > MAX_QR_PARTS {
+        return Err(anyhow!(
+            "Payload needs {} QR parts, over the limit of {}; use a larger chunk size",
+            total,
+            MAX_QR_PARTS
+        ));
+    }
+    Ok(payload
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            format!(
+                "{}/{}-{}/{}/{}",
+                QR_PREFIX,
+                i + 1,
+                total,
+                checksum,
+                hex::encode_upper(chunk)
+            )
+        })
+        .collect())
+}
+
+/// Render a payload as animated QR frames for the frontend
+pub fn encode_frames(payload: &[u8], chunk_size: usize) -> Result<Vec<QrFrame>> {
+    let parts = encode_parts(payload, chunk_size)?;
+    let total = parts.len();
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let code = QrCode::with_error_correction_level(part.as_bytes(), EcLevel::M)
+                .map_err(|e| anyhow!("Failed to encode QR code: {}", e))?;
+            let svg = code.render::<svg::Color>().min_dimensions(256, 256).build();
+            Ok(QrFrame {
+                index: i + 1,
+                total,
+                part,
+                svg,
+            })
+        })
+        .collect()
+}
+
+/// Reassemble scanned parts (any order, duplicates allowed)
+pub fn decode_parts(parts: &[String]) -> Result<QrScan> {
+    let mut header: Option<(usize, [u8; 4])> = None;
+    let mut chunks: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+
+    for text in parts {
+        let part = QrPart::parse(text)?;
+        match header {
+            Some(header) if header != (part.total, part.checksum) => {
+                return Err(anyhow!("QR part belongs to a different transaction"));
+            }
+            Some(_) => {}
+            None => header = Some((part.total, part.checksum)),
+        }
+        chunks.entry(part.index).or_insert(part.data);
+    }
+
+    let (total, checksum) = header.ok_or_else(|| anyhow!("No QR parts scanned"))?;
+    let missing: Vec<usize> = (1..=total).filter(|i| !chunks.contains_key(i)).collect();
+    let payload = if missing.is_empty() {
+        let payload: Vec<u8> = chunks.values().flatten().copied().collect();
+        if payload_checksum(&payload) != checksum {
+            return Err(anyhow!("QR payload checksum mismatch"));
+        }
+        Some(payload)
+    } else {
+        None
+    };
+
+    Ok(QrScan {
+        received: chunks.len(),
+        total,
+        missing,
+        payload,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::Color;
+
+    /// Render a part to a greyscale bitmap and read it back with a QR decoder
+    fn scan(part: &str) -> String {
+        let code = QrCode::with_error_correction_level(part.as_bytes(), EcLevel::M).unwrap();
+        let (modules, colors) = (code.width(), code.to_colors());
+        let (scale, border) = (6, 4);
+        let size = (modules + 2 * border) * scale;
+
+        let mut image = rqrr::PreparedImage::prepare_from_greyscale(size, size, |x, y| {
+            let (mx, my) = (x / scale, y / scale);
+            let inside = (border..border + modules).contains(&mx)
+                && (border..border + modules).contains(&my);
+            if inside && colors[(my - border) * modules + (mx - border)] == Color::Dark {
+                0
+            } else {
+                255
+            }
+        });
+        let grids = image.detect_grids();
+        assert_eq!(grids.len(), 1);
+        grids[0].decode().unwrap().1
+    }
+
+    #[test]
+    fn test_qr_frames_round_trip_through_images() {
+        let payload: Vec<u8> = (0..=255u8).cycle().take(700).collect();
+        let frames = encode_frames(&payload, 200).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|frame| frame.svg.starts_with("<?xml")));
+
+        let mut scanned: Vec<String> = frames.iter().rev().map(|f| scan(&f.part)).collect();
+        let partial = decode_parts(&scanned[..2]).unwrap();
+        assert!(partial.payload.is_none());
+        assert_eq!(partial.missing, vec![1, 2]);
+
+        scanned.push(scan(&frames[3].part));
+        let result = decode_parts(&scanned).unwrap();
+        assert!(result.missing.is_empty());
+        assert_eq!(result.payload.unwrap(), payload);
+
+        let other = encode_parts(&[1u8; 10], 200).unwrap();
+        scanned.extend(other);
+        assert!(decode_parts(&scanned).is_err());
+    }
+
+    #[test]
+    fn test_huge_part_totals_rejected() {
+        let hostile = "RWMTH:PST/1-18446744073709551615/00000000/AB".to_string();
+        assert!(decode_parts(&[hostile]).is_err());
+        let over = format!("RWMTH:PST/1-{}/00000000/AB", MAX_QR_PARTS + 1);
+        assert!(decode_parts(&[over]).is_err());
+        assert!(encode_parts(&[0u8; MAX_QR_PARTS + 1], 1).is_err());
+    }
+}
@@ -0,0 +1,948 @@
+//! Localhost JSON-RPC server through which dApps ask for signatures
+//!
+//! Opt-in with `signer.enabled`. The server only listens on 127.0.0.1 and
+//! only answers requests addressed to localhost, so a web page can't reach it
+//! through DNS rebinding. A dApp starts with `connect`, which the user approves
+//! for its origin along with the accounts, methods, spending limit and expiry
+//! the origin gets. Those permissions are kept in the `dapps` section of the
+//! config, so they outlive the session and can be revoked from the app. The
+//! reply carries a session token that every later request from that origin
+//! sends as a bearer token, so other local processes can't use the session.
+//! Requests outside the origin's permissions are refused before the user sees
+//! them; the rest still wait for the user to approve them.
+
+use crate::config::{DappMethod, DappPermission};
+use crate::pst::PstFormat;
+use crate::types::{SignatureScheme, Transaction};
+use crate::wallet::WalletManager;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use hyper::header::{self, HeaderValue};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Name of the Tauri event each request awaiting approval is emitted as
+pub const SIGNER_REQUEST_EVENT: &str = "signer-request";
+
+/// Largest request body accepted
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+// JSON-RPC error codes; the 4xxx ones are the usual wallet provider codes
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const REJECTED: i64 = 4001;
+const UNAUTHORIZED: i64 = 4100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignerMethod {
+    /// Ask for a session token for the calling origin
+    Connect,
+    /// Addresses of the listed accounts
+    Accounts,
+    /// Sign a text message with the selected account
+    SignMessage,
+    /// Sign and finalize a transaction with the selected account
+    SignTransaction,
+}
+
+impl SignerMethod {
+    /// Permission needed to call the method; `connect` needs none
+    pub fn permission(self) -> Option<DappMethod> {
+        match self {
+            SignerMethod::Connect => None,
+            SignerMethod::Accounts => Some(DappMethod::Accounts),
+            SignerMethod::SignMessage => Some(DappMethod::SignMessage),
+            SignerMethod::SignTransaction => Some(DappMethod::SignTransaction),
+        }
+    }
+}
+
+impl fmt::Display for SignerMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignerMethod::Connect => write!(f, "connect"),
+            SignerMethod::Accounts => write!(f, "accounts"),
+            SignerMethod::SignMessage => write!(f, "signMessage"),
+            SignerMethod::SignTransaction => write!(f, "signTransaction"),
+        }
+    }
+}
+
+/// A dApp request waiting for the user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignerRequest {
+    pub id: u64,
+    pub origin: String,
+    pub method: SignerMethod,
+    pub params: Value,
+}
+
+/// The user's answer to a `SignerRequest`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    pub approved: bool,
+    /// For `connect`: the methods granted, if fewer than the dApp asked for
+    #[serde(default)]
+    pub methods: Option<BTreeSet<DappMethod>>,
+    /// For `connect`: the accounts shared, by default the selected one
+    #[serde(default)]
+    pub accounts: Option<Vec<String>>,
+    /// For `connect`: most the origin's transactions may cost in total
+    #[serde(default)]
+    pub spending_limit: Option<u64>,
+    /// For `connect`: when the permission ends
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What the server needs from the app: showing prompts, keeping permissions and signing
+pub trait SignerBackend: Send + Sync + 'static {
+    /// Show `request` to the user, who answers through `SignerServer::respond`
+    fn prompt(&self, request: &SignerRequest);
+
+    /// Store the permission the user approved for `origin`
+    fn grant(
+        &self,
+        origin: &str,
+        methods: BTreeSet<DappMethod>,
+        approval: Approval,
+    ) -> Result<DappPermission>;
+
+    /// Check `origin` may make the request, before the user is asked
+    fn authorize(&self, origin: &str, method: DappMethod, params: &Value) -> Result<()>;
+
+    /// Carry out an approved request, returning its JSON-RPC result
+    fn execute(&self, origin: &str, method: DappMethod, params: Value) -> Result<Value>;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignMessageParams {
+    message: String,
+    #[serde(default)]
+    scheme: SignatureScheme,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignTransactionParams {
+    transaction: Transaction,
+    #[serde(default)]
+    scheme: SignatureScheme,
+}
+
+/// Check the permissions of `origin` allow a request with `wallet_manager`
+pub fn authorize(
+    wallet_manager: &WalletManager,
+    origin: &str,
+    method: DappMethod,
+    params: &Value,
+) -> Result<DappPermission> {
+    let transaction = match method {
+        DappMethod::SignTransaction => {
+            Some(SignTransactionParams::deserialize(params)?.transaction)
+        }
+        _ => None,
+    };
+    wallet_manager.authorize_dapp_request(origin, method, transaction.as_ref())
+}
+
+/// Carry out an approved request with the wallet manager behind `wallet`,
+/// checking the permissions of `origin` again in case they changed while it
+/// waited. The wallet isn't locked while the selected account's signer signs,
+/// which on a device lasts until the user confirms there.
+pub fn execute(
+    wallet: &Mutex<WalletManager>,
+    origin: &str,
+    method: DappMethod,
+    params: Value,
+) -> Result<Value> {
+    let lock = || {
+        wallet
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock wallet manager"))
+    };
+    match method {
+        DappMethod::Accounts => {
+            let wallet_manager = lock()?;
+            let permission = authorize(&wallet_manager, origin, method, &params)?;
+            let accounts: Vec<String> = wallet_manager
+                .list_accounts(false)?
+                .into_iter()
+                .filter(|account| permission.allows_account(&account.address))
+                .map(|account| format!("0x{}", account.address))
+                .collect();
+            Ok(json!(accounts))
+        }
+        DappMethod::SignMessage => {
+            let signer = {
+                let wallet_manager = lock()?;
+                authorize(&wallet_manager, origin, method, &params)?;
+                wallet_manager.selected_signer()?
+            };
+            let params: SignMessageParams = serde_json::from_value(params)?;
+            Ok(serde_json::to_value(
+                signer.sign_message(&params.message, params.scheme)?,
+            )?)
+        }
+        DappMethod::SignTransaction => {
+            let SignTransactionParams {
+                transaction,
+                scheme,
+            } = serde_json::from_value(params.clone())?;
+            let cost = transaction.total_cost();
+            let (signer, mut pst) = {
+                let wallet_manager = lock()?;
+                authorize(&wallet_manager, origin, method, &params)?;
+                let pst = wallet_manager.create_partial_transaction(
+                    transaction,
+                    None,
+                    PstFormat::Json,
+                )?;
+                (
+                    wallet_manager.selected_signer()?,
+                    wallet_manager.open_partial_transaction(&pst)?,
+                )
+            };
+            signer.sign_partial_transaction(&mut pst, scheme)?;
+
+            // Spending is checked and recorded together, so requests signed
+            // at the same time can't overrun the limit between them
+            let mut wallet_manager = lock()?;
+            authorize(&wallet_manager, origin, method, &params)?;
+            let pst = wallet_manager.record_partial_transaction(&pst, PstFormat::Json)?;
+            let finalized = wallet_manager.finalize_partial_transaction(&pst)?;
+            wallet_manager.record_dapp_spending(origin, cost)?;
+            Ok(serde_json::to_value(finalized)?)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct Shared {
+    backend: Arc<dyn SignerBackend>,
+    approval_timeout: Duration,
+    /// Session tokens of connected origins
+    sessions: Mutex<HashMap<String, String>>,
+    /// Requests waiting for the user, by id
+    pending: Mutex<HashMap<u64, (SignerRequest, oneshot::Sender<Approval>)>>,
+    next_id: AtomicU64,
+}
+
+impl Shared {
+    async fn call(
+        &self,
+        origin: &str,
+        token: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, RpcError> {
+        let method: SignerMethod = serde_json::from_value(Value::String(method.to_string()))
+            .map_err(|_| RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method)))?;
+        let Some(permission) = method.permission() else {
+            return self.connect(origin, params).await;
+        };
+
+        self.authenticate(origin, token)?;
+        let backend = self.backend.clone();
+        let (authorize_origin, authorize_params) = (origin.to_string(), params.clone());
+        // The backend may wait on the wallet lock, so keep it off the runtime
+        tokio::task::spawn_blocking(move || {
+            backend.authorize(&authorize_origin, permission, &authorize_params)
+        })
+        .await
+        .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?
+        .map_err(|e| RpcError::new(UNAUTHORIZED, e.to_string()))?;
+        self.approve(origin, method, params.clone()).await?;
+        let backend = self.backend.clone();
+        let origin = origin.to_string();
+        tokio::task::spawn_blocking(move || backend.execute(&origin, permission, params))
+            .await
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?
+            .map_err(|e| match e.downcast_ref::<serde_json::Error>() {
+                Some(_) => RpcError::new(INVALID_PARAMS, e.to_string()),
+                None => RpcError::new(INTERNAL_ERROR, e.to_string()),
+            })
+    }
+
+    /// Ask the user to let `origin` call the requested methods, then store
+    /// its permission and start its session
+    async fn connect(&self, origin: &str, params: Value) -> Result<Value, RpcError> {
+        #[derive(Deserialize)]
+        struct ConnectParams {
+            methods: BTreeSet<DappMethod>,
+        }
+
+        let requested: BTreeSet<DappMethod> = match params {
+            Value::Null => DappMethod::ALL.into_iter().collect(),
+            params => {
+                serde_json::from_value::<ConnectParams>(params)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?
+                    .methods
+            }
+        };
+
+        let mut approval = self
+            .approve(
+                origin,
+                SignerMethod::Connect,
+                json!({ "methods": requested }),
+            )
+            .await?;
+        // The user can narrow the request but not widen it
+        let methods: BTreeSet<DappMethod> = match approval.methods.take() {
+            Some(granted) => granted.intersection(&requested).copied().collect(),
+            None => requested,
+        };
+        let backend = self.backend.clone();
+        let grant_origin = origin.to_string();
+        let permission =
+            tokio::task::spawn_blocking(move || backend.grant(&grant_origin, methods, approval))
+                .await
+                .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(origin.to_string(), token.clone());
+        Ok(json!({
+            "token": token,
+            "methods": permission.methods,
+            "accounts": permission.accounts,
+            "expiresAt": permission.expires_at,
+        }))
+    }
+
+    /// Check `token` is the session token of `origin`
+    fn authenticate(&self, origin: &str, token: Option<&str>) -> Result<(), RpcError> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(origin)
+            .filter(|session| token.is_some_and(|token| tokens_match(session, token)))
+            .map(|_| ())
+            .ok_or_else(|| RpcError::new(UNAUTHORIZED, "Not connected: call connect first"))
+    }
+
+    /// Show the request to the user and wait for their answer
+    async fn approve(
+        &self,
+        origin: &str,
+        method: SignerMethod,
+        params: Value,
+    ) -> Result<Approval, RpcError> {
+        let request = SignerRequest {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            origin: origin.to_string(),
+            method,
+            params,
+        };
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(request.id, (request.clone(), sender));
+        self.backend.prompt(&request);
+
+        let answer = tokio::time::timeout(self.approval_timeout, receiver).await;
+        self.pending.lock().unwrap().remove(&request.id);
+        match answer {
+            Ok(Ok(approval)) if approval.approved => Ok(approval),
+            Ok(Ok(_)) => Err(RpcError::new(REJECTED, "User rejected the request")),
+            Ok(Err(_)) => Err(RpcError::new(REJECTED, "Signer stopped")),
+            Err(_) => Err(RpcError::new(
+                REJECTED,
+                "Request timed out waiting for approval",
+            )),
+        }
+    }
+}
+
+/// Compare tokens in time that doesn't depend on where they differ
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether a `Host` header names this machine
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+fn plain_response(status: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(message.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+async fn handle(shared: Arc<Shared>, request: Request<Body>) -> Response<Body> {
+    let header = |name: header::HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    if !header(header::HOST).is_some_and(|host| is_local_host(&host)) {
+        return plain_response(
+            StatusCode::FORBIDDEN,
+            "Only localhost requests are answered",
+        );
+    }
+    // Browsers always send one; other clients have to name themselves too
+    let Some(origin) = header(header::ORIGIN) else {
+        return plain_response(StatusCode::FORBIDDEN, "Missing Origin header");
+    };
+    let Ok(origin_header) = HeaderValue::from_str(&origin) else {
+        return plain_response(StatusCode::BAD_REQUEST, "Invalid Origin header");
+    };
+
+    let mut response = match *request.method() {
+        // CORS preflight
+        Method::OPTIONS => {
+            let mut response = plain_response(StatusCode::NO_CONTENT, "");
+            let headers = response.headers_mut();
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static("POST"),
+            );
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                HeaderValue::from_static("content-type, authorization"),
+            );
+            response
+        }
+        Method::POST => {
+            let token = header(header::AUTHORIZATION)
+                .and_then(|value| value.strip_prefix("Bearer ").map(str::to_string));
+            rpc(&shared, &origin, token.as_deref(), request.into_body()).await
+        }
+        _ => plain_response(StatusCode::METHOD_NOT_ALLOWED, "Only POST is supported"),
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin_header);
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+    response
+}
+
+async fn rpc(shared: &Shared, origin: &str, token: Option<&str>, body: Body) -> Response<Body> {
+    use hyper::body::HttpBody;
+
+    if body
+        .size_hint()
+        .upper()
+        .is_none_or(|size| size > MAX_BODY_BYTES)
+    {
+        return plain_response(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large");
+    }
+    let (id, result) = match hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| RpcError::new(INVALID_REQUEST, e.to_string()))
+        .and_then(|bytes| {
+            serde_json::from_slice::<RpcRequest>(&bytes)
+                .map_err(|e| RpcError::new(INVALID_REQUEST, e.to_string()))
+        }) {
+        Ok(request) => (
+            request.id,
+            shared
+                .call(origin, token, &request.method, request.params)
+                .await,
+        ),
+        Err(error) => (Value::Null, Err(error)),
+    };
+    let body = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+    let mut response = Response::new(Body::from(body.to_string()));
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Running signer server; dropping it stops the server and rejects pending requests
+pub struct SignerServer {
+    shared: Arc<Shared>,
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl SignerServer {
+    /// Listen on 127.0.0.1:`port` (0 for any free port). Must be called
+    /// from within the async runtime.
+    pub fn start(
+        port: u16,
+        approval_timeout: Duration,
+        backend: Arc<dyn SignerBackend>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            backend,
+            approval_timeout,
+            sessions: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        });
+
+        let service_state = shared.clone();
+        let server = hyper::Server::from_tcp(listener)?.serve(make_service_fn(move |_| {
+            let shared = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let shared = shared.clone();
+                    async move { Ok::<_, Infallible>(handle(shared, request).await) }
+                }))
+            }
+        }));
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let server = server.with_graceful_shutdown(async {
+                stopped.await.ok();
+            });
+            if let Err(e) = server.await {
+                tracing::warn!("Signer server failed: {}", e);
+            }
+        });
+        tracing::info!("Signer listening on {}", address);
+
+        Ok(Self {
+            shared,
+            address,
+            shutdown: Some(shutdown),
+            task: Some(task),
+        })
+    }
+
+    /// Stop the server and wait until its port is free again
+    pub async fn stop(mut self) {
+        let task = self.task.take();
+        drop(self);
+        if let Some(task) = task {
+            task.await.ok();
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Requests waiting for the user, oldest first
+    pub fn pending(&self) -> Vec<SignerRequest> {
+        let mut pending: Vec<SignerRequest> = self
+            .shared
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(request, _)| request.clone())
+            .collect();
+        pending.sort_by_key(|request| request.id);
+        pending
+    }
+
+    /// Origins with a session
+    pub fn sessions(&self) -> BTreeSet<String> {
+        self.shared
+            .sessions
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// End the session of `origin`, which has to connect again
+    pub fn disconnect(&self, origin: &str) -> bool {
+        self.shared
+            .sessions
+            .lock()
+            .unwrap()
+            .remove(origin)
+            .is_some()
+    }
+
+    /// Answer the request `id`
+    pub fn respond(&self, id: u64, approval: Approval) -> Result<()> {
+        let (_, sender) = self
+            .shared
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| anyhow!("No signer request {} is waiting", id))?;
+        sender
+            .send(approval)
+            .map_err(|_| anyhow!("Signer request {} is no longer waiting", id))
+    }
+}
+
+impl Drop for SignerServer {
+    fn drop(&mut self) {
+        // Waiting requests see their sender dropped and are rejected
+        self.shared.pending.lock().unwrap().clear();
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    const ORIGIN: &str = "https://dapp.example";
+
+    /// Forwards prompts to the test, keeps permissions in memory and signs
+    /// nothing; a request's `cost` param stands in for a transaction's cost
+    struct TestBackend {
+        prompts: mpsc::UnboundedSender<SignerRequest>,
+        permissions: Mutex<HashMap<String, DappPermission>>,
+    }
+
+    impl SignerBackend for TestBackend {
+        fn prompt(&self, request: &SignerRequest) {
+            self.prompts.send(request.clone()).unwrap();
+        }
+
+        fn grant(
+            &self,
+            origin: &str,
+            methods: BTreeSet<DappMethod>,
+            approval: Approval,
+        ) -> Result<DappPermission> {
+            let permission = DappPermission {
+                accounts: approval.accounts.unwrap_or_default(),
+                methods,
+                spending_limit: approval.spending_limit,
+                spent: 0,
+                granted_at: Utc::now(),
+                expires_at: approval.expires_at,
+            };
+            self.permissions
+                .lock()
+                .unwrap()
+                .insert(origin.to_string(), permission.clone());
+            Ok(permission)
+        }
+
+        fn authorize(&self, origin: &str, method: DappMethod, params: &Value) -> Result<()> {
+            let permissions = self.permissions.lock().unwrap();
+            let permission = permissions
+                .get(origin)
+                .ok_or_else(|| anyhow!("{} is not connected", origin))?;
+            if permission.is_expired(Utc::now()) || !permission.methods.contains(&method) {
+                return Err(anyhow!("{} may not call {}", origin, method));
+            }
+            let cost = params["cost"].as_u64().unwrap_or(0);
+            if permission
+                .remaining()
+                .is_some_and(|remaining| cost > remaining)
+            {
+                return Err(anyhow!("{} is over its spending limit", origin));
+            }
+            Ok(())
+        }
+
+        fn execute(&self, origin: &str, method: DappMethod, params: Value) -> Result<Value> {
+            if let Some(permission) = self.permissions.lock().unwrap().get_mut(origin) {
+                permission.spent += params["cost"].as_u64().unwrap_or(0);
+            }
+            Ok(json!({ "method": method, "params": params }))
+        }
+    }
+
+    fn start(timeout: Duration) -> (SignerServer, mpsc::UnboundedReceiver<SignerRequest>) {
+        let (sender, prompts) = mpsc::unbounded_channel();
+        let backend = TestBackend {
+            prompts: sender,
+            permissions: Mutex::new(HashMap::new()),
+        };
+        let server = SignerServer::start(0, timeout, Arc::new(backend)).unwrap();
+        (server, prompts)
+    }
+
+    /// Connect `ORIGIN` with the user giving `approval`, returning the session token
+    async fn connect(
+        server: &SignerServer,
+        prompts: &mut mpsc::UnboundedReceiver<SignerRequest>,
+        approval: Approval,
+    ) -> String {
+        let (response, _) = tokio::join!(
+            call(server, ORIGIN, None, "connect", Value::Null),
+            approve_next(server, prompts, SignerMethod::Connect, approval)
+        );
+        response["result"]["token"].as_str().unwrap().to_string()
+    }
+
+    async fn call(
+        server: &SignerServer,
+        origin: &str,
+        token: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> Value {
+        let mut request = reqwest::Client::new()
+            .post(format!("http://{}", server.address()))
+            .header("Origin", origin)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.unwrap().json().await.unwrap()
+    }
+
+    /// Approve the next prompt, checking it is for `method`
+    async fn approve_next(
+        server: &SignerServer,
+        prompts: &mut mpsc::UnboundedReceiver<SignerRequest>,
+        method: SignerMethod,
+        approval: Approval,
+    ) {
+        let request = prompts.recv().await.unwrap();
+        assert_eq!(request.method, method);
+        assert_eq!(request.origin, ORIGIN);
+        assert_eq!(server.pending(), vec![request.clone()]);
+        server.respond(request.id, approval).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_handshake_and_per_origin_permissions() {
+        let (server, mut prompts) = start(Duration::from_secs(10));
+
+        // Nothing works before connecting
+        let response = call(&server, ORIGIN, Some("guess"), "accounts", Value::Null).await;
+        assert_eq!(response["error"]["code"], UNAUTHORIZED);
+
+        // The user grants accounts only, out of accounts and signMessage
+        let (response, _) = tokio::join!(
+            call(
+                &server,
+                ORIGIN,
+                None,
+                "connect",
+                json!({ "methods": ["accounts", "signMessage"] })
+            ),
+            approve_next(
+                &server,
+                &mut prompts,
+                SignerMethod::Connect,
+                Approval {
+                    approved: true,
+                    methods: Some([DappMethod::Accounts, DappMethod::SignTransaction].into()),
+                    ..Approval::default()
+                },
+            )
+        );
+        assert_eq!(response["result"]["methods"], json!(["accounts"]));
+        let token = response["result"]["token"].as_str().unwrap().to_string();
+
+        // Granted methods still need approval, and get the backend's result
+        let (response, _) = tokio::join!(
+            call(&server, ORIGIN, Some(&token), "accounts", Value::Null),
+            approve_next(
+                &server,
+                &mut prompts,
+                SignerMethod::Accounts,
+                Approval {
+                    approved: true,
+                    ..Approval::default()
+                },
+            )
+        );
+        assert_eq!(response["result"]["method"], "accounts");
+
+        // Not granted, another origin with the same token, or an unknown method
+        let response = call(&server, ORIGIN, Some(&token), "signMessage", json!({})).await;
+        assert_eq!(response["error"]["code"], UNAUTHORIZED);
+        let response = call(
+            &server,
+            "https://evil.example",
+            Some(&token),
+            "accounts",
+            Value::Null,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], UNAUTHORIZED);
+        let response = call(&server, ORIGIN, Some(&token), "exportKeys", Value::Null).await;
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        // A rejected request gets the rejection code
+        let (response, _) = tokio::join!(
+            call(&server, ORIGIN, Some(&token), "accounts", Value::Null),
+            approve_next(
+                &server,
+                &mut prompts,
+                SignerMethod::Accounts,
+                Approval::default()
+            )
+        );
+        assert_eq!(response["error"]["code"], REJECTED);
+        assert!(server.pending().is_empty());
+        assert!(prompts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spending_limit_expiry_and_disconnect() {
+        let (server, mut prompts) = start(Duration::from_secs(10));
+        let token = connect(
+            &server,
+            &mut prompts,
+            Approval {
+                approved: true,
+                spending_limit: Some(100),
+                ..Approval::default()
+            },
+        )
+        .await;
+
+        let (response, _) = tokio::join!(
+            call(
+                &server,
+                ORIGIN,
+                Some(&token),
+                "signTransaction",
+                json!({ "cost": 60 })
+            ),
+            approve_next(
+                &server,
+                &mut prompts,
+                SignerMethod::SignTransaction,
+                Approval {
+                    approved: true,
+                    ..Approval::default()
+                },
+            )
+        );
+        assert_eq!(response["result"]["method"], "signTransaction");
+
+        // Over the limit: refused without asking the user
+        let response = call(
+            &server,
+            ORIGIN,
+            Some(&token),
+            "signTransaction",
+            json!({ "cost": 60 }),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], UNAUTHORIZED);
+        assert!(prompts.try_recv().is_err());
+
+        // An ended session needs a new connect
+        assert_eq!(server.sessions(), [ORIGIN.to_string()].into());
+        assert!(server.disconnect(ORIGIN));
+        let response = call(&server, ORIGIN, Some(&token), "accounts", Value::Null).await;
+        assert_eq!(response["error"]["code"], UNAUTHORIZED);
+
+        // An expired permission allows nothing
+        let token = connect(
+            &server,
+            &mut prompts,
+            Approval {
+                approved: true,
+                expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+                ..Approval::default()
+            },
+        )
+        .await;
+        let response = call(&server, ORIGIN, Some(&token), "accounts", Value::Null).await;
+        assert_eq!(response["error"]["code"], UNAUTHORIZED);
+        assert!(prompts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_requests_time_out_and_foreign_hosts_are_refused() {
+        let (server, mut prompts) = start(Duration::from_millis(50));
+        let response = call(&server, ORIGIN, None, "connect", Value::Null).await;
+        assert_eq!(response["error"]["code"], REJECTED);
+        let request = prompts.recv().await.unwrap();
+        assert_eq!(
+            request.params,
+            json!({ "methods": ["accounts", "signMessage", "signTransaction"] })
+        );
+        assert!(server.respond(request.id, Approval::default()).is_err());
+
+        // A page on another domain resolving to 127.0.0.1 sends its own Host
+        let response = reqwest::Client::new()
+            .post(format!("http://{}", server.address()))
+            .header("Host", "attacker.example")
+            .header("Origin", "http://attacker.example")
+            .body("{}")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        // Preflight requests get CORS headers for the origin
+        let response = reqwest::Client::new()
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("http://{}", server.address()),
+            )
+            .header("Origin", ORIGIN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["access-control-allow-origin"], ORIGIN);
+    }
+
+    #[test]
+    fn test_local_hosts() {
+        assert!(is_local_host("localhost:8547"));
+        assert!(is_local_host("127.0.0.1"));
+        assert!(is_local_host("[::1]:8547"));
+        assert!(!is_local_host("localhost.example:8547"));
+        assert!(!is_local_host("192.168.1.2:8547"));
+        assert!(tokens_match("abc", "abc"));
+        assert!(!tokens_match("abc", "abd"));
+        assert!(!tokens_match("abc", "ab"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Signers that hold account keys
+//!
+//! `WalletManager` signs through the `Signer` trait, so where a key lives
+//! doesn't matter to wallet logic. `LocalSigner` uses the key encrypted in the
+//! wallet file; `ExternalSigner` asks a separate process, such as a hardware
+//! wallet bridge, over a Unix socket.
+//!
+//! The socket protocol is a stream of frames, each a 4-byte big-endian length
+//! followed by that many bytes of JSON. The wallet connects, sends one
+//! `DeviceRequest` and reads one `DeviceResponse`. Signatures travel as 64
+//! bytes: compact for ECDSA, BIP340 for Schnorr. `crate::emulator` implements
+//! the device side in software.
+
+use crate::crypto::CryptoManager;
+use crate::types::{Account, SignatureScheme};
+use anyhow::{Result, anyhow};
+use secp256k1::{Keypair, Message, PublicKey, Secp256k1, SecretKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Prefix of the hash signed for a text message
+const MESSAGE_PREFIX: &[u8] = b"\x19Mazzaroth Signed Message:\n";
+
+/// Largest frame either side accepts
+pub const MAX_FRAME_BYTES: usize = 64 * 1024;
+
+/// How long a device may take to answer, which includes the user confirming on it
+pub const DEVICE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Hash signed for a text message. It covers a prefix and the message length,
+/// so no message signs as a transaction or typed data.
+pub fn message_hash(message: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(MESSAGE_PREFIX);
+    hasher.update(message.len().to_string());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Holder of one account key. Signers are sent to a blocking thread to sign,
+/// since a device can take until the user confirms on it.
+pub trait Signer: Send {
+    fn public_key(&self) -> Result<PublicKey>;
+
+    /// Sign a 32-byte hash, returning the 64-byte signature
+    fn sign_hash(&self, hash: &[u8; 32], scheme: SignatureScheme) -> Result<[u8; 64]>;
+
+    /// Sign a text message. Devices override this to show the text before signing.
+    fn sign_message(&self, message: &str, scheme: SignatureScheme) -> Result<[u8; 64]> {
+        self.sign_hash(&message_hash(message), scheme)
+    }
+}
+
+/// Check `signature` is `public_key`'s over `hash`
+pub fn verify_signature(
+    public_key: &PublicKey,
+    hash: &[u8; 32],
+    signature: &[u8; 64],
+    scheme: SignatureScheme,
+) -> bool {
+    let secp = Secp256k1::verification_only();
+    let message = Message::from_digest(*hash);
+    match scheme {
+        SignatureScheme::Ecdsa => secp256k1::ecdsa::Signature::from_compact(signature)
+            .is_ok_and(|signature| secp.verify_ecdsa(&message, &signature, public_key).is_ok()),
+        SignatureScheme::Schnorr => {
+            secp256k1::schnorr::Signature::from_slice(signature).is_ok_and(|signature| {
+                secp.verify_schnorr(&signature, &message, &public_key.x_only_public_key().0)
+                    .is_ok()
+            })
+        }
+    }
+}
+
+/// Signature as the signing commands return it: DER for ECDSA, 64 bytes for Schnorr
+pub fn encode_signature(signature: &[u8; 64], scheme: SignatureScheme) -> Result<Vec<u8>> {
+    Ok(match scheme {
+        SignatureScheme::Ecdsa => secp256k1::ecdsa::Signature::from_compact(signature)?
+            .serialize_der()
+            .to_vec(),
+        SignatureScheme::Schnorr => signature.to_vec(),
+    })
+}
+
+/// Key kept in memory, such as one decrypted from the wallet file
+pub struct LocalSigner {
+    private_key: SecretKey,
+}
+
+impl LocalSigner {
+    pub fn new(private_key: SecretKey) -> Self {
+        Self { private_key }
+    }
+
+    /// Decrypt the key of `account` with `password`
+    pub fn unlock(account: &Account, password: &str) -> Result<Self> {
+        if account.external.is_some() {
+            return Err(anyhow!(
+                "The key of {} is held by an external signer",
+                account.address
+            ));
+        }
+        let private_key_bytes = CryptoManager::decrypt_data(
+            &account.private_key_encrypted,
+            password,
+            &account.salt,
+            account.kdf_iterations,
+        )?;
+        Ok(Self::new(SecretKey::from_slice(&private_key_bytes)?))
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> Result<PublicKey> {
+        Ok(CryptoManager::get_public_key(&self.private_key))
+    }
+
+    fn sign_hash(&self, hash: &[u8; 32], scheme: SignatureScheme) -> Result<[u8; 64]> {
+        let secp = Secp256k1::new();
+        let message = Message::from_digest(*hash);
+        Ok(match scheme {
+            SignatureScheme::Ecdsa => secp
+                .sign_ecdsa(&message, &self.private_key)
+                .serialize_compact(),
+            SignatureScheme::Schnorr => {
+                let keypair = Keypair::from_secret_key(&secp, &self.private_key);
+                *secp
+                    .sign_schnorr_with_rng(&message, &keypair, &mut rand::thread_rng())
+                    .as_ref()
+            }
+        })
+    }
+}
+
+/// Where an account's key lives when it isn't in the wallet file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalAccount {
+    /// Unix socket the signer listens on
+    pub socket: PathBuf,
+    /// Which of the signer's keys the account uses
+    pub key_index: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeviceRequest {
+    GetPublicKey {
+        key_index: u32,
+    },
+    SignHash {
+        key_index: u32,
+        /// Hex
+        hash: String,
+        scheme: SignatureScheme,
+    },
+    SignMessage {
+        key_index: u32,
+        message: String,
+        scheme: SignatureScheme,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeviceResponse {
+    /// Hex of the compressed key
+    PublicKey { public_key: String },
+    /// Hex of the 64-byte signature
+    Signature { signature: String },
+    /// The device refused, for example because the user rejected the request
+    Error { message: String },
+}
+
+pub fn write_frame(writer: &mut impl Write, value: &impl Serialize) -> Result<()> {
+    let body = serde_json::to_vec(value)?;
+    if body.len() > MAX_FRAME_BYTES {
+        return Err(anyhow!("Frame of {} bytes is too large", body.len()));
+    }
+    writer.write_all(&(body.len() as u32).to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read one frame, or `None` if the other side closed the connection before it
+pub fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_BYTES {
+        return Err(anyhow!("Frame of {} bytes is too large", length));
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Key held by another process, reached over a Unix socket
+#[cfg(unix)]
+pub struct ExternalSigner {
+    account: ExternalAccount,
+    public_key: PublicKey,
+}
+
+#[cfg(unix)]
+impl ExternalSigner {
+    /// Signer of `account` whose key is already known, without reaching it.
+    /// Signatures are checked against `public_key`, so a device holding
+    /// another key is caught when it signs.
+    pub fn new(account: ExternalAccount, public_key: PublicKey) -> Self {
+        Self {
+            account,
+            public_key,
+        }
+    }
+
+    /// Reach the signer of `account` and fetch its public key
+    pub fn connect(account: &ExternalAccount) -> Result<Self> {
+        let response = Self::request(
+            account,
+            &DeviceRequest::GetPublicKey {
+                key_index: account.key_index,
+            },
+        )?;
+        let DeviceResponse::PublicKey { public_key } = response else {
+            return Err(anyhow!("Unexpected answer from the external signer"));
+        };
+        Ok(Self::new(
+            account.clone(),
+            PublicKey::from_slice(&hex::decode(public_key)?)?,
+        ))
+    }
+
+    pub fn account(&self) -> &ExternalAccount {
+        &self.account
+    }
+
+    fn request(account: &ExternalAccount, request: &DeviceRequest) -> Result<DeviceResponse> {
+        let mut stream = std::os::unix::net::UnixStream::connect(&account.socket).map_err(|e| {
+            anyhow!(
+                "Could not reach the external signer at {}: {}",
+                account.socket.display(),
+                e
+            )
+        })?;
+        stream.set_read_timeout(Some(DEVICE_TIMEOUT))?;
+        stream.set_write_timeout(Some(DEVICE_TIMEOUT))?;
+        write_frame(&mut stream, request)?;
+        match read_frame(&mut stream)? {
+            Some(DeviceResponse::Error { message }) => {
+                Err(anyhow!("External signer refused: {}", message))
+            }
+            Some(response) => Ok(response),
+            None => Err(anyhow!("External signer closed the connection")),
+        }
+    }
+
+    /// Send a signing request, checking the signature that comes back is
+    /// the device key's over `hash`
+    fn sign(
+        &self,
+        request: DeviceRequest,
+        hash: &[u8; 32],
+        scheme: SignatureScheme,
+    ) -> Result<[u8; 64]> {
+        let DeviceResponse::Signature { signature } = Self::request(&self.account, &request)?
+        else {
+            return Err(anyhow!("Unexpected answer from the external signer"));
+        };
+        let signature: [u8; 64] = hex::decode(signature)?
+            .try_into()
+            .map_err(|_| anyhow!("External signer returned a malformed signature"))?;
+        if !verify_signature(&self.public_key, hash, &signature, scheme) {
+            return Err(anyhow!(
+                "External signer did not sign with the key of the account"
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(unix)]
+impl Signer for ExternalSigner {
+    fn public_key(&self) -> Result<PublicKey> {
+        Ok(self.public_key)
+    }
+
+    fn sign_hash(&self, hash: &[u8; 32], scheme: SignatureScheme) -> Result<[u8; 64]> {
+        let request = DeviceRequest::SignHash {
+            key_index: self.account.key_index,
+            hash: hex::encode(hash),
+            scheme,
+        };
+        self.sign(request, hash, scheme)
+    }
+
+    fn sign_message(&self, message: &str, scheme: SignatureScheme) -> Result<[u8; 64]> {
+        let request = DeviceRequest::SignMessage {
+            key_index: self.account.key_index,
+            message: message.to_string(),
+            scheme,
+        };
+        self.sign(request, &message_hash(message), scheme)
+    }
+}
@@ -0,0 +1,271 @@
+//! Wallet storage in a sled database
+//!
+//! Every wallet is a tree named `wallet:<name>` holding:
+//!
+//! - `wallet`: the wallet without its accounts (selection, multisig accounts, dates)
+//! - `count`: the number of accounts
+//! - `account:<index>`: each account, keyed by its big-endian `u32` index
+//! - `address:<address>`: the index of the first account with that address
+//!
+//! Writes rewrite these keys in a single transaction, so a wallet is never left
+//! half updated, and accounts can be found by address without loading the rest.
+
+use crate::storage::{WalletName, WalletStore, create_private_dir};
+use crate::types::{Account, WalletData};
+use anyhow::{Result, anyhow};
+use sled::Transactional;
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionalTree,
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Directory of the sled database inside the data directory
+pub const SLED_DB_DIR: &str = "wallets.db";
+
+const WALLET_TREE_PREFIX: &str = "wallet:";
+const WALLET_KEY: &[u8] = b"wallet";
+const COUNT_KEY: &[u8] = b"count";
+const ACCOUNT_PREFIX: &[u8] = b"account:";
+const ADDRESS_PREFIX: &[u8] = b"address:";
+
+type TxResult<T> = ConflictableTransactionResult<T, anyhow::Error>;
+
+/// Wallets kept in a sled database in the data directory. sled locks the
+/// database while it is open, so the CLI can't use it at the same time.
+pub struct SledWalletStore {
+    data_dir: PathBuf,
+    db: sled::Db,
+}
+
+fn account_key(index: usize) -> Vec<u8> {
+    [ACCOUNT_PREFIX, &(index as u32).to_be_bytes()].concat()
+}
+
+fn address_key(address: &str) -> Vec<u8> {
+    [ADDRESS_PREFIX, address.as_bytes()].concat()
+}
+
+fn abort(error: impl Into<anyhow::Error>) -> ConflictableTransactionError<anyhow::Error> {
+    ConflictableTransactionError::Abort(error.into())
+}
+
+fn decode_index(bytes: &[u8]) -> TxResult<usize> {
+    let bytes: [u8; 4] = bytes
+        .try_into()
+        .map_err(|_| abort(anyhow!("Corrupted wallet database: bad index")))?;
+    Ok(u32::from_be_bytes(bytes) as usize)
+}
+
+fn read_account(tx: &TransactionalTree, index: usize) -> TxResult<Account> {
+    let account = tx.get(account_key(index))?.ok_or_else(|| {
+        abort(anyhow!(
+            "Corrupted wallet database: account {} is missing",
+            index
+        ))
+    })?;
+    serde_json::from_slice(&account).map_err(abort)
+}
+
+fn read_count(tx: &TransactionalTree) -> TxResult<usize> {
+    match tx.get(COUNT_KEY)? {
+        Some(count) => decode_index(&count),
+        None => Ok(0),
+    }
+}
+
+fn read_wallet(tx: &TransactionalTree) -> TxResult<Option<WalletData>> {
+    let Some(wallet_data) = tx.get(WALLET_KEY)? else {
+        return Ok(None);
+    };
+    let mut wallet_data: WalletData = serde_json::from_slice(&wallet_data).map_err(abort)?;
+    for index in 0..read_count(tx)? {
+        wallet_data.accounts.push(read_account(tx, index)?);
+    }
+    Ok(Some(wallet_data))
+}
+
+fn clear_wallet(tx: &TransactionalTree) -> TxResult<()> {
+    for index in 0..read_count(tx)? {
+        let old = read_account(tx, index)?;
+        tx.remove(address_key(&old.address))?;
+        tx.remove(account_key(index))?;
+    }
+    tx.remove(COUNT_KEY)?;
+    tx.remove(WALLET_KEY)?;
+    Ok(())
+}
+
+fn write_wallet(tx: &TransactionalTree, wallet_data: &WalletData) -> TxResult<()> {
+    clear_wallet(tx)?;
+
+    let mut addresses = HashSet::new();
+    for (index, account) in wallet_data.accounts.iter().enumerate() {
+        tx.insert(
+            account_key(index),
+            serde_json::to_vec(account).map_err(abort)?,
+        )?;
+        if addresses.insert(account.address.as_str()) {
+            tx.insert(address_key(&account.address), &(index as u32).to_be_bytes())?;
+        }
+    }
+    tx.insert(
+        COUNT_KEY,
+        &(wallet_data.accounts.len() as u32).to_be_bytes(),
+    )?;
+
+    let without_accounts = WalletData {
+        accounts: Vec::new(),
+        ..wallet_data.clone()
+    };
+    tx.insert(
+        WALLET_KEY,
+        serde_json::to_vec(&without_accounts).map_err(abort)?,
+    )?;
+    Ok(())
+}
+
+fn transaction_error(error: TransactionError<anyhow::Error>) -> anyhow::Error {
+    match error {
+        TransactionError::Abort(error) => error,
+        TransactionError::Storage(error) => error.into(),
+    }
+}
+
+impl SledWalletStore {
+    /// Open the database in `data_dir`, creating it if needed
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(SLED_DB_DIR);
+        // sled creates its files inside this directory, so creating it private keeps them private
+        create_private_dir(&path)?;
+        let db = sled::open(&path)
+            .map_err(|e| anyhow!("Failed to open wallet database {}: {}", path.display(), e))?;
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            db,
+        })
+    }
+
+    fn tree(&self, wallet_name: &WalletName) -> Result<sled::Tree> {
+        Ok(self
+            .db
+            .open_tree(format!("{}{}", WALLET_TREE_PREFIX, wallet_name))?)
+    }
+
+    fn transaction<T>(
+        &self,
+        wallet_name: &WalletName,
+        f: impl Fn(&TransactionalTree) -> TxResult<T>,
+    ) -> Result<T> {
+        self.tree(wallet_name)?
+            .transaction(f)
+            .map_err(transaction_error)
+    }
+}
+
+impl WalletStore for SledWalletStore {
+    fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    fn save_wallet(&self, wallet_name: &WalletName, wallet_data: &WalletData) -> Result<()> {
+        self.transaction(wallet_name, |tx| write_wallet(tx, wallet_data))?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn load_wallet(&self, wallet_name: &WalletName) -> Result<Option<WalletData>> {
+        self.transaction(wallet_name, read_wallet)
+    }
+
+    fn wallet_exists(&self, wallet_name: &WalletName) -> bool {
+        self.tree(wallet_name)
+            .and_then(|tree| Ok(tree.contains_key(WALLET_KEY)?))
+            .unwrap_or(false)
+    }
+
+    fn list_wallets(&self) -> Result<Vec<String>> {
+        let mut wallets = Vec::new();
+        for tree_name in self.db.tree_names() {
+            let Some(name) = std::str::from_utf8(&tree_name)
+                .ok()
+                .and_then(|name| name.strip_prefix(WALLET_TREE_PREFIX))
+            else {
+                continue;
+            };
+            // Opening a tree creates it, so empty trees are not wallets
+            if !self.db.open_tree(&tree_name)?.is_empty() {
+                wallets.push(name.to_string());
+            }
+        }
+        wallets.sort();
+        Ok(wallets)
+    }
+
+    fn delete_wallet(&self, wallet_name: &WalletName) -> Result<()> {
+        self.transaction(wallet_name, clear_wallet)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn create_wallet(&self, wallet_name: &WalletName, wallet_data: &WalletData) -> Result<()> {
+        self.transaction(wallet_name, |tx| {
+            if tx.get(WALLET_KEY)?.is_some() {
+                return Err(abort(anyhow!("Wallet already exists: {}", wallet_name)));
+            }
+            write_wallet(tx, wallet_data)
+        })?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn update_wallet(
+        &self,
+        wallet_name: &WalletName,
+        change: &dyn Fn(&mut WalletData) -> Result<()>,
+    ) -> Result<WalletData> {
+        let wallet_data = self.transaction(wallet_name, |tx| {
+            let mut wallet_data = read_wallet(tx)?
+                .ok_or_else(|| abort(anyhow!("Wallet not found: {}", wallet_name)))?;
+            change(&mut wallet_data).map_err(ConflictableTransactionError::Abort)?;
+            write_wallet(tx, &wallet_data)?;
+            Ok(wallet_data)
+        })?;
+        self.db.flush()?;
+        Ok(wallet_data)
+    }
+
+    fn find_account(
+        &self,
+        wallet_name: &WalletName,
+        address: &str,
+    ) -> Result<Option<(usize, Account)>> {
+        self.transaction(wallet_name, |tx| match tx.get(address_key(address))? {
+            Some(index) => {
+                let index = decode_index(&index)?;
+                Ok(Some((index, read_account(tx, index)?)))
+            }
+            None => Ok(None),
+        })
+    }
+
+    /// Move the wallet to its new tree in one transaction over both trees
+    fn rename_wallet(&self, old_name: &WalletName, new_name: &WalletName) -> Result<()> {
+        let old_tree = self.tree(old_name)?;
+        let new_tree = self.tree(new_name)?;
+        (&old_tree, &new_tree)
+            .transaction(|(old_tx, new_tx)| {
+                let wallet_data = read_wallet(old_tx)?
+                    .ok_or_else(|| abort(anyhow!("Wallet not found: {}", old_name)))?;
+                if new_tx.get(WALLET_KEY)?.is_some() {
+                    return Err(abort(anyhow!("Wallet already exists: {}", new_name)));
+                }
+                write_wallet(new_tx, &wallet_data)?;
+                clear_wallet(old_tx)
+            })
+            .map_err(transaction_error)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
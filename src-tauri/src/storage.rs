@@ -1,72 +1,412 @@
-use crate::types::WalletData;
+use crate::config::StorageBackend;
+use crate::sled_store::SledWalletStore;
+use crate::types::{Account, WalletData};
 use anyhow::Result;
-use serde_json;
-use std::fs;
-use std::path::Path;
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-pub struct StorageManager {
-    data_dir: String,
+/// Maximum length of a wallet name, in characters
+pub const MAX_WALLET_NAME_LENGTH: usize = 64;
+
+/// Names that would collide with other files in the data directory:
+/// the address book and the transaction history
+const RESERVED_WALLET_NAMES: &[&str] = &["address_book", "history"];
+
+/// Device names Windows reserves in every directory, with or without an extension
+const WINDOWS_DEVICE_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// A wallet name that is safe to use as a file name inside the data directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WalletName(String);
+
+impl WalletName {
+    /// Validate a wallet name: no path separators, control characters,
+    /// leading dots, trailing dots or spaces, or reserved names
+    pub fn new(name: &str) -> Result<Self> {
+        let invalid = |reason: &str| anyhow::anyhow!("Invalid wallet name {:?}: {}", name, reason);
+
+        if name.is_empty() {
+            return Err(invalid("name is empty"));
+        }
+        if name.chars().count() > MAX_WALLET_NAME_LENGTH {
+            return Err(invalid("name is too long"));
+        }
+        if name.chars().any(|c| {
+            c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+        }) {
+            return Err(invalid(
+                "name contains a path separator or reserved character",
+            ));
+        }
+        if name.starts_with('.') || name.ends_with(['.', ' ']) || name != name.trim() {
+            return Err(invalid(
+                "name starts with a dot or has surrounding whitespace",
+            ));
+        }
+
+        let stem = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+        if RESERVED_WALLET_NAMES.contains(&name.to_ascii_lowercase().as_str())
+            || WINDOWS_DEVICE_NAMES.contains(&stem.as_str())
+        {
+            return Err(invalid("name is reserved"));
+        }
+
+        Ok(WalletName(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
-impl StorageManager {
-    pub fn new() -> Self {
-        let data_dir = Self::get_data_dir();
-        Self { data_dir }
+impl fmt::Display for WalletName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Folder inside the data directory that deleted wallets are moved to
+const TRASH_DIR: &str = "trash";
+
+/// Create `dir` and any missing parents, accessible only by the owner (0700 on Unix).
+/// Directories that already exist keep their permissions, with a warning when
+/// other users can access `dir` itself.
+pub fn create_private_dir(dir: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A new directory is 0700 at most, so only an existing one can be looser
+        let mode = fs::metadata(dir)?.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            tracing::warn!(
+                "{} has permissions {:03o} and is accessible by other users; run chmod 700 on it",
+                dir.display(),
+                mode
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Write `contents` to a file readable only by the owner (0600 on Unix),
+/// tightening the permissions of an existing file before writing
+pub fn write_private_file(path: &Path, contents: &[u8]) -> Result<()> {
+    write_private(path, contents, false)
+}
+
+/// Like `write_private_file`, but fail with `AlreadyExists` rather than
+/// replace a file at `path`
+pub fn create_private_file(path: &Path, contents: &[u8]) -> Result<()> {
+    write_private(path, contents, true)
+}
+
+fn write_private(path: &Path, contents: &[u8], create_new: bool) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if create_new {
+        options.create_new(true);
+    } else {
+        options.create(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents)?;
+    Ok(())
+}
+
+/// Check that a wallet file is private to the current user, the way SSH treats
+/// private keys: files owned by another user or writable by group or others are
+/// refused, and files readable by group or others load with a warning
+pub fn check_private_file(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let metadata = fs::metadata(path)?;
+        // geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        if metadata.uid() != uid {
+            return Err(anyhow::anyhow!(
+                "Refusing to load {}: owned by uid {}, not the current user",
+                path.display(),
+                metadata.uid()
+            ));
+        }
+
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o022 != 0 {
+            return Err(anyhow::anyhow!(
+                "Refusing to load {}: permissions {:03o} allow others to modify it; run chmod 600 on it",
+                path.display(),
+                mode
+            ));
+        }
+        if mode & 0o044 != 0 {
+            tracing::warn!(
+                "{} has permissions {:03o} and is readable by other users; run chmod 600 on it",
+                path.display(),
+                mode
+            );
+        }
     }
+    Ok(())
+}
+
+/// Where wallets are kept
+///
+/// `StorageManager` keeps each wallet as a JSON file and `SledWalletStore` as a
+/// tree in a sled database. Either way the wallets, the address book and the
+/// trash folder live in the data directory.
+pub trait WalletStore: Send {
+    /// Directory holding the wallets and the address book
+    fn data_dir(&self) -> &Path;
+
+    fn save_wallet(&self, wallet_name: &WalletName, wallet_data: &WalletData) -> Result<()>;
+
+    fn load_wallet(&self, wallet_name: &WalletName) -> Result<Option<WalletData>>;
 
-    fn get_data_dir() -> String {
-        // Use Tauri's app data directory
-        let app_name = "rwmth";
-        let home_dir = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-        let data_dir = home_dir.join(".config").join(app_name);
+    fn wallet_exists(&self, wallet_name: &WalletName) -> bool;
+
+    fn list_wallets(&self) -> Result<Vec<String>>;
+
+    fn delete_wallet(&self, wallet_name: &WalletName) -> Result<()>;
+
+    /// Save a new wallet, refusing to replace one with the same name. The
+    /// check and the write can't be split by another writer.
+    fn create_wallet(&self, wallet_name: &WalletName, wallet_data: &WalletData) -> Result<()>;
+
+    /// Load a wallet, apply `change` and save the result. Nothing is saved
+    /// when `change` fails. `change` may run more than once if the store retries.
+    fn update_wallet(
+        &self,
+        wallet_name: &WalletName,
+        change: &dyn Fn(&mut WalletData) -> Result<()>,
+    ) -> Result<WalletData> {
+        let mut wallet_data = self
+            .load_wallet(wallet_name)?
+            .ok_or_else(|| anyhow::anyhow!("Wallet not found: {}", wallet_name))?;
+        change(&mut wallet_data)?;
+        self.save_wallet(wallet_name, &wallet_data)?;
+        Ok(wallet_data)
+    }
+
+    /// Index and account with `address` in a wallet
+    fn find_account(
+        &self,
+        wallet_name: &WalletName,
+        address: &str,
+    ) -> Result<Option<(usize, Account)>> {
+        Ok(self.load_wallet(wallet_name)?.and_then(|wallet_data| {
+            wallet_data
+                .accounts
+                .into_iter()
+                .enumerate()
+                .find(|(_, account)| account.address == address)
+        }))
+    }
+
+    /// A wallet as the JSON document the file backend stores
+    fn export_wallet(&self, wallet_name: &WalletName) -> Result<Option<Value>> {
+        self.load_wallet(wallet_name)?
+            .map(|wallet_data| Ok(serde_json::to_value(wallet_data)?))
+            .transpose()
+    }
+
+    /// Replace a wallet with a JSON document from `export_wallet`
+    fn import_wallet(&self, wallet_name: &WalletName, wallet: &Value) -> Result<()> {
+        let wallet_data: WalletData = serde_json::from_value(wallet.clone())?;
+        self.save_wallet(wallet_name, &wallet_data)
+    }
+
+    /// Move a wallet into the `trash` folder as a JSON file instead of
+    /// deleting it, returning where it ended up
+    fn trash_wallet(&self, wallet_name: &WalletName) -> Result<PathBuf> {
+        let wallet = self
+            .export_wallet(wallet_name)?
+            .ok_or_else(|| anyhow::anyhow!("Wallet not found: {}", wallet_name))?;
+
+        let trash_path = trash_path(self.data_dir(), wallet_name)?;
+        create_private_file(
+            &trash_path,
+            serde_json::to_string_pretty(&wallet)?.as_bytes(),
+        )?;
+        self.delete_wallet(wallet_name)?;
+        Ok(trash_path)
+    }
+
+    /// Rename a wallet, refusing to replace an existing wallet
+    fn rename_wallet(&self, old_name: &WalletName, new_name: &WalletName) -> Result<()> {
+        let wallet_data = self
+            .load_wallet(old_name)?
+            .ok_or_else(|| anyhow::anyhow!("Wallet not found: {}", old_name))?;
+        if self.wallet_exists(new_name) {
+            return Err(anyhow::anyhow!("Wallet already exists: {}", new_name));
+        }
+
+        self.save_wallet(new_name, &wallet_data)?;
+        self.delete_wallet(old_name)
+    }
+
+    fn address_book_path(&self) -> PathBuf {
+        self.data_dir().join(crate::address_book::ADDRESS_BOOK_FILE)
+    }
+}
+
+/// Open the wallets in `data_dir` with the configured backend
+pub fn open_store(backend: StorageBackend, data_dir: PathBuf) -> Result<Box<dyn WalletStore>> {
+    Ok(match backend {
+        StorageBackend::Json => Box::new(StorageManager::new(data_dir)),
+        StorageBackend::Sled => Box::new(SledWalletStore::open(&data_dir)?),
+    })
+}
+
+/// What `migrate_wallets` did with each wallet
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    /// Wallets copied to the target store
+    pub migrated: Vec<String>,
+    /// Wallets left alone because the target already has a wallet with the same name
+    pub skipped: Vec<String>,
+}
+
+/// Copy every wallet from `source` to `target`. Wallets already in `target`
+/// are skipped, and nothing is removed from `source`.
+pub fn migrate_wallets(
+    source: &dyn WalletStore,
+    target: &dyn WalletStore,
+) -> Result<MigrationReport> {
+    // Load everything first so an unreadable wallet stops the migration before any write
+    let mut wallets = Vec::new();
+    for name in source.list_wallets()? {
+        let name = WalletName::new(&name)?;
+        let wallet_data = source
+            .load_wallet(&name)?
+            .ok_or_else(|| anyhow::anyhow!("Wallet not found: {}", name))?;
+        wallets.push((name, wallet_data));
+    }
 
+    let mut report = MigrationReport::default();
+    for (name, wallet_data) in wallets {
+        if target.wallet_exists(&name) {
+            report.skipped.push(name.to_string());
+        } else {
+            target.save_wallet(&name, &wallet_data)?;
+            report.migrated.push(name.to_string());
+        }
+    }
+    Ok(report)
+}
+
+/// `<data_dir>/trash/<name>-<timestamp>.json`, creating the trash folder
+fn trash_path(data_dir: &Path, wallet_name: &WalletName) -> Result<PathBuf> {
+    let trash_dir = data_dir.join(TRASH_DIR);
+    create_private_dir(&trash_dir)?;
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+    Ok(trash_dir.join(format!("{}-{}.json", wallet_name, timestamp)))
+}
+
+/// Move `from` to `to` without replacing anything at `to`. Unlike a rename,
+/// linking fails if `to` exists, so a file that appears there meanwhile is
+/// never overwritten.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::hard_link(from, to)?;
+    fs::remove_file(from)
+}
+
+pub struct StorageManager {
+    data_dir: String,
+}
+
+impl StorageManager {
+    pub fn new(data_dir: PathBuf) -> Self {
         // Create directory if it doesn't exist
         if !data_dir.exists() {
-            fs::create_dir_all(&data_dir).ok();
+            create_private_dir(&data_dir).ok();
         }
 
-        data_dir.to_string_lossy().to_string()
+        Self {
+            data_dir: data_dir.to_string_lossy().to_string(),
+        }
     }
 
-    pub fn save_wallet(&self, wallet_name: &str, wallet_data: &WalletData) -> Result<()> {
+    pub fn get_wallet_path(&self, wallet_name: &WalletName) -> String {
+        Path::new(&self.data_dir)
+            .join(format!("{}.json", wallet_name))
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+impl WalletStore for StorageManager {
+    fn data_dir(&self) -> &Path {
+        Path::new(&self.data_dir)
+    }
+
+    fn save_wallet(&self, wallet_name: &WalletName, wallet_data: &WalletData) -> Result<()> {
         let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
         let json = serde_json::to_string_pretty(wallet_data)?;
-        fs::write(file_path, json)?;
+        write_private_file(&file_path, json.as_bytes())?;
         Ok(())
     }
 
-    pub fn load_wallet(&self, wallet_name: &str) -> Result<Option<WalletData>> {
+    fn load_wallet(&self, wallet_name: &WalletName) -> Result<Option<WalletData>> {
         let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
 
         if !file_path.exists() {
             return Ok(None);
         }
 
+        check_private_file(&file_path)?;
         let content = fs::read_to_string(file_path)?;
         let wallet_data: WalletData = serde_json::from_str(&content)?;
         Ok(Some(wallet_data))
     }
 
-    pub fn wallet_exists(&self, wallet_name: &str) -> bool {
+    fn wallet_exists(&self, wallet_name: &WalletName) -> bool {
         let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
         file_path.exists()
     }
 
-    pub fn list_wallets(&self) -> Result<Vec<String>> {
+    fn list_wallets(&self) -> Result<Vec<String>> {
         let mut wallets = Vec::new();
 
         if let Ok(entries) = fs::read_dir(&self.data_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    if let Some(extension) = entry.path().extension() {
-                        if extension == "json" {
-                            if let Some(file_stem) = entry.path().file_stem() {
-                                if let Some(name) = file_stem.to_str() {
-                                    wallets.push(name.to_string());
-                                }
-                            }
-                        }
-                    }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                // Files that couldn't have been created as wallets, like the address book and history, are skipped
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                    && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+                    && WalletName::new(name).is_ok()
+                {
+                    wallets.push(name.to_string());
                 }
             }
         }
@@ -74,7 +414,7 @@ impl StorageManager {
         Ok(wallets)
     }
 
-    pub fn delete_wallet(&self, wallet_name: &str) -> Result<()> {
+    fn delete_wallet(&self, wallet_name: &WalletName) -> Result<()> {
         let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
         if file_path.exists() {
             fs::remove_file(file_path)?;
@@ -82,10 +422,215 @@ impl StorageManager {
         Ok(())
     }
 
-    pub fn get_wallet_path(&self, wallet_name: &str) -> String {
-        Path::new(&self.data_dir)
-            .join(format!("{}.json", wallet_name))
-            .to_string_lossy()
-            .to_string()
+    fn create_wallet(&self, wallet_name: &WalletName, wallet_data: &WalletData) -> Result<()> {
+        let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
+        let json = serde_json::to_string_pretty(wallet_data)?;
+        create_private_file(&file_path, json.as_bytes()).map_err(|e| {
+            match e.downcast_ref::<io::Error>().map(io::Error::kind) {
+                Some(io::ErrorKind::AlreadyExists) => {
+                    anyhow::anyhow!("Wallet already exists: {}", wallet_name)
+                }
+                _ => e,
+            }
+        })
+    }
+
+    /// The file as stored, so wallets written by the CLI round-trip
+    fn export_wallet(&self, wallet_name: &WalletName) -> Result<Option<Value>> {
+        let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        check_private_file(&file_path)?;
+        Ok(Some(serde_json::from_str(&fs::read_to_string(file_path)?)?))
+    }
+
+    fn import_wallet(&self, wallet_name: &WalletName, wallet: &Value) -> Result<()> {
+        let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
+        write_private_file(&file_path, serde_json::to_string_pretty(wallet)?.as_bytes())
+    }
+
+    fn trash_wallet(&self, wallet_name: &WalletName) -> Result<PathBuf> {
+        let file_path = Path::new(&self.data_dir).join(format!("{}.json", wallet_name));
+        let trash_path = trash_path(Path::new(&self.data_dir), wallet_name)?;
+        move_file(&file_path, &trash_path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => anyhow::anyhow!("Wallet not found: {}", wallet_name),
+            _ => e.into(),
+        })?;
+        Ok(trash_path)
+    }
+
+    fn rename_wallet(&self, old_name: &WalletName, new_name: &WalletName) -> Result<()> {
+        let data_dir = Path::new(&self.data_dir);
+        move_file(
+            &data_dir.join(format!("{}.json", old_name)),
+            &data_dir.join(format!("{}.json", new_name)),
+        )
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => anyhow::anyhow!("Wallet not found: {}", old_name),
+            io::ErrorKind::AlreadyExists => anyhow::anyhow!("Wallet already exists: {}", new_name),
+            _ => e.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_name_validation() {
+        for name in [
+            "default",
+            "work-2024",
+            "My Wallet",
+            "cold_storage.v2",
+            "钱包",
+        ] {
+            assert_eq!(WalletName::new(name).unwrap().as_str(), name);
+        }
+
+        let long_name = "a".repeat(MAX_WALLET_NAME_LENGTH + 1);
+        for name in [
+            "",
+            ".",
+            "..",
+            "../../.ssh/foo",
+            "..\\..\\windows",
+            "/etc/passwd",
+            "a/b",
+            "C:evil",
+            ".hidden",
+            "trailing.",
+            "trailing ",
+            " leading",
+            "new\nline",
+            "nul\0byte",
+            "esc\u{1b}[31m",
+            "what?",
+            "a|b",
+            "CON",
+            "nul.txt",
+            "Lpt1",
+            "address_book",
+            "History",
+            long_name.as_str(),
+        ] {
+            assert!(WalletName::new(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_wallet_files_stay_private_and_in_data_dir() {
+        let data_dir = std::env::temp_dir().join(format!("rwmth-storage-{}", std::process::id()));
+        let storage = StorageManager::new(data_dir.join("wallets"));
+        let name = WalletName::new("work").unwrap();
+        storage.save_wallet(&name, &WalletData::new()).unwrap();
+        assert!(Path::new(&storage.get_wallet_path(&name)).starts_with(data_dir.join("wallets")));
+
+        fs::write(data_dir.join("wallets").join(".hidden.json"), "{}").unwrap();
+        fs::write(data_dir.join("wallets").join("address_book.json"), "{}").unwrap();
+        fs::write(data_dir.join("wallets").join("history.json"), "{}").unwrap();
+        assert_eq!(storage.list_wallets().unwrap(), vec!["work"]);
+
+        assert!(WalletName::new("../work").is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let wallet_path = PathBuf::from(storage.get_wallet_path(&name));
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&data_dir.join("wallets")), 0o700);
+            assert_eq!(mode(&wallet_path), 0o600);
+
+            // Readable by others only warns; writable by others is refused
+            fs::set_permissions(&wallet_path, fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(storage.load_wallet(&name).unwrap().is_some());
+            fs::set_permissions(&wallet_path, fs::Permissions::from_mode(0o620)).unwrap();
+            assert!(storage.load_wallet(&name).is_err());
+            storage.save_wallet(&name, &WalletData::new()).unwrap();
+            assert_eq!(mode(&wallet_path), 0o600);
+        }
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_migration_skips_files_that_are_not_wallets() {
+        let data_dir =
+            std::env::temp_dir().join(format!("rwmth-storage-migrate-{}", std::process::id()));
+        fs::remove_dir_all(&data_dir).ok();
+        let json = StorageManager::new(data_dir.clone());
+        json.create_wallet(&WalletName::new("work").unwrap(), &WalletData::new())
+            .unwrap();
+        fs::write(data_dir.join(crate::address_book::ADDRESS_BOOK_FILE), "{}").unwrap();
+        fs::write(
+            data_dir.join(crate::history::HISTORY_FILE),
+            r#"{"records":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(json.list_wallets().unwrap(), vec!["work"]);
+
+        let sled = SledWalletStore::open(&data_dir).unwrap();
+        let report = migrate_wallets(&json, &sled).unwrap();
+        assert_eq!(report.migrated, vec!["work"]);
+        assert_eq!(sled.list_wallets().unwrap(), vec!["work"]);
+        drop(sled);
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_rename_and_trash_never_replace_wallets() {
+        let data_dir =
+            std::env::temp_dir().join(format!("rwmth-storage-moves-{}", std::process::id()));
+        fs::remove_dir_all(&data_dir).ok();
+        let name = |name: &str| WalletName::new(name).unwrap();
+        let first = WalletData::new();
+        let mut second = WalletData::new();
+        second.created_at = first.created_at - chrono::Duration::days(1);
+        let created_at = |store: &dyn WalletStore, wallet_name: &str| {
+            store
+                .load_wallet(&name(wallet_name))
+                .unwrap()
+                .map(|wallet_data| wallet_data.created_at)
+        };
+
+        for backend in [StorageBackend::Json, StorageBackend::Sled] {
+            let store = open_store(backend, data_dir.join(format!("{:?}", backend))).unwrap();
+            let store = store.as_ref();
+
+            store.create_wallet(&name("work"), &first).unwrap();
+            let error = store.create_wallet(&name("work"), &second).unwrap_err();
+            assert_eq!(error.to_string(), "Wallet already exists: work");
+            assert_eq!(created_at(store, "work"), Some(first.created_at));
+
+            store.create_wallet(&name("home"), &second).unwrap();
+            let error = store
+                .rename_wallet(&name("work"), &name("home"))
+                .unwrap_err();
+            assert_eq!(error.to_string(), "Wallet already exists: home");
+            assert_eq!(created_at(store, "work"), Some(first.created_at));
+            assert_eq!(created_at(store, "home"), Some(second.created_at));
+
+            store.rename_wallet(&name("work"), &name("office")).unwrap();
+            assert_eq!(created_at(store, "work"), None);
+            assert_eq!(created_at(store, "office"), Some(first.created_at));
+            let error = store
+                .rename_wallet(&name("work"), &name("spare"))
+                .unwrap_err();
+            assert_eq!(error.to_string(), "Wallet not found: work");
+
+            // The trashed copy is the wallet as it was, outside the wallet list
+            let trashed = store.trash_wallet(&name("office")).unwrap();
+            assert!(trashed.starts_with(store.data_dir().join(TRASH_DIR)));
+            let wallet_data: WalletData =
+                serde_json::from_str(&fs::read_to_string(&trashed).unwrap()).unwrap();
+            assert_eq!(wallet_data.created_at, first.created_at);
+            assert_eq!(store.list_wallets().unwrap(), vec!["home"]);
+            let error = store.trash_wallet(&name("office")).unwrap_err();
+            assert_eq!(error.to_string(), "Wallet not found: office");
+        }
+        fs::remove_dir_all(data_dir).unwrap();
     }
 }
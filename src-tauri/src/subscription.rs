@@ -0,0 +1,499 @@
+//! Live node events over the node's WebSocket endpoint
+//!
+//! The subscriber asks the node for new blocks and for the balance and
+//! transaction events of the watched accounts. Every event updates the cache
+//! and is forwarded on a channel, which the app turns into Tauri events. A
+//! dropped connection is retried with exponential backoff and the
+//! subscriptions are made again once it is back.
+
+use crate::crypto::CryptoManager;
+use crate::history::TxStatus;
+use crate::node;
+use anyhow::{Result, anyhow};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Name of the Tauri event every `NodeEvent` is emitted as
+pub const NODE_EVENT: &str = "node-event";
+
+/// Delay before the first reconnect attempt
+pub const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Reconnect attempts are never further apart than this
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Something the node reported, or a change of the connection to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NodeEvent {
+    /// Connected and subscribed
+    Connected,
+    /// The connection failed or dropped; retried after `retry_in_ms` unless it is `None`
+    Disconnected {
+        attempt: u32,
+        retry_in_ms: Option<u64>,
+        error: String,
+    },
+    Block {
+        number: u64,
+        hash: String,
+    },
+    Balance {
+        account: String,
+        balance: u64,
+    },
+    Transaction {
+        hash: String,
+        status: TxStatus,
+    },
+}
+
+/// Latest state reported by the node
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeCache {
+    pub connected: bool,
+    /// Number of the newest block seen
+    pub block: Option<u64>,
+    /// Balances by account address
+    pub balances: BTreeMap<String, u64>,
+    /// Statuses by transaction hash
+    pub transactions: BTreeMap<String, TxStatus>,
+}
+
+impl NodeCache {
+    pub fn apply(&mut self, event: &NodeEvent) {
+        match event {
+            NodeEvent::Connected => self.connected = true,
+            NodeEvent::Disconnected { .. } => self.connected = false,
+            NodeEvent::Block { number, .. } => {
+                self.block = Some(self.block.map_or(*number, |block| block.max(*number)))
+            }
+            NodeEvent::Balance { account, balance } => {
+                self.balances.insert(account.clone(), *balance);
+            }
+            NodeEvent::Transaction { hash, status } => {
+                self.transactions.insert(hash.clone(), *status);
+            }
+        }
+    }
+}
+
+/// Exponential delays between reconnect attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Delay before the next attempt: twice the previous one, up to `max`
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(self.max);
+        self.attempt += 1;
+        delay
+    }
+
+    /// Failed attempts since the last successful connection
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Start over from `initial` once connected
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY)
+    }
+}
+
+/// What to subscribe to and how to reconnect
+#[derive(Debug, Clone)]
+pub struct SubscriptionConfig {
+    /// WebSocket endpoint of the node
+    pub url: String,
+    /// Addresses whose balances and transactions are watched
+    pub accounts: Vec<String>,
+    /// Whether to reconnect after the connection drops (`node.auto_reconnect`)
+    pub reconnect: bool,
+    pub backoff: Backoff,
+}
+
+/// Keep `cache` up to date and forward every event to `events` until the
+/// receiver is dropped, or the connection drops without `reconnect`
+pub async fn run(
+    config: SubscriptionConfig,
+    cache: Arc<Mutex<NodeCache>>,
+    events: UnboundedSender<NodeEvent>,
+) {
+    let publish = |event: NodeEvent| {
+        if let Ok(mut cache) = cache.lock() {
+            cache.apply(&event);
+        }
+        events.send(event).is_ok()
+    };
+
+    let mut backoff = config.backoff.clone();
+    loop {
+        let error = match listen(&config, &mut backoff, &publish).await {
+            // The receiver is gone, so nobody is listening anymore
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        let retry = config.reconnect.then(|| backoff.next_delay());
+        tracing::warn!("Node subscription lost: {}", error);
+        let disconnected = NodeEvent::Disconnected {
+            attempt: backoff.attempt(),
+            retry_in_ms: retry.map(|delay| delay.as_millis() as u64),
+            error: error.to_string(),
+        };
+        let listening = publish(disconnected);
+        match retry {
+            Some(delay) if listening => tokio::time::sleep(delay).await,
+            _ => return,
+        }
+    }
+}
+
+/// Connect, subscribe and publish notifications until the connection fails
+/// (an error) or `publish` reports nobody is listening (`Ok`)
+async fn listen(
+    config: &SubscriptionConfig,
+    backoff: &mut Backoff,
+    publish: &impl Fn(NodeEvent) -> bool,
+) -> Result<()> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(config.url.as_str()).await?;
+
+    let mut subscriptions = vec![json!(["blocks"])];
+    for account in &config.accounts {
+        let account = format!("0x{}", account.trim_start_matches("0x"));
+        subscriptions.push(json!(["account", account]));
+    }
+    for (id, params) in subscriptions.into_iter().enumerate() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "subscribe",
+            "params": params,
+        });
+        socket.send(Message::Text(request.to_string())).await?;
+    }
+
+    backoff.reset();
+    if !publish(NodeEvent::Connected) {
+        return Ok(());
+    }
+    while let Some(message) = socket.next().await {
+        let text = match message? {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        if let Some(event) = parse_notification(&text)?
+            && !publish(event)
+        {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("Connection closed by node"))
+}
+
+/// Event in a notification from the node, or `None` for a subscription reply
+fn parse_notification(text: &str) -> Result<Option<NodeEvent>> {
+    #[derive(Deserialize)]
+    struct Notification {
+        method: Option<String>,
+        #[serde(default)]
+        params: Value,
+        error: Option<Value>,
+    }
+    #[derive(Deserialize)]
+    struct Block {
+        number: u64,
+        hash: String,
+    }
+    #[derive(Deserialize)]
+    struct Balance {
+        account: String,
+        balance: u64,
+    }
+    #[derive(Deserialize)]
+    struct Status {
+        hash: String,
+        status: String,
+    }
+
+    let notification: Notification = serde_json::from_str(text)?;
+    if let Some(error) = notification.error {
+        return Err(anyhow!("Node rejected subscription: {}", error));
+    }
+    let params = notification.params;
+    let event = match notification.method.as_deref() {
+        None => return Ok(None),
+        Some("block") => {
+            let block: Block = serde_json::from_value(params)?;
+            NodeEvent::Block {
+                number: block.number,
+                hash: block.hash,
+            }
+        }
+        Some("balance") => {
+            let balance: Balance = serde_json::from_value(params)?;
+            NodeEvent::Balance {
+                account: CryptoManager::parse_address(&balance.account)?,
+                balance: balance.balance,
+            }
+        }
+        Some("tx_status") => {
+            let status: Status = serde_json::from_value(params)?;
+            match node::parse_status(&status.status)? {
+                Some(tx_status) => NodeEvent::Transaction {
+                    hash: status.hash,
+                    status: tx_status,
+                },
+                None => return Ok(None),
+            }
+        }
+        Some(other) => {
+            tracing::debug!("Ignoring node notification: {}", other);
+            return Ok(None);
+        }
+    };
+    Ok(Some(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::WebSocketStream;
+
+    const ACCOUNT: &str = "1111111111111111111111111111111111111111";
+
+    /// Accept one connection and read the subscription requests sent on it
+    async fn accept(
+        listener: &TcpListener,
+        subscriptions: usize,
+    ) -> (WebSocketStream<tokio::net::TcpStream>, Vec<Value>) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let mut requests = Vec::new();
+        for _ in 0..subscriptions {
+            let message = socket.next().await.unwrap().unwrap();
+            let request: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+            requests.push(request["params"].clone());
+        }
+        (socket, requests)
+    }
+
+    async fn notify(
+        socket: &mut WebSocketStream<tokio::net::TcpStream>,
+        method: &str,
+        params: Value,
+    ) {
+        let notification = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        socket
+            .send(Message::Text(notification.to_string()))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<u64> = (0..5)
+            .map(|_| backoff.next_delay().as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+        assert_eq!(backoff.attempt(), 5);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_reconnects_and_resubscribes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let cache = Arc::new(Mutex::new(NodeCache::default()));
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let config = SubscriptionConfig {
+            url,
+            accounts: vec![ACCOUNT.to_string()],
+            reconnect: true,
+            backoff: Backoff::new(Duration::from_millis(20), Duration::from_millis(100)),
+        };
+        let task = tokio::spawn(run(config, cache.clone(), sender));
+
+        // First connection: subscribed to blocks and the account, then dropped
+        let (mut socket, requests) = accept(&listener, 2).await;
+        assert_eq!(
+            requests,
+            vec![
+                json!(["blocks"]),
+                json!(["account", format!("0x{}", ACCOUNT)])
+            ]
+        );
+        assert_eq!(events.recv().await, Some(NodeEvent::Connected));
+        notify(&mut socket, "block", json!({"number": 7, "hash": "0xab"})).await;
+        notify(
+            &mut socket,
+            "balance",
+            json!({"account": format!("0x{}", ACCOUNT), "balance": 500}),
+        )
+        .await;
+        drop(socket);
+
+        assert_eq!(
+            events.recv().await,
+            Some(NodeEvent::Block {
+                number: 7,
+                hash: "0xab".to_string()
+            })
+        );
+        assert_eq!(
+            events.recv().await,
+            Some(NodeEvent::Balance {
+                account: ACCOUNT.to_string(),
+                balance: 500
+            })
+        );
+        match events.recv().await {
+            Some(NodeEvent::Disconnected {
+                attempt,
+                retry_in_ms,
+                ..
+            }) => {
+                assert_eq!(attempt, 1);
+                assert_eq!(retry_in_ms, Some(20));
+            }
+            other => panic!("Expected a disconnect, got {:?}", other),
+        }
+        assert!(!cache.lock().unwrap().connected);
+
+        // Second connection: the subscriptions are made again and the backoff starts over
+        let (mut socket, requests) = accept(&listener, 2).await;
+        assert_eq!(requests.len(), 2);
+        assert_eq!(events.recv().await, Some(NodeEvent::Connected));
+        notify(
+            &mut socket,
+            "tx_status",
+            json!({"hash": "0xcd", "status": "confirmed"}),
+        )
+        .await;
+        assert_eq!(
+            events.recv().await,
+            Some(NodeEvent::Transaction {
+                hash: "0xcd".to_string(),
+                status: TxStatus::Confirmed
+            })
+        );
+
+        let cached = cache.lock().unwrap().clone();
+        assert!(cached.connected);
+        assert_eq!(cached.block, Some(7));
+        assert_eq!(cached.balances.get(ACCOUNT), Some(&500));
+        assert_eq!(cached.transactions.get("0xcd"), Some(&TxStatus::Confirmed));
+
+        // Nobody listening anymore stops the subscriber
+        drop(events);
+        notify(&mut socket, "block", json!({"number": 8, "hash": "0xef"})).await;
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_backs_off_while_node_is_down() {
+        // Reserve a port, then close it so connections are refused
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let config = SubscriptionConfig {
+            url: format!("ws://{}", address),
+            accounts: Vec::new(),
+            reconnect: true,
+            backoff: Backoff::new(Duration::from_millis(10), Duration::from_millis(40)),
+        };
+        let task = tokio::spawn(run(config, Arc::default(), sender));
+
+        let mut delays = Vec::new();
+        for _ in 0..4 {
+            match events.recv().await {
+                Some(NodeEvent::Disconnected { retry_in_ms, .. }) => delays.push(retry_in_ms),
+                other => panic!("Expected a disconnect, got {:?}", other),
+            }
+        }
+        assert_eq!(delays, vec![Some(10), Some(20), Some(40), Some(40)]);
+
+        // Once the node is back the subscriber connects on its next attempt
+        let listener = TcpListener::bind(address).await.unwrap();
+        let (_socket, requests) = accept(&listener, 1).await;
+        assert_eq!(requests, vec![json!(["blocks"])]);
+        loop {
+            match events.recv().await {
+                Some(NodeEvent::Connected) => break,
+                Some(NodeEvent::Disconnected { .. }) => continue,
+                other => panic!("Expected to connect, got {:?}", other),
+            }
+        }
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_stops_without_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let config = SubscriptionConfig {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            accounts: Vec::new(),
+            reconnect: false,
+            backoff: Backoff::default(),
+        };
+        let task = tokio::spawn(run(config, Arc::default(), sender));
+
+        let (mut socket, _) = accept(&listener, 1).await;
+        assert_eq!(events.recv().await, Some(NodeEvent::Connected));
+        // A rejected subscription drops the connection
+        socket
+            .send(Message::Text(
+                json!({"jsonrpc": "2.0", "id": 0, "error": {"code": -32601, "message": "no"}})
+                    .to_string(),
+            ))
+            .await
+            .unwrap();
+        match events.recv().await {
+            Some(NodeEvent::Disconnected { retry_in_ms, .. }) => assert_eq!(retry_in_ms, None),
+            other => panic!("Expected a disconnect, got {:?}", other),
+        }
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,593 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Type name used for the implicit domain struct
+const DOMAIN_TYPE: &str = "Domain";
+
+/// Prefix bytes of the final signing hash (same as EIP-712)
+const SIGNING_PREFIX: [u8; 2] = [0x19, 0x01];
+
+/// A named, typed field of a struct definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypedField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+}
+
+/// Domain the signature is bound to (dApp name, version, chain, contract)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedDataDomain {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verifying_contract: Option<String>,
+}
+
+/// Typed structured data as submitted by a dApp for approval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    pub types: BTreeMap<String, Vec<TypedField>>,
+    pub primary_type: String,
+    pub domain: TypedDataDomain,
+    pub message: Value,
+}
+
+/// Human-readable view of a single encoded field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewField {
+    pub name: String,
+    pub field_type: String,
+    /// Display value for atomic types, `None` for structs and arrays
+    pub value: Option<String>,
+    /// Members of a struct or elements of an array
+    pub fields: Vec<PreviewField>,
+}
+
+/// Everything the user approves when signing typed data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypedDataPreview {
+    pub primary_type: String,
+    pub domain: Vec<PreviewField>,
+    pub message: Vec<PreviewField>,
+    pub domain_separator: String,
+    pub message_hash: String,
+    pub signing_hash: String,
+    pub signer: Option<String>,
+}
+
+/// Result of hashing a struct together with its preview
+struct EncodedStruct {
+    hash: [u8; 32],
+    fields: Vec<PreviewField>,
+}
+
+impl TypedData {
+    /// Parse and validate typed data from its JSON representation
+    pub fn from_json(json: &str) -> Result<Self> {
+        let typed_data: TypedData =
+            serde_json::from_str(json).map_err(|e| anyhow!("Invalid typed data: {}", e))?;
+        typed_data.validate()?;
+        Ok(typed_data)
+    }
+
+    /// Check that every referenced type is defined
+    pub fn validate(&self) -> Result<()> {
+        if self.types.contains_key(DOMAIN_TYPE) {
+            return Err(anyhow!("Type name '{}' is reserved", DOMAIN_TYPE));
+        }
+        if !self.types.contains_key(&self.primary_type) {
+            return Err(anyhow!(
+                "Primary type '{}' is not defined",
+                self.primary_type
+            ));
+        }
+
+        for (type_name, fields) in &self.types {
+            let mut seen = BTreeSet::new();
+            for field in fields {
+                if !seen.insert(field.name.as_str()) {
+                    return Err(anyhow!(
+                        "Duplicate field '{}' in type '{}'",
+                        field.name,
+                        type_name
+                    ));
+                }
+                let base_type = base_type(&field.field_type);
+                if !is_atomic(base_type) && !self.types.contains_key(base_type) {
+                    return Err(anyhow!(
+                        "Unknown type '{}' for field '{}.{}'",
+                        field.field_type,
+                        type_name,
+                        field.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hash that gets signed: sha256(0x1901 || domainSeparator || hashStruct(message))
+    pub fn signing_hash(&self) -> Result<[u8; 32]> {
+        let (domain, message) = self.encode()?;
+        Ok(Self::combine(&domain.hash, &message.hash))
+    }
+
+    /// Build the structured preview shown before signing
+    pub fn preview(&self) -> Result<TypedDataPreview> {
+        let (domain, message) = self.encode()?;
+        let signing_hash = Self::combine(&domain.hash, &message.hash);
+
+        Ok(TypedDataPreview {
+            primary_type: self.primary_type.clone(),
+            domain: domain.fields,
+            message: message.fields,
+            domain_separator: hex::encode(domain.hash),
+            message_hash: hex::encode(message.hash),
+            signing_hash: hex::encode(signing_hash),
+            signer: None,
+        })
+    }
+
+    fn combine(domain_separator: &[u8; 32], message_hash: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SIGNING_PREFIX);
+        hasher.update(domain_separator);
+        hasher.update(message_hash);
+        hasher.finalize().into()
+    }
+
+    fn encode(&self) -> Result<(EncodedStruct, EncodedStruct)> {
+        self.validate()?;
+
+        let mut types = self.types.clone();
+        let (domain_fields, domain_value) = self.domain_struct();
+        types.insert(DOMAIN_TYPE.to_string(), domain_fields);
+
+        let domain = hash_struct(&types, DOMAIN_TYPE, &domain_value)?;
+        let message = hash_struct(&types, &self.primary_type, &self.message)?;
+        Ok((domain, message))
+    }
+
+    /// Domain fields only include the optional members that are set
+    fn domain_struct(&self) -> (Vec<TypedField>, Value) {
+        let mut fields = vec![
+            TypedField {
+                name: "name".to_string(),
+                field_type: "string".to_string(),
+            },
+            TypedField {
+                name: "version".to_string(),
+                field_type: "string".to_string(),
+            },
+        ];
+        let mut value = serde_json::Map::new();
+        value.insert("name".to_string(), Value::from(self.domain.name.clone()));
+        value.insert(
+            "version".to_string(),
+            Value::from(self.domain.version.clone()),
+        );
+
+        if let Some(chain_id) = self.domain.chain_id {
+            fields.push(TypedField {
+                name: "chainId".to_string(),
+                field_type: "uint64".to_string(),
+            });
+            value.insert("chainId".to_string(), Value::from(chain_id));
+        }
+        if let Some(contract) = &self.domain.verifying_contract {
+            fields.push(TypedField {
+                name: "verifyingContract".to_string(),
+                field_type: "address".to_string(),
+            });
+            value.insert(
+                "verifyingContract".to_string(),
+                Value::from(contract.clone()),
+            );
+        }
+
+        (fields, Value::Object(value))
+    }
+}
+
+fn is_atomic(field_type: &str) -> bool {
+    matches!(
+        field_type,
+        "string" | "bytes" | "bytes32" | "bool" | "address" | "uint64" | "int64"
+    )
+}
+
+/// Strip any array suffixes, e.g. `Person[][]` -> `Person`
+fn base_type(field_type: &str) -> &str {
+    let mut base = field_type;
+    while let Some(inner) = base.strip_suffix("[]") {
+        base = inner;
+    }
+    base
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// `Primary(type name,...)` followed by all referenced struct types sorted by name
+fn encode_type(types: &BTreeMap<String, Vec<TypedField>>, primary_type: &str) -> String {
+    let mut deps = BTreeSet::new();
+    collect_dependencies(types, primary_type, &mut deps);
+    deps.remove(primary_type);
+
+    std::iter::once(primary_type)
+        .chain(deps.iter().map(String::as_str))
+        .map(|name| {
+            let members: Vec<String> = types[name]
+                .iter()
+                .map(|field| format!("{} {}", field.field_type, field.name))
+                .collect();
+            format!("{}({})", name, members.join(","))
+        })
+        .collect()
+}
+
+fn collect_dependencies(
+    types: &BTreeMap<String, Vec<TypedField>>,
+    type_name: &str,
+    deps: &mut BTreeSet<String>,
+) {
+    if !types.contains_key(type_name) || !deps.insert(type_name.to_string()) {
+        return;
+    }
+    for field in &types[type_name] {
+        collect_dependencies(types, base_type(&field.field_type), deps);
+    }
+}
+
+fn hash_struct(
+    types: &BTreeMap<String, Vec<TypedField>>,
+    type_name: &str,
+    data: &Value,
+) -> Result<EncodedStruct> {
+    let object = data
+        .as_object()
+        .ok_or_else(|| anyhow!("Value for type '{}' must be an object", type_name))?;
+    let definition = &types[type_name];
+
+    // Refuse members the schema does not describe, they would be invisible in the preview
+    for key in object.keys() {
+        if !definition.iter().any(|field| &field.name == key) {
+            return Err(anyhow!(
+                "Unexpected field '{}' in type '{}'",
+                key,
+                type_name
+            ));
+        }
+    }
+
+    let mut encoded = sha256(encode_type(types, type_name).as_bytes()).to_vec();
+    let mut fields = Vec::with_capacity(definition.len());
+    for field in definition {
+        let value = object
+            .get(&field.name)
+            .ok_or_else(|| anyhow!("Missing field '{}' in type '{}'", field.name, type_name))?;
+        let (word, preview) = encode_value(types, &field.name, &field.field_type, value)?;
+        encoded.extend_from_slice(&word);
+        fields.push(preview);
+    }
+
+    Ok(EncodedStruct {
+        hash: sha256(&encoded),
+        fields,
+    })
+}
+
+/// Encode a value into a 32-byte word and describe it for the preview
+fn encode_value(
+    types: &BTreeMap<String, Vec<TypedField>>,
+    name: &str,
+    field_type: &str,
+    value: &Value,
+) -> Result<([u8; 32], PreviewField)> {
+    let invalid = || {
+        anyhow!(
+            "Invalid value for field '{}' of type '{}'",
+            name,
+            field_type
+        )
+    };
+
+    if let Some(element_type) = field_type.strip_suffix("[]") {
+        let elements = value.as_array().ok_or_else(invalid)?;
+        let mut encoded = Vec::with_capacity(elements.len() * 32);
+        let mut fields = Vec::with_capacity(elements.len());
+        for (index, element) in elements.iter().enumerate() {
+            let (word, preview) =
+                encode_value(types, &format!("[{}]", index), element_type, element)?;
+            encoded.extend_from_slice(&word);
+            fields.push(preview);
+        }
+        return Ok((sha256(&encoded), branch(name, field_type, fields)));
+    }
+
+    if types.contains_key(field_type) {
+        let encoded = hash_struct(types, field_type, value)?;
+        return Ok((encoded.hash, branch(name, field_type, encoded.fields)));
+    }
+
+    let mut word = [0u8; 32];
+    let display = match field_type {
+        "string" => {
+            let text = value.as_str().ok_or_else(invalid)?;
+            word = sha256(text.as_bytes());
+            text.to_string()
+        }
+        "bytes" => {
+            let bytes = decode_hex(value).ok_or_else(invalid)?;
+            word = sha256(&bytes);
+            format!("0x{}", hex::encode(bytes))
+        }
+        "bytes32" => {
+            let bytes = decode_hex(value).ok_or_else(invalid)?;
+            if bytes.len() != 32 {
+                return Err(invalid());
+            }
+            word.copy_from_slice(&bytes);
+            format!("0x{}", hex::encode(bytes))
+        }
+        "address" => {
+            let bytes = decode_hex(value).ok_or_else(invalid)?;
+            if bytes.len() != 20 {
+                return Err(invalid());
+            }
+            word[12..].copy_from_slice(&bytes);
+            format!("0x{}", hex::encode(bytes))
+        }
+        "bool" => {
+            let flag = value.as_bool().ok_or_else(invalid)?;
+            word[31] = flag as u8;
+            flag.to_string()
+        }
+        "uint64" => {
+            let number = match value {
+                Value::String(text) => text.parse::<u64>().ok(),
+                _ => value.as_u64(),
+            }
+            .ok_or_else(invalid)?;
+            word[24..].copy_from_slice(&number.to_be_bytes());
+            number.to_string()
+        }
+        "int64" => {
+            let number = match value {
+                Value::String(text) => text.parse::<i64>().ok(),
+                _ => value.as_i64(),
+            }
+            .ok_or_else(invalid)?;
+            if number < 0 {
+                word = [0xff; 32];
+            }
+            word[24..].copy_from_slice(&number.to_be_bytes());
+            number.to_string()
+        }
+        _ => return Err(anyhow!("Unknown type '{}'", field_type)),
+    };
+
+    Ok((
+        word,
+        PreviewField {
+            name: name.to_string(),
+            field_type: field_type.to_string(),
+            value: Some(display),
+            fields: Vec::new(),
+        },
+    ))
+}
+
+fn branch(name: &str, field_type: &str, fields: Vec<PreviewField>) -> PreviewField {
+    PreviewField {
+        name: name.to_string(),
+        field_type: field_type.to_string(),
+        value: None,
+        fields,
+    }
+}
+
+fn decode_hex(value: &Value) -> Option<Vec<u8>> {
+    let text = value.as_str()?;
+    hex::decode(text.trim_start_matches("0x")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A mail from one person to two, in a domain with every optional member
+    fn mail() -> Value {
+        json!({
+            "types": {
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person[]" },
+                    { "name": "contents", "type": "string" },
+                    { "name": "nonce", "type": "uint64" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xcccccccccccccccccccccccccccccccccccccccc"
+            },
+            "message": {
+                "from": { "name": "Alice", "wallet": "0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826" },
+                "to": [
+                    { "name": "Bob", "wallet": "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb" },
+                    { "name": "Carol", "wallet": "0xcccccccccccccccccccccccccccccccccccccccc" }
+                ],
+                "contents": "Hello, Bob!",
+                "nonce": 7
+            }
+        })
+    }
+
+    fn parse(value: &Value) -> Result<TypedData> {
+        TypedData::from_json(&value.to_string())
+    }
+
+    #[test]
+    fn test_signing_hash_known_answers() {
+        let mut typed_data = mail();
+        let preview = parse(&typed_data).unwrap().preview().unwrap();
+        assert_eq!(
+            preview.domain_separator,
+            "cd32c6f3866b378181ce6c01044f360b094ffd05cdbbccc1151f012ea754ecfa"
+        );
+        assert_eq!(
+            preview.message_hash,
+            "9408e778f1046c3d16df2158007dcc63fbd334cac52e8fd84397eedb0aa4be80"
+        );
+        assert_eq!(
+            preview.signing_hash,
+            "b3caaff88f27d23998758c511d2d6e05201ff73e276c0d14452be0b4eeeccea3"
+        );
+        assert_eq!(
+            hex::encode(parse(&typed_data).unwrap().signing_hash().unwrap()),
+            preview.signing_hash
+        );
+
+        // Domain members that aren't set are left out of the domain type
+        typed_data["domain"] = json!({ "name": "Ether Mail", "version": "1" });
+        let preview = parse(&typed_data).unwrap().preview().unwrap();
+        assert_eq!(
+            preview.domain_separator,
+            "cf2c7cd8f3fcec90e72b5ad0c8f6893a2aebebd6c063ee5c1f0da6cb9bbf4efb"
+        );
+        assert_eq!(
+            preview.signing_hash,
+            "744d884d23053e85bd0c28495395fdd4db18000948f3e57d6df1ec679ac65bc2"
+        );
+    }
+
+    #[test]
+    fn test_atomic_and_array_encoding() {
+        let typed_data = json!({
+            "types": {
+                "Entry": [
+                    { "name": "ok", "type": "bool" },
+                    { "name": "delta", "type": "int64" },
+                    { "name": "data", "type": "bytes" },
+                    { "name": "id", "type": "bytes32" },
+                    { "name": "amounts", "type": "uint64[]" }
+                ]
+            },
+            "primaryType": "Entry",
+            "domain": { "name": "Ledger", "version": "2" },
+            "message": {
+                "ok": true,
+                "delta": "-2",
+                "data": "0x0102",
+                "id": format!("0x{}", "11".repeat(32)),
+                "amounts": [3, "18446744073709551615"]
+            }
+        });
+        let preview = parse(&typed_data).unwrap().preview().unwrap();
+        assert_eq!(
+            preview.message_hash,
+            "ee23b13224a60e18be5b2c13ec0fbe1ddca9a5ebc1fe171abe8cbe2a0d60156f"
+        );
+        assert_eq!(
+            preview.signing_hash,
+            "8a69fc28b1c2b8a4d0f76208bfa21e1bde2a6d2340fa9062804e2c14a3cf58ad"
+        );
+
+        let amounts = &preview.message[4];
+        assert_eq!(amounts.value, None);
+        let elements: Vec<_> = amounts
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_deref()))
+            .collect();
+        assert_eq!(
+            elements,
+            vec![("[0]", Some("3")), ("[1]", Some("18446744073709551615"))]
+        );
+    }
+
+    #[test]
+    fn test_nested_structs_preview_as_branches() {
+        let preview = parse(&mail()).unwrap().preview().unwrap();
+        let to = &preview.message[1];
+        assert_eq!(to.field_type, "Person[]");
+        assert_eq!(to.fields.len(), 2);
+        assert_eq!(to.fields[1].name, "[1]");
+        assert_eq!(to.fields[1].field_type, "Person");
+        assert_eq!(to.fields[1].fields[0].value.as_deref(), Some("Carol"));
+        assert_eq!(
+            encode_type(&parse(&mail()).unwrap().types, "Mail"),
+            "Mail(Person from,Person[] to,string contents,uint64 nonce)Person(string name,address wallet)"
+        );
+    }
+
+    #[test]
+    fn test_unknown_types_and_bad_values_are_rejected() {
+        let mut typed_data = mail();
+        typed_data["types"]["Mail"][3]["type"] = json!("uint256");
+        let error = parse(&typed_data).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown type 'uint256' for field 'Mail.nonce'"
+        );
+
+        let mut typed_data = mail();
+        typed_data["primaryType"] = json!("Letter");
+        assert!(parse(&typed_data).is_err());
+
+        let mut typed_data = mail();
+        typed_data["types"]["Domain"] = json!([]);
+        assert!(parse(&typed_data).is_err());
+
+        // Values are checked when the data is encoded
+        for (pointer, value) in [
+            ("/message/nonce", json!(-1)),
+            ("/message/nonce", json!("seven")),
+            ("/message/from/wallet", json!("0x1234")),
+            ("/message/from/name", json!(5)),
+            ("/message/to", json!({ "name": "Bob" })),
+        ] {
+            let mut typed_data = mail();
+            *typed_data.pointer_mut(pointer).unwrap() = value;
+            assert!(
+                parse(&typed_data).unwrap().signing_hash().is_err(),
+                "{} accepted",
+                pointer
+            );
+        }
+
+        let mut typed_data = mail();
+        typed_data["message"]["from"]["email"] = json!("alice@example.com");
+        let error = parse(&typed_data).unwrap().signing_hash().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unexpected field 'email' in type 'Person'"
+        );
+
+        let mut typed_data = mail();
+        typed_data["message"]
+            .as_object_mut()
+            .unwrap()
+            .remove("contents");
+        let error = parse(&typed_data).unwrap().signing_hash().unwrap_err();
+        assert_eq!(error.to_string(), "Missing field 'contents' in type 'Mail'");
+    }
+}
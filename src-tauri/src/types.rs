@@ -1,15 +1,110 @@
+use crate::config::{Network, NetworkProfile};
+use crate::multisig::MultisigAccount;
+use crate::signing::ExternalAccount;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Wallets written before the KDF setting existed used a fixed iteration count
+fn legacy_kdf_iterations() -> u32 {
+    crate::config::MIN_KDF_ITERATIONS
+}
+
+/// Longest account label accepted
+pub const MAX_LABEL_LENGTH: usize = 64;
+/// Longest account notes accepted
+pub const MAX_NOTES_LENGTH: usize = 1024;
+/// Longest account tag accepted
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// User-editable account details (same fields as the CLI's `AccountMetadata`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountMetadata {
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Tags, sorted and unique
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub archived: bool,
+    /// Display position; accounts without one sort by their index
+    #[serde(default)]
+    pub order: Option<u32>,
+}
+
+impl AccountMetadata {
+    /// Trim the label, drop empty text fields and sort the tags, so a blank
+    /// label or notes clears them
+    pub fn normalize(&mut self) {
+        self.label = self
+            .label
+            .take()
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty());
+        self.notes = self.notes.take().filter(|notes| !notes.trim().is_empty());
+        self.tags.sort();
+        self.tags.dedup();
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(label) = &self.label
+            && (label.chars().count() > MAX_LABEL_LENGTH || label.chars().any(char::is_control))
+        {
+            return Err(anyhow::anyhow!(
+                "Label must be at most {} characters without control characters",
+                MAX_LABEL_LENGTH
+            ));
+        }
+        if let Some(notes) = &self.notes
+            && notes.chars().count() > MAX_NOTES_LENGTH
+        {
+            return Err(anyhow::anyhow!(
+                "Notes must be at most {} characters",
+                MAX_NOTES_LENGTH
+            ));
+        }
+        for tag in &self.tags {
+            if tag.is_empty()
+                || tag.chars().count() > MAX_TAG_LENGTH
+                || tag
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || c == ',')
+            {
+                return Err(anyhow::anyhow!(
+                    "Invalid tag '{}': use 1-{} characters without spaces or commas",
+                    tag,
+                    MAX_TAG_LENGTH
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_listed(&self) -> bool {
+        !self.hidden && !self.archived
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
     pub address: String,
     pub public_key: String,
     pub private_key_encrypted: Vec<u8>,
     pub salt: Vec<u8>,
+    /// PBKDF2 iterations the key was encrypted with
+    #[serde(default = "legacy_kdf_iterations")]
+    pub kdf_iterations: u32,
     pub is_selected: bool,
     pub created_at: DateTime<Utc>,
     pub last_used: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub metadata: AccountMetadata,
+    /// Signer holding the key instead of `private_key_encrypted`, which is then empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<ExternalAccount>,
 }
 
 impl Account {
@@ -18,15 +113,30 @@ impl Account {
         public_key: String,
         private_key_encrypted: Vec<u8>,
         salt: Vec<u8>,
+        kdf_iterations: u32,
     ) -> Self {
         Self {
             address,
             public_key,
             private_key_encrypted,
             salt,
+            kdf_iterations,
             is_selected: false,
             created_at: Utc::now(),
             last_used: None,
+            metadata: AccountMetadata::default(),
+            external: None,
+        }
+    }
+
+    pub fn info(&self, index: usize) -> AccountInfo {
+        AccountInfo {
+            index,
+            address: self.address.clone(),
+            public_key: self.public_key.clone(),
+            is_selected: self.is_selected,
+            created_at: self.created_at,
+            metadata: self.metadata.clone(),
         }
     }
 
@@ -52,6 +162,11 @@ impl Account {
 pub struct WalletData {
     pub accounts: Vec<Account>,
     pub selected_account_index: Option<usize>,
+    #[serde(default)]
+    pub multisig_accounts: Vec<MultisigAccount>,
+    /// Network the wallet is used on, overriding `node.network`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
     pub created_at: DateTime<Utc>,
     pub last_modified: DateTime<Utc>,
     pub version: String,
@@ -62,6 +177,8 @@ impl WalletData {
         Self {
             accounts: Vec::new(),
             selected_account_index: None,
+            multisig_accounts: Vec::new(),
+            network: None,
             created_at: Utc::now(),
             last_modified: Utc::now(),
             version: "1.0.0".to_string(),
@@ -95,6 +212,16 @@ impl WalletData {
             .and_then(|index| self.accounts.get(index))
     }
 
+    /// Account indexes in display order: by `order`, falling back to the index
+    pub fn display_order(&self) -> Vec<usize> {
+        let mut indexes: Vec<usize> = (0..self.accounts.len()).collect();
+        indexes.sort_by_key(|&index| {
+            let order = self.accounts[index].metadata.order;
+            (order.map_or(index as u64, u64::from), index)
+        });
+        indexes
+    }
+
     pub fn get_selected_account_mut(&mut self) -> Option<&mut Account> {
         self.selected_account_index
             .and_then(|index| self.accounts.get_mut(index))
@@ -109,6 +236,17 @@ pub struct WalletInfo {
     pub last_modified: DateTime<Utc>,
 }
 
+/// Network the wallet manager uses for signing and node calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveNetwork {
+    pub network: Network,
+    /// The loaded wallet's own network, if it has one
+    pub wallet_network: Option<Network>,
+    pub profile: NetworkProfile,
+    /// Node endpoint in use, which `node.url` may override
+    pub node_url: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateWalletResponse {
     pub success: bool,
@@ -129,10 +267,59 @@ pub struct ImportWalletResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AccountInfo {
+    /// Position in the wallet file, used to select the account
+    pub index: usize,
     pub address: String,
     pub public_key: String,
     pub is_selected: bool,
     pub created_at: DateTime<Utc>,
+    pub metadata: AccountMetadata,
+}
+
+/// Signature scheme selectable per signing call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// ECDSA over secp256k1 (DER encoded signatures)
+    #[default]
+    Ecdsa,
+    /// BIP340 Schnorr over secp256k1 (64-byte signatures, x-only public keys)
+    Schnorr,
+}
+
+/// Transaction fields, encoded the same way as the CLI's `Transaction`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Transaction {
+    /// Chain the transaction is valid on, which binds the signing hash to one network
+    pub chain_id: u64,
+    pub from: String,
+    pub to: Option<String>,
+    pub amount: Option<u64>,
+    pub data: Option<String>, // Hex string
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub gas_price: u64,
+}
+
+impl Transaction {
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn signing_hash(&self) -> anyhow::Result<[u8; 32]> {
+        use sha2::{Digest, Sha256};
+        Ok(Sha256::digest(self.encode()?).into())
+    }
+
+    /// Most the sender pays in fees: every unit of the gas limit at the gas price
+    pub fn max_fee(&self) -> u64 {
+        self.gas_limit.saturating_mul(self.gas_price)
+    }
+
+    /// Most the transaction can cost the sender: the amount plus the maximum fee
+    pub fn total_cost(&self) -> u64 {
+        self.amount.unwrap_or(0).saturating_add(self.max_fee())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -144,5 +331,24 @@ pub struct SignTransactionRequest {
 pub struct SignTransactionResponse {
     pub success: bool,
     pub signature: String, // Hex string
+    pub scheme: SignatureScheme,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignMessageResponse {
+    pub signature: String,    // Hex string
+    pub signing_hash: String, // Hex string
+    pub scheme: SignatureScheme,
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignTypedDataResponse {
+    pub success: bool,
+    pub signature: String,    // Hex string
+    pub signing_hash: String, // Hex string
+    pub scheme: SignatureScheme,
+    pub address: String,
     pub message: String,
 }
@@ -1,31 +1,280 @@
+use crate::address_book::{AddressBook, Contact};
+use crate::archive::{self, BackupContents, BackupPreview, RestoreMode, RestoreReport};
+use crate::config::{
+    DappMethod, DappPermission, Network, Settings, StorageBackend, default_config_path,
+};
 use crate::crypto::CryptoManager;
-use crate::storage::StorageManager;
+use crate::history::{History, HistoryFilter, HistoryPage, TxRecord, TxStatus};
+use crate::multisig::MultisigAccount;
+use crate::nonce;
+use crate::pst::{
+    FinalizedTransaction, FixedBytes, PartiallySignedTransaction, PstFormat, PstInspection,
+    SignedTransaction,
+};
+use crate::qr::{self, QrFrame, QrImportResult};
+#[cfg(unix)]
+use crate::signing::ExternalSigner;
+use crate::signing::{self, LocalSigner, Signer};
+use crate::storage::{self, MigrationReport, StorageManager, WalletName, WalletStore};
+use crate::typed_data::{TypedData, TypedDataPreview};
 use crate::types::{
-    Account, AccountInfo, CreateWalletResponse, ImportWalletResponse, SignTransactionResponse,
-    WalletData,
+    Account, AccountInfo, AccountMetadata, ActiveNetwork, CreateWalletResponse,
+    ImportWalletResponse, SignMessageResponse, SignTransactionResponse, SignTypedDataResponse,
+    SignatureScheme, Transaction, WalletData,
 };
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use hex;
 use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 const COIN_TYPE: u32 = 55555;
 
 pub struct WalletManager {
-    storage: StorageManager,
-    current_wallet: Option<String>,
+    storage: Box<dyn WalletStore>,
+    settings: Settings,
+    /// Config file the settings are saved to
+    config_path: PathBuf,
+    current_wallet: Option<WalletName>,
     wallet_data: Option<WalletData>,
 }
 
 impl WalletManager {
-    pub fn new() -> Self {
+    pub fn new(settings: Settings) -> Self {
+        Self::with_config_path(settings, default_config_path())
+    }
+
+    /// Manager whose settings are saved to `config_path`
+    pub fn with_config_path(settings: Settings, config_path: PathBuf) -> Self {
+        let storage = storage::open_store(settings.wallet.backend, settings.wallet_dir())
+            .unwrap_or_else(|e| {
+                tracing::warn!("Using JSON wallet files: {}", e);
+                Box::new(StorageManager::new(settings.wallet_dir()))
+            });
         Self {
-            storage: StorageManager::new(),
+            storage,
+            settings,
+            config_path,
             current_wallet: None,
             wallet_data: None,
         }
     }
 
+    pub fn get_settings(&self) -> Settings {
+        self.settings.clone()
+    }
+
+    /// Network in use: the loaded wallet's, else `node.network`
+    pub fn network(&self) -> Network {
+        self.wallet_data
+            .as_ref()
+            .and_then(|wallet_data| wallet_data.network)
+            .unwrap_or(self.settings.node.network)
+    }
+
+    /// Settings with `node.network` set to the network in use, for node calls
+    pub fn active_settings(&self) -> Settings {
+        let mut settings = self.settings.clone();
+        settings.node.network = self.network();
+        settings
+    }
+
+    pub fn active_network(&self) -> ActiveNetwork {
+        let settings = self.active_settings();
+        ActiveNetwork {
+            network: settings.node.network,
+            wallet_network: self
+                .wallet_data
+                .as_ref()
+                .and_then(|wallet_data| wallet_data.network),
+            profile: settings.network().clone(),
+            node_url: settings.node_url().to_string(),
+        }
+    }
+
+    /// Use the loaded wallet on `network`, or on `node.network` again with `None`
+    pub fn set_wallet_network(&mut self, network: Option<Network>) -> Result<Network> {
+        let wallet_data = self
+            .wallet_data
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
+        wallet_data.network = network;
+
+        if let Some(wallet_name) = &self.current_wallet {
+            self.storage.save_wallet(wallet_name, wallet_data)?;
+        }
+        Ok(self.network())
+    }
+
+    /// Validate and persist settings edited from `get_settings`, returning them
+    /// with environment overrides applied. Keys an environment variable
+    /// overrides keep their file values, so the overrides never reach the file.
+    pub fn update_settings(&mut self, settings: Settings) -> Result<Settings> {
+        let path = &self.config_path;
+        let file = Settings::load_file(path)?;
+        settings
+            .file_layer(&file, |name| std::env::var(name).ok())?
+            .save(path)?;
+        self.reload_settings()
+    }
+
+    /// Load the settings file again, switching wallet directory or storage
+    /// backend if either changed
+    fn reload_settings(&mut self) -> Result<Settings> {
+        let settings = Settings::load(&self.config_path)?;
+
+        if settings.wallet_dir() != self.settings.wallet_dir()
+            || settings.wallet.backend != self.settings.wallet.backend
+        {
+            self.storage = storage::open_store(settings.wallet.backend, settings.wallet_dir())?;
+            self.current_wallet = None;
+            self.wallet_data = None;
+        }
+        self.settings = settings.clone();
+        Ok(settings)
+    }
+
+    /// dApp permissions by origin
+    pub fn dapp_permissions(&self) -> BTreeMap<String, DappPermission> {
+        self.settings.dapps.clone()
+    }
+
+    /// Let `origin` use `accounts`, or the selected account if none are given,
+    /// through `methods`. A new grant replaces the old one but keeps what the
+    /// origin has spent, so reconnecting doesn't reset its limit.
+    pub fn grant_dapp_permission(
+        &mut self,
+        origin: &str,
+        methods: BTreeSet<DappMethod>,
+        accounts: Option<Vec<String>>,
+        spending_limit: Option<u64>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<DappPermission> {
+        let accounts = match accounts {
+            Some(accounts) => accounts
+                .iter()
+                .map(|account| CryptoManager::parse_address(account))
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![
+                self.get_selected_account()?
+                    .ok_or_else(|| anyhow::anyhow!("No account selected"))?
+                    .address,
+            ],
+        };
+        let now = Utc::now();
+        self.update_dapps(|dapps| {
+            let spent = dapps
+                .get(origin)
+                .filter(|permission| !permission.is_expired(now))
+                .map_or(0, |permission| permission.spent);
+            let permission = DappPermission {
+                accounts: accounts
+                    .into_iter()
+                    .map(|account| format!("0x{}", account))
+                    .collect(),
+                methods,
+                spending_limit,
+                spent,
+                granted_at: now,
+                expires_at,
+            };
+            dapps.insert(origin.to_string(), permission.clone());
+            Ok(permission)
+        })
+    }
+
+    pub fn revoke_dapp_permission(&mut self, origin: &str) -> Result<DappPermission> {
+        self.update_dapps(|dapps| {
+            dapps
+                .remove(origin)
+                .ok_or_else(|| anyhow::anyhow!("{} has no permissions", origin))
+        })
+    }
+
+    /// Change the `dapps` section of the config file and nothing else, then
+    /// reload the settings
+    fn update_dapps<T>(
+        &mut self,
+        change: impl FnOnce(&mut BTreeMap<String, DappPermission>) -> Result<T>,
+    ) -> Result<T> {
+        let mut file = Settings::load_file(&self.config_path)?;
+        let result = change(&mut file.dapps)?;
+        file.save(&self.config_path)?;
+        self.reload_settings()?;
+        Ok(result)
+    }
+
+    /// Check `origin` may call `method` now with the selected account and,
+    /// for a transaction, that it is sent from an account the origin may use
+    /// and fits in what is left of its spending limit
+    pub fn authorize_dapp_request(
+        &self,
+        origin: &str,
+        method: DappMethod,
+        transaction: Option<&Transaction>,
+    ) -> Result<DappPermission> {
+        let permission = self
+            .settings
+            .dapps
+            .get(origin)
+            .ok_or_else(|| anyhow::anyhow!("{} is not connected", origin))?;
+        if permission.is_expired(Utc::now()) {
+            return Err(anyhow::anyhow!("The permission of {} has expired", origin));
+        }
+        if !permission.methods.contains(&method) {
+            return Err(anyhow::anyhow!("{} may not call {}", origin, method));
+        }
+
+        if method != DappMethod::Accounts {
+            let selected = self
+                .get_selected_account()?
+                .ok_or_else(|| anyhow::anyhow!("No account selected"))?;
+            if !permission.allows_account(&selected.address) {
+                return Err(anyhow::anyhow!(
+                    "The selected account is not shared with {}",
+                    origin
+                ));
+            }
+        }
+        if let Some(transaction) = transaction {
+            if !permission.allows_account(&transaction.from) {
+                return Err(anyhow::anyhow!(
+                    "{} may not send from {}",
+                    origin,
+                    transaction.from
+                ));
+            }
+            if let Some(remaining) = permission.remaining()
+                && transaction.total_cost() > remaining
+            {
+                return Err(anyhow::anyhow!(
+                    "The transaction can cost {} but {} may only spend {} more",
+                    transaction.total_cost(),
+                    origin,
+                    remaining
+                ));
+            }
+        }
+        Ok(permission.clone())
+    }
+
+    /// Count `amount` against the spending limit of `origin`
+    pub fn record_dapp_spending(&mut self, origin: &str, amount: u64) -> Result<()> {
+        self.update_dapps(|dapps| {
+            let permission = dapps
+                .get_mut(origin)
+                .ok_or_else(|| anyhow::anyhow!("{} is not connected", origin))?;
+            permission.spent = permission.spent.saturating_add(amount);
+            Ok(())
+        })
+    }
+
     pub fn create_wallet(&mut self, wallet_name: &str) -> Result<CreateWalletResponse> {
+        let wallet_name = WalletName::new(wallet_name)?;
+        self.ensure_wallet_absent(&wallet_name)?;
+
         // Generate new mnemonic
         let mnemonic = CryptoManager::generate_mnemonic()?;
 
@@ -40,8 +289,13 @@ impl WalletManager {
 
         // Encrypt private key
         let salt: [u8; 32] = rand::thread_rng().r#gen();
-        let encrypted_private_key =
-            CryptoManager::encrypt_data(&private_key.secret_bytes(), "default_password", &salt)?;
+        let kdf_iterations = self.settings.wallet.kdf_iterations;
+        let encrypted_private_key = CryptoManager::encrypt_data(
+            &private_key.secret_bytes(),
+            "default_password",
+            &salt,
+            kdf_iterations,
+        )?;
 
         // Create account
         let account = Account::new(
@@ -49,6 +303,7 @@ impl WalletManager {
             hex::encode(public_key.serialize_uncompressed()),
             encrypted_private_key,
             salt.to_vec(),
+            kdf_iterations,
         );
 
         wallet_data.add_account(account);
@@ -57,10 +312,10 @@ impl WalletManager {
             .map_err(|e| anyhow::anyhow!(e))?;
 
         // Save wallet
-        self.storage.save_wallet(wallet_name, &wallet_data)?;
+        self.storage.create_wallet(&wallet_name, &wallet_data)?;
 
         // Update current state
-        self.current_wallet = Some(wallet_name.to_string());
+        self.current_wallet = Some(wallet_name);
         self.wallet_data = Some(wallet_data);
 
         Ok(CreateWalletResponse {
@@ -77,6 +332,9 @@ impl WalletManager {
         wallet_name: &str,
         mnemonic: &str,
     ) -> Result<ImportWalletResponse> {
+        let wallet_name = WalletName::new(wallet_name)?;
+        self.ensure_wallet_absent(&wallet_name)?;
+
         // Derive first account from mnemonic
         let private_key = CryptoManager::derive_private_key(mnemonic, "m/44'/{COIN_TYPE}'/0'/0/0")?;
         let public_key = CryptoManager::get_public_key(&private_key);
@@ -84,8 +342,13 @@ impl WalletManager {
 
         // Encrypt private key
         let salt: [u8; 32] = rand::thread_rng().r#gen();
-        let encrypted_private_key =
-            CryptoManager::encrypt_data(&private_key.secret_bytes(), "default_password", &salt)?;
+        let kdf_iterations = self.settings.wallet.kdf_iterations;
+        let encrypted_private_key = CryptoManager::encrypt_data(
+            &private_key.secret_bytes(),
+            "default_password",
+            &salt,
+            kdf_iterations,
+        )?;
 
         // Create wallet data
         let mut wallet_data = WalletData::new();
@@ -94,6 +357,7 @@ impl WalletManager {
             hex::encode(public_key.serialize_uncompressed()),
             encrypted_private_key,
             salt.to_vec(),
+            kdf_iterations,
         );
 
         wallet_data.add_account(account);
@@ -102,10 +366,10 @@ impl WalletManager {
             .map_err(|e| anyhow::anyhow!(e))?;
 
         // Save wallet
-        self.storage.save_wallet(wallet_name, &wallet_data)?;
+        self.storage.create_wallet(&wallet_name, &wallet_data)?;
 
         // Update current state
-        self.current_wallet = Some(wallet_name.to_string());
+        self.current_wallet = Some(wallet_name);
         self.wallet_data = Some(wallet_data);
 
         Ok(ImportWalletResponse {
@@ -118,8 +382,9 @@ impl WalletManager {
     }
 
     pub fn load_wallet(&mut self, wallet_name: &str) -> Result<bool> {
-        if let Some(wallet_data) = self.storage.load_wallet(wallet_name)? {
-            self.current_wallet = Some(wallet_name.to_string());
+        let wallet_name = WalletName::new(wallet_name)?;
+        if let Some(wallet_data) = self.storage.load_wallet(&wallet_name)? {
+            self.current_wallet = Some(wallet_name);
             self.wallet_data = Some(wallet_data);
             Ok(true)
         } else {
@@ -142,8 +407,13 @@ impl WalletManager {
 
         // Encrypt private key
         let salt: [u8; 32] = rand::thread_rng().r#gen();
-        let encrypted_private_key =
-            CryptoManager::encrypt_data(&private_key.secret_bytes(), "default_password", &salt)?;
+        let kdf_iterations = self.settings.wallet.kdf_iterations;
+        let encrypted_private_key = CryptoManager::encrypt_data(
+            &private_key.secret_bytes(),
+            "default_password",
+            &salt,
+            kdf_iterations,
+        )?;
 
         // Create account
         let account = Account::new(
@@ -151,6 +421,7 @@ impl WalletManager {
             hex::encode(public_key.serialize_uncompressed()),
             encrypted_private_key,
             salt.to_vec(),
+            kdf_iterations,
         );
 
         wallet_data.add_account(account);
@@ -160,12 +431,48 @@ impl WalletManager {
             self.storage.save_wallet(wallet_name, wallet_data)?;
         }
 
-        Ok(AccountInfo {
+        Ok(wallet_data.accounts[account_index].info(account_index))
+    }
+
+    /// Add the account of an external signer, connected beforehand with
+    /// `ExternalSigner::connect`
+    #[cfg(unix)]
+    pub fn add_external_account(&mut self, signer: &ExternalSigner) -> Result<AccountInfo> {
+        let wallet_data = self
+            .wallet_data
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
+
+        let public_key = signer.public_key()?;
+        let address = CryptoManager::generate_address(&public_key);
+        if wallet_data
+            .accounts
+            .iter()
+            .any(|account| account.address == address)
+        {
+            return Err(anyhow::anyhow!(
+                "Account {} is already in the wallet",
+                address
+            ));
+        }
+
+        let account_index = wallet_data.accounts.len();
+        let mut account = Account::new(
             address,
-            public_key: hex::encode(public_key.serialize_uncompressed()),
-            is_selected: false,
-            created_at: chrono::Utc::now(),
-        })
+            hex::encode(public_key.serialize_uncompressed()),
+            Vec::new(),
+            Vec::new(),
+            0,
+        );
+        account.external = Some(signer.account().clone());
+        wallet_data.add_account(account);
+
+        // Save wallet
+        if let Some(wallet_name) = &self.current_wallet {
+            self.storage.save_wallet(wallet_name, wallet_data)?;
+        }
+
+        Ok(wallet_data.accounts[account_index].info(account_index))
     }
 
     pub fn create_account(&mut self) -> Result<AccountInfo> {
@@ -186,8 +493,13 @@ impl WalletManager {
 
         // Encrypt private key
         let salt: [u8; 32] = rand::thread_rng().r#gen();
-        let encrypted_private_key =
-            CryptoManager::encrypt_data(&private_key.secret_bytes(), "default_password", &salt)?;
+        let kdf_iterations = self.settings.wallet.kdf_iterations;
+        let encrypted_private_key = CryptoManager::encrypt_data(
+            &private_key.secret_bytes(),
+            "default_password",
+            &salt,
+            kdf_iterations,
+        )?;
 
         // Create account
         let account = Account::new(
@@ -195,6 +507,7 @@ impl WalletManager {
             hex::encode(public_key.serialize_uncompressed()),
             encrypted_private_key,
             salt.to_vec(),
+            kdf_iterations,
         );
 
         wallet_data.add_account(account);
@@ -204,31 +517,49 @@ impl WalletManager {
             self.storage.save_wallet(wallet_name, wallet_data)?;
         }
 
-        Ok(AccountInfo {
-            address,
-            public_key: hex::encode(public_key.serialize_uncompressed()),
-            is_selected: false,
-            created_at: chrono::Utc::now(),
-        })
+        Ok(wallet_data.accounts[account_index].info(account_index))
     }
 
-    pub fn list_accounts(&self) -> Result<Vec<AccountInfo>> {
+    pub fn list_accounts(&self, include_hidden: bool) -> Result<Vec<AccountInfo>> {
         let wallet_data = self
             .wallet_data
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
 
-        let mut accounts = Vec::new();
-        for account in &wallet_data.accounts {
-            accounts.push(AccountInfo {
-                address: account.address.clone(),
-                public_key: account.public_key.clone(),
-                is_selected: account.is_selected,
-                created_at: account.created_at,
-            });
+        Ok(wallet_data
+            .display_order()
+            .into_iter()
+            .map(|index| wallet_data.accounts[index].info(index))
+            .filter(|account| include_hidden || account.metadata.is_listed())
+            .collect())
+    }
+
+    pub fn update_account_metadata(
+        &mut self,
+        index: usize,
+        mut metadata: AccountMetadata,
+    ) -> Result<AccountInfo> {
+        let wallet_data = self
+            .wallet_data
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
+        let account = wallet_data
+            .accounts
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("Account index out of bounds"))?;
+
+        metadata.normalize();
+        metadata.validate()?;
+        account.metadata = metadata;
+        let info = account.info(index);
+        wallet_data.last_modified = chrono::Utc::now();
+
+        // Save wallet
+        if let Some(wallet_name) = &self.current_wallet {
+            self.storage.save_wallet(wallet_name, wallet_data)?;
         }
 
-        Ok(accounts)
+        Ok(info)
     }
 
     pub fn select_account(&mut self, index: usize) -> Result<()> {
@@ -255,19 +586,13 @@ impl WalletManager {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
 
-        if let Some(account) = wallet_data.get_selected_account() {
-            Ok(Some(AccountInfo {
-                address: account.address.clone(),
-                public_key: account.public_key.clone(),
-                is_selected: account.is_selected,
-                created_at: account.created_at,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(wallet_data
+            .selected_account_index
+            .zip(wallet_data.get_selected_account())
+            .map(|(index, account)| account.info(index)))
     }
 
-    pub fn sign_transaction(&self, data: &str) -> Result<SignTransactionResponse> {
+    pub fn get_x_only_public_key(&self) -> Result<String> {
         let wallet_data = self
             .wallet_data
             .as_ref()
@@ -277,25 +602,315 @@ impl WalletManager {
             .get_selected_account()
             .ok_or_else(|| anyhow::anyhow!("No account selected"))?;
 
-        // Decrypt private key
-        let private_key_bytes = CryptoManager::decrypt_data(
-            &selected_account.private_key_encrypted,
-            "default_password",
-            &selected_account.salt,
-        )?;
+        let public_key =
+            secp256k1::PublicKey::from_slice(&hex::decode(&selected_account.public_key)?)?;
+        Ok(CryptoManager::get_x_only_public_key_hex(&public_key))
+    }
 
-        let private_key = secp256k1::SecretKey::from_slice(&private_key_bytes)?;
+    pub fn preview_typed_data(&self, typed_data: &str) -> Result<TypedDataPreview> {
+        let typed_data = TypedData::from_json(typed_data)?;
+        let mut preview = typed_data.preview()?;
 
-        // Decode hex data
-        let data_bytes = hex::decode(data.trim_start_matches("0x"))?;
+        preview.signer = self
+            .wallet_data
+            .as_ref()
+            .and_then(|wallet_data| wallet_data.get_selected_account())
+            .map(|account| account.address.clone());
 
-        // Sign data
-        let signature = CryptoManager::sign_data(&private_key, &data_bytes)?;
+        Ok(preview)
+    }
 
-        Ok(SignTransactionResponse {
-            success: true,
-            signature: hex::encode(signature),
-            message: "Transaction signed successfully".to_string(),
+    /// Parse typed data for the selected account's signer to sign, checking
+    /// a domain bound to a chain is bound to the active network's
+    pub fn parse_typed_data(&self, typed_data: &str) -> Result<TypedData> {
+        let typed_data = TypedData::from_json(typed_data)?;
+        if let Some(chain_id) = typed_data.domain.chain_id {
+            self.active_settings().check_chain_id(chain_id)?;
+        }
+        Ok(typed_data)
+    }
+
+    pub fn create_multisig_account(
+        &mut self,
+        name: &str,
+        threshold: usize,
+        participants: &[String],
+    ) -> Result<MultisigAccount> {
+        let wallet_data = self
+            .wallet_data
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
+
+        let multisig = MultisigAccount::new(name, threshold, participants)?;
+        if wallet_data
+            .multisig_accounts
+            .iter()
+            .any(|m| m.name == multisig.name || m.address == multisig.address)
+        {
+            return Err(anyhow::anyhow!("Multisig account already exists"));
+        }
+
+        wallet_data.multisig_accounts.push(multisig.clone());
+        wallet_data.last_modified = chrono::Utc::now();
+
+        // Save wallet
+        if let Some(wallet_name) = &self.current_wallet {
+            self.storage.save_wallet(wallet_name, wallet_data)?;
+        }
+
+        Ok(multisig)
+    }
+
+    pub fn list_multisig_accounts(&self) -> Result<Vec<MultisigAccount>> {
+        let wallet_data = self
+            .wallet_data
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
+
+        Ok(wallet_data.multisig_accounts.clone())
+    }
+
+    pub fn create_partial_transaction(
+        &self,
+        mut transaction: Transaction,
+        multisig: Option<&str>,
+        format: PstFormat,
+    ) -> Result<String> {
+        self.active_settings()
+            .check_chain_id(transaction.chain_id)?;
+        if let Some(to) = &transaction.to {
+            transaction.to = Some(self.resolve_recipient(to)?);
+        }
+
+        let pst = match multisig {
+            Some(multisig) => {
+                let multisig = self
+                    .list_multisig_accounts()?
+                    .into_iter()
+                    .find(|m| m.name == multisig || m.address == multisig.trim_start_matches("0x"))
+                    .ok_or_else(|| anyhow::anyhow!("Multisig account not found: {}", multisig))?;
+                PartiallySignedTransaction::for_multisig(transaction, &multisig)?
+            }
+            None => {
+                let signer = self.selected_signer()?;
+                PartiallySignedTransaction::new(
+                    transaction,
+                    vec![FixedBytes(signer.public_key()?.serialize())],
+                    1,
+                )?
+            }
+        };
+        self.record_history(TxRecord::from_partial(&pst));
+        pst.to_text(format)
+    }
+
+    pub fn inspect_partial_transaction(&self, pst: &str) -> Result<PstInspection> {
+        Ok(PartiallySignedTransaction::from_text(pst)?.inspect())
+    }
+
+    /// Parse a partial transaction for the selected account's signer to sign,
+    /// checking it is for the active network
+    pub fn open_partial_transaction(&self, pst: &str) -> Result<PartiallySignedTransaction> {
+        let pst = PartiallySignedTransaction::from_text(pst)?;
+        self.active_settings()
+            .check_chain_id(pst.transaction.chain_id)?;
+        Ok(pst)
+    }
+
+    /// Record a partial transaction signed outside the manager in the history
+    /// and encode it in `format`
+    pub fn record_partial_transaction(
+        &self,
+        pst: &PartiallySignedTransaction,
+        format: PstFormat,
+    ) -> Result<String> {
+        self.record_history(TxRecord::from_partial(pst));
+        pst.to_text(format)
+    }
+
+    pub fn combine_partial_transactions(
+        &self,
+        psts: &[String],
+        format: PstFormat,
+    ) -> Result<String> {
+        let (first, rest) = psts
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("No transactions to combine"))?;
+
+        let mut combined = PartiallySignedTransaction::from_text(first)?;
+        for pst in rest {
+            combined.combine(&PartiallySignedTransaction::from_text(pst)?)?;
+        }
+        self.record_history(TxRecord::from_partial(&combined));
+        combined.to_text(format)
+    }
+
+    pub fn finalize_partial_transaction(&self, pst: &str) -> Result<FinalizedTransaction> {
+        let signed = PartiallySignedTransaction::from_text(pst)?.finalize()?;
+        self.record_history(TxRecord::from_signed(&signed)?);
+        signed.finalized()
+    }
+
+    /// Refuse to submit a finalized transaction signed for another network
+    pub fn check_submission(&self, signed_transaction: &str) -> Result<()> {
+        let raw = hex::decode(signed_transaction.trim_start_matches("0x"))?;
+        let signed = SignedTransaction::from_bytes(&raw)?;
+        self.active_settings()
+            .check_chain_id(signed.transaction.chain_id)
+    }
+
+    /// Record a finalized transaction as sent to the node
+    pub fn record_submitted(&self, signed_transaction: &str) -> Result<()> {
+        let raw = hex::decode(signed_transaction.trim_start_matches("0x"))?;
+        let mut record = TxRecord::from_signed(&SignedTransaction::from_bytes(&raw)?)?;
+        record.status = TxStatus::Submitted;
+        self.record_history(record);
+        Ok(())
+    }
+
+    pub fn list_transactions(
+        &self,
+        mut filter: HistoryFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Result<HistoryPage> {
+        if let Some(account) = &filter.account {
+            filter.account = Some(CryptoManager::parse_address(account)?);
+        }
+        if let Some(counterparty) = &filter.counterparty {
+            filter.counterparty = Some(self.resolve_recipient(counterparty)?);
+        }
+        Ok(History::load(&self.history_path())?.query(&filter, offset, limit))
+    }
+
+    /// Hashes of finalized transactions the node hasn't settled yet
+    pub fn unsettled_transactions(&self) -> Result<Vec<String>> {
+        Ok(History::load(&self.history_path())?.unsettled())
+    }
+
+    /// Apply the statuses reported by the node, returning how many transactions changed
+    pub fn settle_transactions(&self, statuses: &[(String, TxStatus)]) -> Result<usize> {
+        let path = self.history_path();
+        let mut history = History::load(&path)?;
+        let updated = statuses
+            .iter()
+            .filter(|(hash, status)| history.settle(hash, *status))
+            .count();
+        if updated > 0 {
+            history.save(&path)?;
+        }
+        Ok(updated)
+    }
+
+    /// Remove a transaction that was never submitted, such as an abandoned draft
+    pub fn drop_transaction(&self, id_or_hash: &str) -> Result<TxRecord> {
+        let path = self.history_path();
+        let mut history = History::load(&path)?;
+        let record = history.remove(id_or_hash)?;
+        history.save(&path)?;
+        Ok(record)
+    }
+
+    /// Address of `account`, or of the selected account, for nonce lookups
+    pub fn nonce_account(&self, account: Option<&str>) -> Result<String> {
+        match account {
+            Some(account) => CryptoManager::parse_address(account),
+            None => self
+                .get_selected_account()?
+                .map(|account| account.address)
+                .ok_or_else(|| anyhow::anyhow!("No account selected")),
+        }
+    }
+
+    pub fn transaction_history(&self) -> Result<History> {
+        History::load(&self.history_path())
+    }
+
+    /// Transaction replacing the unsettled transaction `id_or_hash` with the same nonce:
+    /// the same transaction with a higher gas price, or a cancel sending nothing to itself
+    pub fn build_replacement(
+        &self,
+        id_or_hash: &str,
+        cancel: bool,
+        gas_price: Option<u64>,
+    ) -> Result<Transaction> {
+        let history = self.transaction_history()?;
+        let original = history
+            .get(id_or_hash)
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found in history: {}", id_or_hash))?;
+        self.active_settings().check_chain_id(original.chain_id)?;
+        nonce::build_replacement(original, cancel, gas_price)
+    }
+
+    fn history_path(&self) -> PathBuf {
+        History::path(self.storage.data_dir())
+    }
+
+    /// Record a transaction in the history, warning instead of failing the command
+    fn record_history(&self, record: TxRecord) {
+        let path = self.history_path();
+        let recorded = History::load(&path).and_then(|mut history| {
+            history.record(record);
+            history.save(&path)
+        });
+        if let Err(e) = recorded {
+            tracing::warn!("Failed to record transaction history: {}", e);
+        }
+    }
+
+    pub fn export_qr_frames(&self, pst: &str, chunk_size: usize) -> Result<Vec<QrFrame>> {
+        let pst = PartiallySignedTransaction::from_text(pst)?;
+        qr::encode_frames(&pst.to_bytes(PstFormat::Binary)?, chunk_size)
+    }
+
+    pub fn import_qr_parts(&self, parts: &[String], format: PstFormat) -> Result<QrImportResult> {
+        let scan = qr::decode_parts(parts)?;
+        let pst = match &scan.payload {
+            Some(payload) => {
+                Some(PartiallySignedTransaction::from_bytes(payload)?.to_text(format)?)
+            }
+            None => None,
+        };
+
+        Ok(QrImportResult {
+            received: scan.received,
+            total: scan.total,
+            complete: pst.is_some(),
+            missing: scan.missing,
+            pst,
+        })
+    }
+
+    /// Signer of the selected account: its external signer if it has one,
+    /// else its key decrypted from the wallet. The external signer isn't
+    /// reached here, so callers can release the wallet before signing.
+    pub fn selected_signer(&self) -> Result<AccountSigner> {
+        let wallet_data = self
+            .wallet_data
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?;
+
+        let selected_account = wallet_data
+            .get_selected_account()
+            .ok_or_else(|| anyhow::anyhow!("No account selected"))?;
+
+        let signer: Box<dyn Signer> = match &selected_account.external {
+            #[cfg(unix)]
+            Some(external) => Box::new(ExternalSigner::new(
+                external.clone(),
+                secp256k1::PublicKey::from_slice(&hex::decode(&selected_account.public_key)?)?,
+            )),
+            #[cfg(not(unix))]
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "External signers are only supported on Unix"
+                ));
+            }
+            None => Box::new(LocalSigner::unlock(selected_account, "default_password")?),
+        };
+        Ok(AccountSigner {
+            address: selected_account.address.clone(),
+            signer,
         })
     }
 
@@ -303,6 +918,203 @@ impl WalletManager {
         self.storage.list_wallets()
     }
 
+    /// Write an encrypted backup archive of the named wallets (the current one
+    /// when none are named), the address book and optionally the settings
+    pub fn create_backup(
+        &self,
+        path: &str,
+        password: &str,
+        wallet_names: &[String],
+        include_settings: bool,
+    ) -> Result<BackupPreview> {
+        if password.is_empty() {
+            return Err(anyhow::anyhow!("Backup password cannot be empty"));
+        }
+        let wallets = if wallet_names.is_empty() {
+            vec![
+                self.current_wallet
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("No wallet loaded"))?,
+            ]
+        } else {
+            wallet_names
+                .iter()
+                .map(|name| WalletName::new(name))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let config_path = self.config_path.clone();
+        let contents = BackupContents::collect(
+            self.storage.as_ref(),
+            &wallets,
+            include_settings.then_some(config_path.as_path()),
+        )?;
+        archive::write_archive(
+            Path::new(path),
+            &contents,
+            password,
+            self.settings.wallet.kdf_iterations,
+        )?;
+        contents.preview(self.storage.as_ref())
+    }
+
+    /// Decrypt a backup archive and describe what restoring it would do
+    pub fn preview_backup(&self, path: &str, password: &str) -> Result<BackupPreview> {
+        archive::read_archive(Path::new(path), password)?.preview(self.storage.as_ref())
+    }
+
+    /// Restore a backup archive, merging into existing wallets unless `replace` is set
+    pub fn restore_backup(
+        &mut self,
+        path: &str,
+        password: &str,
+        replace: bool,
+    ) -> Result<RestoreReport> {
+        let contents = archive::read_archive(Path::new(path), password)?;
+        let mode = if replace {
+            RestoreMode::Replace
+        } else {
+            RestoreMode::Merge
+        };
+        let report = contents.restore(self.storage.as_ref(), &self.config_path, mode)?;
+
+        // Pick up changes to the loaded wallet and the settings
+        if let Some(wallet_name) = &self.current_wallet {
+            self.wallet_data = self.storage.load_wallet(wallet_name)?;
+        }
+        if report.settings_restored {
+            self.reload_settings()?;
+        }
+        Ok(report)
+    }
+
+    /// Copy every wallet to the `to` storage backend in the same wallet
+    /// directory. The wallets stay where they are, and the app keeps using the
+    /// configured backend until the setting is changed.
+    pub fn migrate_storage(&self, to: StorageBackend) -> Result<MigrationReport> {
+        if to == self.settings.wallet.backend {
+            return Err(anyhow::anyhow!("Wallets are already stored with {}", to));
+        }
+        let target = storage::open_store(to, self.settings.wallet_dir())?;
+        storage::migrate_wallets(self.storage.as_ref(), target.as_ref())
+    }
+
+    /// Refuse to create or import over an existing wallet before deriving any
+    /// keys. `WalletStore::create_wallet` checks again as it writes.
+    fn ensure_wallet_absent(&self, wallet_name: &WalletName) -> Result<()> {
+        if self.storage.wallet_exists(wallet_name) {
+            return Err(anyhow::anyhow!("Wallet already exists: {}", wallet_name));
+        }
+        Ok(())
+    }
+
+    /// Delete a wallet after checking its password, moving the file to the
+    /// trash folder rather than removing it. Returns the trashed file's path.
+    pub fn delete_wallet(&mut self, wallet_name: &str, password: &str) -> Result<String> {
+        let wallet_name = WalletName::new(wallet_name)?;
+        let wallet_data = self
+            .storage
+            .load_wallet(&wallet_name)?
+            .ok_or_else(|| anyhow::anyhow!("Wallet not found: {}", wallet_name))?;
+        Self::verify_password(&wallet_data, password)?;
+
+        let trash_path = self.storage.trash_wallet(&wallet_name)?;
+        if self.current_wallet.as_ref() == Some(&wallet_name) {
+            self.current_wallet = None;
+            self.wallet_data = None;
+        }
+        Ok(trash_path.to_string_lossy().to_string())
+    }
+
+    pub fn rename_wallet(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let old_name = WalletName::new(old_name)?;
+        let new_name = WalletName::new(new_name)?;
+
+        self.storage.rename_wallet(&old_name, &new_name)?;
+        if self.current_wallet.as_ref() == Some(&old_name) {
+            self.current_wallet = Some(new_name);
+        }
+        Ok(())
+    }
+
+    /// Check a password against the first account. Decryption itself can't
+    /// detect a wrong password, so the decrypted key's public key is compared
+    /// with the stored one.
+    fn verify_password(wallet_data: &WalletData, password: &str) -> Result<()> {
+        // Accounts on external signers have no key in the file to check
+        let Some(account) = wallet_data
+            .accounts
+            .iter()
+            .find(|account| account.external.is_none())
+        else {
+            return Ok(());
+        };
+
+        let private_key_bytes = CryptoManager::decrypt_data(
+            &account.private_key_encrypted,
+            password,
+            &account.salt,
+            account.kdf_iterations,
+        )?;
+        let matches = secp256k1::SecretKey::from_slice(&private_key_bytes).is_ok_and(|key| {
+            hex::encode(CryptoManager::get_public_key(&key).serialize_uncompressed())
+                == account.public_key
+        });
+        if !matches {
+            return Err(anyhow::anyhow!("Incorrect password"));
+        }
+        Ok(())
+    }
+
+    /// Contacts on the current network, or on every network with `all`
+    pub fn list_contacts(&self, all: bool) -> Result<Vec<Contact>> {
+        let book = AddressBook::load(&self.storage.address_book_path())?;
+        Ok(book.list((!all).then_some(self.network())))
+    }
+
+    pub fn add_contact(
+        &mut self,
+        name: &str,
+        address: &str,
+        memo: Option<String>,
+        network: Option<Network>,
+    ) -> Result<Contact> {
+        let path = self.storage.address_book_path();
+        let mut book = AddressBook::load(&path)?;
+        let contact = Contact::new(name, address, memo, network.unwrap_or(self.network()))?;
+        book.add(contact.clone())?;
+        book.save(&path)?;
+        Ok(contact)
+    }
+
+    /// Replace the contact called `name` (validating the new name and address)
+    pub fn update_contact(&mut self, name: &str, contact: Contact) -> Result<Contact> {
+        let path = self.storage.address_book_path();
+        let mut book = AddressBook::load(&path)?;
+        let contact = Contact::new(
+            &contact.name,
+            &contact.address,
+            contact.memo,
+            contact.network,
+        )?;
+        book.update(name, contact.clone())?;
+        book.save(&path)?;
+        Ok(contact)
+    }
+
+    pub fn remove_contact(&mut self, name: &str) -> Result<Contact> {
+        let path = self.storage.address_book_path();
+        let mut book = AddressBook::load(&path)?;
+        let contact = book.remove(name)?;
+        book.save(&path)?;
+        Ok(contact)
+    }
+
+    /// Turn a recipient (address or contact name) into an address on the current network
+    pub fn resolve_recipient(&self, recipient: &str) -> Result<String> {
+        AddressBook::load(&self.storage.address_book_path())?.resolve(recipient, self.network())
+    }
+
     pub fn get_wallet_info(&self) -> Result<Option<crate::types::WalletInfo>> {
         let wallet_data = self
             .wallet_data
@@ -319,3 +1131,331 @@ impl WalletManager {
         }))
     }
 }
+
+/// Key of the selected account, taken out of the manager so signing, which
+/// can wait on a device, runs without the wallet locked
+pub struct AccountSigner {
+    pub address: String,
+    signer: Box<dyn Signer>,
+}
+
+impl AccountSigner {
+    pub fn public_key(&self) -> Result<secp256k1::PublicKey> {
+        self.signer.public_key()
+    }
+
+    pub fn sign_transaction(
+        &self,
+        data: &str,
+        scheme: SignatureScheme,
+    ) -> Result<SignTransactionResponse> {
+        // Decode hex data
+        let data_bytes = hex::decode(data.trim_start_matches("0x"))?;
+
+        // Sign the hash of the data
+        let hash: [u8; 32] = Sha256::digest(&data_bytes).into();
+        let signature = self.signer.sign_hash(&hash, scheme)?;
+
+        Ok(SignTransactionResponse {
+            success: true,
+            signature: hex::encode(signing::encode_signature(&signature, scheme)?),
+            scheme,
+            message: "Transaction signed successfully".to_string(),
+        })
+    }
+
+    pub fn sign_typed_data(
+        &self,
+        typed_data: &TypedData,
+        scheme: SignatureScheme,
+    ) -> Result<SignTypedDataResponse> {
+        let signing_hash = typed_data.signing_hash()?;
+        let signature = self.signer.sign_hash(&signing_hash, scheme)?;
+
+        Ok(SignTypedDataResponse {
+            success: true,
+            signature: hex::encode(signing::encode_signature(&signature, scheme)?),
+            signing_hash: hex::encode(signing_hash),
+            scheme,
+            address: self.address.clone(),
+            message: "Typed data signed successfully".to_string(),
+        })
+    }
+
+    /// Sign a text message, hashed with `signing::message_hash`
+    pub fn sign_message(
+        &self,
+        message: &str,
+        scheme: SignatureScheme,
+    ) -> Result<SignMessageResponse> {
+        let signature = self.signer.sign_message(message, scheme)?;
+        Ok(SignMessageResponse {
+            signature: hex::encode(signing::encode_signature(&signature, scheme)?),
+            signing_hash: hex::encode(signing::message_hash(message)),
+            scheme,
+            address: self.address.clone(),
+        })
+    }
+
+    pub fn sign_partial_transaction(
+        &self,
+        pst: &mut PartiallySignedTransaction,
+        scheme: SignatureScheme,
+    ) -> Result<()> {
+        pst.sign(self.signer.as_ref(), scheme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: &str = "https://dapp.example";
+
+    /// Manager with a wallet loaded, keeping its files and config under a
+    /// fresh directory named after `name`
+    fn manager(name: &str) -> (WalletManager, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("rwmth-wallet-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let mut settings = Settings::default();
+        settings.wallet.dir = Some(dir.join("wallets"));
+        let config_path = dir.join("config.toml");
+        settings.save(&config_path).unwrap();
+        let mut wallet_manager = WalletManager::with_config_path(settings, config_path);
+        wallet_manager.create_wallet("main").unwrap();
+        (wallet_manager, dir)
+    }
+
+    fn transaction(from: &str, amount: u64) -> Transaction {
+        Transaction {
+            chain_id: 1,
+            from: from.to_string(),
+            to: None,
+            amount: Some(amount),
+            data: None,
+            nonce: 0,
+            gas_limit: 10,
+            gas_price: 10,
+        }
+    }
+
+    #[test]
+    fn test_dapp_requests_are_checked_against_the_grant() {
+        let (mut wallet_manager, dir) = manager("dapp-checks");
+        let selected = wallet_manager.get_selected_account().unwrap().unwrap();
+        let other = wallet_manager.create_account().unwrap();
+
+        wallet_manager
+            .grant_dapp_permission(
+                ORIGIN,
+                [DappMethod::SignMessage, DappMethod::SignTransaction].into(),
+                None,
+                Some(1000),
+                None,
+            )
+            .unwrap();
+        let authorize =
+            |wallet_manager: &WalletManager, method, transaction: Option<&Transaction>| {
+                wallet_manager.authorize_dapp_request(ORIGIN, method, transaction)
+            };
+
+        // Only granted methods, and only with the shared account selected
+        assert!(authorize(&wallet_manager, DappMethod::SignMessage, None).is_ok());
+        assert!(authorize(&wallet_manager, DappMethod::Accounts, None).is_err());
+        wallet_manager.select_account(other.index).unwrap();
+        assert!(authorize(&wallet_manager, DappMethod::SignMessage, None).is_err());
+        wallet_manager.select_account(selected.index).unwrap();
+
+        // Transactions from a shared account that fit the limit, fees included
+        let fits = transaction(&selected.address, 900);
+        assert!(authorize(&wallet_manager, DappMethod::SignTransaction, Some(&fits)).is_ok());
+        let over = transaction(&selected.address, 901);
+        assert!(authorize(&wallet_manager, DappMethod::SignTransaction, Some(&over)).is_err());
+        let foreign = transaction(&other.address, 1);
+        assert!(authorize(&wallet_manager, DappMethod::SignTransaction, Some(&foreign)).is_err());
+
+        // What was spent counts against the limit, and survives a new grant
+        wallet_manager.record_dapp_spending(ORIGIN, 600).unwrap();
+        let rest = transaction(&selected.address, 300);
+        assert!(authorize(&wallet_manager, DappMethod::SignTransaction, Some(&rest)).is_ok());
+        assert!(authorize(&wallet_manager, DappMethod::SignTransaction, Some(&fits)).is_err());
+        wallet_manager
+            .grant_dapp_permission(
+                ORIGIN,
+                [DappMethod::Accounts].into(),
+                None,
+                Some(2000),
+                None,
+            )
+            .unwrap();
+        assert_eq!(wallet_manager.dapp_permissions()[ORIGIN].spent, 600);
+        assert!(authorize(&wallet_manager, DappMethod::Accounts, None).is_ok());
+        assert!(authorize(&wallet_manager, DappMethod::SignMessage, None).is_err());
+
+        // Revoked origins can do nothing
+        wallet_manager.revoke_dapp_permission(ORIGIN).unwrap();
+        assert!(authorize(&wallet_manager, DappMethod::Accounts, None).is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_expired_dapp_grants_allow_nothing_and_only_dapps_are_saved() {
+        let (mut wallet_manager, dir) = manager("dapp-expiry");
+        wallet_manager
+            .grant_dapp_permission(
+                ORIGIN,
+                DappMethod::ALL.into_iter().collect(),
+                None,
+                Some(1000),
+                Some(Utc::now() - chrono::Duration::seconds(1)),
+            )
+            .unwrap();
+        let error = wallet_manager
+            .authorize_dapp_request(ORIGIN, DappMethod::Accounts, None)
+            .unwrap_err();
+        assert!(error.to_string().contains("expired"));
+
+        // The grant is in the file, next to the settings that were already there
+        let file = Settings::load_file(dir.join("config.toml")).unwrap();
+        assert_eq!(file.dapps, wallet_manager.dapp_permissions());
+        assert_eq!(file.wallet.dir, Some(dir.join("wallets")));
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_accounts_sign_through_their_device() {
+        use crate::emulator::Emulator;
+
+        let (mut wallet_manager, dir) = manager("external");
+        let emulator = Emulator::start(dir.join("device.sock"), [9; 32]).unwrap();
+        let external = signing::ExternalAccount {
+            socket: emulator.socket().to_path_buf(),
+            key_index: 2,
+        };
+        let account = wallet_manager
+            .add_external_account(&ExternalSigner::connect(&external).unwrap())
+            .unwrap();
+        wallet_manager.select_account(account.index).unwrap();
+
+        // The device is only reached when the signer signs
+        let signer = wallet_manager.selected_signer().unwrap();
+        let signed = signer
+            .sign_message("hello", SignatureScheme::Ecdsa)
+            .unwrap();
+        assert_eq!(signed.address, account.address);
+        let public_key = LocalSigner::new(Emulator::key(&[9; 32], 2))
+            .public_key()
+            .unwrap();
+        assert!(
+            CryptoManager::verify_hash(
+                &public_key,
+                &signing::message_hash("hello"),
+                &hex::decode(signed.signature).unwrap(),
+                SignatureScheme::Ecdsa
+            )
+            .unwrap()
+        );
+
+        // A device that now holds other keys can't sign for the account
+        drop(emulator);
+        let emulator = Emulator::start(dir.join("device.sock"), [10; 32]).unwrap();
+        let error = signer
+            .sign_message("hello", SignatureScheme::Ecdsa)
+            .unwrap_err();
+        assert!(error.to_string().contains("key of the account"));
+        drop(emulator);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_typed_data_for_another_chain_is_refused() {
+        let (wallet_manager, dir) = manager("typed-data");
+        let typed_data = |chain_id: Option<u64>| {
+            serde_json::json!({
+                "types": { "Mail": [{ "name": "contents", "type": "string" }] },
+                "primaryType": "Mail",
+                "domain": { "name": "Mail", "version": "1", "chainId": chain_id },
+                "message": { "contents": "hello" },
+            })
+            .to_string()
+        };
+
+        let chain_id = wallet_manager.active_settings().network().chain_id;
+        assert!(wallet_manager.parse_typed_data(&typed_data(None)).is_ok());
+        assert!(
+            wallet_manager
+                .parse_typed_data(&typed_data(Some(chain_id)))
+                .is_ok()
+        );
+        let error = wallet_manager
+            .parse_typed_data(&typed_data(Some(chain_id + 1)))
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains(&format!("chain {}", chain_id + 1))
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_wallets_are_deleted_to_the_trash_and_never_overwritten() {
+        let (mut wallet_manager, dir) = manager("delete-rename");
+        let name = |name: &str| WalletName::new(name).unwrap();
+        let mnemonic = CryptoManager::generate_mnemonic().unwrap();
+        for error in [
+            wallet_manager.create_wallet("main").unwrap_err(),
+            wallet_manager.import_wallet("main", &mnemonic).unwrap_err(),
+        ] {
+            assert_eq!(error.to_string(), "Wallet already exists: main");
+        }
+
+        wallet_manager.import_wallet("spare", &mnemonic).unwrap();
+        let error = wallet_manager.rename_wallet("main", "spare").unwrap_err();
+        assert_eq!(error.to_string(), "Wallet already exists: spare");
+        wallet_manager.rename_wallet("spare", "imported").unwrap();
+        assert_eq!(wallet_manager.current_wallet, Some(name("imported")));
+
+        // The password is checked before anything moves
+        let error = wallet_manager
+            .delete_wallet("main", "wrong password")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Incorrect password");
+        assert!(wallet_manager.storage.wallet_exists(&name("main")));
+
+        let trashed = wallet_manager
+            .delete_wallet("imported", "default_password")
+            .unwrap();
+        assert!(Path::new(&trashed).starts_with(dir.join("wallets").join("trash")));
+        assert!(!wallet_manager.storage.wallet_exists(&name("imported")));
+        assert!(wallet_manager.get_wallet_info().is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_blank_labels_and_notes_clear_them() {
+        let (mut wallet_manager, dir) = manager("metadata");
+        let metadata = |label: &str, notes: &str| AccountMetadata {
+            label: Some(label.to_string()),
+            notes: Some(notes.to_string()),
+            ..AccountMetadata::default()
+        };
+
+        let info = wallet_manager
+            .update_account_metadata(0, metadata(" Savings ", "Paper backup"))
+            .unwrap();
+        assert_eq!(info.metadata.label.as_deref(), Some("Savings"));
+        let info = wallet_manager
+            .update_account_metadata(0, metadata("  ", ""))
+            .unwrap();
+        assert_eq!(info.metadata, AccountMetadata::default());
+        assert!(
+            wallet_manager
+                .update_account_metadata(0, metadata(&"a".repeat(65), ""))
+                .is_err()
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
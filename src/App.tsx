@@ -5,7 +5,8 @@ import Sidebar from "./components/Sidebar";
 import MainContent from "./components/MainContent";
 import TopMenuBar from "./components/TopMenuBar";
 import ErrorBoundary from "./components/ErrorBoundary";
-import { MenuItem, AccountInfo, WalletInfo, CreateWalletResponse, ImportWalletResponse, SignTransactionResponse } from "./types";
+import SignerPrompt from "./components/SignerPrompt";
+import { MenuItem, AccountInfo, AccountMetadata, WalletInfo, CreateWalletResponse, ImportWalletResponse, SignTransactionResponse } from "./types";
 import { ThemeProvider } from "./contexts/ThemeContext";
 
 function App() {
@@ -114,6 +115,10 @@ function App() {
         setCurrentWallet(walletName);
         console.log('Wallet loaded, loading accounts...');
         await loadAccounts();
+        // Live balances and transaction statuses; the wallet works without them
+        invoke("start_subscriptions").catch((error) =>
+          console.error('Error subscribing to node events:', error)
+        );
         console.log('Accounts loaded, loading wallet info...');
         await loadWalletInfo();
         console.log('Wallet info loaded');
@@ -128,7 +133,41 @@ function App() {
     }
   }
 
+  async function deleteWallet(walletName: string, password: string) {
+    try {
+      setLoading(true);
+      const trashPath = await invoke<string>("delete_wallet", { walletName, password });
+      if (currentWallet === walletName) {
+        returnToWalletSelection();
+      }
+      await loadWallets();
+      setMessage(`Wallet ${walletName} moved to ${trashPath}`);
+    } catch (error) {
+      setMessage(`Error deleting wallet: ${error}`);
+    } finally {
+      setLoading(false);
+    }
+  }
+
+  async function renameWallet(oldName: string, newName: string) {
+    try {
+      setLoading(true);
+      const trimmedName = newName.trim();
+      await invoke("rename_wallet", { oldName, newName: trimmedName });
+      if (currentWallet === oldName) {
+        setCurrentWallet(trimmedName);
+      }
+      await loadWallets();
+      setMessage(`Wallet ${oldName} renamed to ${trimmedName}`);
+    } catch (error) {
+      setMessage(`Error renaming wallet: ${error}`);
+    } finally {
+      setLoading(false);
+    }
+  }
+
   function returnToWalletSelection() {
+    invoke("stop_subscriptions").catch(() => {});
     setCurrentWallet("");
     setAccounts([]);
     setWalletInfo(null);
@@ -140,7 +179,7 @@ function App() {
   async function loadAccounts() {
     try {
       console.log('Loading accounts...');
-      const accountList = await invoke<AccountInfo[]>("list_accounts");
+      const accountList = await invoke<AccountInfo[]>("list_accounts", { includeHidden: true });
       console.log('Accounts loaded:', accountList);
       setAccounts(accountList);
     } catch (error) {
@@ -161,6 +200,16 @@ function App() {
     }
   }
 
+  async function updateAccountMetadata(index: number, metadata: AccountMetadata) {
+    try {
+      await invoke("update_account_metadata", { index, metadata });
+      await loadAccounts();
+      setMessage("Account details saved");
+    } catch (error) {
+      setMessage(`Error saving account details: ${error}`);
+    }
+  }
+
   async function selectAccount(index: number) {
     try {
       await invoke("select_account", { index });
@@ -266,10 +315,13 @@ function App() {
               createWallet={createWallet}
               importWallet={importWallet}
               loadWallet={loadWallet}
+              deleteWallet={deleteWallet}
+              renameWallet={renameWallet}
               returnToWalletSelection={returnToWalletSelection}
               addAccount={addAccount}
               createAccount={createAccount}
               selectAccount={selectAccount}
+              updateAccountMetadata={updateAccountMetadata}
               accounts={accounts}
               walletInfo={walletInfo}
               sendAmount={sendAmount}
@@ -284,6 +336,7 @@ function App() {
               setMenuCollapsed={setMenuCollapsed}
             />
           </div>
+          <SignerPrompt />
         </div>
       </ErrorBoundary>
     </ThemeProvider>
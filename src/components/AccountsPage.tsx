@@ -1,6 +1,7 @@
 import React, { useState } from 'react';
 import WalletSetup from './WalletSetup';
 import WalletOverview from './WalletOverview';
+import { AccountMetadata } from '../types';
 
 interface AccountsPageProps {
   currentWallet: string;
@@ -13,10 +14,13 @@ interface AccountsPageProps {
   createWallet: () => void;
   importWallet: () => void;
   loadWallet: (walletName: string) => void;
+  deleteWallet: (walletName: string, password: string) => void;
+  renameWallet: (oldName: string, newName: string) => void;
   returnToWalletSelection: () => void;
   createAccount: () => void;
   addAccount: () => void;
   selectAccount: (index: number) => void;
+  updateAccountMetadata: (index: number, metadata: AccountMetadata) => void;
   accounts: any[];
 }
 
@@ -31,10 +35,13 @@ const AccountsPage: React.FC<AccountsPageProps> = ({
   createWallet,
   importWallet,
   loadWallet,
+  deleteWallet,
+  renameWallet,
   returnToWalletSelection,
   createAccount,
   addAccount,
   selectAccount,
+  updateAccountMetadata,
   accounts,
 }) => {
   const [showImportForm, setShowImportForm] = useState(false);
@@ -56,6 +63,8 @@ const AccountsPage: React.FC<AccountsPageProps> = ({
           createWallet={createWallet}
           importWallet={importWallet}
           loadWallet={loadWallet}
+          deleteWallet={deleteWallet}
+          renameWallet={renameWallet}
         />
       ) : (
         <div className="accounts-content">
@@ -90,6 +99,7 @@ const AccountsPage: React.FC<AccountsPageProps> = ({
           
           <WalletOverview
             selectAccount={selectAccount}
+            updateAccountMetadata={updateAccountMetadata}
             accounts={accounts}
           />
         </div>
@@ -1,4 +1,6 @@
-import React from 'react';
+import React, { useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { PreviewField, SignTypedDataResponse, TypedDataPreview } from '../types';
 
 interface AdvancedPageProps {
   transactionData: string;
@@ -8,6 +10,24 @@ interface AdvancedPageProps {
   signature: string;
 }
 
+// Nested fields of typed data, one line per member with its declared type
+const PreviewFields: React.FC<{ fields: PreviewField[] }> = ({ fields }) => (
+  <ul className="typed-data-fields">
+    {fields.map((field, index) => (
+      <li key={`${field.name}-${index}`}>
+        <strong>{field.name}</strong> <span className="field-type">{field.field_type}</span>
+        {field.value !== null && (
+          <>
+            {': '}
+            <code>{field.value}</code>
+          </>
+        )}
+        {field.fields.length > 0 && <PreviewFields fields={field.fields} />}
+      </li>
+    ))}
+  </ul>
+);
+
 const AdvancedPage: React.FC<AdvancedPageProps> = ({
   transactionData,
   setTransactionData,
@@ -15,6 +35,52 @@ const AdvancedPage: React.FC<AdvancedPageProps> = ({
   signTransaction,
   signature,
 }) => {
+  const [typedData, setTypedData] = useState('');
+  const [typedDataPreview, setTypedDataPreview] = useState<TypedDataPreview | null>(null);
+  const [typedDataSignature, setTypedDataSignature] = useState<SignTypedDataResponse | null>(null);
+  const [typedDataMessage, setTypedDataMessage] = useState('');
+  const [typedDataBusy, setTypedDataBusy] = useState(false);
+
+  // Any edit drops the preview, so only what was shown can be approved
+  function editTypedData(value: string) {
+    setTypedData(value);
+    setTypedDataPreview(null);
+    setTypedDataSignature(null);
+    setTypedDataMessage('');
+  }
+
+  async function previewTypedData() {
+    setTypedDataBusy(true);
+    setTypedDataSignature(null);
+    setTypedDataMessage('');
+    try {
+      setTypedDataPreview(await invoke<TypedDataPreview>('preview_typed_data', { typedData }));
+    } catch (error) {
+      setTypedDataPreview(null);
+      setTypedDataMessage(`Error previewing typed data: ${error}`);
+    } finally {
+      setTypedDataBusy(false);
+    }
+  }
+
+  async function approveTypedData() {
+    setTypedDataBusy(true);
+    try {
+      const response = await invoke<SignTypedDataResponse>('sign_typed_data', { typedData });
+      setTypedDataSignature(response);
+      setTypedDataMessage(response.message);
+    } catch (error) {
+      setTypedDataMessage(`Error signing typed data: ${error}`);
+    } finally {
+      setTypedDataBusy(false);
+    }
+  }
+
+  function rejectTypedData() {
+    setTypedDataPreview(null);
+    setTypedDataMessage('Signing request rejected');
+  }
+
   return (
     <div className="advanced-page">
       <div className="page-header">
@@ -52,6 +118,68 @@ const AdvancedPage: React.FC<AdvancedPageProps> = ({
             </div>
           )}
         </div>
+
+        <div className="advanced-section">
+          <h3>Typed Data Signing</h3>
+          <div className="form-group">
+            <label>Typed Data (JSON)</label>
+            <textarea
+              placeholder='{"types": {...}, "primaryType": "...", "domain": {...}, "message": {...}}'
+              value={typedData}
+              onChange={(e) => editTypedData(e.target.value)}
+            />
+          </div>
+          {!typedDataPreview && (
+            <button onClick={previewTypedData} disabled={typedDataBusy || !typedData.trim()}>
+              {typedDataBusy ? "Checking..." : "Preview"}
+            </button>
+          )}
+
+          {typedDataPreview && !typedDataSignature && (
+            <div className="typed-data-preview">
+              <h4>You are signing a {typedDataPreview.primary_type}</h4>
+              <p className="input-help">
+                Signer: <code>{typedDataPreview.signer ?? "No account selected"}</code>
+              </p>
+              <h4>Domain</h4>
+              <PreviewFields fields={typedDataPreview.domain} />
+              <h4>Message</h4>
+              <PreviewFields fields={typedDataPreview.message} />
+              <p className="input-help">
+                Signing hash: <code>{typedDataPreview.signing_hash}</code>
+              </p>
+              <div className="form-actions">
+                <button onClick={rejectTypedData} className="cancel-btn" disabled={typedDataBusy}>
+                  Reject
+                </button>
+                <button
+                  onClick={approveTypedData}
+                  className="import-btn"
+                  disabled={typedDataBusy || !typedDataPreview.signer}
+                >
+                  {typedDataBusy ? "Signing..." : "Approve"}
+                </button>
+              </div>
+            </div>
+          )}
+
+          {typedDataMessage && <p className="input-help">{typedDataMessage}</p>}
+
+          {typedDataSignature && (
+            <div className="signature-result">
+              <h4>Signature</h4>
+              <div className="signature-display">
+                <code>{typedDataSignature.signature}</code>
+                <button
+                  onClick={() => navigator.clipboard.writeText(typedDataSignature.signature)}
+                  className="copy-button"
+                >
+                  Copy
+                </button>
+              </div>
+            </div>
+          )}
+        </div>
       </div>
     </div>
   );
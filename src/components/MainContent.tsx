@@ -1,5 +1,5 @@
 import React from 'react';
-import { MenuItem } from '../types';
+import { AccountMetadata, MenuItem } from '../types';
 import AccountsPage from './AccountsPage';
 import SendPage from './SendPage';
 import ReceivePage from './ReceivePage';
@@ -22,10 +22,13 @@ interface MainContentProps {
   createWallet: () => void;
   importWallet: () => void;
   loadWallet: (walletName: string) => void;
+  deleteWallet: (walletName: string, password: string) => void;
+  renameWallet: (oldName: string, newName: string) => void;
   returnToWalletSelection: () => void;
   createAccount: () => void;
   addAccount: () => void;
   selectAccount: (index: number) => void;
+  updateAccountMetadata: (index: number, metadata: AccountMetadata) => void;
   accounts: any[];
   walletInfo: any;
   // Send page props
@@ -56,10 +59,13 @@ const MainContent: React.FC<MainContentProps> = ({
   createWallet,
   importWallet,
   loadWallet,
+  deleteWallet,
+  renameWallet,
   returnToWalletSelection,
   createAccount,
   addAccount,
   selectAccount,
+  updateAccountMetadata,
   accounts,
   walletInfo,
   sendAmount,
@@ -88,10 +94,13 @@ const MainContent: React.FC<MainContentProps> = ({
             createWallet={createWallet}
             importWallet={importWallet}
             loadWallet={loadWallet}
+            deleteWallet={deleteWallet}
+            renameWallet={renameWallet}
             returnToWalletSelection={returnToWalletSelection}
             createAccount={createAccount}
             addAccount={addAccount}
             selectAccount={selectAccount}
+            updateAccountMetadata={updateAccountMetadata}
             accounts={accounts}
           />
         );
@@ -152,10 +161,13 @@ const MainContent: React.FC<MainContentProps> = ({
             createWallet={createWallet}
             importWallet={importWallet}
             loadWallet={loadWallet}
+            deleteWallet={deleteWallet}
+            renameWallet={renameWallet}
             returnToWalletSelection={returnToWalletSelection}
             createAccount={createAccount}
             addAccount={addAccount}
             selectAccount={selectAccount}
+            updateAccountMetadata={updateAccountMetadata}
             accounts={accounts}
           />
         );
@@ -1,4 +1,6 @@
-import React from 'react';
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { AccountInfo, ActiveNetwork, Contact, FeeEstimate, FeeTier, NonceReport, Settings, Transaction } from '../types';
 
 interface SendPageProps {
   sendAmount: string;
@@ -23,6 +25,91 @@ const SendPage: React.FC<SendPageProps> = ({
   signTransaction,
   signature,
 }) => {
+  const [contacts, setContacts] = useState<Contact[]>([]);
+  const [resolvedAddress, setResolvedAddress] = useState('');
+  const [recipientError, setRecipientError] = useState('');
+  const [newContact, setNewContact] = useState({ name: '', address: '', memo: '' });
+  const [feeTier, setFeeTier] = useState<FeeTier>('normal');
+  const [fee, setFee] = useState<FeeEstimate | null>(null);
+  const [feeError, setFeeError] = useState('');
+
+  async function loadContacts() {
+    try {
+      setContacts(await invoke<Contact[]>('list_contacts'));
+    } catch (error) {
+      setRecipientError(`Error loading contacts: ${error}`);
+    }
+  }
+
+  useEffect(() => {
+    loadContacts();
+    invoke<Settings>('get_settings')
+      .then((settings) => setFeeTier(settings.fee.tier))
+      .catch(() => {});
+  }, []);
+
+  async function resolveRecipient(recipient: string) {
+    setResolvedAddress('');
+    setRecipientError('');
+    if (!recipient.trim()) return;
+    try {
+      setResolvedAddress(await invoke<string>('resolve_recipient', { recipient }));
+    } catch (error) {
+      setRecipientError(`${error}`);
+    }
+  }
+
+  // Ask the node for the gas of the transaction so its total cost is shown before signing
+  async function estimateFee() {
+    setFee(null);
+    setFeeError('');
+    try {
+      const account = await invoke<AccountInfo | null>('get_selected_account');
+      if (!account) {
+        setFeeError('No account selected');
+        return;
+      }
+      const nonces = await invoke<NonceReport>('get_nonce_state', { account: account.address });
+      const active = await invoke<ActiveNetwork>('get_active_network');
+      const transaction: Transaction = {
+        chain_id: active.profile.chain_id,
+        from: account.address,
+        to: resolvedAddress || undefined,
+        amount: sendAmount ? Number(sendAmount) : undefined,
+        data: transactionData || undefined,
+        nonce: nonces.next,
+        gas_limit: 0,
+        gas_price: 0,
+      };
+      setFee(await invoke<FeeEstimate>('estimate_fee', { transaction, tier: feeTier }));
+    } catch (error) {
+      setFeeError(`Could not estimate the fee: ${error}`);
+    }
+  }
+
+  async function addContact() {
+    try {
+      await invoke<Contact>('add_contact', {
+        name: newContact.name,
+        address: newContact.address,
+        memo: newContact.memo || null,
+      });
+      setNewContact({ name: '', address: '', memo: '' });
+      await loadContacts();
+    } catch (error) {
+      setRecipientError(`Error adding contact: ${error}`);
+    }
+  }
+
+  async function removeContact(name: string) {
+    try {
+      await invoke<Contact>('remove_contact', { name });
+      await loadContacts();
+    } catch (error) {
+      setRecipientError(`Error removing contact: ${error}`);
+    }
+  }
+
   return (
     <div className="send-page">
       <div className="page-header">
@@ -41,13 +128,26 @@ const SendPage: React.FC<SendPageProps> = ({
         </div>
 
         <div className="form-group">
-          <label>Recipient Address</label>
+          <label>Recipient</label>
           <input
             type="text"
-            placeholder="Enter recipient address"
+            placeholder="Enter recipient address or contact name"
+            list="contact-names"
             value={sendAddress}
             onChange={(e) => setSendAddress(e.target.value)}
+            onBlur={(e) => resolveRecipient(e.target.value)}
           />
+          <datalist id="contact-names">
+            {contacts.map((contact) => (
+              <option key={contact.name} value={contact.name}>
+                {contact.address}
+              </option>
+            ))}
+          </datalist>
+          {resolvedAddress && resolvedAddress !== sendAddress && (
+            <span className="setting-unit">→ {resolvedAddress}</span>
+          )}
+          {recipientError && <span className="setting-unit">{recipientError}</span>}
         </div>
 
         <div className="form-group">
@@ -60,10 +160,73 @@ const SendPage: React.FC<SendPageProps> = ({
           />
         </div>
 
+        <div className="form-group">
+          <label>Fee</label>
+          <select
+            className="setting-select"
+            value={feeTier}
+            onChange={(e) => {
+              setFeeTier(e.target.value as FeeTier);
+              setFee(null);
+            }}
+          >
+            <option value="slow">Slow</option>
+            <option value="normal">Normal</option>
+            <option value="fast">Fast</option>
+          </select>
+          <button className="secondary-btn" onClick={estimateFee}>Estimate Fee</button>
+          {fee && (
+            <span className="setting-unit">
+              Gas limit {fee.gas_limit} × gas price {fee.gas_price}
+              {fee.capped && ' (capped)'} = max fee {fee.gas_limit * fee.gas_price} MTH.
+              Total cost up to {(Number(sendAmount) || 0) + fee.gas_limit * fee.gas_price} MTH
+            </span>
+          )}
+          {feeError && <span className="setting-unit">{feeError}</span>}
+        </div>
+
         <button onClick={signTransaction} disabled={loading} className="send-btn">
           {loading ? "Signing..." : "Sign & Send Transaction"}
         </button>
 
+        <div className="form-group">
+          <label>Address Book</label>
+          {contacts.map((contact) => (
+            <div key={contact.name} className="setting-item">
+              <span onClick={() => setSendAddress(contact.name)}>
+                {contact.name} <code>{contact.address}</code>
+                {contact.memo && <span className="setting-unit"> {contact.memo}</span>}
+              </span>
+              <button className="copy-button" onClick={() => removeContact(contact.name)}>
+                ✕
+              </button>
+            </div>
+          ))}
+          <div className="setting-item">
+            <input
+              type="text"
+              placeholder="Name"
+              value={newContact.name}
+              onChange={(e) => setNewContact({ ...newContact, name: e.target.value })}
+            />
+            <input
+              type="text"
+              placeholder="Address"
+              value={newContact.address}
+              onChange={(e) => setNewContact({ ...newContact, address: e.target.value })}
+            />
+            <input
+              type="text"
+              placeholder="Memo (optional)"
+              value={newContact.memo}
+              onChange={(e) => setNewContact({ ...newContact, memo: e.target.value })}
+            />
+            <button className="secondary-btn" onClick={addContact}>
+              Add Contact
+            </button>
+          </div>
+        </div>
+
         {signature && (
           <div className="signature-result">
             <h3>Transaction Signed</h3>
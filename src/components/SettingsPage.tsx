@@ -1,5 +1,7 @@
-import React, { useState } from 'react';
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { useTheme } from '../contexts/ThemeContext';
+import { ActiveNetwork, BackupPreview, DappConnection, FeeTier, LogLevel, MigrationReport, Network, RestoreReport, Settings, SignerStatus, StorageBackend } from '../types';
 
 interface SettingsPageProps {
   currentWallet: string;
@@ -18,6 +20,140 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
 }) => {
   const { theme, toggleTheme } = useTheme();
   const [activeTab, setActiveTab] = useState<SettingsTab>('wallet');
+  const [settings, setSettings] = useState<Settings | null>(null);
+  const [activeNetwork, setActiveNetwork] = useState<ActiveNetwork | null>(null);
+  const [signerStatus, setSignerStatus] = useState<SignerStatus | null>(null);
+  const [dapps, setDapps] = useState<DappConnection[]>([]);
+  const [settingsMessage, setSettingsMessage] = useState('');
+  const [backupPath, setBackupPath] = useState('');
+  const [backupPassword, setBackupPassword] = useState('');
+  const [backupAllWallets, setBackupAllWallets] = useState(false);
+  const [backupPreview, setBackupPreview] = useState<BackupPreview | null>(null);
+
+  useEffect(() => {
+    invoke<Settings>('get_settings')
+      .then(setSettings)
+      .catch((error) => setSettingsMessage(`Error loading settings: ${error}`));
+    loadActiveNetwork();
+    loadSignerStatus();
+  }, []);
+
+  function loadSignerStatus() {
+    invoke<SignerStatus>('get_signer_status')
+      .then(setSignerStatus)
+      .catch(() => setSignerStatus(null));
+    invoke<DappConnection[]>('list_dapp_permissions')
+      .then(setDapps)
+      .catch(() => setDapps([]));
+  }
+
+  async function revokeDapp(origin: string) {
+    try {
+      await invoke('revoke_dapp_permission', { origin });
+      setSettingsMessage(`${origin} can no longer use the signer`);
+    } catch (error) {
+      setSettingsMessage(`Error revoking ${origin}: ${error}`);
+    }
+    loadSignerStatus();
+  }
+
+  function loadActiveNetwork() {
+    invoke<ActiveNetwork>('get_active_network')
+      .then(setActiveNetwork)
+      .catch(() => setActiveNetwork(null));
+  }
+
+  // The wallet's own network overrides the configured one
+  async function setWalletNetwork(network: Network | null) {
+    try {
+      setActiveNetwork(await invoke<ActiveNetwork>('set_wallet_network', { network }));
+      setSettingsMessage(network ? `${currentWallet} now uses ${network}` : `${currentWallet} now uses the configured network`);
+    } catch (error) {
+      setSettingsMessage(`Error setting wallet network: ${error}`);
+    }
+  }
+
+  async function updateSettings<K extends keyof Settings>(section: K, changes: Partial<Settings[K]>) {
+    if (!settings) return;
+    try {
+      const updated = await invoke<Settings>('update_settings', {
+        settings: { ...settings, [section]: { ...settings[section], ...changes } },
+      });
+      setSettings(updated);
+      loadActiveNetwork();
+      setSettingsMessage('Settings saved');
+    } catch (error) {
+      setSettingsMessage(`Error saving settings: ${error}`);
+    }
+    loadSignerStatus();
+  }
+
+  async function createBackup() {
+    try {
+      const walletNames = backupAllWallets ? await invoke<string[]>('list_wallets') : [];
+      const written = await invoke<BackupPreview>('create_backup', {
+        path: backupPath,
+        password: backupPassword,
+        walletNames,
+        includeSettings: true,
+      });
+      setBackupPreview(null);
+      setSettingsMessage(`Backed up ${written.wallets.map((w) => w.name).join(', ')} to ${backupPath}`);
+    } catch (error) {
+      setSettingsMessage(`Error creating backup: ${error}`);
+    }
+  }
+
+  async function previewBackup() {
+    try {
+      setBackupPreview(await invoke<BackupPreview>('preview_backup', { path: backupPath, password: backupPassword }));
+      setSettingsMessage('');
+    } catch (error) {
+      setBackupPreview(null);
+      setSettingsMessage(`Error reading backup: ${error}`);
+    }
+  }
+
+  async function restoreBackup(replace: boolean) {
+    try {
+      const report = await invoke<RestoreReport>('restore_backup', {
+        path: backupPath,
+        password: backupPassword,
+        replace,
+      });
+      const parts = [
+        report.restored.length ? `restored ${report.restored.join(', ')}` : '',
+        report.merged.length ? `merged ${report.merged.join(', ')}` : '',
+        report.replaced.length ? `replaced ${report.replaced.join(', ')}` : '',
+        report.skipped.length ? `skipped ${report.skipped.join(', ')}` : '',
+        report.contacts_added ? `added ${report.contacts_added} contacts` : '',
+        report.settings_restored ? 'restored settings' : '',
+      ].filter((part) => part);
+      setBackupPreview(null);
+      setSettingsMessage(`Backup restored: ${parts.join('; ') || 'nothing new'}`);
+      if (report.settings_restored) {
+        setSettings(await invoke<Settings>('get_settings'));
+      }
+    } catch (error) {
+      setSettingsMessage(`Error restoring backup: ${error}`);
+    }
+  }
+
+  async function migrateStorage(to: StorageBackend) {
+    try {
+      const report = await invoke<MigrationReport>('migrate_storage', { to });
+      const parts = [
+        report.migrated.length ? `copied ${report.migrated.join(', ')}` : '',
+        report.skipped.length ? `already there: ${report.skipped.join(', ')}` : '',
+      ].filter((part) => part);
+      setSettingsMessage(`Migrated to ${to}: ${parts.join('; ') || 'no wallets'}`);
+    } catch (error) {
+      setSettingsMessage(`Error migrating wallets: ${error}`);
+    }
+  }
+
+  const backend = settings?.wallet.backend ?? 'json';
+  const otherBackend: StorageBackend = backend === 'json' ? 'sled' : 'json';
 
   const tabs = [
     { id: 'wallet' as SettingsTab, label: 'Wallet', icon: '💼' },
@@ -51,7 +187,11 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
               <h3>Wallet Management</h3>
               <div className="setting-item">
                 <label>Auto-lock Timeout</label>
-                <select className="setting-select">
+                <select
+                  className="setting-select"
+                  value={settings?.wallet.auto_lock_minutes ?? 15}
+                  onChange={(e) => updateSettings('wallet', { auto_lock_minutes: Number(e.target.value) })}
+                >
                   <option value="5">5 minutes</option>
                   <option value="15">15 minutes</option>
                   <option value="30">30 minutes</option>
@@ -59,6 +199,23 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
                   <option value="0">Never</option>
                 </select>
               </div>
+              <div className="setting-item">
+                <label>Storage Backend</label>
+                <select
+                  className="setting-select"
+                  value={backend}
+                  onChange={(e) => updateSettings('wallet', { backend: e.target.value as StorageBackend })}
+                >
+                  <option value="json">JSON files</option>
+                  <option value="sled">sled database</option>
+                </select>
+              </div>
+              <div className="setting-item">
+                <label>Migrate Wallets</label>
+                <button className="secondary-btn" onClick={() => migrateStorage(otherBackend)} disabled={!settings}>
+                  Copy Wallets to {otherBackend === 'json' ? 'JSON Files' : 'sled Database'}
+                </button>
+              </div>
               <div className="setting-item">
                 <label>Backup Frequency</label>
                 <select className="setting-select">
@@ -69,6 +226,82 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
                 </select>
               </div>
             </div>
+            <div className="settings-section">
+              <h3>Backup &amp; Restore</h3>
+              <div className="setting-item">
+                <label>Archive File</label>
+                <input
+                  type="text"
+                  className="setting-input"
+                  placeholder="/path/to/wallets.rwmth"
+                  value={backupPath}
+                  onChange={(e) => setBackupPath(e.target.value)}
+                />
+              </div>
+              <div className="setting-item">
+                <label>Archive Password</label>
+                <input
+                  type="password"
+                  className="setting-input"
+                  value={backupPassword}
+                  onChange={(e) => setBackupPassword(e.target.value)}
+                />
+              </div>
+              <div className="setting-item">
+                <label>Include All Wallets</label>
+                <input
+                  type="checkbox"
+                  className="setting-checkbox"
+                  checked={backupAllWallets}
+                  onChange={(e) => setBackupAllWallets(e.target.checked)}
+                />
+              </div>
+              <div className="setting-item">
+                <button
+                  className="secondary-btn"
+                  onClick={createBackup}
+                  disabled={!backupPath || !backupPassword || (!backupAllWallets && !currentWallet)}
+                >
+                  Create Backup
+                </button>
+                <button className="secondary-btn" onClick={previewBackup} disabled={!backupPath || !backupPassword}>
+                  Preview
+                </button>
+              </div>
+              {backupPreview && (
+                <>
+                  <div className="setting-item">
+                    <label>Created</label>
+                    <span>{new Date(backupPreview.created_at).toLocaleString()}</span>
+                  </div>
+                  {backupPreview.wallets.map((wallet) => (
+                    <div className="setting-item" key={wallet.name}>
+                      <label>{wallet.name}</label>
+                      <span>
+                        {wallet.addresses ? `${wallet.addresses.length} accounts` : 'Unrecognized format'}
+                        {wallet.exists ? ' (exists, will merge)' : ' (new)'}
+                      </span>
+                    </div>
+                  ))}
+                  <div className="setting-item">
+                    <label>Address Book</label>
+                    <span>{backupPreview.contacts === null ? 'Not included' : `${backupPreview.contacts} contacts`}</span>
+                  </div>
+                  <div className="setting-item">
+                    <label>Settings</label>
+                    <span>{backupPreview.settings ? 'Included' : 'Not included'}</span>
+                  </div>
+                  <div className="setting-item">
+                    <button className="secondary-btn" onClick={() => restoreBackup(false)}>
+                      Restore (Merge)
+                    </button>
+                    <button className="danger-btn" onClick={() => restoreBackup(true)}>
+                      Restore (Replace)
+                    </button>
+                  </div>
+                </>
+              )}
+            </div>
           </div>
         );
 
@@ -125,18 +358,44 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
                 <input 
                   type="text" 
                   className="setting-input" 
-                  placeholder="https://node.mazzaroth.io"
-                  defaultValue="https://node.mazzaroth.io"
+                  placeholder={settings?.networks[settings.node.network].node_url ?? "https://node.mazzaroth.io"}
+                  key={settings?.node.url ?? ''}
+                  defaultValue={settings?.node.url ?? ''}
+                  onBlur={(e) => updateSettings('node', { url: e.target.value || null })}
                 />
               </div>
               <div className="setting-item">
                 <label>Network</label>
-                <select className="setting-select">
+                <select
+                  className="setting-select"
+                  value={settings?.node.network ?? 'mainnet'}
+                  onChange={(e) => updateSettings('node', { network: e.target.value as Network })}
+                >
                   <option value="mainnet">Mainnet</option>
                   <option value="testnet">Testnet</option>
                   <option value="devnet">Devnet</option>
                 </select>
               </div>
+              <div className="setting-item">
+                <label>Wallet Network</label>
+                <select
+                  className="setting-select"
+                  value={activeNetwork?.wallet_network ?? ''}
+                  disabled={!activeNetwork}
+                  onChange={(e) => setWalletNetwork((e.target.value || null) as Network | null)}
+                >
+                  <option value="">Same as above</option>
+                  <option value="mainnet">Mainnet</option>
+                  <option value="testnet">Testnet</option>
+                  <option value="devnet">Devnet</option>
+                </select>
+              </div>
+              {activeNetwork && (
+                <div className="setting-item">
+                  <label>Active Chain</label>
+                  <span>{activeNetwork.network} (chain {activeNetwork.profile.chain_id}) via {activeNetwork.node_url}</span>
+                </div>
+              )}
             </div>
             <div className="settings-section">
               <h3>Connection Settings</h3>
@@ -146,15 +405,130 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
                   type="number" 
                   className="setting-input" 
                   placeholder="30"
-                  defaultValue="30"
+                  key={settings?.node.timeout_secs}
+                  defaultValue={settings?.node.timeout_secs ?? 30}
+                  onBlur={(e) => updateSettings('node', { timeout_secs: Number(e.target.value) })}
                 />
                 <span className="setting-unit">seconds</span>
               </div>
               <div className="setting-item">
                 <label>Auto-reconnect</label>
-                <input type="checkbox" className="setting-checkbox" defaultChecked />
+                <input
+                  type="checkbox"
+                  className="setting-checkbox"
+                  checked={settings?.node.auto_reconnect ?? true}
+                  onChange={(e) => updateSettings('node', { auto_reconnect: e.target.checked })}
+                />
               </div>
             </div>
+            <div className="settings-section">
+              <h3>Fees</h3>
+              <div className="setting-item">
+                <label>Fee Tier</label>
+                <select
+                  className="setting-select"
+                  value={settings?.fee.tier ?? 'normal'}
+                  onChange={(e) => updateSettings('fee', { tier: e.target.value as FeeTier })}
+                >
+                  <option value="slow">Slow</option>
+                  <option value="normal">Normal</option>
+                  <option value="fast">Fast</option>
+                </select>
+              </div>
+              <div className="setting-item">
+                <label>Gas Limit Margin</label>
+                <input
+                  type="number"
+                  className="setting-input"
+                  placeholder="20"
+                  key={settings?.fee.gas_limit_margin_percent}
+                  defaultValue={settings?.fee.gas_limit_margin_percent ?? 20}
+                  onBlur={(e) => updateSettings('fee', { gas_limit_margin_percent: Number(e.target.value) })}
+                />
+                <span className="setting-unit">%</span>
+              </div>
+              <div className="setting-item">
+                <label>Max Gas Limit</label>
+                <input
+                  type="number"
+                  className="setting-input"
+                  placeholder="10000000"
+                  key={settings?.fee.max_gas_limit}
+                  defaultValue={settings?.fee.max_gas_limit ?? 10000000}
+                  onBlur={(e) => updateSettings('fee', { max_gas_limit: Number(e.target.value) })}
+                />
+              </div>
+              <div className="setting-item">
+                <label>Max Gas Price</label>
+                <input
+                  type="number"
+                  className="setting-input"
+                  placeholder="1000000"
+                  key={settings?.fee.max_gas_price}
+                  defaultValue={settings?.fee.max_gas_price ?? 1000000}
+                  onBlur={(e) => updateSettings('fee', { max_gas_price: Number(e.target.value) })}
+                />
+              </div>
+            </div>
+            <div className="settings-section">
+              <h3>dApp Signer</h3>
+              <div className="setting-item">
+                <label>Enable Signer</label>
+                <input
+                  type="checkbox"
+                  checked={settings?.signer.enabled ?? false}
+                  onChange={(e) => updateSettings('signer', { enabled: e.target.checked })}
+                />
+                <span className="setting-unit">
+                  {signerStatus?.address ? `Listening on ${signerStatus.address}` : 'Stopped'}
+                </span>
+              </div>
+              <div className="setting-item">
+                <label>Port</label>
+                <input
+                  type="number"
+                  className="setting-input"
+                  placeholder="8547"
+                  key={settings?.signer.port}
+                  defaultValue={settings?.signer.port ?? 8547}
+                  onBlur={(e) => updateSettings('signer', { port: Number(e.target.value) })}
+                />
+              </div>
+              <div className="setting-item">
+                <label>Approval Timeout</label>
+                <input
+                  type="number"
+                  className="setting-input"
+                  placeholder="120"
+                  key={settings?.signer.approval_timeout_secs}
+                  defaultValue={settings?.signer.approval_timeout_secs ?? 120}
+                  onBlur={(e) => updateSettings('signer', { approval_timeout_secs: Number(e.target.value) })}
+                />
+                <span className="setting-unit">seconds</span>
+              </div>
+            </div>
+            <div className="settings-section">
+              <h3>Connected dApps</h3>
+              {dapps.length === 0 && <p className="input-help">No dApp has been given access</p>}
+              {dapps.map((dapp) => (
+                <div key={dapp.origin} className="setting-item">
+                  <label>
+                    <code>{dapp.origin}</code>
+                    {dapp.connected && ' (connected)'}
+                  </label>
+                  <span className="setting-unit">
+                    {dapp.methods.join(', ')} with {dapp.accounts.length} account(s).
+                    {dapp.spending_limit != null && ` Spent ${dapp.spent} of ${dapp.spending_limit} MTH.`}
+                    {dapp.expires_at
+                      ? ` ${new Date(dapp.expires_at) <= new Date() ? 'Expired' : 'Expires'} ${new Date(dapp.expires_at).toLocaleString()}.`
+                      : ' Never expires.'}
+                  </span>
+                  <button className="secondary-btn" onClick={() => revokeDapp(dapp.origin)}>
+                    Revoke
+                  </button>
+                </div>
+              ))}
+            </div>
           </div>
         );
 
@@ -165,7 +539,11 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
               <h3>Logging Configuration</h3>
               <div className="setting-item">
                 <label>Log Level</label>
-                <select className="setting-select">
+                <select
+                  className="setting-select"
+                  value={settings?.log.level ?? 'info'}
+                  onChange={(e) => updateSettings('log', { level: e.target.value as LogLevel })}
+                >
                   <option value="error">Error</option>
                   <option value="warn">Warning</option>
                   <option value="info">Info</option>
@@ -175,7 +553,12 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
               </div>
               <div className="setting-item">
                 <label>Log to File</label>
-                <input type="checkbox" className="setting-checkbox" defaultChecked />
+                <input
+                  type="checkbox"
+                  className="setting-checkbox"
+                  checked={settings?.log.to_file ?? false}
+                  onChange={(e) => updateSettings('log', { to_file: e.target.checked })}
+                />
               </div>
               <div className="setting-item">
                 <label>Max Log File Size</label>
@@ -183,7 +566,9 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
                   type="number" 
                   className="setting-input" 
                   placeholder="10"
-                  defaultValue="10"
+                  key={settings?.log.max_file_size_mb}
+                  defaultValue={settings?.log.max_file_size_mb ?? 10}
+                  onBlur={(e) => updateSettings('log', { max_file_size_mb: Number(e.target.value) })}
                 />
                 <span className="setting-unit">MB</span>
               </div>
@@ -232,7 +617,7 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
               <div className="setting-item">
                 <label>Wallet Path</label>
                 <div className="path-display">
-                  <span className="path-value">~/Library/Application Support/MazzarothWallet/wallets/</span>
+                  <span className="path-value">{settings?.wallet.dir ?? "~/.config/rwmth"}</span>
                   <button className="copy-btn" title="Copy path">📋</button>
                 </div>
               </div>
@@ -246,7 +631,7 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
               <div className="setting-item">
                 <label>Config Path</label>
                 <div className="path-display">
-                  <span className="path-value">~/Library/Application Support/MazzarothWallet/config/</span>
+                  <span className="path-value">~/.config/rwmth/config.toml</span>
                   <button className="copy-btn" title="Copy path">📋</button>
                 </div>
               </div>
@@ -278,6 +663,7 @@ const SettingsPage: React.FC<SettingsPageProps> = ({
     <div className="settings-page">
       <div className="page-header">
         <h2>Settings</h2>
+        {settingsMessage && <span className="setting-unit">{settingsMessage}</span>}
       </div>
 
       <div className="settings-content">
@@ -0,0 +1,171 @@
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { AccountInfo, Approval, DappMethod, SignerMethod, SignerRequest, SignerStatus } from '../types';
+
+const METHOD_LABELS: Record<SignerMethod, string> = {
+  connect: 'Connect',
+  accounts: 'See your account addresses',
+  signMessage: 'Sign a message',
+  signTransaction: 'Sign a transaction',
+};
+
+// How long a connection lasts, in hours; 0 never expires
+const EXPIRY_HOURS = [0, 1, 24, 24 * 7, 24 * 30];
+
+// Requests from dApps through the localhost signer, answered one at a time
+const SignerPrompt: React.FC = () => {
+  const [queue, setQueue] = useState<SignerRequest[]>([]);
+  const [granted, setGranted] = useState<DappMethod[]>([]);
+  const [accounts, setAccounts] = useState<AccountInfo[]>([]);
+  const [shared, setShared] = useState<string[]>([]);
+  const [spendingLimit, setSpendingLimit] = useState('');
+  const [expiryHours, setExpiryHours] = useState(24);
+
+  useEffect(() => {
+    invoke<SignerStatus>('get_signer_status')
+      .then((status) => setQueue(status.pending))
+      .catch(() => {});
+    const unlisten = listen<SignerRequest>('signer-request', (event) => {
+      setQueue((queue) => [...queue, event.payload]);
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  const request = queue[0];
+  const requested: DappMethod[] = request?.method === 'connect' ? request.params.methods : [];
+
+  useEffect(() => {
+    setGranted(requested);
+    setSpendingLimit('');
+    setExpiryHours(24);
+    if (request?.method === 'connect') {
+      // Only the selected account is shared unless the user picks more
+      invoke<AccountInfo[]>('list_accounts')
+        .then((accounts) => {
+          setAccounts(accounts);
+          setShared(accounts.filter((account) => account.is_selected).map((account) => account.address));
+        })
+        .catch(() => setAccounts([]));
+    }
+  }, [request?.id]);
+
+  if (!request) return null;
+
+  async function respond(approved: boolean) {
+    const approval: Approval =
+      request.method === 'connect'
+        ? {
+            approved,
+            methods: granted,
+            accounts: shared,
+            spending_limit: spendingLimit ? Number(spendingLimit) : null,
+            expires_at: expiryHours ? new Date(Date.now() + expiryHours * 3600 * 1000).toISOString() : null,
+          }
+        : { approved };
+    try {
+      await invoke('respond_signer_request', { id: request.id, approval });
+    } catch (error) {
+      // Timed out or the signer stopped; the dApp already got its answer
+      console.error('Error answering signer request:', error);
+    }
+    setQueue((queue) => queue.filter((queued) => queued.id !== request.id));
+  }
+
+  return (
+    <div className="import-form-overlay">
+      <div className="import-form">
+        <div className="import-form-header">
+          <h3>{METHOD_LABELS[request.method]}</h3>
+        </div>
+
+        <div className="form-section">
+          <label>Requested by</label>
+          <code>{request.origin}</code>
+        </div>
+
+        {request.method === 'connect' ? (
+          <div className="form-section">
+            <label>Allow this site to ask to</label>
+            {requested.map((method) => (
+              <label key={method} className="setting-unit">
+                <input
+                  type="checkbox"
+                  checked={granted.includes(method)}
+                  onChange={(e) =>
+                    setGranted(e.target.checked ? [...granted, method] : granted.filter((m) => m !== method))
+                  }
+                />
+                {METHOD_LABELS[method]}
+              </label>
+            ))}
+            <p className="input-help">Each request will still ask for your approval.</p>
+
+            <label>Share these accounts</label>
+            {accounts.map((account) => (
+              <label key={account.address} className="setting-unit">
+                <input
+                  type="checkbox"
+                  checked={shared.includes(account.address)}
+                  onChange={(e) =>
+                    setShared(
+                      e.target.checked ? [...shared, account.address] : shared.filter((a) => a !== account.address),
+                    )
+                  }
+                />
+                {account.metadata.label || `Account ${account.index}`} <code>{account.address}</code>
+              </label>
+            ))}
+
+            <label>Spending limit</label>
+            <input
+              type="number"
+              className="setting-input"
+              placeholder="No limit"
+              value={spendingLimit}
+              onChange={(e) => setSpendingLimit(e.target.value)}
+            />
+            <span className="setting-unit">MTH, amounts and fees included</span>
+
+            <label>Expires</label>
+            <select
+              className="setting-select"
+              value={expiryHours}
+              onChange={(e) => setExpiryHours(Number(e.target.value))}
+            >
+              {EXPIRY_HOURS.map((hours) => (
+                <option key={hours} value={hours}>
+                  {hours === 0 ? 'Never' : hours < 24 ? `In ${hours} hour(s)` : `In ${hours / 24} day(s)`}
+                </option>
+              ))}
+            </select>
+          </div>
+        ) : request.params !== null ? (
+          <div className="form-section">
+            <label>Details</label>
+            <pre>{JSON.stringify(request.params, null, 2)}</pre>
+          </div>
+        ) : null}
+
+        {queue.length > 1 && <p className="input-help">{queue.length - 1} more request(s) waiting</p>}
+
+        <div className="form-actions">
+          <button onClick={() => respond(false)} className="cancel-btn">
+            Reject
+          </button>
+          <button
+            onClick={() => respond(true)}
+            className="import-btn"
+            disabled={request.method === 'connect' && shared.length === 0}
+          >
+            Approve
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+};
+
+export default SignerPrompt;
@@ -1,9 +1,117 @@
-import React from 'react';
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { FinalizedTransaction, HistoryFilter, HistoryPage, NodeEvent, NonceReport, Transaction, TxRecord, TxStatus } from '../types';
+
+const PAGE_SIZE = 20;
+
+const STATUSES: TxStatus[] = ['built', 'signed', 'submitted', 'confirmed', 'failed'];
 
 interface TransactionsPageProps {
 }
 
 const TransactionsPage: React.FC<TransactionsPageProps> = ({}) => {
+  const [page, setPage] = useState<HistoryPage | null>(null);
+  const [offset, setOffset] = useState(0);
+  const [status, setStatus] = useState<TxStatus | ''>('');
+  const [counterparty, setCounterparty] = useState('');
+  const [message, setMessage] = useState('');
+  const [syncing, setSyncing] = useState(false);
+  const [nonces, setNonces] = useState<NonceReport | null>(null);
+
+  async function loadTransactions() {
+    const filter: HistoryFilter = {};
+    if (status) filter.status = status;
+    if (counterparty.trim()) filter.counterparty = counterparty.trim();
+    try {
+      setPage(await invoke<HistoryPage>('list_transactions', { filter, offset, limit: PAGE_SIZE }));
+    } catch (error) {
+      setMessage(`Error loading transactions: ${error}`);
+    }
+  }
+
+  useEffect(() => {
+    loadTransactions();
+  }, [offset, status]);
+
+  // Statuses pushed by the node are already in the history by the time the event arrives
+  useEffect(() => {
+    const unlisten = listen<NodeEvent>('node-event', (event) => {
+      if (event.payload.kind === 'transaction') {
+        loadTransactions();
+      }
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [offset, status, counterparty]);
+
+  async function syncTransactions() {
+    setSyncing(true);
+    try {
+      const updated = await invoke<number>('sync_transactions');
+      setMessage(`${updated} transaction(s) updated from the node`);
+      await loadTransactions();
+    } catch (error) {
+      // Offline: keep showing the local history
+      setMessage(`Could not reach the node: ${error}`);
+    } finally {
+      setSyncing(false);
+    }
+  }
+
+  async function checkNonces() {
+    try {
+      setNonces(await invoke<NonceReport>('get_nonce_state', { account: null }));
+    } catch (error) {
+      setMessage(`Could not reach the node: ${error}`);
+    }
+  }
+
+  // Sign and submit a transaction with the same nonce and a higher gas price
+  async function replaceTransaction(record: TxRecord, cancel: boolean) {
+    try {
+      const transaction = await invoke<Transaction>('build_replacement', {
+        idOrHash: record.hash ?? record.id,
+        cancel,
+        gasPrice: null,
+      });
+      const pst = await invoke<string>('create_partial_transaction', { transaction });
+      const signed = await invoke<string>('sign_partial_transaction', { pst });
+      const finalized = await invoke<FinalizedTransaction>('finalize_partial_transaction', { pst: signed });
+      const hash = await invoke<string>('submit_transaction', {
+        signedTransaction: finalized.signed_transaction,
+      });
+      setMessage(`${cancel ? 'Cancel' : 'Speed-up'} submitted: ${hash}`);
+      await loadTransactions();
+    } catch (error) {
+      setMessage(`Error replacing transaction: ${error}`);
+    }
+  }
+
+  // Never submitted, so nothing but the history holds its nonce
+  async function dropTransaction(record: TxRecord) {
+    try {
+      await invoke<TxRecord>('drop_transaction', { idOrHash: record.id });
+      setMessage(`Transaction dropped; nonce ${record.nonce} is free again`);
+      await loadTransactions();
+    } catch (error) {
+      setMessage(`Error dropping transaction: ${error}`);
+    }
+  }
+
+  function applyFilter() {
+    setMessage('');
+    if (offset === 0) {
+      loadTransactions();
+    } else {
+      setOffset(0);
+    }
+  }
+
+  const records = page?.records ?? [];
+  const total = page?.total ?? 0;
+
   return (
     <div className="transactions-page">
       <div className="page-header">
@@ -11,10 +119,105 @@ const TransactionsPage: React.FC<TransactionsPageProps> = ({}) => {
       </div>
 
       <div className="transactions-content">
-        <div className="no-transactions">
-          <h3>No Transactions Yet</h3>
-          <p>Transaction history will appear here once you start using your wallet.</p>
+        <div className="setting-item">
+          <label>Status</label>
+          <select
+            className="setting-select"
+            value={status}
+            onChange={(e) => {
+              setStatus(e.target.value as TxStatus | '');
+              setOffset(0);
+            }}
+          >
+            <option value="">All</option>
+            {STATUSES.map((s) => (
+              <option key={s} value={s}>{s}</option>
+            ))}
+          </select>
+        </div>
+        <div className="setting-item">
+          <label>Recipient</label>
+          <input
+            className="setting-input"
+            placeholder="Address or contact name"
+            value={counterparty}
+            onChange={(e) => setCounterparty(e.target.value)}
+          />
+          <button className="secondary-btn" onClick={applyFilter}>Filter</button>
+          <button onClick={syncTransactions} disabled={syncing}>
+            {syncing ? 'Syncing...' : 'Sync with Node'}
+          </button>
+          <button className="secondary-btn" onClick={checkNonces}>Check Nonces</button>
         </div>
+        {message && <span className="setting-unit">{message}</span>}
+        {nonces && (
+          <div className="setting-item">
+            <label>Next nonce</label>
+            <span className="info-value">{nonces.next}</span>
+            <span className="setting-unit">
+              on chain {nonces.on_chain}
+              {nonces.gaps.length > 0 && `, gaps ${nonces.gaps.join(', ')}`}
+              {nonces.stuck.length > 0 && `, ${nonces.stuck.length} stuck`}
+              {nonces.drafts.length > 0 && `, ${nonces.drafts.length} unsigned draft(s) not holding a nonce`}
+            </span>
+          </div>
+        )}
+
+        {records.length === 0 ? (
+          <div className="no-transactions">
+            <h3>No Transactions Yet</h3>
+            <p>Transaction history will appear here once you start using your wallet.</p>
+          </div>
+        ) : (
+          <>
+            {records.map((record) => (
+              <div className="setting-item" key={record.id}>
+                <label>{new Date(record.created_at).toLocaleString()}</label>
+                <span className="info-value">{record.hash ?? record.id}</span>
+                <span>→ {record.counterparty ?? '-'}</span>
+                <span>{record.amount != null ? `${record.amount} MTH` : '-'}</span>
+                <span className="setting-unit">nonce {record.nonce}</span>
+                <span className={`status-badge ${record.status === 'failed' ? 'inactive' : 'active'}`}>
+                  {record.status}
+                </span>
+                {(record.status === 'built' || record.status === 'signed') && (
+                  <button className="secondary-btn" onClick={() => dropTransaction(record)}>
+                    Drop
+                  </button>
+                )}
+                {record.status === 'submitted' && (
+                  <>
+                    <button className="secondary-btn" onClick={() => replaceTransaction(record, false)}>
+                      Speed Up
+                    </button>
+                    <button className="secondary-btn" onClick={() => replaceTransaction(record, true)}>
+                      Cancel
+                    </button>
+                  </>
+                )}
+              </div>
+            ))}
+            <div className="setting-item">
+              <span className="setting-unit">
+                {offset + 1}-{offset + records.length} of {total}
+              </span>
+              <button
+                className="secondary-btn"
+                disabled={offset === 0}
+                onClick={() => setOffset(Math.max(0, offset - PAGE_SIZE))}
+              >
+                Previous
+              </button>
+              <button
+                className="secondary-btn"
+                disabled={offset + records.length >= total}
+                onClick={() => setOffset(offset + PAGE_SIZE)}
+              >
+                Next
+              </button>
+            </div>
+          </>
+        )}
       </div>
     </div>
   );
@@ -1,36 +1,106 @@
-import React, { useState } from 'react';
-import { AccountInfo } from '../types';
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { AccountInfo, AccountMetadata, NodeCache, NodeEvent } from '../types';
 
 // Mazzaroth coin type for BIP44 derivation
 const COIN_TYPE = 55555;
 
 interface WalletOverviewProps {
   selectAccount: (index: number) => void;
+  updateAccountMetadata: (index: number, metadata: AccountMetadata) => void;
   accounts: AccountInfo[];
 }
 
 const WalletOverview: React.FC<WalletOverviewProps> = ({
   selectAccount,
-  accounts,
+  updateAccountMetadata,
+  accounts: allAccounts,
 }) => {
   const [selectedAccountIndex, setSelectedAccountIndex] = useState<number | null>(null);
+  const [showHidden, setShowHidden] = useState(false);
+  const [draft, setDraft] = useState<AccountMetadata | null>(null);
+  const [tagsText, setTagsText] = useState('');
+  const [nodeCache, setNodeCache] = useState<NodeCache | null>(null);
+  const [nodeStatus, setNodeStatus] = useState('');
+
+  // Balances come from the node subscription, so they update as blocks arrive
+  useEffect(() => {
+    invoke<NodeCache>('get_node_cache').then(setNodeCache).catch(() => {});
+    const unlisten = listen<NodeEvent>('node-event', (event) => {
+      const nodeEvent = event.payload;
+      if (nodeEvent.kind === 'disconnected') {
+        setNodeStatus(
+          nodeEvent.retry_in_ms === null
+            ? `Disconnected from node: ${nodeEvent.error}`
+            : `Reconnecting to node in ${Math.ceil(nodeEvent.retry_in_ms / 1000)}s (attempt ${nodeEvent.attempt})`
+        );
+      } else if (nodeEvent.kind === 'connected') {
+        setNodeStatus('');
+      }
+      invoke<NodeCache>('get_node_cache').then(setNodeCache).catch(() => {});
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  const balanceOf = (account: AccountInfo) => nodeCache?.balances[account.address];
+
+  // Positions below refer to the visible list; `account.index` is the wallet index
+  const accounts = showHidden
+    ? allAccounts
+    : allAccounts.filter((account) => !account.metadata.hidden && !account.metadata.archived);
+  const hiddenCount = allAccounts.length - accounts.length;
 
   const handleAccountClick = (index: number) => {
     setSelectedAccountIndex(selectedAccountIndex === index ? null : index);
   };
 
-  const selectedAccount = selectedAccountIndex !== null ? accounts[selectedAccountIndex] : null;
+  const selectedAccount = selectedAccountIndex !== null ? accounts[selectedAccountIndex] ?? null : null;
+
+  useEffect(() => {
+    setDraft(selectedAccount ? { ...selectedAccount.metadata } : null);
+    setTagsText(selectedAccount ? selectedAccount.metadata.tags.join(', ') : '');
+  }, [selectedAccount?.address, selectedAccount?.metadata]);
+
+  const saveMetadata = () => {
+    if (!selectedAccount || !draft) return;
+    const tags = tagsText.split(',').map((tag) => tag.trim()).filter((tag) => tag.length > 0);
+    updateAccountMetadata(selectedAccount.index, { ...draft, tags });
+  };
+
+  const accountName = (account: AccountInfo) =>
+    account.metadata.label || `Account ${account.index + 1}`;
 
   return (
     <div className="wallet-overview">
       <div className="accounts-section">
         <div className="accounts-header">
           <h3>Your Accounts ({accounts.length})</h3>
+          {nodeStatus ? (
+            <span className="setting-unit">{nodeStatus}</span>
+          ) : nodeCache?.connected && nodeCache.block !== null ? (
+            <span className="setting-unit">Block #{nodeCache.block}</span>
+          ) : null}
           <div className="account-actions">
+            {hiddenCount > 0 || showHidden ? (
+              <label className="setting-unit">
+                <input
+                  type="checkbox"
+                  checked={showHidden}
+                  onChange={(e) => {
+                    setShowHidden(e.target.checked);
+                    setSelectedAccountIndex(null);
+                  }}
+                />
+                Show hidden and archived ({hiddenCount})
+              </label>
+            ) : null}
             <button 
               className="action-btn select-btn"
-              onClick={() => selectedAccountIndex !== null && selectAccount(selectedAccountIndex)}
-              disabled={selectedAccountIndex === null}
+              onClick={() => selectedAccount && selectAccount(selectedAccount.index)}
+              disabled={selectedAccount === null}
             >
               Set as Active
             </button>
@@ -54,13 +124,20 @@ const WalletOverview: React.FC<WalletOverviewProps> = ({
                     onClick={() => handleAccountClick(index)}
                   >
                     <div className="account-list-header">
-                      <span className="account-name">Account {index + 1}</span>
-                      <span className="account-path">m/44'/{COIN_TYPE}'/0'/0/{index}</span>
+                      <span className="account-name">{accountName(account)}</span>
+                      <span className="account-path">m/44'/{COIN_TYPE}'/0'/0/{account.index}</span>
                       {account.is_selected && <span className="active-badge">Active</span>}
+                      {account.metadata.archived && <span className="selected-badge">Archived</span>}
+                      {account.metadata.tags.map((tag) => (
+                        <span key={tag} className="account-path">#{tag}</span>
+                      ))}
                       {selectedAccountIndex === index && <span className="selected-badge">Selected</span>}
                     </div>
                     <div className="account-list-address">
                       {account.address.substring(0, 8)}...{account.address.substring(account.address.length - 8)}
+                      {balanceOf(account) !== undefined && (
+                        <span className="account-path"> {balanceOf(account)}</span>
+                      )}
                     </div>
                   </div>
                 ))}
@@ -74,11 +151,11 @@ const WalletOverview: React.FC<WalletOverviewProps> = ({
                 <div className="account-details-card">
                   <div className="detail-group">
                     <label>Account Index</label>
-                    <span>{selectedAccountIndex! + 1}</span>
+                    <span>{selectedAccount.index + 1}</span>
                   </div>
                   <div className="detail-group">
                     <label>BIP44 Path</label>
-                    <span className="bip44-path">m/44'/{COIN_TYPE}'/0'/0/{selectedAccountIndex}</span>
+                    <span className="bip44-path">m/44'/{COIN_TYPE}'/0'/0/{selectedAccount.index}</span>
                   </div>
                   <div className="detail-group">
                     <label>Address</label>
@@ -116,6 +193,72 @@ const WalletOverview: React.FC<WalletOverviewProps> = ({
                     <label>Created</label>
                     <span>{new Date(selectedAccount.created_at).toLocaleString()}</span>
                   </div>
+                  {draft && (
+                    <>
+                      <div className="detail-group">
+                        <label>Label</label>
+                        <input
+                          type="text"
+                          className="setting-input"
+                          placeholder={`Account ${selectedAccount.index + 1}`}
+                          maxLength={64}
+                          value={draft.label ?? ''}
+                          onChange={(e) => setDraft({ ...draft, label: e.target.value || null })}
+                        />
+                      </div>
+                      <div className="detail-group">
+                        <label>Notes</label>
+                        <textarea
+                          className="setting-input"
+                          maxLength={1024}
+                          value={draft.notes ?? ''}
+                          onChange={(e) => setDraft({ ...draft, notes: e.target.value || null })}
+                        />
+                      </div>
+                      <div className="detail-group">
+                        <label>Tags</label>
+                        <input
+                          type="text"
+                          className="setting-input"
+                          placeholder="cold, savings"
+                          value={tagsText}
+                          onChange={(e) => setTagsText(e.target.value)}
+                        />
+                      </div>
+                      <div className="detail-group">
+                        <label>Display Order</label>
+                        <input
+                          type="number"
+                          min={0}
+                          className="setting-input"
+                          placeholder={String(selectedAccount.index)}
+                          value={draft.order ?? ''}
+                          onChange={(e) =>
+                            setDraft({ ...draft, order: e.target.value === '' ? null : Number(e.target.value) })
+                          }
+                        />
+                      </div>
+                      <div className="detail-group">
+                        <label>Hidden</label>
+                        <input
+                          type="checkbox"
+                          checked={draft.hidden}
+                          onChange={(e) => setDraft({ ...draft, hidden: e.target.checked })}
+                        />
+                      </div>
+                      <div className="detail-group">
+                        <label>Archived</label>
+                        <input
+                          type="checkbox"
+                          checked={draft.archived}
+                          onChange={(e) => setDraft({ ...draft, archived: e.target.checked })}
+                        />
+                      </div>
+                      <button className="action-btn select-btn" onClick={saveMetadata}>
+                        Save Details
+                      </button>
+                    </>
+                  )}
                 </div>
               </div>
             )}
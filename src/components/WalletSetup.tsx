@@ -11,6 +11,8 @@ interface WalletSetupProps {
   createWallet: () => void;
   importWallet: () => void;
   loadWallet: (walletName: string) => void;
+  deleteWallet: (walletName: string, password: string) => void;
+  renameWallet: (oldName: string, newName: string) => void;
 }
 
 const WalletSetup: React.FC<WalletSetupProps> = ({
@@ -24,9 +26,20 @@ const WalletSetup: React.FC<WalletSetupProps> = ({
   createWallet,
   importWallet,
   loadWallet,
+  deleteWallet,
+  renameWallet,
 }) => {
   const [showCreateForm, setShowCreateForm] = useState(false);
   const [showImportForm, setShowImportForm] = useState(false);
+  const [managedWallet, setManagedWallet] = useState<string | null>(null);
+  const [renameTo, setRenameTo] = useState('');
+  const [deletePassword, setDeletePassword] = useState('');
+
+  const openManageForm = (wallet: string) => {
+    setManagedWallet(wallet);
+    setRenameTo(wallet);
+    setDeletePassword('');
+  };
   return (
     <div className="wallet-setup">
       <div className="wallet-setup-header">
@@ -61,6 +74,16 @@ const WalletSetup: React.FC<WalletSetupProps> = ({
               >
                 <span>📂 {wallet}</span>
                 {currentWallet === wallet && <span className="badge">Active</span>}
+                <button
+                  className="cancel-btn"
+                  onClick={(e) => {
+                    e.stopPropagation();
+                    openManageForm(wallet);
+                  }}
+                  disabled={loading}
+                >
+                  Manage
+                </button>
               </div>
             ))}
           </div>
@@ -179,6 +202,82 @@ const WalletSetup: React.FC<WalletSetupProps> = ({
           </div>
         </div>
       )}
+
+      {/* Rename / Delete Wallet Form */}
+      {managedWallet && (
+        <div className="wallet-form-overlay">
+          <div className="wallet-form">
+            <div className="wallet-form-header">
+              <h3>Manage {managedWallet}</h3>
+              <button
+                className="close-btn"
+                onClick={() => setManagedWallet(null)}
+              >
+                ✕
+              </button>
+            </div>
+
+            <div className="form-section">
+              <label htmlFor="renameWallet">New Name</label>
+              <input
+                id="renameWallet"
+                type="text"
+                value={renameTo}
+                onChange={(e) => setRenameTo(e.target.value)}
+                disabled={loading}
+              />
+            </div>
+
+            <div className="form-actions">
+              <button
+                onClick={() => {
+                  renameWallet(managedWallet, renameTo);
+                  setManagedWallet(null);
+                }}
+                disabled={loading || !renameTo.trim() || renameTo.trim() === managedWallet}
+                className="create-btn"
+              >
+                Rename Wallet
+              </button>
+            </div>
+
+            <div className="form-section">
+              <label htmlFor="deletePassword">Password</label>
+              <input
+                id="deletePassword"
+                type="password"
+                placeholder="Wallet password"
+                value={deletePassword}
+                onChange={(e) => setDeletePassword(e.target.value)}
+                disabled={loading}
+              />
+              <p className="input-help">
+                Deleted wallets are moved to the trash folder in the wallet directory, not erased.
+              </p>
+            </div>
+
+            <div className="form-actions">
+              <button
+                onClick={() => setManagedWallet(null)}
+                className="cancel-btn"
+                disabled={loading}
+              >
+                Cancel
+              </button>
+              <button
+                onClick={() => {
+                  deleteWallet(managedWallet, deletePassword);
+                  setManagedWallet(null);
+                }}
+                disabled={loading || !deletePassword}
+                className="import-btn"
+              >
+                Delete Wallet
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
     </div>
   );
 };
@@ -1,8 +1,26 @@
+export interface Contact {
+  name: string;
+  address: string;
+  memo?: string | null;
+  network: Network;
+}
+
+export interface AccountMetadata {
+  label: string | null;
+  notes: string | null;
+  tags: string[];
+  hidden: boolean;
+  archived: boolean;
+  order: number | null;
+}
+
 export interface AccountInfo {
+  index: number;
   address: string;
   public_key: string;
   is_selected: boolean;
   created_at: string;
+  metadata: AccountMetadata;
 }
 
 export interface WalletInfo {
@@ -28,12 +46,287 @@ export interface ImportWalletResponse {
   message: string;
 }
 
+export type SignatureScheme = 'ecdsa' | 'schnorr';
+
 export interface SignTransactionResponse {
   success: boolean;
   signature: string;
+  scheme: SignatureScheme;
+  message: string;
+}
+
+export interface PreviewField {
+  name: string;
+  field_type: string;
+  value: string | null;
+  fields: PreviewField[];
+}
+
+export interface TypedDataPreview {
+  primary_type: string;
+  domain: PreviewField[];
+  message: PreviewField[];
+  domain_separator: string;
+  message_hash: string;
+  signing_hash: string;
+  signer: string | null;
+}
+
+export interface SignTypedDataResponse {
+  success: boolean;
+  signature: string;
+  signing_hash: string;
+  scheme: SignatureScheme;
+  address: string;
   message: string;
 }
 
+export interface MultisigAccount {
+  name: string;
+  threshold: number;
+  participants: string[];
+  address: string;
+}
+
+export interface Transaction {
+  chain_id: number;
+  from: string;
+  to?: string;
+  amount?: number;
+  data?: string;
+  nonce: number;
+  gas_limit: number;
+  gas_price: number;
+}
+
+export type PstFormat = 'json' | 'binary';
+
+export interface PstInspection {
+  transaction: Transaction;
+  signing_hash: string;
+  threshold: number;
+  required_signers: string[];
+  signed: string[];
+  missing: string[];
+  complete: boolean;
+}
+
+export interface FinalizedTransaction {
+  hash: string;
+  signed_transaction: string;
+}
+
+export type TxStatus = 'built' | 'signed' | 'submitted' | 'confirmed' | 'failed';
+
+export type NodeEvent =
+  | { kind: 'connected' }
+  | { kind: 'disconnected'; attempt: number; retry_in_ms: number | null; error: string }
+  | { kind: 'block'; number: number; hash: string }
+  | { kind: 'balance'; account: string; balance: number }
+  | { kind: 'transaction'; hash: string; status: TxStatus };
+
+export interface NodeCache {
+  connected: boolean;
+  block: number | null;
+  balances: Record<string, number>;
+  transactions: Record<string, TxStatus>;
+}
+
+export interface TxRecord {
+  id: string;
+  hash: string | null;
+  account: string;
+  counterparty: string | null;
+  amount: number | null;
+  nonce: number;
+  gas_price: number;
+  status: TxStatus;
+  created_at: string;
+  updated_at: string;
+  raw: string | null;
+}
+
+export interface HistoryFilter {
+  account?: string;
+  counterparty?: string;
+  status?: TxStatus;
+  since?: string;
+  until?: string;
+}
+
+export interface HistoryPage {
+  total: number;
+  offset: number;
+  records: TxRecord[];
+}
+
+export interface NonceReport {
+  account: string;
+  on_chain: number;
+  next: number;
+  pending: number[];
+  drafts: string[];
+  gaps: number[];
+  stuck: string[];
+  stale: string[];
+}
+
+export interface QrFrame {
+  index: number;
+  total: number;
+  part: string;
+  svg: string;
+}
+
+export interface QrImportResult {
+  received: number;
+  total: number;
+  missing: number[];
+  complete: boolean;
+  pst?: string;
+}
+
+export type Network = 'mainnet' | 'testnet' | 'devnet';
+
+export interface NetworkProfile {
+  chain_id: number;
+  node_url: string;
+  address_prefix: string;
+  explorer_url?: string;
+}
+
+export interface ActiveNetwork {
+  network: Network;
+  wallet_network: Network | null;
+  profile: NetworkProfile;
+  node_url: string;
+}
+
+export type StorageBackend = 'json' | 'sled';
+
+export type FeeTier = 'slow' | 'normal' | 'fast';
+
+export interface SignerSettings {
+  enabled: boolean;
+  port: number;
+  approval_timeout_secs: number;
+}
+
+export type SignerMethod = 'connect' | 'accounts' | 'signMessage' | 'signTransaction';
+
+export type DappMethod = Exclude<SignerMethod, 'connect'>;
+
+export interface DappPermission {
+  accounts: string[];
+  methods: DappMethod[];
+  spending_limit?: number | null;
+  spent: number;
+  granted_at: string;
+  expires_at?: string | null;
+}
+
+export interface DappConnection extends DappPermission {
+  origin: string;
+  connected: boolean;
+}
+
+export interface Approval {
+  approved: boolean;
+  methods?: DappMethod[] | null;
+  accounts?: string[] | null;
+  spending_limit?: number | null;
+  expires_at?: string | null;
+}
+
+export interface SignerRequest {
+  id: number;
+  origin: string;
+  method: SignerMethod;
+  params: any;
+}
+
+export interface SignerStatus {
+  address: string | null;
+  pending: SignerRequest[];
+}
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface Settings {
+  wallet: {
+    dir?: string;
+    backend: StorageBackend;
+    default_wallet: string;
+    auto_lock_minutes: number;
+    kdf_iterations: number;
+  };
+  node: {
+    url?: string | null;
+    network: Network;
+    timeout_secs: number;
+    auto_reconnect: boolean;
+  };
+  networks: Record<Network, NetworkProfile>;
+  fee: {
+    tier: FeeTier;
+    gas_limit_margin_percent: number;
+    max_gas_limit: number;
+    max_gas_price: number;
+  };
+  signer: SignerSettings;
+  dapps?: Record<string, DappPermission>;
+  log: {
+    level: LogLevel;
+    to_file: boolean;
+    max_file_size_mb: number;
+  };
+}
+
+export interface GasPrices {
+  slow: number;
+  normal: number;
+  fast: number;
+}
+
+export interface FeeEstimate {
+  tier: FeeTier;
+  estimated_gas: number;
+  gas_limit: number;
+  gas_price: number;
+  capped: boolean;
+  prices: GasPrices;
+}
+
+export interface WalletPreview {
+  name: string;
+  addresses: string[] | null;
+  exists: boolean;
+}
+
+export interface BackupPreview {
+  created_at: string;
+  wallets: WalletPreview[];
+  contacts: number | null;
+  settings: boolean;
+}
+
+export interface RestoreReport {
+  restored: string[];
+  merged: string[];
+  replaced: string[];
+  unchanged: string[];
+  skipped: string[];
+  contacts_added: number;
+  address_book_replaced: boolean;
+  settings_restored: boolean;
+  set_aside: string[];
+}
+
+export interface MigrationReport {
+  migrated: string[];
+  skipped: string[];
+}
+
 export type MenuItem = 'accounts' | 'send' | 'receive' | 'transactions' | 'advanced' | 'settings';
 
 export type Theme = 'dark' | 'light';